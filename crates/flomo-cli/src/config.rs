@@ -0,0 +1,61 @@
+use flomo_core::db::{self, Database};
+use flomo_core::error::AppError;
+use flomo_core::settings::{AppSettings, CONFIG_FILE, SETTINGS_KEY, TOKEN_KEY};
+use std::path::{Path, PathBuf};
+
+/// Tauri `identifier` of the app, which names its data directory.
+const APP_IDENTIFIER: &str = "dev.neurora.flomo-garden";
+
+/// The app's data directory, the same one the GUI uses, unless overridden.
+pub fn data_dir(custom: Option<PathBuf>) -> Result<PathBuf, AppError> {
+    custom
+        .or_else(|| dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER)))
+        .ok_or_else(|| AppError::Internal("Could not find the data directory; pass --data-dir".to_string()))
+}
+
+/// The token and settings saved by the app.
+#[derive(Default)]
+pub struct StoredConfig {
+    pub token: Option<String>,
+    pub settings: AppSettings,
+}
+
+impl StoredConfig {
+    /// Reads `config.json`; a missing file means the app was never set up.
+    pub fn load(data_dir: &Path) -> Result<Self, AppError> {
+        let path = data_dir.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+        let mut config: serde_json::Value = serde_json::from_str(&text)?;
+        let token = config.get(TOKEN_KEY).and_then(|token| token.as_str()).map(str::to_string);
+        let settings = match config.get_mut(SETTINGS_KEY) {
+            Some(settings) => {
+                let (settings, dropped) = AppSettings::from_json(settings.take());
+                for reason in dropped {
+                    eprintln!("warning: ignoring saved setting {}", reason);
+                }
+                settings
+            }
+            None => AppSettings::default(),
+        };
+
+        Ok(Self { token, settings })
+    }
+}
+
+/// Opens the app's database (`file` in `data_dir`), creating it if needed.
+/// Encrypted databases need the passphrase set in the app.
+pub fn open_database(data_dir: &Path, file: &str, passphrase: Option<&str>) -> Result<Database, AppError> {
+    std::fs::create_dir_all(data_dir)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", data_dir.display(), e)))?;
+    let path = data_dir.join(file);
+
+    if db::is_encrypted(&path) && passphrase.is_none() {
+        return Err(AppError::Locked);
+    }
+    Database::open(&path, passphrase)
+}
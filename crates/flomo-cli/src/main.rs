@@ -0,0 +1,342 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use flomo_core::db::{Database, MemoFilter, MemoOrder};
+use flomo_core::error::AppError;
+use flomo_core::export::{self, ExportFormat, ExportOptions};
+use flomo_core::logging::{self, LogLevel};
+use flomo_core::search::{self, SearchExpr, SearchHit};
+use flomo_core::templates::TemplateContext;
+use flomo_core::{demo, launcher, outbox, sync, FlomoClient};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tokio_util::sync::CancellationToken;
+
+mod config;
+use config::StoredConfig;
+
+/// Flomo Garden without the window: sync, search, export and capture
+/// against the app's own database.
+#[derive(Parser)]
+#[command(name = "flomo", version)]
+struct Cli {
+    /// Data directory holding flomo.db and config.json; defaults to the app's
+    #[arg(long, global = true, env = "FLOMO_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Passphrase of an encrypted database
+    #[arg(long, global = true, env = "FLOMO_DB_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+    /// Work on generated memos in a separate database instead of a Flomo
+    /// account; also on when the app's demo mode is
+    #[arg(long, global = true, env = "FLOMO_DEMO")]
+    demo: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Pull every memo from Flomo into the local database
+    Sync {
+        /// Flomo token; defaults to the one saved by the app
+        #[arg(long, env = "FLOMO_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// Search local memos with the app's query syntax
+    Search {
+        query: String,
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+        /// Treat the query as a regular expression
+        #[arg(long, conflicts_with = "fuzzy")]
+        regex: bool,
+        /// Tolerate typos in the query
+        #[arg(long)]
+        fuzzy: bool,
+        /// Print the hits as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print matching memos as Alfred/Raycast Script Filter JSON; each
+    /// result opens the memo in the app
+    Launcher {
+        /// Search query; empty lists the latest memos
+        #[arg(default_value = "")]
+        query: String,
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Export local memos, newest first
+    Export {
+        #[arg(long, value_enum)]
+        format: Format,
+        /// File to write; defaults to standard output
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Start with a UTF-8 byte order mark, so Excel reads Chinese text in CSV correctly
+        #[arg(long)]
+        bom: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Show writing statistics
+    Stats {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Create a memo on Flomo, or queue it in the outbox when offline
+    Capture {
+        /// Memo text; read from standard input when omitted
+        #[arg(conflicts_with = "template")]
+        content: Option<String>,
+        /// Capture template configured in the app; `{{clipboard}}` renders empty
+        #[arg(long)]
+        template: Option<String>,
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        #[arg(long, env = "FLOMO_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+}
+
+#[derive(clap::Args)]
+struct FilterArgs {
+    /// Only memos with this tag or a tag below it
+    #[arg(long)]
+    tag: Option<String>,
+    /// Only memos created on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    from: Option<String>,
+    /// Only memos created on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    to: Option<String>,
+}
+
+impl FilterArgs {
+    fn into_filter(self, pinyin: bool) -> MemoFilter {
+        MemoFilter { tag: self.tag, from_date: self.from, to_date: self.to, pinyin, ..Default::default() }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Md,
+    Csv,
+    Json,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), AppError> {
+    // Commands print their own progress; the library only reports problems
+    logging::init(None, LogLevel::Warn)?;
+    let data_dir = config::data_dir(cli.data_dir)?;
+    let mut stored = StoredConfig::load(&data_dir)?;
+    stored.settings.demo_mode |= cli.demo;
+    flomo_core::set_text_width(stored.settings.text_width);
+    if stored.settings.demo_mode {
+        // Any token works, and the saved one isn't sent anywhere
+        stored.token = Some(demo::DEMO_TOKEN.to_string());
+    }
+    let db = config::open_database(
+        &stored.settings.database_dir(&data_dir),
+        stored.settings.database_file(),
+        cli.passphrase.as_deref(),
+    )?;
+
+    match cli.command {
+        Command::Sync { token } => run_sync(&db, stored, token).await,
+        Command::Search { query, filter, limit, regex, fuzzy, json } => {
+            let filter = filter.into_filter(stored.settings.pinyin_search);
+            let hits = run_search(&db, query, filter, limit, regex, fuzzy).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&hits)?);
+            } else {
+                for hit in &hits {
+                    println!("{}  {}  {}", hit.memo.created_at, hit.memo.slug, first_line(&hit.memo.content));
+                }
+            }
+            Ok(())
+        }
+        Command::Launcher { query, limit } => {
+            let filter = MemoFilter { pinyin: stored.settings.pinyin_search, ..Default::default() };
+            let results = db.run(move |db| launcher::query(db, &query, &filter, limit)).await?;
+            println!("{}", serde_json::to_string(&results)?);
+            Ok(())
+        }
+        Command::Export { format, output, bom, filter } => {
+            let options = ExportOptions {
+                locale: stored.settings.locale,
+                utf8_bom: bom,
+                filter: filter.into_filter(stored.settings.pinyin_search),
+                ..Default::default()
+            };
+            let format = match format {
+                Format::Md => ExportFormat::Markdown,
+                Format::Csv => ExportFormat::Csv,
+                Format::Json => ExportFormat::Json,
+            };
+            let out: Box<dyn Write + Send> = match &output {
+                Some(path) => Box::new(std::fs::File::create(path)
+                    .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", path.display(), e)))?),
+                None => Box::new(std::io::stdout()),
+            };
+
+            let tz = stored.settings.timezone();
+            let written = db.run(move |db| {
+                export::export_from_db(db, std::io::BufWriter::new(out), format, options, tz, |_| {})
+                    .map(|(_, written)| written)
+            })
+            .await?;
+            if output.is_some() {
+                eprintln!("Exported {} memos", written);
+            }
+            Ok(())
+        }
+        Command::Stats { json } => {
+            let today = chrono::Utc::now().with_timezone(&stored.settings.timezone()).date_naive();
+            let stats = db.run(move |db| db.get_writing_stats(today)).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+                return Ok(());
+            }
+
+            const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+            println!("Memos:           {}", stats.total_memos);
+            println!("Active days:     {}", stats.active_days);
+            println!("First memo:      {}", stats.first_memo_at.as_deref().unwrap_or("-"));
+            println!("Words:           {} ({:.1} per memo)", stats.total_words, stats.average_words_per_memo);
+            println!("Characters:      {}", stats.total_chars);
+            println!("Current streak:  {} days", stats.current_streak);
+            println!("Longest streak:  {} days", stats.longest_streak);
+            println!("Per day:         {:.2}", stats.average_per_day);
+            println!("Per week:        {:.2}", stats.average_per_week);
+            if let Some(weekday) = stats.most_active_weekday {
+                println!("Busiest weekday: {}", WEEKDAYS[weekday.clamp(0, 6) as usize]);
+            }
+            if let Some(hour) = stats.most_active_hour {
+                println!("Busiest hour:    {:02}:00", hour);
+            }
+            Ok(())
+        }
+        Command::Capture { content, template, mut tags, token } => {
+            let content = match (content, template) {
+                (Some(content), _) => content,
+                (None, Some(id)) => {
+                    let template = stored.settings.capture_templates.iter()
+                        .find(|template| template.id == id)
+                        .ok_or_else(|| AppError::NotFound(format!("Capture template {}", id)))?;
+                    tags.extend(template.tags.iter().cloned());
+                    template.render(&TemplateContext {
+                        now: chrono::Utc::now().with_timezone(&stored.settings.timezone()),
+                        locale: stored.settings.locale,
+                        clipboard: None,
+                    })?
+                }
+                (None, None) => std::io::read_to_string(std::io::stdin())
+                    .map_err(|e| AppError::Internal(format!("Failed to read standard input: {}", e)))?,
+            };
+
+            let client = token.or(stored.token)
+                .map(|token| {
+                    FlomoClient::new(token)
+                        .with_demo(stored.settings.demo_mode)
+                        .with_timezone(stored.settings.timezone())
+                        .with_api_settings(&stored.settings.api)?
+                        .with_proxy(stored.settings.proxy.as_ref())
+                })
+                .transpose()?;
+            let result = outbox::compose(&db, client.as_ref(), content, tags).await?;
+            match (result.memo, result.queued) {
+                (Some(memo), _) => println!("Created {}", memo.slug),
+                (None, Some(queued)) => println!("Queued as #{}; the app pushes it once Flomo is reachable", queued.id),
+                (None, None) => {}
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn run_sync(db: &Database, stored: StoredConfig, token: Option<String>) -> Result<(), AppError> {
+    let token = token.or(stored.token)
+        .ok_or_else(|| AppError::Auth("No token; log in with the app or pass --token".to_string()))?;
+
+    let cancel = CancellationToken::new();
+    let on_interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_interrupt.cancel();
+        }
+    });
+
+    let client = FlomoClient::new(token)
+        .with_demo(stored.settings.demo_mode)
+        .with_timezone(stored.settings.timezone())
+        .with_api_settings(&stored.settings.api)?
+        .with_proxy(stored.settings.proxy.as_ref())?
+        .with_cancellation(cancel.clone());
+    let (progress, changes) = sync::sync_all(db, &client, &cancel, |progress| {
+        eprintln!("{}", progress.message);
+        Ok(())
+    })
+    .await?;
+
+    // Keep the app's feed, git mirror, webhooks, backups and auto-exports current, as a sync from the app would
+    let report = sync::run_after_sync(db, stored.settings, &progress, &changes).await;
+    if let Some(commit) = report.git_mirror.and_then(|mirrored| mirrored.commit) {
+        eprintln!("Committed {} to the git mirror", &commit[..commit.len().min(7)]);
+    }
+    if let Some(entry) = report.backup {
+        eprintln!("Backed up as {}", entry.name);
+    }
+
+    println!("{}", progress.message);
+    Ok(())
+}
+
+async fn run_search(
+    db: &Database,
+    query: String,
+    filter: MemoFilter,
+    limit: i64,
+    regex: bool,
+    fuzzy: bool,
+) -> Result<Vec<SearchHit>, AppError> {
+    let limit = limit.max(0);
+    if fuzzy {
+        return db.run(move |db| search::fuzzy_search(db, &query, &filter, 0, limit as usize)).await;
+    }
+    if regex {
+        let pattern = search::compile_regex(&query)?;
+        let matcher = pattern.clone();
+        let limit = (limit as usize).min(search::REGEX_RESULT_CAP);
+        let memos = db.run(move |db| db.search_memos_regex(&matcher, MemoOrder::CreatedAt, "desc", &filter, 0, limit)).await?;
+        return Ok(memos.into_iter().map(|memo| SearchHit::for_regex(memo, &pattern)).collect());
+    }
+
+    let terms = SearchExpr::parse(&query)
+        .map(|expr| expr.highlight_terms())
+        .unwrap_or_default();
+    let memos = db.run(move |db| db.search_memos(&query, MemoOrder::CreatedAt, "desc", &filter, 0, limit)).await?;
+    Ok(memos.into_iter().map(|memo| SearchHit::new(memo, &terms)).collect())
+}
+
+fn first_line(content: &str) -> String {
+    const MAX_CHARS: usize = 80;
+    let line = content.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().trim();
+    if line.chars().count() > MAX_CHARS {
+        format!("{}…", line.chars().take(MAX_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Seek, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export;
+use crate::i18n::Locale;
+use crate::{format_date, Memo};
+
+const ASSETS_DIR: &str = "assets";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ArchiveOptions {
+    /// Date format for the Markdown headings
+    pub date_format: String,
+    pub filter: MemoFilter,
+    /// Language of generated labels; set from the app settings
+    #[serde(skip)]
+    pub locale: Locale,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        Self {
+            date_format: "yyyy-MM-dd HH:mm".to_string(),
+            filter: MemoFilter::default(),
+            locale: Locale::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ArchiveExportResult {
+    pub memos: usize,
+    pub assets: usize,
+    /// Images that could not be downloaded and keep their remote link
+    pub assets_failed: usize,
+}
+
+#[derive(Serialize)]
+struct ArchivedMemo<'a> {
+    #[serde(flatten)]
+    memo: &'a Memo,
+    /// Paths of this memo's images inside the archive
+    assets: Vec<String>,
+}
+
+/// Writes a zip backup with `memos.md`, `memos.json` and every image the
+/// memos reference under `assets/`. Image links in the archived HTML point
+/// at the bundled copies.
+pub fn export_archive(db: &Database, path: &Path, options: &ArchiveOptions, tz: FixedOffset) -> Result<ArchiveExportResult, AppError> {
+    let mut memos = export::load_memos_chronological(db, &options.filter)?;
+    export::attach_content_html(db, &mut memos)?;
+
+    let file = std::fs::File::create(path)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", path.display(), e)))?;
+
+    let result = write_archive(file, &mut memos, options, &tz);
+    if result.is_err() {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+fn write_archive<W: Write + Seek>(
+    out: W,
+    memos: &mut [Memo],
+    options: &ArchiveOptions,
+    tz: &FixedOffset,
+) -> Result<ArchiveExportResult, AppError> {
+    let zip_err = |e: zip::result::ZipError| AppError::Internal(format!("Failed to write archive: {}", e));
+    let io_err = |e: std::io::Error| AppError::Internal(format!("Failed to write archive: {}", e));
+
+    let mut zip = ZipWriter::new(out);
+    // Images are already compressed
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let client = export::image_client()?;
+    let mut result = ArchiveExportResult { memos: memos.len(), ..Default::default() };
+    // Image src -> archive path, so images shared between memos are stored once
+    let mut assets: HashMap<String, Option<String>> = HashMap::new();
+    let mut memo_assets: Vec<Vec<String>> = Vec::with_capacity(memos.len());
+
+    for memo in memos.iter_mut() {
+        let mut paths = Vec::new();
+        let Some(html) = memo.content_html.clone() else {
+            memo_assets.push(paths);
+            continue;
+        };
+
+        let mut rewritten = html.clone();
+        for src in export::image_sources(&html) {
+            if !src.starts_with("http://") && !src.starts_with("https://") {
+                continue;
+            }
+            if !assets.contains_key(&src) {
+                let asset = match export::fetch_image(&client, &src) {
+                    Ok((content_type, bytes)) => {
+                        // Named after the URL so re-exports produce the same paths
+                        let name = format!(
+                            "{}/{:x}.{}",
+                            ASSETS_DIR,
+                            md5::compute(&src),
+                            export::image_extension(&content_type)
+                        );
+                        zip.start_file(name.as_str(), stored).map_err(zip_err)?;
+                        zip.write_all(&bytes).map_err(io_err)?;
+                        result.assets += 1;
+                        Some(name)
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to archive image {}: {}", src, e);
+                        result.assets_failed += 1;
+                        None
+                    }
+                };
+                assets.insert(src.clone(), asset);
+            }
+
+            if let Some(Some(asset)) = assets.get(&src) {
+                rewritten = export::replace_image_src(&rewritten, &src, asset);
+                paths.push(asset.clone());
+            }
+        }
+
+        memo.content_html = Some(rewritten);
+        memo_assets.push(paths);
+    }
+
+    let archived: Vec<ArchivedMemo> = memos.iter()
+        .zip(memo_assets)
+        .map(|(memo, assets)| ArchivedMemo { memo, assets })
+        .collect();
+
+    zip.start_file("memos.md", deflated).map_err(zip_err)?;
+    zip.write_all(markdown(&archived, options, tz).as_bytes()).map_err(io_err)?;
+    zip.start_file("memos.json", deflated).map_err(zip_err)?;
+    zip.write_all(serde_json::to_string_pretty(&archived)?.as_bytes()).map_err(io_err)?;
+
+    zip.finish().map_err(zip_err)?;
+    Ok(result)
+}
+
+fn markdown(memos: &[ArchivedMemo], options: &ArchiveOptions, tz: &FixedOffset) -> String {
+    let mut output = String::new();
+
+    for ArchivedMemo { memo, assets } in memos {
+        output.push_str(&format!("## {}\n\n", format_date(&memo.created_at, &options.date_format, tz)));
+        output.push_str(&format!("{}\n", memo.content.trim()));
+        if !assets.is_empty() {
+            output.push('\n');
+            for asset in assets {
+                output.push_str(&format!("![]({})\n", asset));
+            }
+        }
+        if !memo.tags.is_empty() {
+            output.push_str(&format!("\n**{}**: {}\n", options.locale.strings().tags, memo.tags.join(", ")));
+        }
+        output.push_str("\n---\n\n");
+    }
+
+    output
+}
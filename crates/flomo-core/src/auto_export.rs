@@ -0,0 +1,123 @@
+use chrono::{FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::blog::{self, BlogOptions};
+use crate::db::Database;
+use crate::error::AppError;
+use crate::export::{self, ExportFormat, ExportOptions, ImageMode};
+use crate::i18n::Locale;
+use crate::logseq::{self, LogseqOptions};
+
+/// Placeholder in `AutoExportRule::path` replaced with the sync date, so
+/// each day's export gets its own file.
+pub const DATE_PLACEHOLDER: &str = "{date}";
+
+/// An export run after every successful sync.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoExportRule {
+    /// Names the rule in `get_export_runs`
+    pub name: String,
+    /// File or folder written; may contain `{date}`
+    pub path: String,
+    pub target: AutoExportTarget,
+}
+
+/// What an auto-export rule writes to its path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AutoExportTarget {
+    /// One file in an export format, e.g. a JSON backup
+    File {
+        format: ExportFormat,
+        #[serde(default)]
+        options: ExportOptions,
+    },
+    /// Logseq journal pages under `<path>/journals`
+    Logseq {
+        #[serde(default)]
+        options: LogseqOptions,
+    },
+    /// A Markdown file with front matter per memo, e.g. into an Obsidian
+    /// vault or a Hugo site
+    Markdown {
+        #[serde(default)]
+        options: BlogOptions,
+    },
+}
+
+/// Runs each rule in turn and records how it went; a failing rule doesn't
+/// stop the rest. Returns the names of the rules that failed with their
+/// errors.
+pub fn run_rules(
+    db: &Database,
+    rules: &[AutoExportRule],
+    tz: FixedOffset,
+    locale: Locale,
+) -> Result<Vec<(String, AppError)>, AppError> {
+    let mut failed = Vec::new();
+    for rule in rules {
+        let started_at = Utc::now().to_rfc3339();
+        let date = Utc::now().with_timezone(&tz).format("%Y-%m-%d").to_string();
+        let path = rule.path.trim().replace(DATE_PLACEHOLDER, &date);
+
+        let result = run_rule(db, &rule.target, &path, tz, locale);
+        let memo_count = result.as_ref().ok().copied().map(|count| count as i64);
+        let error = result.err();
+        let message = error.as_ref().map(|e| e.to_string());
+        db.record_export_run(&rule.name, &path, &started_at, memo_count, message.as_deref())?;
+        if let Some(error) = error {
+            failed.push((rule.name.clone(), error));
+        }
+    }
+    Ok(failed)
+}
+
+// Writes one rule's export and returns how many memos went into it
+fn run_rule(db: &Database, target: &AutoExportTarget, path: &str, tz: FixedOffset, locale: Locale) -> Result<usize, AppError> {
+    if path.is_empty() {
+        return Err(AppError::InvalidInput("Auto-export path is empty".to_string()));
+    }
+    let path = Path::new(path);
+
+    match target {
+        AutoExportTarget::File { format, options } => {
+            let mut options = options.clone();
+            options.locale = locale;
+            if matches!(format, ExportFormat::Html) && options.image_mode == ImageMode::Files {
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+                options.asset_dir = Some(path.with_file_name(format!("{}_files", stem)));
+            }
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)
+                    .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", dir.display(), e)))?;
+            }
+
+            // Written beside the target and renamed, so a failed run keeps the last good export
+            let partial = path.with_extension("partial");
+            let file = std::fs::File::create(&partial)
+                .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", partial.display(), e)))?;
+            let written = export::export_from_db(db, std::io::BufWriter::new(file), *format, options, tz, |_| {})
+                .and_then(|(out, written)| {
+                    out.into_inner()
+                        .map_err(|e| AppError::Internal(format!("Failed to write {}: {}", partial.display(), e)))?;
+                    Ok(written)
+                });
+            match written {
+                Ok(written) => {
+                    std::fs::rename(&partial, path)
+                        .map_err(|e| AppError::Internal(format!("Failed to replace {}: {}", path.display(), e)))?;
+                    Ok(written)
+                }
+                Err(e) => {
+                    let _ = std::fs::remove_file(&partial);
+                    Err(e)
+                }
+            }
+        }
+        AutoExportTarget::Logseq { options } => {
+            logseq::export_logseq(db, path, options, tz).map(|result| result.memos)
+        }
+        AutoExportTarget::Markdown { options } => blog::export_blog(db, path, options, tz),
+    }
+}
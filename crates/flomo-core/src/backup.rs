@@ -0,0 +1,261 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::db::{Database, UpsertStats};
+use crate::error::AppError;
+use crate::export::{self, ExportFormat, ExportOptions};
+use crate::Memo;
+
+const FILE_PREFIX: &str = "flomo-garden-";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+/// How often the app checks whether a scheduled backup is due.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// What gets uploaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupKind {
+    /// The SQLite file itself, with search history, tags and everything else
+    #[default]
+    Database,
+    /// Memos as JSON Lines, readable by other tools
+    Json,
+}
+
+impl BackupKind {
+    fn extension(self) -> &'static str {
+        match self {
+            BackupKind::Database => "db",
+            BackupKind::Json => "jsonl",
+        }
+    }
+}
+
+/// A WebDAV folder backups are uploaded to, e.g. on 坚果云 or Nextcloud.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Folder URL, e.g. "https://dav.jianguoyun.com/dav/flomo-garden/";
+    /// created if missing
+    pub url: String,
+    pub username: String,
+    /// 坚果云 needs an app password here, not the account password
+    pub password: String,
+    pub kind: BackupKind,
+    /// Back up after every successful sync
+    pub after_sync: bool,
+    /// Also back up when the newest backup is this old
+    pub interval_hours: Option<u32>,
+    /// Versions kept on the server; older ones are deleted after an upload
+    pub keep: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            username: String::new(),
+            password: String::new(),
+            kind: BackupKind::default(),
+            after_sync: false,
+            interval_hours: None,
+            keep: 10,
+        }
+    }
+}
+
+/// A backup file on the server.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupEntry {
+    pub name: String,
+    pub kind: BackupKind,
+    /// When the backup was made, from its file name
+    pub created_at: DateTime<Utc>,
+    pub size: Option<u64>,
+}
+
+impl BackupEntry {
+    /// Reads a file name written by `backup`; other files in the folder are
+    /// never listed, restored or pruned.
+    pub fn parse(name: &str, size: Option<u64>) -> Option<Self> {
+        let (stem, extension) = name.strip_prefix(FILE_PREFIX)?.rsplit_once('.')?;
+        let kind = [BackupKind::Database, BackupKind::Json].into_iter()
+            .find(|kind| kind.extension() == extension)?;
+        let created_at = NaiveDateTime::parse_from_str(stem, TIMESTAMP_FORMAT).ok()?.and_utc();
+
+        Some(Self { name: name.to_string(), kind, created_at, size })
+    }
+}
+
+pub struct WebDavClient {
+    client: reqwest::Client,
+    /// Folder URL ending in '/'
+    base: String,
+    username: String,
+    password: String,
+}
+
+impl WebDavClient {
+    pub fn new(settings: &BackupSettings) -> Result<Self, AppError> {
+        let url = settings.url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(AppError::InvalidInput("WebDAV URL must start with http:// or https://".to_string()));
+        }
+        let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+
+        Ok(Self {
+            client,
+            base: format!("{}/", url.trim_end_matches('/')),
+            username: settings.username.clone(),
+            password: settings.password.clone(),
+        })
+    }
+
+    fn request(&self, method: Method, name: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, format!("{}{}", self.base, name))
+            .basic_auth(&self.username, Some(&self.password))
+    }
+
+    /// Backups on the server, newest first.
+    pub async fn list(&self) -> Result<Vec<BackupEntry>, AppError> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?><propfind xmlns="DAV:"><prop><getcontentlength/></prop></propfind>"#;
+        let response = self.request(Method::from_bytes(b"PROPFIND").unwrap(), "")
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let xml = check(response, "list backups").await?.text().await?;
+
+        let mut entries = parse_listing(&xml)?;
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+        Ok(entries)
+    }
+
+    pub async fn upload(&self, name: &str, content: Vec<u8>) -> Result<(), AppError> {
+        // 405 means the folder already exists
+        let response = self.request(Method::from_bytes(b"MKCOL").unwrap(), "").send().await?;
+        if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+            check(response, "create the backup folder").await?;
+        }
+
+        let response = self.request(Method::PUT, name).body(content).send().await?;
+        check(response, "upload the backup").await?;
+        Ok(())
+    }
+
+    pub async fn download(&self, name: &str) -> Result<Vec<u8>, AppError> {
+        let response = self.request(Method::GET, name).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(AppError::NotFound(format!("Backup {}", name)));
+        }
+        Ok(check(response, "download the backup").await?.bytes().await?.to_vec())
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), AppError> {
+        let response = self.request(Method::DELETE, name).send().await?;
+        check(response, "delete an old backup").await?;
+        Ok(())
+    }
+}
+
+/// Uploads a new versioned backup and deletes the ones beyond `keep`.
+pub async fn backup(db: &Database, settings: &BackupSettings) -> Result<BackupEntry, AppError> {
+    let webdav = WebDavClient::new(settings)?;
+    let created_at = Utc::now();
+    let name = format!("{}{}.{}", FILE_PREFIX, created_at.format(TIMESTAMP_FORMAT), settings.kind.extension());
+
+    let kind = settings.kind;
+    let content = db.run(move |db| snapshot(db, kind)).await?;
+    let size = content.len() as u64;
+    webdav.upload(&name, content).await?;
+
+    let entries = webdav.list().await?;
+    for old in entries.iter().skip(settings.keep.max(1)) {
+        if let Err(e) = webdav.delete(&old.name).await {
+            tracing::warn!("Failed to delete backup {}: {}", old.name, e);
+        }
+    }
+
+    Ok(BackupEntry { name, kind, created_at, size: Some(size) })
+}
+
+/// Whether the newest backup is older than `interval_hours`.
+pub fn is_due(newest: Option<&BackupEntry>, interval_hours: u32) -> bool {
+    newest.is_none_or(|entry| Utc::now() - entry.created_at >= chrono::Duration::hours(interval_hours.into()))
+}
+
+/// Merges the memos of a JSON backup into the database, keeping memos that
+/// aren't in the backup.
+pub fn restore_memos(db: &Database, content: &[u8]) -> Result<UpsertStats, AppError> {
+    let memos = String::from_utf8_lossy(content)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str::<Memo>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Parse(format!("Not a memo backup: {}", e)))?;
+    db.bulk_upsert_memos(&memos)
+}
+
+// The backup file's content, built on a blocking thread
+fn snapshot(db: &Database, kind: BackupKind) -> Result<Vec<u8>, AppError> {
+    match kind {
+        BackupKind::Database => {
+            let path = std::env::temp_dir().join(format!("{}{}.db", FILE_PREFIX, std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            db.snapshot_to(&path)?;
+            let content = std::fs::read(&path)
+                .map_err(|e| AppError::Internal(format!("Failed to read database copy: {}", e)));
+            let _ = std::fs::remove_file(&path);
+            content
+        }
+        BackupKind::Json => {
+            let tz = crate::date_format::system_timezone();
+            let (content, _) = export::export_from_db(db, Vec::new(), ExportFormat::Jsonl, ExportOptions::default(), tz, |_| {})?;
+            Ok(content)
+        }
+    }
+}
+
+async fn check(response: reqwest::Response, action: &str) -> Result<reqwest::Response, AppError> {
+    let status = response.status();
+    match status.as_u16() {
+        200..=299 => Ok(response),
+        401 | 403 => Err(AppError::Auth(format!("WebDAV server refused to {}: HTTP {}", action, status))),
+        429 | 503 => Err(AppError::RateLimited(format!("WebDAV server is busy: HTTP {}", status))),
+        _ => {
+            let body = response.text().await.unwrap_or_default();
+            Err(AppError::Network(format!("Failed to {}: HTTP {} {}", action, status, body.trim())))
+        }
+    }
+}
+
+// Reads a PROPFIND multistatus response; the folder itself and foreign
+// files are skipped by `BackupEntry::parse`
+fn parse_listing(xml: &str) -> Result<Vec<BackupEntry>, AppError> {
+    let document = roxmltree::Document::parse(xml)
+        .map_err(|e| AppError::Parse(format!("Invalid WebDAV listing: {}", e)))?;
+    fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+        node.descendants()
+            .find(|child| child.tag_name().name() == name)
+            .and_then(|child| child.text())
+            .map(str::trim)
+    }
+
+    Ok(document.descendants()
+        .filter(|node| node.tag_name().name() == "response")
+        .filter_map(|response| {
+            let href = child_text(response, "href")?;
+            let name = href.trim_end_matches('/').rsplit('/').next()?;
+            let size = child_text(response, "getcontentlength").and_then(|size| size.parse().ok());
+            BackupEntry::parse(name, size)
+        })
+        .collect())
+}
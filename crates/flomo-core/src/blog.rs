@@ -0,0 +1,122 @@
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export;
+use crate::{parse_memo_time, Memo};
+
+const TITLE_MAX_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlogLayout {
+    /// `content/posts/<slug>.md` with a `draft` flag
+    Hugo,
+    /// `_posts/YYYY-MM-DD-<slug>.md` with a `published` flag
+    Jekyll,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BlogOptions {
+    pub layout: BlogLayout,
+    /// Mark every post as a draft so nothing goes live by accident
+    pub draft: bool,
+    /// Usually a tag such as "publish" selecting the memos to post
+    pub filter: MemoFilter,
+}
+
+impl Default for BlogOptions {
+    fn default() -> Self {
+        Self {
+            layout: BlogLayout::Hugo,
+            draft: true,
+            filter: MemoFilter::default(),
+        }
+    }
+}
+
+/// Writes one Markdown post with front matter per memo into the site
+/// directory `dir`. Returns the number of posts written.
+pub fn export_blog(db: &Database, dir: &Path, options: &BlogOptions, tz: FixedOffset) -> Result<usize, AppError> {
+    let memos = export::load_memos_chronological(db, &options.filter)?;
+    let posts_dir = match options.layout {
+        BlogLayout::Hugo => dir.join("content").join("posts"),
+        BlogLayout::Jekyll => dir.join("_posts"),
+    };
+    std::fs::create_dir_all(&posts_dir)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", posts_dir.display(), e)))?;
+
+    for memo in &memos {
+        let date = parse_memo_time(&memo.created_at, &tz);
+        let file_name = match (options.layout, date) {
+            (BlogLayout::Jekyll, Some(date)) => format!("{}-{}.md", date.format("%Y-%m-%d"), memo.slug),
+            _ => format!("{}.md", memo.slug),
+        };
+
+        let post = format!("{}\n{}\n", front_matter(memo, options, &tz), memo.content.trim());
+        let path = posts_dir.join(file_name);
+        std::fs::write(&path, post)
+            .map_err(|e| AppError::Internal(format!("Failed to write {}: {}", path.display(), e)))?;
+    }
+
+    Ok(memos.len())
+}
+
+fn front_matter(memo: &Memo, options: &BlogOptions, tz: &FixedOffset) -> String {
+    let date = parse_memo_time(&memo.created_at, tz)
+        .map(|date| match options.layout {
+            BlogLayout::Hugo => date.to_rfc3339(),
+            BlogLayout::Jekyll => date.format("%Y-%m-%d %H:%M:%S %z").to_string(),
+        })
+        .unwrap_or_else(|| memo.created_at.clone());
+    let tags: Vec<String> = memo.tags.iter().map(|tag| yaml_string(tag)).collect();
+
+    let mut lines = vec![
+        "---".to_string(),
+        format!("title: {}", yaml_string(&post_title(memo))),
+        format!("date: {}", date),
+        format!("slug: {}", yaml_string(&memo.slug)),
+        format!("tags: [{}]", tags.join(", ")),
+    ];
+    match options.layout {
+        BlogLayout::Hugo => lines.push(format!("draft: {}", options.draft)),
+        BlogLayout::Jekyll => {
+            lines.push("layout: post".to_string());
+            lines.push(format!("published: {}", !options.draft));
+        }
+    }
+    lines.push("---".to_string());
+
+    lines.join("\n")
+}
+
+/// First non-empty line with heading markers and #tags stripped, falling
+/// back to the memo's date when nothing is left.
+pub(crate) fn post_title(memo: &Memo) -> String {
+    let first_line = memo.content.lines()
+        .map(|line| {
+            // "## Heading" loses its markers, but "#tag" at the start is a tag
+            let heading = line.trim_start().trim_start_matches('#');
+            let line = if heading.starts_with(' ') { heading } else { line };
+            line.split_whitespace()
+                .filter(|word| !word.starts_with('#'))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .find(|line| !line.is_empty());
+
+    match first_line {
+        Some(line) if line.chars().count() > TITLE_MAX_CHARS => {
+            format!("{}…", line.chars().take(TITLE_MAX_CHARS).collect::<String>())
+        }
+        Some(line) => line,
+        None => memo.created_at.clone(),
+    }
+}
+
+pub(crate) fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
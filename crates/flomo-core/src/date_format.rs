@@ -0,0 +1,176 @@
+use chrono::{DateTime, Datelike, FixedOffset, Local, Offset, Timelike, Utc};
+
+use crate::parse_memo_time;
+
+const MONTHS_EN: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+const MONTHS_ZH: [&str; 12] = [
+    "一月", "二月", "三月", "四月", "五月", "六月",
+    "七月", "八月", "九月", "十月", "十一月", "十二月",
+];
+const WEEKDAYS_EN: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+const WEEKDAYS_ZH: [&str; 7] = ["一", "二", "三", "四", "五", "六", "日"];
+const QUARTERS_ZH: [&str; 4] = ["一", "二", "三", "四"];
+
+/// Language for month and weekday names, AM/PM and relative dates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateLocale {
+    Zh,
+    En,
+}
+
+impl DateLocale {
+    /// Formats containing Chinese text get Chinese names.
+    fn detect(format: &str) -> Self {
+        if format.chars().any(|ch| ('\u{4e00}'..='\u{9fff}').contains(&ch)) {
+            DateLocale::Zh
+        } else {
+            DateLocale::En
+        }
+    }
+}
+
+/// Formats a memo timestamp with a pattern such as "yyyy-MM-dd HH:mm" or
+/// "yyyy年M月d日 EEEE".
+///
+/// Tokens: `yyyy`/`yy` year, `Q` quarter (`QQ` "Q2", `QQQ` spelled out),
+/// `M`/`MM` month, `MMM`/`MMMM` short/full month name, `d`/`dd` day,
+/// `E`/`EEEE` short/full weekday, `H`/`HH` 24-hour, `h`/`hh` 12-hour,
+/// `m`/`mm` minute, `s`/`ss` second, `a` AM/PM. Text in single quotes is
+/// copied as is. The whole format "relative" (or "相对") gives "3 days ago"
+/// (or "3 天前") instead. Unparseable dates are returned unchanged.
+pub fn format_date(date_str: &str, format: &str, tz: &FixedOffset) -> String {
+    let Some(date) = parse_memo_time(date_str, tz) else {
+        return date_str.to_string();
+    };
+
+    match format.trim() {
+        "relative" => relative(&date, DateLocale::En),
+        "相对" => relative(&date, DateLocale::Zh),
+        _ => format_tokens(&date, format, DateLocale::detect(format)),
+    }
+}
+
+/// The current UTC offset of the system timezone.
+pub fn system_timezone() -> FixedOffset {
+    Local::now().offset().fix()
+}
+
+/// Like `format_date`, but month and weekday names are Chinese when `zh`
+/// is set instead of following the language of the pattern.
+pub(crate) fn format_date_in(date_str: &str, format: &str, tz: &FixedOffset, zh: bool) -> String {
+    let Some(date) = parse_memo_time(date_str, tz) else {
+        return date_str.to_string();
+    };
+
+    format_tokens(&date, format, if zh { DateLocale::Zh } else { DateLocale::En })
+}
+
+fn format_tokens(date: &DateTime<FixedOffset>, format: &str, locale: DateLocale) -> String {
+    let chars: Vec<char> = format.chars().collect();
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+
+        if ch == '\'' {
+            // 'literal', with '' for a quote
+            if chars.get(i + 1) == Some(&'\'') {
+                output.push('\'');
+                i += 2;
+                continue;
+            }
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' {
+                output.push(chars[i]);
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        let run = chars[i..].iter().take_while(|&&c| c == ch).count();
+        match token(date, ch, run, locale) {
+            Some(value) => output.push_str(&value),
+            None => output.extend(&chars[i..i + run]),
+        }
+        i += run;
+    }
+
+    output
+}
+
+fn token(date: &DateTime<FixedOffset>, ch: char, run: usize, locale: DateLocale) -> Option<String> {
+    let zh = locale == DateLocale::Zh;
+    let pad = |value: u32| if run >= 2 { format!("{:02}", value) } else { value.to_string() };
+    let month = date.month0() as usize;
+    let weekday = date.weekday().num_days_from_monday() as usize;
+    let quarter = date.month0() / 3;
+
+    Some(match (ch, run) {
+        ('y', 2) => format!("{:02}", date.year() % 100),
+        ('y', _) => date.year().to_string(),
+        ('Q', 1) => (quarter + 1).to_string(),
+        ('Q', 2) => format!("Q{}", quarter + 1),
+        ('Q', _) if zh => format!("第{}季度", QUARTERS_ZH[quarter as usize]),
+        ('Q', _) => format!("{} quarter", ["1st", "2nd", "3rd", "4th"][quarter as usize]),
+        ('M', 1 | 2) => pad(date.month()),
+        ('M', 3) if zh => format!("{}月", date.month()),
+        ('M', 3) => MONTHS_EN[month][..3].to_string(),
+        ('M', _) if zh => MONTHS_ZH[month].to_string(),
+        ('M', _) => MONTHS_EN[month].to_string(),
+        ('d', _) => pad(date.day()),
+        ('E', 1..=3) if zh => format!("周{}", WEEKDAYS_ZH[weekday]),
+        ('E', 1..=3) => WEEKDAYS_EN[weekday][..3].to_string(),
+        ('E', _) if zh => format!("星期{}", WEEKDAYS_ZH[weekday]),
+        ('E', _) => WEEKDAYS_EN[weekday].to_string(),
+        ('H', _) => pad(date.hour()),
+        ('h', _) => pad(date.hour12().1),
+        ('m', _) => pad(date.minute()),
+        ('s', _) => pad(date.second()),
+        ('a', _) => {
+            let pm = date.hour12().0;
+            match (zh, pm) {
+                (true, false) => "上午",
+                (true, true) => "下午",
+                (false, false) => "AM",
+                (false, true) => "PM",
+            }
+            .to_string()
+        }
+        _ => return None,
+    })
+}
+
+/// "just now", "5 minutes ago", "yesterday", "3 days ago"; dates more than
+/// a month back fall back to the plain date.
+fn relative(date: &DateTime<FixedOffset>, locale: DateLocale) -> String {
+    let seconds = (Utc::now() - date.with_timezone(&Utc)).num_seconds().max(0);
+    let (minutes, hours, days) = (seconds / 60, seconds / 3600, seconds / 86400);
+
+    if days > 30 {
+        return format_tokens(date, "yyyy-MM-dd", locale);
+    }
+
+    let zh = locale == DateLocale::Zh;
+    let (count, unit_zh, unit_en) = if minutes < 1 {
+        return if zh { "刚刚" } else { "just now" }.to_string();
+    } else if hours < 1 {
+        (minutes, "分钟", "minute")
+    } else if days < 1 {
+        (hours, "小时", "hour")
+    } else if days == 1 {
+        return if zh { "昨天" } else { "yesterday" }.to_string();
+    } else {
+        (days, "天", "day")
+    };
+
+    if zh {
+        format!("{} {}前", count, unit_zh)
+    } else {
+        format!("{} {}{} ago", count, unit_en, if count == 1 { "" } else { "s" })
+    }
+}
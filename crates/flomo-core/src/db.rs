@@ -0,0 +1,3974 @@
+use base64::Engine;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+use regex::Regex;
+use rusqlite::types::Value;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::AppError;
+use crate::export::{char_count, escape_html, word_count};
+use crate::links;
+use crate::search::{self, SearchExpr};
+use crate::todos::{self, Todo};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbMemo {
+    pub id: i64,
+    pub slug: String,
+    pub content: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub tags: String, // JSON string
+    pub url: String,
+    pub synced_at: String,
+    pub pinned: bool,
+    pub local: bool,
+    pub word_count: Option<i64>,
+    pub char_count: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub id: i64,
+    pub last_sync_at: Option<String>,
+    pub total_memos: i64,
+    pub status: String, // "idle", "syncing", "completed", "failed", "cancelled"
+    pub error_message: Option<String>,
+}
+
+/// One recorded sync attempt, newest first in `get_sync_history`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub id: i64,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub pages_fetched: i64,
+    pub new_count: i64,
+    pub updated_count: i64,
+    pub unchanged_count: i64,
+    pub deleted_count: i64,
+    pub result: String, // "running", "completed", "failed", "cancelled"
+    pub error_message: Option<String>,
+}
+
+/// One run of an auto-export rule, newest first in `get_export_runs`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportRun {
+    pub id: i64,
+    /// Name of the rule in the settings
+    pub rule: String,
+    /// File or folder written
+    pub path: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub result: String, // "completed", "failed"
+    /// Memos written, when the export reports it
+    pub memo_count: Option<i64>,
+    pub error_message: Option<String>,
+}
+
+/// A Flomo response, or one record of it, that couldn't be parsed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiDiagnostic {
+    pub id: i64,
+    pub recorded_at: String,
+    /// What was being fetched, e.g. "memo page"
+    pub source: String,
+    pub error: String,
+    pub raw: String,
+}
+
+/// A page of memos and the cursor continuing after it, if there are more.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoPage {
+    pub memos: Vec<crate::Memo>,
+    pub next_cursor: Option<String>,
+}
+
+/// Where the next page of a list starts. Handed to the frontend as an
+/// opaque string (see `encode`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageCursor {
+    /// Sort key of the last memo on the previous page, so the next page is
+    /// found through the index instead of by skipping rows
+    After {
+        /// ORDER BY the key belongs to
+        order: String,
+        pinned: Option<bool>,
+        value: CursorValue,
+        slug: String,
+        /// Shuffle of a random order
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<i64>,
+    },
+    /// Position in lists without a usable sort key, like fuzzy search hits
+    Offset(usize),
+    /// Last memo of a collection page, which is ordered by when memos were
+    /// added rather than by a memo column
+    Collection {
+        collection_id: i64,
+        added_at: String,
+        created_at: String,
+        id: i64,
+    },
+}
+
+/// A sort key in a cursor, typed so it compares like the column or
+/// expression it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CursorValue {
+    Integer(i64),
+    Text(String),
+}
+
+impl From<CursorValue> for Value {
+    fn from(value: CursorValue) -> Self {
+        match value {
+            CursorValue::Integer(value) => Value::Integer(value),
+            CursorValue::Text(value) => Value::Text(value),
+        }
+    }
+}
+
+impl PageCursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+    
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(cursor.trim())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| AppError::InvalidInput("Invalid page cursor".to_string()))
+    }
+    
+    /// Rows to skip for an offset cursor; no cursor starts at the top.
+    pub fn offset(cursor: Option<&Self>) -> Result<usize, AppError> {
+        match cursor {
+            None => Ok(0),
+            Some(PageCursor::Offset(offset)) => Ok(*offset),
+            Some(_) => Err(AppError::InvalidInput("Page cursor belongs to a different list".to_string())),
+        }
+    }
+}
+
+/// A previously executed search, most recent first in `get_search_history`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchHistoryEntry {
+    pub id: i64,
+    pub query: String,
+    pub searched_at: String,
+    pub result_count: i64,
+}
+
+/// A memo written while offline, waiting in the outbox to be created on Flomo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMemo {
+    pub id: i64,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    /// Failed pushes so far
+    pub attempts: i64,
+    /// Earliest time of the next automatic push
+    pub next_attempt_at: String,
+    pub last_error: Option<String>,
+}
+
+/// A destructive action taken on Flomo, newest first in `get_audit_log`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub action: String, // "delete_remote"
+    pub slug: String,
+    /// Memo content at the time, so a deleted memo can still be recovered by hand
+    pub content: Option<String>,
+    pub performed_at: String,
+}
+
+/// How a batch upsert was applied.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct UpsertStats {
+    pub new: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+impl std::ops::AddAssign for UpsertStats {
+    fn add_assign(&mut self, other: Self) {
+        self.new += other.new;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+    }
+}
+
+/// Slugs of the memos an upsert inserted or changed.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MemoChanges {
+    pub new: Vec<String>,
+    pub updated: Vec<String>,
+}
+
+impl std::ops::AddAssign for MemoChanges {
+    fn add_assign(&mut self, other: Self) {
+        self.new.extend(other.new);
+        self.updated.extend(other.updated);
+    }
+}
+
+/// Writing activity over all memos outside the trash.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WritingStats {
+    pub total_memos: i64,
+    /// Days with at least one memo
+    pub active_days: i64,
+    pub first_memo_at: Option<String>,
+    /// Words and characters over all memos, counted like `Memo::word_count`
+    pub total_words: i64,
+    pub total_chars: i64,
+    pub average_words_per_memo: f64,
+    /// Consecutive days with memos up to today, or up to yesterday if
+    /// nothing has been written yet today
+    pub current_streak: i64,
+    pub longest_streak: i64,
+    /// Averages over the days since the first memo
+    pub average_per_day: f64,
+    pub average_per_week: f64,
+    /// 0 = Sunday .. 6 = Saturday
+    pub most_active_weekday: Option<i64>,
+    /// 0..23, in the timezone memos were stored in
+    pub most_active_hour: Option<i64>,
+    /// Memo counts indexed like `most_active_weekday`
+    pub weekday_counts: Vec<i64>,
+    /// Memo counts indexed by hour
+    pub hour_counts: Vec<i64>,
+}
+
+/// What's in the database and how much room it takes, for the About page.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DbStats {
+    /// Memos outside the trash
+    pub memo_count: i64,
+    /// Distinct tags on those memos
+    pub tag_count: i64,
+    pub last_sync_at: Option<String>,
+    pub schema_version: i64,
+    /// Bytes on disk, including the write-ahead log
+    pub file_size: u64,
+}
+
+/// What `check_database` found, and fixed when asked to repair.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// Problems reported by `PRAGMA integrity_check`, after any repair;
+    /// empty when the file is sound
+    pub integrity_errors: Vec<String>,
+    /// Rows pointing at memos that no longer exist, by table
+    pub orphaned_rows: BTreeMap<String, i64>,
+    /// Attachment files linked from memos but missing on disk
+    pub missing_attachments: Vec<String>,
+    /// Files in the attachments folder no memo links to. Reported only;
+    /// repair never deletes files.
+    pub unreferenced_attachments: Vec<String>,
+    pub repaired: bool,
+    /// Orphaned rows deleted by the repair
+    pub orphans_removed: i64,
+    /// Bytes the database file shrank by after vacuuming
+    pub bytes_reclaimed: i64,
+}
+
+/// A distinct tag with how many memos use it and when it was last used.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+    pub last_used_at: Option<String>,
+}
+
+/// A named set of memos built up from multi-selections.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+    pub memo_count: i64,
+}
+
+/// A node in the nested tag hierarchy ("项目/开源/flomo").
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagNode {
+    /// Last path segment, e.g. "flomo"
+    pub name: String,
+    /// Full tag path, e.g. "项目/开源/flomo"
+    pub path: String,
+    /// Memos tagged with exactly this path
+    pub count: i64,
+    /// Distinct memos tagged with this path or any descendant
+    pub total_count: i64,
+    pub children: Vec<TagNode>,
+}
+
+/// Links to one domain across all memos outside the trash.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DomainStats {
+    pub domain: String,
+    /// Distinct URLs
+    pub link_count: i64,
+    /// Memos linking to the domain
+    pub memo_count: i64,
+    /// URLs that failed their last check
+    pub dead_count: i64,
+}
+
+/// A link that failed its last check, with the memos containing it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeadLink {
+    pub url: String,
+    pub domain: String,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub checked_at: String,
+    pub slugs: Vec<String>,
+}
+
+/// Tags as nodes, linked by how many memos use both.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagGraph {
+    pub nodes: Vec<TagCount>,
+    pub edges: Vec<TagEdge>,
+}
+
+/// Two tags used together on `weight` memos; `source` sorts before `target`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagEdge {
+    pub source: String,
+    pub target: String,
+    pub weight: i64,
+}
+
+/// A local tag rename; `pending_remote` marks it for write-back to Flomo.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagMapping {
+    pub source: String,
+    pub target: String,
+    pub created_at: String,
+    pub pending_remote: bool,
+}
+
+/// Optional predicates shared by the list and search queries.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MemoFilter {
+    /// Inclusive lower bound on `created_at`, "YYYY-MM-DD" or "YYYY-MM-DD HH:MM:SS"
+    pub from_date: Option<String>,
+    /// Inclusive upper bound on `created_at`; a bare date covers that whole day
+    pub to_date: Option<String>,
+    /// Exact tag; also matches nested tags below it ("读书" matches "读书/小说")
+    pub tag: Option<String>,
+    /// Also return memos in the local trash
+    pub include_deleted: bool,
+    /// Return only memos in the local trash
+    pub only_deleted: bool,
+    /// Return only locally pinned memos
+    pub pinned_only: bool,
+    /// Only these memos, e.g. the current multi-selection
+    pub slugs: Option<Vec<String>>,
+    /// Local tag added with `set_local_tag`; matches nested tags like `tag`
+    pub local_tag: Option<String>,
+    /// Name of a collection the memos were added to
+    pub collection: Option<String>,
+    /// Search box query, same syntax as `search_memos`
+    pub search: Option<String>,
+    /// Also match Latin search terms against the pinyin of Chinese text;
+    /// set from the app settings
+    #[serde(skip)]
+    pub pinyin: bool,
+}
+
+impl MemoFilter {
+    /// Whether nothing narrows the memos down, so every memo outside the
+    /// trash matches.
+    pub fn is_empty(&self) -> bool {
+        let blank = |value: &Option<String>| value.as_deref().is_none_or(|value| value.trim().is_empty());
+        blank(&self.from_date)
+            && blank(&self.to_date)
+            && blank(&self.tag)
+            && blank(&self.search)
+            && blank(&self.local_tag)
+            && blank(&self.collection)
+            && !self.pinned_only
+            && !self.only_deleted
+            && self.slugs.is_none()
+    }
+    
+    fn conditions(&self) -> Result<SqlConditions, AppError> {
+        let mut conditions = SqlConditions::default();
+        
+        // created_at is stored as "YYYY-MM-DD HH:MM:SS", so string comparison
+        // orders correctly and can use idx_memos_created_at
+        if let Some(from) = &self.from_date {
+            let from = parse_date_bound(from)?;
+            conditions.push("created_at >= ?", [Value::from(from.format(DATE_TIME_FORMAT).to_string())]);
+        }
+        
+        if let Some(to) = &self.to_date {
+            if let Ok(date) = NaiveDate::parse_from_str(to, "%Y-%m-%d") {
+                let next_day = date.succ_opt().unwrap_or(date).and_hms_opt(0, 0, 0).unwrap();
+                conditions.push("created_at < ?", [Value::from(next_day.format(DATE_TIME_FORMAT).to_string())]);
+            } else {
+                let to = parse_date_bound(to)?;
+                conditions.push("created_at <= ?", [Value::from(to.format(DATE_TIME_FORMAT).to_string())]);
+            }
+        }
+        
+        if let Some(tag) = &self.tag {
+            let (clause, params) = tag_clause(tag);
+            conditions.push(clause, params);
+        }
+        
+        if let Some(search) = &self.search {
+            conditions.push_search(search, self.pinyin);
+        }
+        
+        if self.pinned_only {
+            conditions.push("pinned = 1", []);
+        }
+        
+        if let Some(slugs) = &self.slugs {
+            let slugs = serde_json::to_string(slugs).unwrap_or_default();
+            conditions.push("slug IN (SELECT value FROM json_each(?))", [Value::from(slugs)]);
+        }
+        
+        if let Some(tag) = &self.local_tag {
+            let tag = normalize_tag(tag);
+            conditions.push(
+                "slug IN (SELECT slug FROM local_tags WHERE tag = ? OR (tag >= ? AND tag < ?))",
+                [Value::from(tag.to_string()), Value::from(format!("{}/", tag)), Value::from(format!("{}0", tag))],
+            );
+        }
+        
+        if let Some(name) = &self.collection {
+            conditions.push(
+                "slug IN (SELECT slug FROM collection_memos JOIN collections ON collections.id = collection_memos.collection_id WHERE collections.name = ?)",
+                [Value::from(name.trim().to_string())],
+            );
+        }
+        
+        if self.only_deleted {
+            conditions.push("deleted_at IS NOT NULL", []);
+        } else if !self.include_deleted {
+            conditions.push("deleted_at IS NULL", []);
+        }
+        
+        Ok(conditions)
+    }
+}
+
+// Aggregates (slug, tag) pairs into a tree. Intermediate paths that are never
+// used directly still get a node so every tag has a parent to hang from.
+fn build_tag_tree(pairs: &[(String, String)]) -> Vec<TagNode> {
+    let mut direct: HashMap<&str, i64> = HashMap::new();
+    let mut subtree: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+    
+    for (slug, tag) in pairs {
+        let tag = normalize_tag(tag);
+        if tag.is_empty() {
+            continue;
+        }
+        *direct.entry(tag).or_default() += 1;
+        
+        for (i, c) in tag.char_indices() {
+            if c == '/' {
+                subtree.entry(&tag[..i]).or_default().insert(slug);
+            }
+        }
+        subtree.entry(tag).or_default().insert(slug);
+    }
+    
+    fn children(
+        parent: Option<&str>,
+        direct: &HashMap<&str, i64>,
+        subtree: &BTreeMap<&str, HashSet<&str>>,
+    ) -> Vec<TagNode> {
+        subtree
+            .iter()
+            .filter(|(path, _)| path.rsplit_once('/').map(|(p, _)| p) == parent)
+            .map(|(path, slugs)| TagNode {
+                name: path.rsplit('/').next().unwrap_or(path).to_string(),
+                path: path.to_string(),
+                count: direct.get(path).copied().unwrap_or(0),
+                total_count: slugs.len() as i64,
+                children: children(Some(path), direct, subtree),
+            })
+            .collect()
+    }
+    
+    children(None, &direct, &subtree)
+}
+
+// Index of the largest count, the earliest one on ties; None if all are zero
+fn busiest(counts: &[i64]) -> Option<i64> {
+    let max = counts.iter().copied().max().filter(|&max| max > 0)?;
+    counts.iter().position(|&count| count == max).map(|index| index as i64)
+}
+
+/// Strips the leading '#' and surrounding slashes users tend to type.
+pub fn normalize_tag(tag: &str) -> &str {
+    tag.trim().trim_start_matches('#').trim_matches('/')
+}
+
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+fn parse_date_bound(value: &str) -> Result<NaiveDateTime, AppError> {
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|d| d.and_hms_opt(0, 0, 0).unwrap())
+        })
+        .map_err(|_| AppError::InvalidInput(format!("Invalid date: {}", value)))
+}
+
+// Fills content_pinyin for rows stored before the column existed
+fn backfill_pinyin(conn: &Connection) -> Result<(), AppError> {
+    let mut stmt = conn.prepare("SELECT slug, content FROM memos WHERE content_pinyin IS NULL")
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+    let rows: Vec<(String, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| AppError::Db(format!("Failed to load memos for pinyin: {}", e)))?;
+    
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+    for (slug, content) in rows {
+        tx.execute(
+            "UPDATE memos SET content_pinyin = ?1 WHERE slug = ?2",
+            params![search::pinyin_index(&content), slug],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to store pinyin: {}", e)))?;
+    }
+    
+    tx.commit()
+        .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))
+}
+
+// Fills word_count and char_count for rows stored before the columns existed
+fn backfill_counts(conn: &Connection) -> Result<(), AppError> {
+    let mut stmt = conn.prepare("SELECT slug, content FROM memos WHERE word_count IS NULL OR char_count IS NULL")
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+    let rows: Vec<(String, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| AppError::Db(format!("Failed to load memos for counting: {}", e)))?;
+    
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+    for (slug, content) in rows {
+        tx.execute(
+            "UPDATE memos SET word_count = ?1, char_count = ?2 WHERE slug = ?3",
+            params![word_count(&content) as i64, char_count(&content) as i64, slug],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to store counts: {}", e)))?;
+    }
+    
+    tx.commit()
+        .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))
+}
+
+/// Matches memos tagged `tag` or any tag nested below it.
+pub(crate) fn tag_clause(tag: &str) -> (&'static str, [Value; 3]) {
+    let tag = normalize_tag(tag);
+    // "tag/" <= t < "tag0" selects exactly the descendants, since '0'
+    // is the character right after '/', and keeps the range indexable
+    (
+        "slug IN (SELECT slug FROM memo_tags WHERE tag = ? OR (tag >= ? AND tag < ?))",
+        [
+            Value::from(tag.to_string()),
+            Value::from(format!("{}/", tag)),
+            Value::from(format!("{}0", tag)),
+        ],
+    )
+}
+
+/// WHERE clauses and their positional parameters, built up piece by piece.
+#[derive(Default)]
+struct SqlConditions {
+    clauses: Vec<String>,
+    params: Vec<Value>,
+}
+
+impl SqlConditions {
+    fn push<I: IntoIterator<Item = Value>>(&mut self, clause: &str, params: I) {
+        self.clauses.push(clause.to_string());
+        self.params.extend(params);
+    }
+    
+    /// Adds the predicate for a search box query, if it has any terms.
+    fn push_search(&mut self, query: &str, pinyin: bool) {
+        if let Some(expr) = SearchExpr::parse(query) {
+            let clause = expr.to_sql(&mut self.params, pinyin);
+            self.clauses.push(clause);
+        }
+    }
+    
+    fn where_sql(&self) -> String {
+        if self.clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", self.clauses.join(" AND "))
+        }
+    }
+}
+
+/// Distinct queries kept in `search_history`.
+const SEARCH_HISTORY_LIMIT: i64 = 100;
+/// Unparseable API responses kept in `api_diagnostics`.
+const API_DIAGNOSTICS_KEPT: i64 = 50;
+/// Bumped whenever `initialize` changes the schema; stored as the
+/// database's `user_version`.
+pub const SCHEMA_VERSION: i64 = 5;
+
+const MEMO_COLUMNS: &str =
+    "id, slug, content, created_at, updated_at, tags, url, synced_at, pinned, origin = 'local', word_count, char_count";
+
+/// `memos.origin` of memos synced from Flomo
+const ORIGIN_FLOMO: &str = "flomo";
+/// `memos.origin` of memos imported or written in the app and not on Flomo
+const ORIGIN_LOCAL: &str = "local";
+
+/// What memo lists are sorted by. Deserializes from the snake_case names
+/// the frontend sends, so unknown orders are rejected before any SQL is built.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoOrder {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    SyncedAt,
+    /// Characters in the stored text
+    ContentLength,
+    WordCount,
+    CharCount,
+    TagCount,
+    /// Shuffled, with the shuffle kept stable across pages of one list
+    Random,
+}
+
+impl MemoOrder {
+    /// SQL expression the rows are sorted by. `seed` only matters for
+    /// `Random`.
+    fn sql_key(self, seed: i64) -> String {
+        match self {
+            MemoOrder::CreatedAt => "created_at".to_string(),
+            MemoOrder::UpdatedAt => "updated_at".to_string(),
+            MemoOrder::SyncedAt => "synced_at".to_string(),
+            MemoOrder::ContentLength => "length(content)".to_string(),
+            MemoOrder::WordCount => "coalesce(word_count, 0)".to_string(),
+            MemoOrder::CharCount => "coalesce(char_count, 0)".to_string(),
+            MemoOrder::TagCount => "json_array_length(tags)".to_string(),
+            MemoOrder::Random => format!("shuffle_key(slug, {})", seed),
+        }
+    }
+}
+
+/// Sort key of `slug` in the shuffle picked by `seed`; registered as the
+/// `shuffle_key` SQL function.
+fn shuffle_key(slug: &str, seed: i64) -> i64 {
+    use std::hash::{Hash, Hasher};
+    
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    seed.hash(&mut hasher);
+    slug.hash(&mut hasher);
+    hasher.finish() as i64
+}
+
+/// Seed for a new random order.
+fn new_shuffle_seed() -> i64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    shuffle_key(&now.as_nanos().to_string(), 0)
+}
+
+// Offset-paged lists have no cursor to carry a seed, so they share one per
+// run to keep their pages from overlapping
+fn session_shuffle_seed() -> i64 {
+    static SEED: std::sync::OnceLock<i64> = std::sync::OnceLock::new();
+    *SEED.get_or_init(new_shuffle_seed)
+}
+
+fn order_direction(order_dir: &str) -> &'static str {
+    match order_dir {
+        "asc" => "ASC",
+        _ => "DESC",
+    }
+}
+
+// Ties, common when sorting by length, are broken by slug so offset pages
+// don't overlap
+fn order_clause(order_by: MemoOrder, order_dir: &str) -> String {
+    let direction = order_direction(order_dir);
+    format!("{} {}, slug {}", order_by.sql_key(session_shuffle_seed()), direction, direction)
+}
+
+/// ORDER BY for paging through memos in `order_by`/`order_dir` order
+/// (pinned memos first if asked), after adding the condition for rows past
+/// `cursor`, and the shuffle seed it uses. Ties are broken by slug so every
+/// memo has a unique position.
+fn keyset_order(
+    conditions: &mut SqlConditions,
+    order_by: MemoOrder,
+    order_dir: &str,
+    pinned_first: bool,
+    cursor: Option<&PageCursor>,
+) -> Result<(String, i64), AppError> {
+    let seed = match cursor {
+        Some(PageCursor::After { seed: Some(seed), .. }) => *seed,
+        _ if order_by == MemoOrder::Random => new_shuffle_seed(),
+        _ => 0,
+    };
+    let key = order_by.sql_key(seed);
+    let direction = order_direction(order_dir);
+    let order = format!(
+        "{}{} {}, slug {}",
+        if pinned_first { "pinned DESC, " } else { "" },
+        key,
+        direction,
+        direction
+    );
+    
+    match cursor {
+        None => {}
+        Some(PageCursor::After { order: cursor_order, pinned, value, slug, .. }) if *cursor_order == order => {
+            let cmp = if direction == "ASC" { ">" } else { "<" };
+            let after_key = format!("({0} {1} ? OR ({0} = ? AND slug {1} ?))", key, cmp);
+            let key_params = [Value::from(value.clone()), Value::from(value.clone()), Value::from(slug.clone())];
+            match pinned.filter(|_| pinned_first) {
+                Some(pinned) => conditions.push(
+                    &format!("(pinned < ? OR (pinned = ? AND {}))", after_key),
+                    [Value::from(pinned as i64), Value::from(pinned as i64)].into_iter().chain(key_params),
+                ),
+                None => conditions.push(&after_key, key_params),
+            }
+        }
+        Some(_) => return Err(AppError::InvalidInput("Page cursor belongs to a different list".to_string())),
+    }
+    Ok((order, seed))
+}
+
+/// Drops the extra row fetched past `limit` and returns the cursor after
+/// the last memo kept, or `None` when there was no extra row.
+fn next_cursor(
+    conn: &Connection,
+    memos: &mut Vec<crate::Memo>,
+    order: &str,
+    order_by: MemoOrder,
+    seed: i64,
+    pinned_first: bool,
+    limit: i64,
+) -> rusqlite::Result<Option<String>> {
+    let limit = limit.max(0) as usize;
+    if memos.len() <= limit {
+        return Ok(None);
+    }
+    memos.truncate(limit);
+    let Some(last) = memos.last() else {
+        return Ok(None);
+    };
+    // Read back from SQLite so the key compares exactly like the ORDER BY
+    let value = conn.query_row(
+        &format!("SELECT {} FROM memos WHERE slug = ?", order_by.sql_key(seed)),
+        [&last.slug],
+        |row| match row.get::<_, Value>(0)? {
+            Value::Integer(value) => Ok(CursorValue::Integer(value)),
+            Value::Text(value) => Ok(CursorValue::Text(value)),
+            _ => Ok(CursorValue::Integer(0)),
+        },
+    )?;
+    Ok(Some(PageCursor::After {
+        order: order.to_string(),
+        pinned: pinned_first.then_some(last.pinned),
+        value,
+        slug: last.slug.clone(),
+        seed: (order_by == MemoOrder::Random).then_some(seed),
+    }
+    .encode()))
+}
+
+// Stores new text for a memo along with everything derived from it
+fn update_memo_text(
+    conn: &Connection,
+    slug: &str,
+    text: &str,
+    html: &str,
+    tags_json: &str,
+    url: &str,
+) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE memos SET content = ?1, content_pinyin = ?2, content_hash = ?3, word_count = ?4, char_count = ?5 WHERE slug = ?6",
+        params![
+            text,
+            search::pinyin_index(text),
+            content_hash(text, html, tags_json, url),
+            word_count(text) as i64,
+            char_count(text) as i64,
+            slug
+        ],
+    )
+    .map_err(|e| AppError::Db(format!("Failed to update memo text: {}", e)))?;
+    replace_extracted(conn, slug, text, Some(html), &EXTRACTED_TABLES)
+}
+
+fn row_to_db_memo(row: &Row) -> rusqlite::Result<DbMemo> {
+    Ok(DbMemo {
+        id: row.get(0)?,
+        slug: row.get(1)?,
+        content: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+        tags: row.get(5)?,
+        url: row.get(6)?,
+        synced_at: row.get(7)?,
+        pinned: row.get(8)?,
+        local: row.get(9)?,
+        word_count: row.get(10)?,
+        char_count: row.get(11)?,
+    })
+}
+
+impl From<DbMemo> for crate::Memo {
+    fn from(row: DbMemo) -> Self {
+        let tags: Vec<String> = serde_json::from_str(&row.tags).unwrap_or_default();
+        crate::Memo {
+            slug: row.slug,
+            content: row.content,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            tags,
+            url: Some(row.url).filter(|url| !url.is_empty()),
+            pinned: row.pinned,
+            local: row.local,
+            content_html: None,
+            word_count: row.word_count,
+            char_count: row.char_count,
+        }
+    }
+}
+
+/// True when the file exists but lacks the plaintext SQLite header, i.e. it
+/// was written by SQLCipher and needs a passphrase to open.
+pub fn is_encrypted(db_path: &Path) -> bool {
+    use std::io::Read;
+    
+    let mut header = [0u8; 16];
+    match std::fs::File::open(db_path) {
+        Ok(mut file) => file.read_exact(&mut header).is_ok() && &header != b"SQLite format 3\0",
+        Err(_) => false,
+    }
+}
+
+pub fn encryption_supported() -> bool {
+    Connection::open_in_memory()
+        .map(|conn| ensure_sqlcipher(&conn).is_ok())
+        .unwrap_or(false)
+}
+
+/// Plain SQLite silently ignores `PRAGMA key`, so refuse to "encrypt"
+/// unless this build links SQLCipher (the `sqlcipher` cargo feature).
+fn ensure_sqlcipher(conn: &Connection) -> Result<(), AppError> {
+    let version: Option<String> = conn
+        .query_row("PRAGMA cipher_version", [], |row| row.get(0))
+        .optional()
+        .map_err(|e| AppError::Db(format!("Failed to query cipher version: {}", e)))?;
+    
+    match version {
+        Some(_) => Ok(()),
+        None => Err(AppError::InvalidInput(
+            "This build does not support database encryption".to_string(),
+        )),
+    }
+}
+
+fn content_hash(content: &str, content_html: &str, tags_json: &str, url: &str) -> String {
+    let mut context = md5::Context::new();
+    for part in [content, content_html, tags_json, url] {
+        context.consume(part.as_bytes());
+        // Separator so ("ab", "c") and ("a", "bc") hash differently
+        context.consume([0u8]);
+    }
+    format!("{:x}", context.compute())
+}
+
+fn replace_memo_tags(conn: &Connection, slug: &str, tags: &[String]) -> Result<(), AppError> {
+    conn.execute("DELETE FROM memo_tags WHERE slug = ?1", params![slug])
+        .map_err(|e| AppError::Db(format!("Failed to clear memo tags: {}", e)))?;
+    
+    let mut stmt = conn.prepare_cached("INSERT OR IGNORE INTO memo_tags (slug, tag) VALUES (?1, ?2)")
+        .map_err(|e| AppError::Db(format!("Failed to prepare tag insert: {}", e)))?;
+    for tag in tags {
+        stmt.execute(params![slug, tag])
+            .map_err(|e| AppError::Db(format!("Failed to insert memo tag: {}", e)))?;
+    }
+    
+    Ok(())
+}
+
+fn replace_memo_links(conn: &Connection, slug: &str, links: &[String]) -> Result<(), AppError> {
+    conn.execute("DELETE FROM links WHERE slug = ?1", params![slug])
+        .map_err(|e| AppError::Db(format!("Failed to clear memo links: {}", e)))?;
+    
+    let mut stmt = conn.prepare_cached("INSERT OR IGNORE INTO links (slug, url, domain) VALUES (?1, ?2, ?3)")
+        .map_err(|e| AppError::Db(format!("Failed to prepare link insert: {}", e)))?;
+    for link in links {
+        let domain = links::domain(link).unwrap_or_default();
+        stmt.execute(params![slug, link, domain])
+            .map_err(|e| AppError::Db(format!("Failed to insert memo link: {}", e)))?;
+    }
+    
+    Ok(())
+}
+
+fn replace_memo_references(conn: &Connection, slug: &str, references: &[String]) -> Result<(), AppError> {
+    conn.execute("DELETE FROM backlinks WHERE source_slug = ?1", params![slug])
+        .map_err(|e| AppError::Db(format!("Failed to clear memo references: {}", e)))?;
+    
+    let mut stmt = conn.prepare_cached("INSERT OR IGNORE INTO backlinks (source_slug, target) VALUES (?1, ?2)")
+        .map_err(|e| AppError::Db(format!("Failed to prepare reference insert: {}", e)))?;
+    for reference in references.iter().filter(|reference| *reference != slug) {
+        stmt.execute(params![slug, reference])
+            .map_err(|e| AppError::Db(format!("Failed to insert memo reference: {}", e)))?;
+    }
+    
+    Ok(())
+}
+
+fn replace_memo_todos(conn: &Connection, slug: &str, todos: &[(String, bool)]) -> Result<(), AppError> {
+    conn.execute("DELETE FROM todos WHERE slug = ?1", params![slug])
+        .map_err(|e| AppError::Db(format!("Failed to clear memo todos: {}", e)))?;
+    
+    let mut stmt = conn.prepare_cached("INSERT INTO todos (slug, position, text, done) VALUES (?1, ?2, ?3, ?4)")
+        .map_err(|e| AppError::Db(format!("Failed to prepare todo insert: {}", e)))?;
+    for (position, (text, done)) in todos.iter().enumerate() {
+        stmt.execute(params![slug, position as i64, text, done])
+            .map_err(|e| AppError::Db(format!("Failed to insert memo todo: {}", e)))?;
+    }
+    
+    Ok(())
+}
+
+/// Tables derived from memo content, rebuilt whenever a memo is stored.
+const EXTRACTED_TABLES: [&str; 3] = ["links", "backlinks", "todos"];
+
+// Refreshes the rows `tables` hold for one memo
+fn replace_extracted(
+    conn: &Connection,
+    slug: &str,
+    content: &str,
+    content_html: Option<&str>,
+    tables: &[&str],
+) -> Result<(), AppError> {
+    for table in tables {
+        match *table {
+            "links" => replace_memo_links(conn, slug, &links::extract_links(content, content_html))?,
+            "backlinks" => replace_memo_references(conn, slug, &links::memo_references(content, content_html))?,
+            "todos" => replace_memo_todos(conn, slug, &todos::extract_todos(content))?,
+            _ => {}
+        }
+    }
+    
+    Ok(())
+}
+
+// Fills extracted tables that didn't exist when the memos were stored
+fn backfill_extracted(conn: &Connection, tables: &[&str]) -> Result<(), AppError> {
+    let mut stmt = conn.prepare("SELECT slug, content, content_html FROM memos")
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+    let rows: Vec<(String, String, Option<String>)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| AppError::Db(format!("Failed to load memos for {}: {}", tables.join(", "), e)))?;
+    
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+    for (slug, content, content_html) in rows {
+        replace_extracted(&tx, &slug, &content, content_html.as_deref(), tables)?;
+    }
+    
+    tx.commit()
+        .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, AppError> {
+    conn.prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")
+        .and_then(|mut stmt| stmt.exists(params![table]))
+        .map_err(|e| AppError::Db(format!("Failed to inspect schema: {}", e)))
+}
+
+// Adds a column to an existing table unless an earlier version already did
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<(), AppError> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))
+        .and_then(|mut stmt| stmt.exists(params![column]))
+        .map_err(|e| AppError::Db(format!("Failed to inspect {} table: {}", table, e)))?;
+    
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])
+            .map_err(|e| AppError::Db(format!("Failed to add {}.{}: {}", table, column, e)))?;
+    }
+    
+    Ok(())
+}
+
+fn load_tag_mappings(conn: &Connection) -> Result<Vec<(String, String)>, AppError> {
+    let mut stmt = conn.prepare_cached("SELECT source, target FROM tag_mappings")
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+    
+    let mappings = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to load tag mappings: {}", e)))?;
+    
+    Ok(mappings)
+}
+
+// Rewrites `tag` if it is `source` or nested below it
+fn rename_tag_path(tag: &str, source: &str, target: &str) -> Option<String> {
+    if tag == source {
+        Some(target.to_string())
+    } else {
+        tag.strip_prefix(source)
+            .filter(|rest| rest.starts_with('/'))
+            .map(|rest| format!("{}{}", target, rest))
+    }
+}
+
+// The most specific mapping wins, so "a/b -> x" beats "a -> y" for "a/b/c"
+fn map_tag(tag: &str, mappings: &[(String, String)]) -> String {
+    mappings
+        .iter()
+        .filter_map(|(source, target)| {
+            rename_tag_path(tag, source, target).map(|renamed| (source.len(), renamed))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, renamed)| renamed)
+        .unwrap_or_else(|| tag.to_string())
+}
+
+fn apply_tag_mappings(tags: &[String], mappings: &[(String, String)]) -> Vec<String> {
+    let mut mapped: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = map_tag(tag, mappings);
+        if !mapped.contains(&tag) {
+            mapped.push(tag);
+        }
+    }
+    mapped
+}
+
+// Runs a query selecting MEMO_COLUMNS and converts the rows
+fn query_memos(conn: &Connection, sql: &str, params: Vec<Value>) -> rusqlite::Result<Vec<crate::Memo>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params_from_iter(params), row_to_db_memo)?;
+    
+    rows.map(|row| row.map(crate::Memo::from)).collect()
+}
+
+/// Pages kept by the query cache.
+const QUERY_CACHE_SIZE: usize = 64;
+
+/// Larger pages (exports, "load everything") are read straight from SQLite
+/// rather than held in the cache.
+const QUERY_CACHE_MAX_LIMIT: i64 = 100;
+
+// Recently fetched list and search pages, most recent first, so paging
+// back and forth or repeating a search doesn't go back to SQLite
+#[derive(Default)]
+struct QueryCache {
+    pages: VecDeque<(String, MemoPage)>,
+    /// Bumped whenever memos change
+    generation: u64,
+}
+
+impl QueryCache {
+    fn get(&mut self, key: &str) -> Option<MemoPage> {
+        let index = self.pages.iter().position(|(cached, _)| cached == key)?;
+        let entry = self.pages.remove(index)?;
+        let page = entry.1.clone();
+        self.pages.push_front(entry);
+        Some(page)
+    }
+    
+    /// Caches `page` unless memos changed since `generation`, which was read
+    /// before the page was queried.
+    fn insert(&mut self, generation: u64, key: String, page: MemoPage) {
+        if generation != self.generation {
+            return;
+        }
+        self.pages.push_front((key, page));
+        self.pages.truncate(QUERY_CACHE_SIZE);
+    }
+    
+    fn invalidate(&mut self) {
+        self.pages.clear();
+        self.generation += 1;
+    }
+}
+
+/// Idle read connections kept open for reuse.
+const MAX_IDLE_READERS: usize = 4;
+
+// Read-only connections to the same file; WAL lets them read while the
+// main connection writes
+struct ReadPool {
+    path: PathBuf,
+    passphrase: Option<String>,
+    /// Each with the `data_version` it last reported, if any
+    idle: Mutex<Vec<(Connection, Option<i64>)>>,
+}
+
+// A read connection borrowed from the pool, returned to it when dropped
+struct PooledReader<'a> {
+    conn: Option<Connection>,
+    data_version: Option<i64>,
+    pool: &'a ReadPool,
+}
+
+impl PooledReader<'_> {
+    /// Whether another connection, such as the CLI's, may have committed
+    /// since this one last checked.
+    fn data_changed(&mut self) -> Result<bool, AppError> {
+        let version = self.query_row("PRAGMA data_version", [], |row| row.get::<_, i64>(0))
+            .map_err(|e| AppError::Db(format!("Failed to read data version: {}", e)))?;
+        Ok(self.data_version.replace(version) != Some(version))
+    }
+}
+
+impl Deref for PooledReader<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledReader<'_> {
+    fn drop(&mut self) {
+        let mut idle = self.pool.idle.lock().unwrap();
+        if let Some(conn) = self.conn.take().filter(|_| idle.len() < MAX_IDLE_READERS) {
+            idle.push((conn, self.data_version));
+        }
+    }
+}
+
+// Opens and configures a connection, keying it first for encrypted databases
+fn connect(db_path: &Path, passphrase: Option<&str>) -> Result<Connection, AppError> {
+    let conn = Connection::open(db_path)
+        .map_err(|e| AppError::Db(format!("Failed to connect to database: {}", e)))?;
+    
+    if let Some(passphrase) = passphrase {
+        ensure_sqlcipher(&conn)?;
+        conn.pragma_update(None, "key", passphrase)
+            .map_err(|e| AppError::Db(format!("Failed to set database key: {}", e)))?;
+        
+        // The key is only checked on first read
+        conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+            .map_err(|_| AppError::InvalidInput("Incorrect database passphrase".to_string()))?;
+    }
+
+    // WAL lets readers proceed while a long bulk upsert holds the write lock,
+    // and busy_timeout makes remaining contention wait instead of failing
+    conn.busy_timeout(std::time::Duration::from_secs(5))
+        .map_err(|e| AppError::Db(format!("Failed to set busy timeout: {}", e)))?;
+    conn.execute_batch(
+        r#"
+        PRAGMA journal_mode = WAL;
+        PRAGMA synchronous = NORMAL;
+        PRAGMA cache_size = -16000;
+        PRAGMA temp_store = MEMORY;
+        PRAGMA foreign_keys = ON;
+        "#,
+    )
+    .map_err(|e| AppError::Db(format!("Failed to configure database: {}", e)))?;
+    
+    conn.create_scalar_function(
+        "pinyin_match",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let index: Option<String> = ctx.get(0)?;
+            let query: String = ctx.get(1)?;
+            Ok(index.is_some_and(|index| search::pinyin_matches(&index, &query)))
+        },
+    )
+    .and_then(|_| {
+        conn.create_scalar_function(
+            "shuffle_key",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| Ok(shuffle_key(&ctx.get::<String>(0)?, ctx.get(1)?)),
+        )
+    })
+    .map_err(|e| AppError::Db(format!("Failed to register search functions: {}", e)))?;
+    
+    Ok(conn)
+}
+
+#[derive(Clone)]
+pub struct Database {
+    /// The connection all writes go through
+    conn: Arc<Mutex<Connection>>,
+    readers: Arc<ReadPool>,
+    query_cache: Arc<Mutex<QueryCache>>,
+}
+
+impl Database {
+    pub fn new(db_path: &Path) -> Result<Self, AppError> {
+        Self::open(db_path, None)
+    }
+    
+    /// Opens the database, keying it with `passphrase` first when it is
+    /// SQLCipher-encrypted.
+    pub fn open(db_path: &Path, passphrase: Option<&str>) -> Result<Self, AppError> {
+        let conn = connect(db_path, passphrase)?;
+        
+        let db = Self { 
+            conn: Arc::new(Mutex::new(conn)),
+            readers: Arc::new(ReadPool {
+                path: db_path.to_path_buf(),
+                passphrase: passphrase.map(str::to_string),
+                idle: Mutex::new(Vec::new()),
+            }),
+            query_cache: Arc::default(),
+        };
+        db.initialize()?;
+        
+        Ok(db)
+    }
+    
+    // A read-only connection for queries, so lists and searches don't wait
+    // behind a long write transaction
+    fn reader(&self) -> Result<PooledReader<'_>, AppError> {
+        let idle = self.readers.idle.lock().unwrap().pop();
+        let (conn, data_version) = match idle {
+            Some(idle) => idle,
+            None => {
+                let conn = connect(&self.readers.path, self.readers.passphrase.as_deref())?;
+                conn.pragma_update(None, "query_only", true)
+                    .map_err(|e| AppError::Db(format!("Failed to configure database: {}", e)))?;
+                (conn, None)
+            }
+        };
+        Ok(PooledReader { conn: Some(conn), data_version, pool: &self.readers })
+    }
+    
+    // Looks `key` up in the query cache, first clearing it if `conn` sees
+    // commits from another connection. Returns the cached page, if any, and
+    // the generation to cache a freshly queried page under.
+    fn cached_page(&self, conn: &mut PooledReader<'_>, key: &str, reuse: bool) -> Result<(Option<MemoPage>, u64), AppError> {
+        let changed = conn.data_changed()?;
+        let mut cache = self.query_cache.lock().unwrap();
+        if changed {
+            cache.invalidate();
+        }
+        Ok((cache.get(key).filter(|_| reuse), cache.generation))
+    }
+    
+    /// Writes an encrypted copy of the whole database to `dest`.
+    pub fn export_encrypted(&self, dest: &Path, passphrase: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        ensure_sqlcipher(&conn)?;
+        
+        let dest = dest.to_string_lossy();
+        conn.execute("ATTACH DATABASE ?1 AS encrypted KEY ?2", params![dest, passphrase])
+            .map_err(|e| AppError::Db(format!("Failed to create encrypted database: {}", e)))?;
+        let exported = conn
+            .query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+            .map_err(|e| AppError::Db(format!("Failed to export encrypted database: {}", e)));
+        conn.execute("DETACH DATABASE encrypted", [])
+            .map_err(|e| AppError::Db(format!("Failed to detach encrypted database: {}", e)))?;
+        
+        exported
+    }
+    
+    /// Writes a compacted, consistent copy of the database to `dest`, which
+    /// must not exist yet. Safe while other connections keep writing.
+    pub fn snapshot_to(&self, dest: &Path) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("VACUUM INTO ?1", params![dest.to_string_lossy()])
+            .map_err(|e| AppError::Db(format!("Failed to copy database: {}", e)))?;
+        Ok(())
+    }
+    
+    /// Runs a database operation on the blocking thread pool so async commands
+    /// don't stall the Tauri runtime while SQLite is busy.
+    pub async fn run<F, T>(&self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&Database) -> Result<T, AppError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .map_err(|e| AppError::Internal(format!("Database task failed: {}", e)))?
+    }
+    
+    /// How many clones of this handle are alive, this one included.
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.conn)
+    }
+    
+    // Forgets cached pages once memos have changed
+    fn invalidate_query_cache(&self) {
+        self.query_cache.lock().unwrap().invalidate();
+    }
+    
+    fn initialize(&self) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        
+        // Create memos table
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS memos (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                slug TEXT NOT NULL UNIQUE,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                tags TEXT NOT NULL DEFAULT '[]',
+                url TEXT NOT NULL,
+                synced_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create memos table: {}", e)))?;
+        
+        // Create sync_status table
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS sync_status (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                last_sync_at TEXT,
+                total_memos INTEGER DEFAULT 0,
+                status TEXT DEFAULT 'idle',
+                error_message TEXT
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create sync_status table: {}", e)))?;
+        
+        // Append-only log of every sync run
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS sync_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                finished_at TEXT,
+                pages_fetched INTEGER NOT NULL DEFAULT 0,
+                new_count INTEGER NOT NULL DEFAULT 0,
+                updated_count INTEGER NOT NULL DEFAULT 0,
+                unchanged_count INTEGER NOT NULL DEFAULT 0,
+                deleted_count INTEGER NOT NULL DEFAULT 0, -- synced memos no longer on Flomo
+                result TEXT NOT NULL DEFAULT 'running',
+                error_message TEXT
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create sync_runs table: {}", e)))?;
+        
+        // Initialize sync_status if it doesn't exist
+        conn.execute(
+            r#"
+            INSERT OR IGNORE INTO sync_status (id, status) VALUES (1, 'idle')
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to initialize sync_status: {}", e)))?;
+        
+        // Create indexes
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_memos_created_at ON memos(created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create index: {}", e)))?;
+        
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_memos_updated_at ON memos(updated_at)",
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create index: {}", e)))?;
+        
+        // Normalized tags, one row per (memo, tag), so tag lookups can use an index
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS memo_tags (
+                slug TEXT NOT NULL REFERENCES memos(slug) ON DELETE CASCADE,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (slug, tag)
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create memo_tags table: {}", e)))?;
+        
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_memo_tags_tag ON memo_tags(tag)",
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create index: {}", e)))?;
+        
+        // Local tag renames/merges, applied to remote tags on every upsert
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS tag_mappings (
+                source TEXT PRIMARY KEY,
+                target TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                pending_remote INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create tag_mappings table: {}", e)))?;
+        
+        // Backfill databases created before memo_tags existed
+        conn.execute(
+            r#"
+            INSERT OR IGNORE INTO memo_tags (slug, tag)
+            SELECT memos.slug, json_each.value FROM memos, json_each(memos.tags)
+            WHERE NOT EXISTS (SELECT 1 FROM memo_tags)
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to backfill memo_tags: {}", e)))?;
+        
+        // Local trash: memos with deleted_at set are hidden until restored or purged
+        ensure_column(&conn, "memos", "deleted_at", "TEXT")?;
+        
+        // Local-only pinning; Flomo's API doesn't expose its own pin state
+        ensure_column(&conn, "memos", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+        
+        // Hash of the stored content/tags/url, used to skip no-op upserts
+        ensure_column(&conn, "memos", "content_hash", "TEXT")?;
+        
+        // Raw API HTML, kept for exports that want the original markup
+        ensure_column(&conn, "memos", "content_html", "TEXT")?;
+        
+        // Where a memo came from: "flomo" when synced, "local" when imported
+        ensure_column(&conn, "memos", "origin", "TEXT NOT NULL DEFAULT 'flomo'")?;
+        
+        // Pinyin of the content so Latin queries can find Chinese text
+        ensure_column(&conn, "memos", "content_pinyin", "TEXT")?;
+        backfill_pinyin(&conn)?;
+        
+        // Counted on the way in so lists can sort by length and stats can sum it
+        ensure_column(&conn, "memos", "word_count", "INTEGER")?;
+        ensure_column(&conn, "memos", "char_count", "INTEGER")?;
+        backfill_counts(&conn)?;
+        
+        // Also narrows the lookup of memos with the same text
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_memos_char_count ON memos(char_count)", [])
+            .map_err(|e| AppError::Db(format!("Failed to create index: {}", e)))?;
+        
+        // Purged slugs are remembered so the next sync doesn't bring them back
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS purged_memos (
+                slug TEXT PRIMARY KEY,
+                purged_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create purged_memos table: {}", e)))?;
+        
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS search_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                query TEXT NOT NULL UNIQUE,
+                searched_at TEXT NOT NULL,
+                result_count INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create search_history table: {}", e)))?;
+        
+        // Remote deletions and other actions that can't be undone from the app
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                action TEXT NOT NULL,
+                slug TEXT NOT NULL,
+                content TEXT,
+                performed_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create audit_log table: {}", e)))?;
+        
+        // Outbox of memos composed offline, pushed when Flomo is reachable again
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS pending_memos (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                content TEXT NOT NULL,
+                tags TEXT NOT NULL DEFAULT '[]',
+                created_at TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                next_attempt_at TEXT NOT NULL,
+                last_error TEXT
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create pending_memos table: {}", e)))?;
+        
+        let mut missing = Vec::new();
+        for table in EXTRACTED_TABLES {
+            if !table_exists(&conn, table)? {
+                missing.push(table);
+            }
+        }
+        
+        // URLs found in memo content, refreshed on every upsert
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS links (
+                slug TEXT NOT NULL REFERENCES memos(slug) ON DELETE CASCADE,
+                url TEXT NOT NULL,
+                domain TEXT NOT NULL,
+                PRIMARY KEY (slug, url)
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create links table: {}", e)))?;
+        
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_links_domain ON links(domain)", [])
+            .map_err(|e| AppError::Db(format!("Failed to create index: {}", e)))?;
+        
+        // References between memos, stored as written and resolved when queried
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS backlinks (
+                source_slug TEXT NOT NULL REFERENCES memos(slug) ON DELETE CASCADE,
+                target TEXT NOT NULL,
+                PRIMARY KEY (source_slug, target)
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create backlinks table: {}", e)))?;
+        
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_backlinks_target ON backlinks(target)", [])
+            .map_err(|e| AppError::Db(format!("Failed to create index: {}", e)))?;
+        
+        // Checklist items, one row per checkbox line
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS todos (
+                slug TEXT NOT NULL REFERENCES memos(slug) ON DELETE CASCADE,
+                position INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                done INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (slug, position)
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create todos table: {}", e)))?;
+        
+        if !missing.is_empty() {
+            backfill_extracted(&conn, &missing)?;
+        }
+        
+        // Last dead-link check per URL; kept apart from links so re-syncs don't lose it
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS link_checks (
+                url TEXT PRIMARY KEY,
+                status INTEGER,
+                ok INTEGER NOT NULL,
+                error TEXT,
+                checked_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create link_checks table: {}", e)))?;
+        
+        // Memo versions already sent to Readwise, so pushes only send what's new
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS readwise_pushes (
+                slug TEXT PRIMARY KEY,
+                updated_at TEXT NOT NULL,
+                pushed_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create readwise_pushes table: {}", e)))?;
+        
+        // Weekly digests sent, so a restart doesn't send the same week twice
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS digests (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                sent_at TEXT NOT NULL,
+                memo_count INTEGER NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create digests table: {}", e)))?;
+        
+        // API responses that couldn't be parsed, kept for bug reports
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS api_diagnostics (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                recorded_at TEXT NOT NULL,
+                source TEXT NOT NULL,
+                error TEXT NOT NULL,
+                raw TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create api_diagnostics table: {}", e)))?;
+        
+        // Memos merged away as duplicates, kept after they're purged from the trash
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS memo_duplicates (
+                slug TEXT PRIMARY KEY,
+                duplicate_of TEXT NOT NULL,
+                merged_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create memo_duplicates table: {}", e)))?;
+        
+        // Exports run after syncs by the auto-export rules
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS export_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                rule TEXT NOT NULL,
+                path TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                finished_at TEXT NOT NULL,
+                result TEXT NOT NULL,
+                memo_count INTEGER,
+                error_message TEXT
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create export_runs table: {}", e)))?;
+        
+        // Tags added in the app only; kept apart from memo_tags, which syncs rewrite
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS local_tags (
+                slug TEXT NOT NULL REFERENCES memos(slug) ON DELETE CASCADE,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (slug, tag)
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create local_tags table: {}", e)))?;
+        
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_local_tags_tag ON local_tags(tag)", [])
+            .map_err(|e| AppError::Db(format!("Failed to create index: {}", e)))?;
+        
+        // Named sets of memos gathered from multi-selections
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS collections (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create collections table: {}", e)))?;
+        
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS collection_memos (
+                collection_id INTEGER NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+                slug TEXT NOT NULL REFERENCES memos(slug) ON DELETE CASCADE,
+                added_at TEXT NOT NULL,
+                PRIMARY KEY (collection_id, slug)
+            )
+            "#,
+            [],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to create collection_memos table: {}", e)))?;
+        
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(|e| AppError::Db(format!("Failed to record schema version: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    pub fn upsert_memo(&self, memo: &crate::Memo) -> Result<(), AppError> {
+        self.bulk_upsert_memos(std::slice::from_ref(memo)).map(|_| ())
+    }
+    
+    /// Inserts or updates memos, skipping rows whose content hash and
+    /// updated_at are unchanged so re-syncs don't rewrite the whole table.
+    pub fn bulk_upsert_memos(&self, memos: &[crate::Memo]) -> Result<UpsertStats, AppError> {
+        self.upsert_memos(memos, ORIGIN_FLOMO).map(|(stats, _)| stats)
+    }
+    
+    /// Like `bulk_upsert_memos`, also naming the memos that were written.
+    pub fn bulk_upsert_memos_tracked(&self, memos: &[crate::Memo]) -> Result<(UpsertStats, MemoChanges), AppError> {
+        self.upsert_memos(memos, ORIGIN_FLOMO)
+    }
+    
+    /// Stores memos that only exist in this app, such as imported files.
+    pub fn import_local_memos(&self, memos: &[crate::Memo]) -> Result<UpsertStats, AppError> {
+        self.upsert_memos(memos, ORIGIN_LOCAL).map(|(stats, _)| stats)
+    }
+    
+    fn upsert_memos(&self, memos: &[crate::Memo], origin: &str) -> Result<(UpsertStats, MemoChanges), AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        // Local renames must survive re-syncs, so remote tags are remapped on the way in
+        let mappings = load_tag_mappings(&tx)?;
+        
+        let mut purged_stmt = tx.prepare("SELECT 1 FROM purged_memos WHERE slug = ?1")
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        let mut existing_stmt = tx.prepare("SELECT content_hash, updated_at FROM memos WHERE slug = ?1")
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        let mut stats = UpsertStats::default();
+        let mut changes = MemoChanges::default();
+        
+        for memo in memos {
+            let purged = purged_stmt.exists(params![&memo.slug])
+                .map_err(|e| AppError::Db(format!("Failed to check purged memos: {}", e)))?;
+            if purged {
+                continue;
+            }
+            
+            let tags = apply_tag_mappings(&memo.tags, &mappings);
+            let tags_json = serde_json::to_string(&tags)
+                .map_err(|e| AppError::Parse(format!("Failed to serialize tags: {}", e)))?;
+            
+            let url = memo.url.as_ref().unwrap_or(&String::new()).clone();
+            let synced_at = Utc::now().to_rfc3339();
+            let content_hash = content_hash(
+                &memo.content,
+                memo.content_html.as_deref().unwrap_or(""),
+                &tags_json,
+                &url,
+            );
+            
+            let existing: Option<(Option<String>, String)> = existing_stmt
+                .query_row(params![&memo.slug], |row| Ok((row.get(0)?, row.get(1)?)))
+                .optional()
+                .map_err(|e| AppError::Db(format!("Failed to look up memo: {}", e)))?;
+            
+            match &existing {
+                Some((hash, updated_at))
+                    if hash.as_deref() == Some(content_hash.as_str()) && *updated_at == memo.updated_at =>
+                {
+                    stats.unchanged += 1;
+                    continue;
+                }
+                Some(_) => {
+                    stats.updated += 1;
+                    changes.updated.push(memo.slug.clone());
+                }
+                None => {
+                    stats.new += 1;
+                    changes.new.push(memo.slug.clone());
+                }
+            }
+            
+            tx.execute(
+                r#"
+                INSERT INTO memos (slug, content, created_at, updated_at, tags, url, synced_at, content_hash, content_html, origin, content_pinyin, word_count, char_count)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                ON CONFLICT(slug) DO UPDATE SET
+                    content = excluded.content,
+                    content_pinyin = excluded.content_pinyin,
+                    word_count = excluded.word_count,
+                    char_count = excluded.char_count,
+                    content_html = COALESCE(excluded.content_html, content_html),
+                    updated_at = excluded.updated_at,
+                    tags = excluded.tags,
+                    url = excluded.url,
+                    synced_at = excluded.synced_at,
+                    content_hash = excluded.content_hash
+                "#,
+                params![
+                    &memo.slug,
+                    &memo.content,
+                    &memo.created_at,
+                    &memo.updated_at,
+                    &tags_json,
+                    &url,
+                    &synced_at,
+                    &content_hash,
+                    &memo.content_html,
+                    origin,
+                    search::pinyin_index(&memo.content),
+                    word_count(&memo.content) as i64,
+                    char_count(&memo.content) as i64
+                ],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to upsert memo in transaction: {}", e)))?;
+            
+            replace_memo_tags(&tx, &memo.slug, &tags)?;
+            replace_extracted(&tx, &memo.slug, &memo.content, memo.content_html.as_deref(), &EXTRACTED_TABLES)?;
+        }
+        
+        drop(purged_stmt);
+        drop(existing_stmt);
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok((stats, changes))
+    }
+    
+    /// Moves a memo to the local trash. Flomo itself is not touched.
+    pub fn delete_memo_local(&self, slug: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE memos SET deleted_at = COALESCE(deleted_at, ?1) WHERE slug = ?2",
+            params![Utc::now().to_rfc3339(), slug],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to delete memo: {}", e)))?;
+        
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Memo {}", slug)));
+        }
+        
+        self.invalidate_query_cache();
+        Ok(())
+    }
+    
+    pub fn restore_memo(&self, slug: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute("UPDATE memos SET deleted_at = NULL WHERE slug = ?1", params![slug])
+            .map_err(|e| AppError::Db(format!("Failed to restore memo: {}", e)))?;
+        
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Memo {}", slug)));
+        }
+        
+        self.invalidate_query_cache();
+        Ok(())
+    }
+    
+    /// Moves `duplicates` to the trash and records them as duplicates of
+    /// `keep`, which is pinned if any of them was. Returns how many were
+    /// merged.
+    pub fn merge_duplicates(&self, keep: &str, duplicates: &[String]) -> Result<usize, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let kept = tx.query_row("SELECT 1 FROM memos WHERE slug = ?1 AND deleted_at IS NULL", [keep], |_| Ok(()))
+            .optional()
+            .map_err(|e| AppError::Db(format!("Failed to fetch memo: {}", e)))?;
+        if kept.is_none() {
+            return Err(AppError::NotFound(format!("Memo {}", keep)));
+        }
+        
+        let now = Utc::now().to_rfc3339();
+        let mut merged = 0;
+        let mut pinned = false;
+        for slug in duplicates.iter().filter(|slug| slug.as_str() != keep) {
+            let was_pinned: Option<bool> = tx.query_row("SELECT pinned FROM memos WHERE slug = ?1", [slug], |row| row.get(0))
+                .optional()
+                .map_err(|e| AppError::Db(format!("Failed to fetch memo: {}", e)))?;
+            let Some(was_pinned) = was_pinned else {
+                return Err(AppError::NotFound(format!("Memo {}", slug)));
+            };
+            pinned |= was_pinned;
+            
+            tx.execute(
+                "UPDATE memos SET deleted_at = COALESCE(deleted_at, ?1), pinned = 0 WHERE slug = ?2",
+                params![&now, slug],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to delete memo: {}", e)))?;
+            tx.execute(
+                "INSERT OR REPLACE INTO memo_duplicates (slug, duplicate_of, merged_at) VALUES (?1, ?2, ?3)",
+                params![slug, keep, &now],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to record duplicate: {}", e)))?;
+            merged += 1;
+        }
+        if pinned {
+            tx.execute("UPDATE memos SET pinned = 1 WHERE slug = ?1", [keep])
+                .map_err(|e| AppError::Db(format!("Failed to update pin: {}", e)))?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(merged)
+    }
+    
+    pub fn set_pinned(&self, slug: &str, pinned: bool) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute("UPDATE memos SET pinned = ?1 WHERE slug = ?2", params![pinned, slug])
+            .map_err(|e| AppError::Db(format!("Failed to update pin: {}", e)))?;
+        
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Memo {}", slug)));
+        }
+        
+        self.invalidate_query_cache();
+        Ok(())
+    }
+    
+    /// Pins or unpins all of `slugs` in one transaction. Slugs that don't
+    /// exist are skipped; returns how many memos were updated.
+    pub fn set_pinned_bulk(&self, slugs: &[String], pinned: bool) -> Result<usize, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let mut updated = 0;
+        for slug in slugs {
+            updated += tx.execute("UPDATE memos SET pinned = ?1 WHERE slug = ?2", params![pinned, slug])
+                .map_err(|e| AppError::Db(format!("Failed to update pin: {}", e)))?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(updated)
+    }
+    
+    /// Adds or removes a local tag on all of `slugs` in one transaction.
+    /// Local tags stay in the app and survive syncs. Returns how many memos
+    /// changed.
+    pub fn set_local_tag(&self, slugs: &[String], tag: &str, tagged: bool) -> Result<usize, AppError> {
+        let tag = normalize_tag(tag);
+        if tag.is_empty() {
+            return Err(AppError::InvalidInput("Tag is empty".to_string()));
+        }
+        
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let mut changed = 0;
+        for slug in slugs {
+            changed += if tagged {
+                tx.execute(
+                    "INSERT OR IGNORE INTO local_tags (slug, tag) SELECT slug, ?2 FROM memos WHERE slug = ?1",
+                    params![slug, tag],
+                )
+            } else {
+                tx.execute("DELETE FROM local_tags WHERE slug = ?1 AND tag = ?2", params![slug, tag])
+            }
+            .map_err(|e| AppError::Db(format!("Failed to update local tag: {}", e)))?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(changed)
+    }
+    
+    /// Local tags in use, with how many memos outside the trash carry each.
+    pub fn get_local_tags(&self) -> Result<Vec<TagCount>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT local_tags.tag, COUNT(*), MAX(memos.created_at)
+            FROM local_tags JOIN memos ON memos.slug = local_tags.slug
+            WHERE memos.deleted_at IS NULL
+            GROUP BY local_tags.tag
+            ORDER BY COUNT(*) DESC, local_tags.tag ASC
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let tags = stmt.query_map([], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                count: row.get(1)?,
+                last_used_at: row.get(2)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch local tags: {}", e)))?;
+        
+        Ok(tags)
+    }
+    
+    /// Creates an empty collection; fails if the name is already taken.
+    pub fn create_collection(&self, name: &str) -> Result<Collection, AppError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::InvalidInput("Collection name is empty".to_string()));
+        }
+        
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().to_rfc3339();
+        let created = conn.execute("INSERT OR IGNORE INTO collections (name, created_at) VALUES (?1, ?2)", params![name, &now])
+            .map_err(|e| AppError::Db(format!("Failed to create collection: {}", e)))?;
+        if created == 0 {
+            return Err(AppError::InvalidInput(format!("Collection {} already exists", name)));
+        }
+        
+        Ok(Collection {
+            id: conn.last_insert_rowid(),
+            name: name.to_string(),
+            created_at: now,
+            memo_count: 0,
+        })
+    }
+    
+    /// Renames the collection `name` to `new_name`, keeping its memos.
+    pub fn rename_collection(&self, name: &str, new_name: &str) -> Result<(), AppError> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(AppError::InvalidInput("Collection name is empty".to_string()));
+        }
+        
+        let conn = self.conn.lock().unwrap();
+        let taken = conn.query_row("SELECT 1 FROM collections WHERE name = ?1", [new_name], |_| Ok(()))
+            .optional()
+            .map_err(|e| AppError::Db(format!("Failed to fetch collection: {}", e)))?;
+        if taken.is_some() && new_name != name.trim() {
+            return Err(AppError::InvalidInput(format!("Collection {} already exists", new_name)));
+        }
+        
+        let renamed = conn.execute("UPDATE collections SET name = ?1 WHERE name = ?2", params![new_name, name.trim()])
+            .map_err(|e| AppError::Db(format!("Failed to rename collection: {}", e)))?;
+        if renamed == 0 {
+            return Err(AppError::NotFound(format!("Collection {}", name)));
+        }
+        
+        self.invalidate_query_cache();
+        Ok(())
+    }
+    
+    /// Adds all of `slugs` to the collection `name`, creating it if needed,
+    /// in one transaction. Returns how many memos were newly added.
+    pub fn add_to_collection(&self, name: &str, slugs: &[String]) -> Result<usize, AppError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::InvalidInput("Collection name is empty".to_string()));
+        }
+        
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let now = Utc::now().to_rfc3339();
+        tx.execute("INSERT OR IGNORE INTO collections (name, created_at) VALUES (?1, ?2)", params![name, &now])
+            .map_err(|e| AppError::Db(format!("Failed to create collection: {}", e)))?;
+        let id: i64 = tx.query_row("SELECT id FROM collections WHERE name = ?1", [name], |row| row.get(0))
+            .map_err(|e| AppError::Db(format!("Failed to fetch collection: {}", e)))?;
+        
+        let mut added = 0;
+        for slug in slugs {
+            added += tx.execute(
+                "INSERT OR IGNORE INTO collection_memos (collection_id, slug, added_at) SELECT ?1, slug, ?3 FROM memos WHERE slug = ?2",
+                params![id, slug, &now],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to add to collection: {}", e)))?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(added)
+    }
+    
+    /// Removes `slugs` from the collection `name` in one transaction. Returns
+    /// how many memos were removed.
+    pub fn remove_from_collection(&self, name: &str, slugs: &[String]) -> Result<usize, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let mut removed = 0;
+        for slug in slugs {
+            removed += tx.execute(
+                r#"
+                DELETE FROM collection_memos
+                WHERE slug = ?2 AND collection_id = (SELECT id FROM collections WHERE name = ?1)
+                "#,
+                params![name.trim(), slug],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to remove from collection: {}", e)))?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(removed)
+    }
+    
+    /// Every collection with how many memos outside the trash it holds,
+    /// newest first.
+    pub fn get_collections(&self) -> Result<Vec<Collection>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT collections.id, collections.name, collections.created_at, COUNT(memos.slug)
+            FROM collections
+            LEFT JOIN collection_memos ON collection_memos.collection_id = collections.id
+            LEFT JOIN memos ON memos.slug = collection_memos.slug AND memos.deleted_at IS NULL
+            GROUP BY collections.id
+            ORDER BY collections.created_at DESC, collections.id DESC
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let collections = stmt.query_map([], |row| {
+            Ok(Collection {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+                memo_count: row.get(3)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch collections: {}", e)))?;
+        
+        Ok(collections)
+    }
+    
+    /// A page of the memos in the collection `name` outside the trash, most
+    /// recently added first, continuing after `cursor`.
+    pub fn get_collection_memos(&self, name: &str, cursor: Option<&PageCursor>, limit: i64) -> Result<MemoPage, AppError> {
+        // When each memo was added to this collection; takes the collection id
+        const ADDED_AT: &str = "(SELECT added_at FROM collection_memos WHERE collection_id = ? AND slug = memos.slug)";
+        
+        let conn = self.reader()?;
+        let collection_id: i64 = conn.query_row("SELECT id FROM collections WHERE name = ?1", [name.trim()], |row| row.get(0))
+            .optional()
+            .map_err(|e| AppError::Db(format!("Failed to fetch collection: {}", e)))?
+            .ok_or_else(|| AppError::NotFound(format!("Collection {}", name)))?;
+        
+        let mut conditions = MemoFilter::default().conditions()?;
+        conditions.push("slug IN (SELECT slug FROM collection_memos WHERE collection_id = ?)", [Value::from(collection_id)]);
+        match cursor {
+            None => {}
+            Some(PageCursor::Collection { collection_id: cursor_collection, added_at, created_at, id }) if *cursor_collection == collection_id => {
+                conditions.push(
+                    &format!("({}, created_at, id) < (?, ?, ?)", ADDED_AT),
+                    [
+                        Value::from(collection_id),
+                        Value::from(added_at.clone()),
+                        Value::from(created_at.clone()),
+                        Value::from(*id),
+                    ],
+                );
+            }
+            Some(_) => return Err(AppError::InvalidInput("Page cursor belongs to a different list".to_string())),
+        }
+        
+        let query = format!(
+            "SELECT {} FROM memos {} ORDER BY {} DESC, created_at DESC, id DESC LIMIT ?",
+            MEMO_COLUMNS,
+            conditions.where_sql(),
+            ADDED_AT
+        );
+        conditions.params.push(Value::from(collection_id));
+        // One extra row tells whether there is a next page
+        conditions.params.push(Value::from(limit.max(0) + 1));
+        let mut memos = query_memos(&conn, &query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to fetch collection memos: {}", e)))?;
+        
+        let limit = limit.max(0) as usize;
+        let mut next_cursor = None;
+        if memos.len() > limit {
+            memos.truncate(limit);
+            if let Some(last) = memos.last() {
+                let cursor = conn.query_row(
+                    r#"
+                    SELECT collection_memos.added_at, memos.created_at, memos.id
+                    FROM memos JOIN collection_memos ON collection_memos.slug = memos.slug
+                    WHERE collection_memos.collection_id = ?1 AND memos.slug = ?2
+                    "#,
+                    params![collection_id, &last.slug],
+                    |row| Ok(PageCursor::Collection { collection_id, added_at: row.get(0)?, created_at: row.get(1)?, id: row.get(2)? }),
+                )
+                .map_err(|e| AppError::Db(format!("Failed to fetch collection memos: {}", e)))?;
+                next_cursor = Some(cursor.encode());
+            }
+        }
+        
+        Ok(MemoPage { memos, next_cursor })
+    }
+    
+    /// Deletes the collection `name`; its memos are left alone.
+    pub fn delete_collection(&self, name: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute("DELETE FROM collections WHERE name = ?1", [name.trim()])
+            .map_err(|e| AppError::Db(format!("Failed to delete collection: {}", e)))?;
+        
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!("Collection {}", name)));
+        }
+        
+        self.invalidate_query_cache();
+        Ok(())
+    }
+    
+    /// Permanently removes trashed memos from the local database. Returns how many were purged.
+    pub fn empty_trash(&self) -> Result<usize, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        tx.execute(
+            r#"
+            INSERT OR REPLACE INTO purged_memos (slug, purged_at)
+            SELECT slug, ?1 FROM memos WHERE deleted_at IS NOT NULL
+            "#,
+            params![Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to record purged memos: {}", e)))?;
+        
+        let purged = tx.execute("DELETE FROM memos WHERE deleted_at IS NOT NULL", [])
+            .map_err(|e| AppError::Db(format!("Failed to empty trash: {}", e)))?;
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(purged)
+    }
+    
+    /// Removes a memo that was deleted on Flomo, remembering the slug so a
+    /// sync can't bring it back, and records the deletion in the audit log.
+    pub fn remove_deleted_memo(&self, slug: &str) -> Result<(), AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let now = Utc::now().to_rfc3339();
+        let content: Option<String> = tx
+            .query_row("SELECT content FROM memos WHERE slug = ?1", params![slug], |row| row.get(0))
+            .optional()
+            .map_err(|e| AppError::Db(format!("Failed to look up memo: {}", e)))?;
+        
+        tx.execute(
+            "INSERT OR REPLACE INTO purged_memos (slug, purged_at) VALUES (?1, ?2)",
+            params![slug, &now],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to record purged memo: {}", e)))?;
+        tx.execute("DELETE FROM memos WHERE slug = ?1", params![slug])
+            .map_err(|e| AppError::Db(format!("Failed to delete memo: {}", e)))?;
+        tx.execute(
+            "INSERT INTO audit_log (action, slug, content, performed_at) VALUES ('delete_remote', ?1, ?2, ?3)",
+            params![slug, content, &now],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to write audit log: {}", e)))?;
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(())
+    }
+    
+    /// Queues a memo for pushing, due immediately.
+    pub fn add_pending_memo(&self, content: &str, tags: &[String]) -> Result<PendingMemo, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let tags_json = serde_json::to_string(tags)
+            .map_err(|e| AppError::Parse(format!("Failed to serialize tags: {}", e)))?;
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO pending_memos (content, tags, created_at, next_attempt_at) VALUES (?1, ?2, ?3, ?3)",
+            params![content, tags_json, &now],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to queue memo: {}", e)))?;
+        
+        Ok(PendingMemo {
+            id: conn.last_insert_rowid(),
+            content: content.to_string(),
+            tags: tags.to_vec(),
+            created_at: now.clone(),
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+        })
+    }
+    
+    /// Queued memos, oldest first; with `due_by`, only those due by then.
+    pub fn get_pending_memos(&self, due_by: Option<&str>) -> Result<Vec<PendingMemo>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, content, tags, created_at, attempts, next_attempt_at, last_error
+            FROM pending_memos
+            WHERE ?1 IS NULL OR next_attempt_at <= ?1
+            ORDER BY id ASC
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let pending = stmt.query_map(params![due_by], |row| {
+            let tags: String = row.get(2)?;
+            Ok(PendingMemo {
+                id: row.get(0)?,
+                content: row.get(1)?,
+                tags: serde_json::from_str(&tags).unwrap_or_default(),
+                created_at: row.get(3)?,
+                attempts: row.get(4)?,
+                next_attempt_at: row.get(5)?,
+                last_error: row.get(6)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch pending memos: {}", e)))?;
+        
+        Ok(pending)
+    }
+    
+    pub fn record_push_failure(&self, id: i64, error: &str, next_attempt_at: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE pending_memos SET attempts = attempts + 1, last_error = ?1, next_attempt_at = ?2 WHERE id = ?3",
+            params![error, next_attempt_at, id],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to update pending memo: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    /// Drops a memo from the outbox; false if it wasn't queued.
+    pub fn remove_pending_memo(&self, id: i64) -> Result<bool, AppError> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute("DELETE FROM pending_memos WHERE id = ?1", params![id])
+            .map_err(|e| AppError::Db(format!("Failed to remove pending memo: {}", e)))?;
+        
+        Ok(removed > 0)
+    }
+    
+    pub fn get_audit_log(&self, limit: i64) -> Result<Vec<AuditEntry>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, action, slug, content, performed_at FROM audit_log ORDER BY id DESC LIMIT ?1",
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let entries = stmt.query_map(params![limit], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                action: row.get(1)?,
+                slug: row.get(2)?,
+                content: row.get(3)?,
+                performed_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| AppError::Db(format!("Failed to query audit log: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Db(format!("Failed to collect audit log: {}", e)))?;
+        
+        Ok(entries)
+    }
+    
+    /// Renames a tag (and its nested children) on every local memo and remembers
+    /// the mapping so later syncs apply it too. Returns the number of memos changed.
+    pub fn rename_tag(&self, old_tag: &str, new_tag: &str, queue_remote: bool) -> Result<usize, AppError> {
+        self.merge_tags(&[old_tag.to_string()], new_tag, queue_remote)
+    }
+    
+    /// Folds several tags into one target tag. Returns the number of memos changed.
+    pub fn merge_tags(&self, sources: &[String], target: &str, queue_remote: bool) -> Result<usize, AppError> {
+        let target = normalize_tag(target);
+        if target.is_empty() {
+            return Err(AppError::InvalidInput("Target tag must not be empty".to_string()));
+        }
+        
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let mut mappings = load_tag_mappings(&tx)?;
+        let mut affected = HashSet::new();
+        
+        for source in sources {
+            let source = normalize_tag(source);
+            if source.is_empty() || source == target {
+                continue;
+            }
+            
+            // Renaming into a tag means it should stay; a mapping away from it would undo this
+            tx.execute("DELETE FROM tag_mappings WHERE source = ?1", params![target])
+                .map_err(|e| AppError::Db(format!("Failed to update tag mappings: {}", e)))?;
+            mappings.retain(|(s, _)| s != target);
+            
+            // Follow existing mappings so chains like a -> b -> c collapse to a -> c
+            let resolved = map_tag(target, &mappings);
+            for (existing_source, existing_target) in mappings.iter_mut() {
+                if let Some(renamed) = rename_tag_path(existing_target, source, &resolved) {
+                    *existing_target = renamed;
+                    tx.execute(
+                        "UPDATE tag_mappings SET target = ?1 WHERE source = ?2",
+                        params![&*existing_target, &*existing_source],
+                    )
+                    .map_err(|e| AppError::Db(format!("Failed to update tag mappings: {}", e)))?;
+                }
+            }
+            
+            tx.execute(
+                r#"
+                INSERT INTO tag_mappings (source, target, created_at, pending_remote)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT(source) DO UPDATE SET
+                    target = excluded.target,
+                    created_at = excluded.created_at,
+                    pending_remote = excluded.pending_remote
+                "#,
+                params![source, resolved, Utc::now().to_rfc3339(), queue_remote],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to save tag mapping: {}", e)))?;
+            tx.execute("DELETE FROM tag_mappings WHERE source = target", [])
+                .map_err(|e| AppError::Db(format!("Failed to update tag mappings: {}", e)))?;
+            
+            mappings.retain(|(s, _)| s != source);
+            mappings.push((source.to_string(), resolved));
+            mappings.retain(|(s, t)| s != t);
+            
+            let mut stmt = tx.prepare(
+                "SELECT DISTINCT slug FROM memo_tags WHERE tag = ?1 OR (tag >= ?2 AND tag < ?3)",
+            )
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+            let slugs = stmt
+                .query_map(
+                    params![source, format!("{}/", source), format!("{}0", source)],
+                    |row| row.get::<_, String>(0),
+                )
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| AppError::Db(format!("Failed to find tagged memos: {}", e)))?;
+            affected.extend(slugs);
+        }
+        
+        for slug in &affected {
+            let tags_json: String = tx
+                .query_row("SELECT tags FROM memos WHERE slug = ?1", params![slug], |row| row.get(0))
+                .map_err(|e| AppError::Db(format!("Failed to load memo tags: {}", e)))?;
+            let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
+            let tags = apply_tag_mappings(&tags, &mappings);
+            
+            tx.execute(
+                "UPDATE memos SET tags = ?1 WHERE slug = ?2",
+                params![serde_json::to_string(&tags)?, slug],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to update memo tags: {}", e)))?;
+            replace_memo_tags(&tx, slug, &tags)?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(affected.len())
+    }
+    
+    pub fn get_tag_mappings(&self) -> Result<Vec<TagMapping>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT source, target, created_at, pending_remote FROM tag_mappings ORDER BY source",
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let mappings = stmt.query_map([], |row| {
+            Ok(TagMapping {
+                source: row.get(0)?,
+                target: row.get(1)?,
+                created_at: row.get(2)?,
+                pending_remote: row.get(3)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch tag mappings: {}", e)))?;
+        
+        Ok(mappings)
+    }
+    
+    pub fn get_memos_page(
+        &self,
+        order_by: MemoOrder,
+        order_dir: &str,
+        filter: &MemoFilter,
+        pinned_first: bool,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let mut conditions = filter.conditions()?;
+        
+        let query = format!(
+            "SELECT {} FROM memos {} ORDER BY {}{} LIMIT ? OFFSET ?",
+            MEMO_COLUMNS,
+            conditions.where_sql(),
+            if pinned_first { "pinned DESC, " } else { "" },
+            order_clause(order_by, order_dir)
+        );
+        conditions.params.push(Value::from(limit));
+        conditions.params.push(Value::from(offset));
+        
+        query_memos(&conn, &query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to fetch memos: {}", e)))
+    }
+    
+    /// Like `get_memos_page`, but continuing after `cursor` instead of
+    /// skipping `offset` rows, which stays fast deep into long lists.
+    pub fn get_memos_after(
+        &self,
+        order_by: MemoOrder,
+        order_dir: &str,
+        filter: &MemoFilter,
+        pinned_first: bool,
+        cursor: Option<&PageCursor>,
+        limit: i64,
+    ) -> Result<MemoPage, AppError> {
+        let mut conn = self.reader()?;
+        let key = format!("list|{:?}|{}|{}|{:?}|{:?}|{}", order_by, order_dir, pinned_first, filter, cursor, limit);
+        let mut generation = None;
+        if limit <= QUERY_CACHE_MAX_LIMIT {
+            // A first random page should be a new shuffle each time
+            match self.cached_page(&mut conn, &key, order_by != MemoOrder::Random)? {
+                (Some(page), _) => return Ok(page),
+                (None, current) => generation = Some(current),
+            }
+        }
+        
+        let mut conditions = filter.conditions()?;
+        let (order, seed) = keyset_order(&mut conditions, order_by, order_dir, pinned_first, cursor)?;
+        
+        let query = format!("SELECT {} FROM memos {} ORDER BY {} LIMIT ?", MEMO_COLUMNS, conditions.where_sql(), order);
+        // One extra row tells whether there is a next page
+        conditions.params.push(Value::from(limit.max(0) + 1));
+        let mut memos = query_memos(&conn, &query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to fetch memos: {}", e)))?;
+        
+        let next_cursor = next_cursor(&conn, &mut memos, &order, order_by, seed, pinned_first, limit)
+            .map_err(|e| AppError::Db(format!("Failed to fetch memos: {}", e)))?;
+        let page = MemoPage { memos, next_cursor };
+        if let Some(generation) = generation {
+            self.query_cache.lock().unwrap().insert(generation, key, page.clone());
+        }
+        Ok(page)
+    }
+    
+    /// Up to `count` memos matching `filter`, picked at random.
+    pub fn get_random_memos(&self, count: i64, filter: &MemoFilter) -> Result<Vec<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let mut conditions = filter.conditions()?;
+        
+        let query = format!(
+            "SELECT {} FROM memos {} ORDER BY RANDOM() LIMIT ?",
+            MEMO_COLUMNS,
+            conditions.where_sql()
+        );
+        conditions.params.push(Value::from(count));
+        
+        query_memos(&conn, &query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to fetch random memos: {}", e)))
+    }
+    
+    /// Memos written on `date`'s month and day in earlier years, newest
+    /// first. On February 28th of a non-leap year, February 29th memos are
+    /// included too.
+    pub fn get_memos_on_this_day(&self, date: NaiveDate) -> Result<Vec<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let mut conditions = MemoFilter::default().conditions()?;
+        
+        let mut days = vec![date.format("%m-%d").to_string()];
+        if date.month() == 2 && date.day() == 28 && date.succ_opt().is_some_and(|next| next.month() == 3) {
+            days.push("02-29".to_string());
+        }
+        let placeholders = vec!["?"; days.len()].join(", ");
+        // created_at starts with "YYYY-MM-DD", so the month and day sit at 6..11
+        conditions.push(
+            &format!("substr(created_at, 6, 5) IN ({})", placeholders),
+            days.into_iter().map(Value::from),
+        );
+        conditions.push("created_at < ?", [Value::from(format!("{:04}-01-01", date.year()))]);
+        
+        let query = format!(
+            "SELECT {} FROM memos {} ORDER BY created_at DESC",
+            MEMO_COLUMNS,
+            conditions.where_sql()
+        );
+        
+        query_memos(&conn, &query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to fetch memos on this day: {}", e)))
+    }
+    
+    /// Memos matching a search box query (see `SearchExpr`) and `filter`.
+    pub fn search_memos(
+        &self,
+        query: &str,
+        order_by: MemoOrder,
+        order_dir: &str,
+        filter: &MemoFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let mut conditions = filter.conditions()?;
+        conditions.push_search(query, filter.pinyin);
+        
+        let search_query = format!(
+            "SELECT {} FROM memos {} ORDER BY {} LIMIT ? OFFSET ?",
+            MEMO_COLUMNS,
+            conditions.where_sql(),
+            order_clause(order_by, order_dir)
+        );
+        conditions.params.push(Value::from(limit));
+        conditions.params.push(Value::from(offset));
+        
+        query_memos(&conn, &search_query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to search memos: {}", e)))
+    }
+    
+    /// Like `search_memos`, but continuing after `cursor`.
+    pub fn search_memos_after(
+        &self,
+        query: &str,
+        order_by: MemoOrder,
+        order_dir: &str,
+        filter: &MemoFilter,
+        cursor: Option<&PageCursor>,
+        limit: i64,
+    ) -> Result<MemoPage, AppError> {
+        let mut conn = self.reader()?;
+        let key = format!("search|{}|{:?}|{}|{:?}|{:?}|{}", query, order_by, order_dir, filter, cursor, limit);
+        let mut generation = None;
+        if limit <= QUERY_CACHE_MAX_LIMIT {
+            match self.cached_page(&mut conn, &key, order_by != MemoOrder::Random)? {
+                (Some(page), _) => return Ok(page),
+                (None, current) => generation = Some(current),
+            }
+        }
+        
+        let mut conditions = filter.conditions()?;
+        conditions.push_search(query, filter.pinyin);
+        let (order, seed) = keyset_order(&mut conditions, order_by, order_dir, false, cursor)?;
+        
+        let search_query = format!("SELECT {} FROM memos {} ORDER BY {} LIMIT ?", MEMO_COLUMNS, conditions.where_sql(), order);
+        conditions.params.push(Value::from(limit.max(0) + 1));
+        let mut memos = query_memos(&conn, &search_query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to search memos: {}", e)))?;
+        
+        let next_cursor = next_cursor(&conn, &mut memos, &order, order_by, seed, false, limit)
+            .map_err(|e| AppError::Db(format!("Failed to search memos: {}", e)))?;
+        let page = MemoPage { memos, next_cursor };
+        if let Some(generation) = generation {
+            self.query_cache.lock().unwrap().insert(generation, key, page.clone());
+        }
+        Ok(page)
+    }
+    
+    /// A single memo, including ones in the trash.
+    pub fn get_memo_by_slug(&self, slug: &str) -> Result<Option<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let query = format!("SELECT {} FROM memos WHERE slug = ?", MEMO_COLUMNS);
+        
+        let memos = query_memos(&conn, &query, vec![Value::from(slug.to_string())])
+            .map_err(|e| AppError::Db(format!("Failed to fetch memo: {}", e)))?;
+        Ok(memos.into_iter().next())
+    }
+    
+    /// The memos `slugs` in that order, skipping ones that don't exist.
+    pub fn get_memos_by_slugs(&self, slugs: &[String]) -> Result<Vec<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let query = format!("SELECT {} FROM memos WHERE slug = ?", MEMO_COLUMNS);
+        let fetch_err = |e: rusqlite::Error| AppError::Db(format!("Failed to fetch memos: {}", e));
+        let mut stmt = conn.prepare(&query).map_err(fetch_err)?;
+        
+        let mut memos = Vec::with_capacity(slugs.len());
+        for slug in slugs {
+            if let Some(row) = stmt.query_row([slug], row_to_db_memo).optional().map_err(fetch_err)? {
+                memos.push(row.into());
+            }
+        }
+        Ok(memos)
+    }
+    
+    /// Memos matching `filter` whose content matches `pattern`. Rows are
+    /// tested as they are read, stopping once `limit` matches past `offset`
+    /// are found, so the regex never runs over more rows than needed.
+    pub fn search_memos_regex(
+        &self,
+        pattern: &Regex,
+        order_by: MemoOrder,
+        order_dir: &str,
+        filter: &MemoFilter,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let conditions = filter.conditions()?;
+        let query = format!(
+            "SELECT {} FROM memos {} ORDER BY {}",
+            MEMO_COLUMNS,
+            conditions.where_sql(),
+            order_clause(order_by, order_dir)
+        );
+        
+        let search_err = |e: rusqlite::Error| AppError::Db(format!("Failed to search memos: {}", e));
+        let mut stmt = conn.prepare(&query).map_err(search_err)?;
+        let rows = stmt.query_map(params_from_iter(conditions.params), row_to_db_memo).map_err(search_err)?;
+        
+        let mut memos = Vec::new();
+        let mut skipped = 0;
+        for row in rows {
+            let row = row.map_err(search_err)?;
+            if !pattern.is_match(&row.content) {
+                continue;
+            }
+            if skipped < offset {
+                skipped += 1;
+                continue;
+            }
+            memos.push(crate::Memo::from(row));
+            if memos.len() >= limit {
+                break;
+            }
+        }
+        
+        Ok(memos)
+    }
+    
+    /// How many memos matching `filter` have content matching `pattern`.
+    pub fn count_memos_regex(&self, pattern: &Regex, filter: &MemoFilter) -> Result<i64, AppError> {
+        let conn = self.reader()?;
+        let conditions = filter.conditions()?;
+        let query = format!("SELECT content FROM memos {}", conditions.where_sql());
+        
+        let count_err = |e: rusqlite::Error| AppError::Db(format!("Failed to count memos: {}", e));
+        let mut stmt = conn.prepare(&query).map_err(count_err)?;
+        let mut rows = stmt.query(params_from_iter(conditions.params)).map_err(count_err)?;
+        let mut count = 0;
+        while let Some(row) = rows.next().map_err(count_err)? {
+            if pattern.is_match(row.get_ref(0).map_err(count_err)?.as_str().unwrap_or_default()) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+    
+    /// Calls `visit` with every memo matching `filter`, reading rows one at
+    /// a time instead of loading them all first.
+    pub fn scan_memos<F: FnMut(crate::Memo)>(&self, filter: &MemoFilter, mut visit: F) -> Result<(), AppError> {
+        let conn = self.reader()?;
+        let conditions = filter.conditions()?;
+        let query = format!("SELECT {} FROM memos {}", MEMO_COLUMNS, conditions.where_sql());
+        
+        let scan_err = |e: rusqlite::Error| AppError::Db(format!("Failed to read memos: {}", e));
+        let mut stmt = conn.prepare(&query).map_err(scan_err)?;
+        let rows = stmt.query_map(params_from_iter(conditions.params), row_to_db_memo).map_err(scan_err)?;
+        for row in rows {
+            visit(crate::Memo::from(row.map_err(scan_err)?));
+        }
+        
+        Ok(())
+    }
+    
+    pub fn get_all_memos(&self) -> Result<Vec<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let query = format!("SELECT {} FROM memos ORDER BY created_at DESC", MEMO_COLUMNS);
+        
+        query_memos(&conn, &query, Vec::new())
+            .map_err(|e| AppError::Db(format!("Failed to fetch all memos: {}", e)))
+    }
+    
+    pub fn get_all_tags(&self) -> Result<Vec<TagCount>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT memo_tags.tag, COUNT(*), MAX(memos.created_at)
+            FROM memo_tags JOIN memos ON memos.slug = memo_tags.slug
+            WHERE memos.deleted_at IS NULL
+            GROUP BY memo_tags.tag
+            ORDER BY COUNT(*) DESC, memo_tags.tag ASC
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let tags = stmt.query_map([], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                count: row.get(1)?,
+                last_used_at: row.get(2)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch tags: {}", e)))?;
+        
+        Ok(tags)
+    }
+    
+    /// Memos containing at least one link, or a link to `domain` when given.
+    pub fn get_memos_with_links(&self, domain: Option<&str>, offset: i64, limit: i64) -> Result<Vec<crate::Memo>, AppError> {
+        let conn = self.reader()?;
+        let mut conditions = MemoFilter::default().conditions()?;
+        match domain {
+            Some(domain) => conditions.push("slug IN (SELECT slug FROM links WHERE domain = ?)", [Value::from(domain.to_string())]),
+            None => conditions.push("slug IN (SELECT slug FROM links)", []),
+        }
+        
+        let query = format!(
+            "SELECT {} FROM memos {} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+            MEMO_COLUMNS,
+            conditions.where_sql()
+        );
+        conditions.params.push(Value::from(limit));
+        conditions.params.push(Value::from(offset));
+        
+        query_memos(&conn, &query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to fetch memos with links: {}", e)))
+    }
+    
+    /// Unchecked checklist items from memos matching `filter`, newest memo
+    /// first and in order within each memo.
+    pub fn get_open_todos(&self, filter: &MemoFilter) -> Result<Vec<Todo>, AppError> {
+        let conn = self.reader()?;
+        let mut conditions = filter.conditions()?;
+        conditions.push("todos.done = 0", []);
+        
+        let query = format!(
+            r#"
+            SELECT todos.slug, todos.position, todos.text, todos.done, memos.created_at
+            FROM todos JOIN memos USING (slug)
+            {}
+            ORDER BY memos.created_at DESC, todos.position ASC
+            "#,
+            conditions.where_sql()
+        );
+        
+        let mut stmt = conn.prepare(&query)
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        let todos = stmt.query_map(params_from_iter(conditions.params), |row| {
+            Ok(Todo {
+                slug: row.get(0)?,
+                position: row.get(1)?,
+                text: row.get(2)?,
+                done: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch todos: {}", e)))?;
+        
+        Ok(todos)
+    }
+    
+    /// Memos that refer to `slug`, by its slug or by its first line in
+    /// `[[...]]`, newest first.
+    pub fn get_backlinks(&self, slug: &str) -> Result<Vec<crate::Memo>, AppError> {
+        let first_line = self.get_memo_by_slug(slug)?
+            .and_then(|memo| memo.content.lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string))
+            .unwrap_or_default();
+        
+        let conn = self.reader()?;
+        let mut conditions = MemoFilter::default().conditions()?;
+        conditions.push(
+            "slug IN (SELECT source_slug FROM backlinks WHERE target IN (?, ?)) AND slug != ?",
+            [
+                Value::from(slug.to_string()),
+                Value::from(first_line),
+                Value::from(slug.to_string()),
+            ],
+        );
+        
+        let query = format!(
+            "SELECT {} FROM memos {} ORDER BY created_at DESC",
+            MEMO_COLUMNS,
+            conditions.where_sql()
+        );
+        
+        query_memos(&conn, &query, conditions.params)
+            .map_err(|e| AppError::Db(format!("Failed to fetch backlinks: {}", e)))
+    }
+    
+    /// The `limit` most linked domains.
+    pub fn get_link_stats(&self, limit: i64) -> Result<Vec<DomainStats>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT links.domain,
+                   COUNT(DISTINCT links.url),
+                   COUNT(DISTINCT links.slug),
+                   COUNT(DISTINCT CASE WHEN link_checks.ok = 0 THEN links.url END)
+            FROM links
+            JOIN memos ON memos.slug = links.slug
+            LEFT JOIN link_checks ON link_checks.url = links.url
+            WHERE memos.deleted_at IS NULL
+            GROUP BY links.domain
+            ORDER BY COUNT(DISTINCT links.slug) DESC, links.domain ASC
+            LIMIT ?1
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let stats = stmt.query_map(params![limit], |row| {
+            Ok(DomainStats {
+                domain: row.get(0)?,
+                link_count: row.get(1)?,
+                memo_count: row.get(2)?,
+                dead_count: row.get(3)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch link stats: {}", e)))?;
+        
+        Ok(stats)
+    }
+    
+    /// Distinct URLs in memos outside the trash, optionally for one domain.
+    pub fn get_link_urls(&self, domain: Option<&str>) -> Result<Vec<String>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT DISTINCT links.url
+            FROM links JOIN memos ON memos.slug = links.slug
+            WHERE memos.deleted_at IS NULL AND (?1 IS NULL OR links.domain = ?1)
+            ORDER BY links.url
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let urls = stmt.query_map(params![domain], |row| row.get(0))
+            .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+            .map_err(|e| AppError::Db(format!("Failed to fetch links: {}", e)))?;
+        
+        Ok(urls)
+    }
+    
+    pub fn save_link_checks(&self, checks: &[links::LinkCheck]) -> Result<(), AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let checked_at = Utc::now().to_rfc3339();
+        for check in checks {
+            tx.execute(
+                "INSERT OR REPLACE INTO link_checks (url, status, ok, error, checked_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![&check.url, check.status, check.ok, &check.error, &checked_at],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to save link check: {}", e)))?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))
+    }
+    
+    /// `updated_at` of each memo as it was last pushed to Readwise, by slug.
+    pub fn get_readwise_pushes(&self) -> Result<HashMap<String, String>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare("SELECT slug, updated_at FROM readwise_pushes")
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        let pushes = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .and_then(|rows| rows.collect::<Result<HashMap<String, String>, _>>())
+            .map_err(|e| AppError::Db(format!("Failed to fetch Readwise pushes: {}", e)))?;
+        
+        Ok(pushes)
+    }
+    
+    pub fn record_readwise_pushes(&self, memos: &[crate::Memo]) -> Result<(), AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let pushed_at = Utc::now().to_rfc3339();
+        for memo in memos {
+            tx.execute(
+                "INSERT OR REPLACE INTO readwise_pushes (slug, updated_at, pushed_at) VALUES (?1, ?2, ?3)",
+                params![&memo.slug, &memo.updated_at, &pushed_at],
+            )
+            .map_err(|e| AppError::Db(format!("Failed to record Readwise push: {}", e)))?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))
+    }
+    
+    pub fn record_digest(&self, memo_count: usize) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO digests (sent_at, memo_count) VALUES (?1, ?2)",
+            params![Utc::now().to_rfc3339(), memo_count as i64],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to record digest: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    pub fn last_digest_sent_at(&self) -> Result<Option<DateTime<Utc>>, AppError> {
+        let conn = self.reader()?;
+        let sent_at: Option<String> = conn
+            .query_row("SELECT MAX(sent_at) FROM digests", [], |row| row.get(0))
+            .map_err(|e| AppError::Db(format!("Failed to fetch last digest: {}", e)))?;
+        
+        Ok(sent_at
+            .and_then(|sent_at| DateTime::parse_from_rfc3339(&sent_at).ok())
+            .map(|sent_at| sent_at.with_timezone(&Utc)))
+    }
+    
+    /// Keeps an unparseable response for inspection, dropping all but the
+    /// newest `API_DIAGNOSTICS_KEPT`.
+    pub fn record_api_diagnostic(&self, source: &str, error: &str, raw: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO api_diagnostics (recorded_at, source, error, raw) VALUES (?1, ?2, ?3, ?4)",
+            params![Utc::now().to_rfc3339(), source, error, raw],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to record API diagnostic: {}", e)))?;
+        conn.execute(
+            "DELETE FROM api_diagnostics WHERE id NOT IN (SELECT id FROM api_diagnostics ORDER BY id DESC LIMIT ?1)",
+            params![API_DIAGNOSTICS_KEPT],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prune API diagnostics: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    /// Recorded unparseable responses, newest first.
+    pub fn get_api_diagnostics(&self, limit: i64) -> Result<Vec<ApiDiagnostic>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, recorded_at, source, error, raw FROM api_diagnostics ORDER BY id DESC LIMIT ?1",
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let diagnostics = stmt.query_map(params![limit], |row| {
+            Ok(ApiDiagnostic {
+                id: row.get(0)?,
+                recorded_at: row.get(1)?,
+                source: row.get(2)?,
+                error: row.get(3)?,
+                raw: row.get(4)?,
+            })
+        })
+        .map_err(|e| AppError::Db(format!("Failed to query API diagnostics: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Db(format!("Failed to collect API diagnostics: {}", e)))?;
+        
+        Ok(diagnostics)
+    }
+    
+    pub fn clear_api_diagnostics(&self) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM api_diagnostics", [])
+            .map_err(|e| AppError::Db(format!("Failed to clear API diagnostics: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    /// Converts the stored HTML of synced memos to text again, wrapped at
+    /// `width` columns if given, e.g. after the wrap setting changed.
+    /// Returns how many memos' text changed.
+    pub fn reconvert_memo_text(&self, width: Option<usize>) -> Result<usize, AppError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let rows: Vec<(String, String, String, String, String)> = tx
+            .prepare("SELECT slug, content, content_html, tags, COALESCE(url, '') FROM memos WHERE content_html IS NOT NULL AND content_html != ''")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?
+                    .collect()
+            })
+            .map_err(|e| AppError::Db(format!("Failed to load memos: {}", e)))?;
+        
+        let mut changed = 0;
+        for (slug, content, html, tags_json, url) in rows {
+            let text = crate::html_to_text(&html, width);
+            if text == content {
+                continue;
+            }
+            
+            // The hash covers the text, so the next sync still sees the memo as unchanged
+            update_memo_text(&tx, &slug, &text, &html, &tags_json, &url)?;
+            changed += 1;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(changed)
+    }
+    
+    /// Points links to attachments in `from` at the same files in `to`, for
+    /// when the attachments folder moves. Returns how many memos changed.
+    pub fn relocate_attachments(&self, from: &Path, to: &Path) -> Result<usize, AppError> {
+        let (from, to) = (from.to_string_lossy(), to.to_string_lossy());
+        let (from_html, to_html) = (escape_html(&from), escape_html(&to));
+        
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Db(format!("Failed to begin transaction: {}", e)))?;
+        
+        let rows: Vec<(String, String, String, String, String)> = tx
+            .prepare(
+                "SELECT slug, content, COALESCE(content_html, ''), tags, COALESCE(url, '') FROM memos
+                 WHERE instr(content, ?1) > 0 OR instr(content_html, ?2) > 0",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map(params![from, from_html], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+                })?
+                .collect()
+            })
+            .map_err(|e| AppError::Db(format!("Failed to load memos: {}", e)))?;
+        
+        for (slug, content, html, tags_json, url) in &rows {
+            let text = content.replace(from.as_ref(), &to);
+            let html = html.replace(&from_html, &to_html);
+            update_memo_text(&tx, slug, &text, &html, tags_json, url)?;
+            tx.execute("UPDATE memos SET content_html = ?1 WHERE slug = ?2 AND content_html IS NOT NULL", params![&html, slug])
+                .map_err(|e| AppError::Db(format!("Failed to update memo text: {}", e)))?;
+        }
+        
+        tx.commit()
+            .map_err(|e| AppError::Db(format!("Failed to commit transaction: {}", e)))?;
+        
+        self.invalidate_query_cache();
+        Ok(rows.len())
+    }
+    
+    /// Links whose last check failed and that still appear in a memo.
+    pub fn get_dead_links(&self) -> Result<Vec<DeadLink>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT link_checks.url, MIN(links.domain), link_checks.status, link_checks.error,
+                   link_checks.checked_at, json_group_array(links.slug)
+            FROM link_checks
+            JOIN links ON links.url = link_checks.url
+            JOIN memos ON memos.slug = links.slug
+            WHERE link_checks.ok = 0 AND memos.deleted_at IS NULL
+            GROUP BY link_checks.url
+            ORDER BY link_checks.url
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let dead = stmt.query_map([], |row| {
+            let slugs: String = row.get(5)?;
+            Ok(DeadLink {
+                url: row.get(0)?,
+                domain: row.get(1)?,
+                status: row.get(2)?,
+                error: row.get(3)?,
+                checked_at: row.get(4)?,
+                slugs: serde_json::from_str(&slugs).unwrap_or_default(),
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch dead links: {}", e)))?;
+        
+        Ok(dead)
+    }
+    
+    /// Tag co-occurrence graph, dropping edges used on fewer than
+    /// `min_weight` memos.
+    pub fn get_tag_graph(&self, min_weight: i64) -> Result<TagGraph, AppError> {
+        let nodes = self.get_all_tags()?;
+        
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT a.tag, b.tag, COUNT(*)
+            FROM memo_tags a
+            JOIN memo_tags b ON b.slug = a.slug AND a.tag < b.tag
+            JOIN memos ON memos.slug = a.slug
+            WHERE memos.deleted_at IS NULL
+            GROUP BY a.tag, b.tag
+            HAVING COUNT(*) >= ?1
+            ORDER BY COUNT(*) DESC, a.tag ASC, b.tag ASC
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let edges = stmt.query_map(params![min_weight], |row| {
+            Ok(TagEdge {
+                source: row.get(0)?,
+                target: row.get(1)?,
+                weight: row.get(2)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::Db(format!("Failed to fetch tag graph: {}", e)))?;
+        
+        Ok(TagGraph { nodes, edges })
+    }
+    
+    pub fn get_tag_tree(&self) -> Result<Vec<TagNode>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT memo_tags.slug, memo_tags.tag
+            FROM memo_tags JOIN memos ON memos.slug = memo_tags.slug
+            WHERE memos.deleted_at IS NULL
+            "#,
+        )
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let pairs = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| AppError::Db(format!("Failed to fetch tags: {}", e)))?;
+        
+        Ok(build_tag_tree(&pairs))
+    }
+    
+    /// Computes `WritingStats` with `today` as the end of the current streak.
+    pub fn get_writing_stats(&self, today: NaiveDate) -> Result<WritingStats, AppError> {
+        let conn = self.reader()?;
+        let stats_err = |e: rusqlite::Error| AppError::Db(format!("Failed to compute writing stats: {}", e));
+        let mut stats = WritingStats {
+            weekday_counts: vec![0; 7],
+            hour_counts: vec![0; 24],
+            ..Default::default()
+        };
+        
+        (stats.total_memos, stats.first_memo_at, stats.total_words, stats.total_chars) = conn.query_row(
+            r#"
+            SELECT COUNT(*), MIN(created_at), COALESCE(SUM(word_count), 0), COALESCE(SUM(char_count), 0)
+            FROM memos WHERE deleted_at IS NULL
+            "#,
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(stats_err)?;
+        if stats.total_memos > 0 {
+            stats.average_words_per_memo = stats.total_words as f64 / stats.total_memos as f64;
+        }
+        
+        // Runs of consecutive days: within a run, the day number minus the
+        // row number is constant
+        let mut stmt = conn.prepare(
+            r#"
+            WITH days AS (
+                SELECT DISTINCT substr(created_at, 1, 10) AS day FROM memos WHERE deleted_at IS NULL
+            ),
+            runs AS (
+                SELECT day, julianday(day) - ROW_NUMBER() OVER (ORDER BY day) AS run FROM days
+            )
+            SELECT MAX(day), COUNT(*) FROM runs GROUP BY run
+            "#,
+        )
+        .map_err(stats_err)?;
+        let runs: Vec<(String, i64)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(stats_err)?;
+        
+        let yesterday = today.pred_opt().unwrap_or(today);
+        for (last_day, length) in runs {
+            stats.active_days += length;
+            stats.longest_streak = stats.longest_streak.max(length);
+            if let Ok(last_day) = NaiveDate::parse_from_str(&last_day, "%Y-%m-%d") {
+                if last_day == today || last_day == yesterday {
+                    stats.current_streak = length;
+                }
+            }
+        }
+        
+        if let Some(first_day) = stats.first_memo_at.as_deref()
+            .and_then(|first| first.get(..10))
+            .and_then(|first| NaiveDate::parse_from_str(first, "%Y-%m-%d").ok())
+        {
+            let days = (today - first_day).num_days().max(0) + 1;
+            stats.average_per_day = stats.total_memos as f64 / days as f64;
+            stats.average_per_week = stats.average_per_day * 7.0;
+        }
+        
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT CAST(strftime('%w', created_at) AS INTEGER), CAST(strftime('%H', created_at) AS INTEGER), COUNT(*)
+            FROM memos
+            WHERE deleted_at IS NULL AND strftime('%w', created_at) IS NOT NULL
+            GROUP BY 1, 2
+            "#,
+        )
+        .map_err(stats_err)?;
+        let buckets: Vec<(usize, usize, i64)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(stats_err)?;
+        
+        for (weekday, hour, count) in buckets {
+            if let Some(slot) = stats.weekday_counts.get_mut(weekday) {
+                *slot += count;
+            }
+            if let Some(slot) = stats.hour_counts.get_mut(hour) {
+                *slot += count;
+            }
+        }
+        
+        stats.most_active_weekday = busiest(&stats.weekday_counts);
+        stats.most_active_hour = busiest(&stats.hour_counts);
+        
+        Ok(stats)
+    }
+    
+    pub fn get_memo_count(&self) -> Result<i64, AppError> {
+        let conn = self.reader()?;
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM memos", [], |row| row.get(0))
+            .map_err(|e| AppError::Db(format!("Failed to count memos: {}", e)))?;
+        
+        Ok(count)
+    }
+    
+    /// Counts synced memos outside the trash whose slug isn't in `slugs`;
+    /// after a full sync, the ones deleted on Flomo.
+    pub fn count_memos_missing_from(&self, slugs: &HashSet<String>) -> Result<usize, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare("SELECT slug FROM memos WHERE origin = ?1 AND deleted_at IS NULL")
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        let mut rows = stmt.query([ORIGIN_FLOMO])
+            .map_err(|e| AppError::Db(format!("Failed to fetch memo slugs: {}", e)))?;
+        
+        let mut missing = 0;
+        while let Some(row) = rows.next().map_err(|e| AppError::Db(format!("Failed to read row: {}", e)))? {
+            let slug: String = row.get(0).map_err(|e| AppError::Db(format!("Failed to read row: {}", e)))?;
+            if !slugs.contains(&slug) {
+                missing += 1;
+            }
+        }
+        Ok(missing)
+    }
+    
+    /// Looks up the stored API HTML for `slugs`; memos synced before the
+    /// column existed are simply missing from the map.
+    pub fn get_content_html(&self, slugs: &[String]) -> Result<HashMap<String, String>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare("SELECT content_html FROM memos WHERE slug = ?1 AND content_html IS NOT NULL")
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let mut html = HashMap::new();
+        for slug in slugs {
+            let content: Option<String> = stmt.query_row(params![slug], |row| row.get(0))
+                .optional()
+                .map_err(|e| AppError::Db(format!("Failed to fetch memo HTML: {}", e)))?;
+            if let Some(content) = content {
+                html.insert(slug.clone(), content);
+            }
+        }
+        
+        Ok(html)
+    }
+    
+    /// Slug and content of every stored memo, trashed ones included, for
+    /// matching imports against.
+    pub fn get_memo_contents(&self) -> Result<Vec<(String, String)>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare("SELECT slug, content FROM memos")
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| AppError::Db(format!("Failed to query memos: {}", e)))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Db(format!("Failed to read memo: {}", e)))
+    }
+    
+    /// Whether a stored memo, trashed ones included, has the same text as
+    /// `content` apart from whitespace. Only memos with as many
+    /// non-whitespace characters are compared.
+    pub fn has_memo_with_content(&self, content: &str) -> Result<bool, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare("SELECT content FROM memos WHERE char_count = ?1")
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let candidates = stmt.query_map([char_count(content) as i64], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| AppError::Db(format!("Failed to query memos: {}", e)))?;
+        Ok(candidates.iter().any(|stored| stored.split_whitespace().eq(content.split_whitespace())))
+    }
+    
+    pub fn count_memos(&self, filter: &MemoFilter) -> Result<i64, AppError> {
+        let conn = self.reader()?;
+        let conditions = filter.conditions()?;
+        let query = format!("SELECT COUNT(*) FROM memos {}", conditions.where_sql());
+        
+        conn.query_row(&query, params_from_iter(conditions.params), |row| row.get(0))
+            .map_err(|e| AppError::Db(format!("Failed to count memos: {}", e)))
+    }
+    
+    pub fn update_sync_status(
+        &self,
+        status: &str,
+        total_memos: Option<i64>,
+        error_message: Option<&str>,
+    ) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        
+        match (total_memos, error_message, status) {
+            (Some(total), Some(error), _) => {
+                conn.execute(
+                    "UPDATE sync_status SET status = ?1, total_memos = ?2, error_message = ?3 WHERE id = 1",
+                    params![status, total, error],
+                )
+            }
+            (Some(total), None, "completed") => {
+                conn.execute(
+                    "UPDATE sync_status SET status = ?1, total_memos = ?2, last_sync_at = ?3, error_message = NULL WHERE id = 1",
+                    params![status, total, Utc::now().to_rfc3339()],
+                )
+            }
+            (Some(total), None, _) => {
+                conn.execute(
+                    "UPDATE sync_status SET status = ?1, total_memos = ?2 WHERE id = 1",
+                    params![status, total],
+                )
+            }
+            (None, Some(error), _) => {
+                conn.execute(
+                    "UPDATE sync_status SET status = ?1, error_message = ?2 WHERE id = 1",
+                    params![status, error],
+                )
+            }
+            (None, None, "completed") => {
+                conn.execute(
+                    "UPDATE sync_status SET status = ?1, last_sync_at = ?2, error_message = NULL WHERE id = 1",
+                    params![status, Utc::now().to_rfc3339()],
+                )
+            }
+            (None, None, "idle") => {
+                conn.execute(
+                    "UPDATE sync_status SET status = ?1, error_message = NULL WHERE id = 1",
+                    params![status],
+                )
+            }
+            (None, None, _) => {
+                conn.execute(
+                    "UPDATE sync_status SET status = ?1 WHERE id = 1",
+                    params![status],
+                )
+            }
+        }
+        .map_err(|e| AppError::Db(format!("Failed to update sync status: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    pub fn get_sync_status(&self) -> Result<SyncStatus, AppError> {
+        let conn = self.reader()?;
+        let status = conn.query_row(
+            "SELECT id, last_sync_at, total_memos, status, error_message FROM sync_status WHERE id = 1",
+            [],
+            |row| {
+                Ok(SyncStatus {
+                    id: row.get(0)?,
+                    last_sync_at: row.get(1)?,
+                    total_memos: row.get(2)?,
+                    status: row.get(3)?,
+                    error_message: row.get(4)?,
+                })
+            },
+        )
+        .map_err(|e| AppError::Db(format!("Failed to get sync status: {}", e)))?;
+        
+        Ok(status)
+    }
+    
+    pub fn get_db_stats(&self) -> Result<DbStats, AppError> {
+        let conn = self.reader()?;
+        let (memo_count, tag_count, last_sync_at) = conn.query_row(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM memos WHERE deleted_at IS NULL),
+                (SELECT COUNT(DISTINCT memo_tags.tag) FROM memo_tags
+                    JOIN memos ON memos.slug = memo_tags.slug
+                    WHERE memos.deleted_at IS NULL),
+                (SELECT last_sync_at FROM sync_status WHERE id = 1)
+            "#,
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| AppError::Db(format!("Failed to get database stats: {}", e)))?;
+        let schema_version = conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| AppError::Db(format!("Failed to get schema version: {}", e)))?;
+        
+        let path = &self.readers.path;
+        let mut wal = path.clone().into_os_string();
+        wal.push("-wal");
+        let file_size = [path.clone(), PathBuf::from(wal)]
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        
+        Ok(DbStats { memo_count, tag_count, last_sync_at, schema_version, file_size })
+    }
+    
+    /// Runs SQLite's integrity check, looks for rows orphaned from their
+    /// memo and attachments linked from memos that are missing from
+    /// `attachments_dir`. With `repair`, orphaned rows are deleted and the
+    /// indexes rebuilt before the file is vacuumed.
+    pub fn check_database(&self, attachments_dir: &Path, repair: bool) -> Result<IntegrityReport, AppError> {
+        let check_err = |e: rusqlite::Error| AppError::Db(format!("Failed to check database: {}", e));
+        let mut report = IntegrityReport::default();
+        
+        let orphans: Vec<(String, i64)> = {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare("PRAGMA foreign_key_check").map_err(check_err)?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(check_err)?;
+            rows.collect::<Result<_, _>>().map_err(check_err)?
+        };
+        for (table, _) in &orphans {
+            *report.orphaned_rows.entry(table.clone()).or_default() += 1;
+        }
+        
+        let (missing, unreferenced) = self.check_attachments(attachments_dir)?;
+        report.missing_attachments = missing;
+        report.unreferenced_attachments = unreferenced;
+        
+        if repair {
+            let size_before = self.get_db_stats()?.file_size as i64;
+            {
+                let mut conn = self.conn.lock().unwrap();
+                let tx = conn.transaction().map_err(check_err)?;
+                for (table, rowid) in &orphans {
+                    // Table names come from SQLite's own report, not from input
+                    let removed = tx.execute(&format!("DELETE FROM \"{}\" WHERE rowid = ?", table), [rowid])
+                        .map_err(|e| AppError::Db(format!("Failed to repair database: {}", e)))?;
+                    report.orphans_removed += removed as i64;
+                }
+                tx.execute_batch("REINDEX;")
+                    .map_err(|e| AppError::Db(format!("Failed to repair database: {}", e)))?;
+                tx.commit().map_err(|e| AppError::Db(format!("Failed to repair database: {}", e)))?;
+                
+                conn.execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+                    .map_err(|e| AppError::Db(format!("Failed to vacuum database: {}", e)))?;
+            }
+            self.invalidate_query_cache();
+            report.repaired = true;
+            report.bytes_reclaimed = size_before - self.get_db_stats()?.file_size as i64;
+        }
+        
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("PRAGMA integrity_check").map_err(check_err)?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(check_err)?;
+        report.integrity_errors = rows.collect::<Result<Vec<_>, _>>()
+            .map_err(check_err)?
+            .into_iter()
+            .filter(|line| line != "ok")
+            .collect();
+        
+        Ok(report)
+    }
+    
+    // Attachment file names linked from memos but missing in `dir`, and
+    // file names in `dir` no memo links to
+    fn check_attachments(&self, dir: &Path) -> Result<(Vec<String>, Vec<String>), AppError> {
+        let link = Regex::new(&format!(r#"{}[/\\]([^"'<>\s)/\\]+)"#, regex::escape(&dir.to_string_lossy())))
+            .map_err(|e| AppError::Internal(format!("Invalid attachment pattern: {}", e)))?;
+        
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare("SELECT coalesce(content_html, ''), content FROM memos")
+            .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| AppError::Db(format!("Failed to query memos: {}", e)))?;
+        
+        let mut linked = HashSet::new();
+        for row in rows {
+            let (html, content) = row.map_err(|e| AppError::Db(format!("Failed to read memo: {}", e)))?;
+            for text in [html, content] {
+                linked.extend(link.captures_iter(&text).map(|captures| captures[1].to_string()));
+            }
+        }
+        
+        let files: HashSet<String> = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        let mut missing: Vec<String> = linked.difference(&files).cloned().collect();
+        let mut unreferenced: Vec<String> = files.difference(&linked).cloned().collect();
+        missing.sort();
+        unreferenced.sort();
+        Ok((missing, unreferenced))
+    }
+    
+    /// Opens a sync_runs entry and returns its id.
+    pub fn begin_sync_run(&self) -> Result<i64, AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO sync_runs (started_at, result) VALUES (?1, 'running')",
+            params![Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to record sync run: {}", e)))?;
+        
+        Ok(conn.last_insert_rowid())
+    }
+    
+    pub fn finish_sync_run(
+        &self,
+        id: i64,
+        result: &str,
+        pages_fetched: usize,
+        stats: UpsertStats,
+        deleted: usize,
+        error_message: Option<&str>,
+    ) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            r#"
+            UPDATE sync_runs SET
+                finished_at = ?1,
+                pages_fetched = ?2,
+                new_count = ?3,
+                updated_count = ?4,
+                unchanged_count = ?5,
+                deleted_count = ?6,
+                result = ?7,
+                error_message = ?8
+            WHERE id = ?9
+            "#,
+            params![
+                Utc::now().to_rfc3339(),
+                pages_fetched as i64,
+                stats.new as i64,
+                stats.updated as i64,
+                stats.unchanged as i64,
+                deleted as i64,
+                result,
+                error_message,
+                id
+            ],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to finish sync run: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    pub fn get_sync_history(&self, limit: i64) -> Result<Vec<SyncRun>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, started_at, finished_at, pages_fetched, new_count, updated_count,
+                   unchanged_count, deleted_count, result, error_message
+            FROM sync_runs
+            ORDER BY id DESC
+            LIMIT ?1
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let runs = stmt.query_map(params![limit], |row| {
+            Ok(SyncRun {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                finished_at: row.get(2)?,
+                pages_fetched: row.get(3)?,
+                new_count: row.get(4)?,
+                updated_count: row.get(5)?,
+                unchanged_count: row.get(6)?,
+                deleted_count: row.get(7)?,
+                result: row.get(8)?,
+                error_message: row.get(9)?,
+            })
+        })
+        .map_err(|e| AppError::Db(format!("Failed to query sync history: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Db(format!("Failed to collect sync history: {}", e)))?;
+        
+        Ok(runs)
+    }
+    
+    /// Records a finished auto-export run.
+    pub fn record_export_run(
+        &self,
+        rule: &str,
+        path: &str,
+        started_at: &str,
+        memo_count: Option<i64>,
+        error_message: Option<&str>,
+    ) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            r#"
+            INSERT INTO export_runs (rule, path, started_at, finished_at, result, memo_count, error_message)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+            params![
+                rule,
+                path,
+                started_at,
+                Utc::now().to_rfc3339(),
+                if error_message.is_some() { "failed" } else { "completed" },
+                memo_count,
+                error_message
+            ],
+        )
+        .map_err(|e| AppError::Db(format!("Failed to record export run: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    pub fn get_export_runs(&self, limit: i64) -> Result<Vec<ExportRun>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, rule, path, started_at, finished_at, result, memo_count, error_message
+            FROM export_runs
+            ORDER BY id DESC
+            LIMIT ?1
+            "#,
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let runs = stmt.query_map(params![limit], |row| {
+            Ok(ExportRun {
+                id: row.get(0)?,
+                rule: row.get(1)?,
+                path: row.get(2)?,
+                started_at: row.get(3)?,
+                finished_at: row.get(4)?,
+                result: row.get(5)?,
+                memo_count: row.get(6)?,
+                error_message: row.get(7)?,
+            })
+        })
+        .map_err(|e| AppError::Db(format!("Failed to query export runs: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Db(format!("Failed to collect export runs: {}", e)))?;
+        
+        Ok(runs)
+    }
+    
+    /// Records an executed search. Repeating a query moves it to the top
+    /// instead of adding another entry, and only the newest
+    /// `SEARCH_HISTORY_LIMIT` queries are kept.
+    pub fn record_search(&self, query: &str, result_count: i64) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        let history_err = |e: rusqlite::Error| AppError::Db(format!("Failed to record search: {}", e));
+        
+        conn.execute("DELETE FROM search_history WHERE query = ?1", params![query])
+            .map_err(history_err)?;
+        conn.execute(
+            "INSERT INTO search_history (query, searched_at, result_count) VALUES (?1, ?2, ?3)",
+            params![query, Utc::now().to_rfc3339(), result_count],
+        )
+        .map_err(history_err)?;
+        conn.execute(
+            "DELETE FROM search_history WHERE id NOT IN (SELECT id FROM search_history ORDER BY id DESC LIMIT ?1)",
+            params![SEARCH_HISTORY_LIMIT],
+        )
+        .map_err(history_err)?;
+        
+        Ok(())
+    }
+    
+    pub fn get_search_history(&self, limit: i64) -> Result<Vec<SearchHistoryEntry>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, query, searched_at, result_count FROM search_history ORDER BY id DESC LIMIT ?1",
+        )
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
+        
+        let entries = stmt.query_map(params![limit], |row| {
+            Ok(SearchHistoryEntry {
+                id: row.get(0)?,
+                query: row.get(1)?,
+                searched_at: row.get(2)?,
+                result_count: row.get(3)?,
+            })
+        })
+        .map_err(|e| AppError::Db(format!("Failed to query search history: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Db(format!("Failed to collect search history: {}", e)))?;
+        
+        Ok(entries)
+    }
+    
+    pub fn clear_search_history(&self) -> Result<usize, AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM search_history", [])
+            .map_err(|e| AppError::Db(format!("Failed to clear search history: {}", e)))
+    }
+    
+    pub fn clear_all_memos(&self) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM memos", [])
+            .map_err(|e| AppError::Db(format!("Failed to clear memos: {}", e)))?;
+        self.invalidate_query_cache();
+        
+        drop(conn); // Release the lock before calling update_sync_status
+        self.update_sync_status("idle", Some(0), None)?;
+        
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A database file in the temp directory, removed with its WAL files
+    struct TempDatabase {
+        db: Database,
+        path: PathBuf,
+    }
+
+    impl TempDatabase {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("flomo-core-{}-{}.db", name, std::process::id()));
+            remove_database_files(&path);
+            let db = Database::new(&path).unwrap();
+            TempDatabase { db, path }
+        }
+    }
+
+    impl Drop for TempDatabase {
+        fn drop(&mut self) {
+            remove_database_files(&self.path);
+        }
+    }
+
+    fn remove_database_files(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    fn memo(slug: &str, created_at: &str) -> crate::Memo {
+        crate::Memo {
+            slug: slug.to_string(),
+            content: format!("memo {}", slug),
+            created_at: created_at.to_string(),
+            updated_at: created_at.to_string(),
+            tags: Vec::new(),
+            url: None,
+            pinned: false,
+            local: false,
+            content_html: None,
+            word_count: None,
+            char_count: None,
+        }
+    }
+
+    #[test]
+    fn page_cursor_round_trips() {
+        let cursors = [
+            PageCursor::After {
+                order: "created_at DESC".to_string(),
+                pinned: Some(true),
+                value: CursorValue::Text("2024-01-01 08:00:00".to_string()),
+                slug: "abc".to_string(),
+                seed: None,
+            },
+            PageCursor::After {
+                order: "random".to_string(),
+                pinned: None,
+                value: CursorValue::Integer(42),
+                slug: "xyz".to_string(),
+                seed: Some(7),
+            },
+            PageCursor::Offset(100),
+            PageCursor::Collection {
+                collection_id: 3,
+                added_at: "2024-02-01T00:00:00Z".to_string(),
+                created_at: "2024-01-01 08:00:00".to_string(),
+                id: 9,
+            },
+        ];
+
+        for cursor in cursors {
+            let encoded = cursor.encode();
+            assert!(encoded.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'));
+            let decoded = PageCursor::decode(&encoded).unwrap();
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", cursor));
+        }
+    }
+
+    #[test]
+    fn page_cursor_rejects_garbage() {
+        assert!(matches!(PageCursor::decode("not a cursor"), Err(AppError::InvalidInput(_))));
+        assert!(matches!(PageCursor::decode(""), Err(AppError::InvalidInput(_))));
+        assert!(matches!(PageCursor::offset(Some(&PageCursor::Offset(5))), Ok(5)));
+        assert!(PageCursor::offset(Some(&PageCursor::Collection {
+            collection_id: 1,
+            added_at: String::new(),
+            created_at: String::new(),
+            id: 1,
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn keyset_pages_cover_every_memo_once() {
+        let temp = TempDatabase::new("keyset");
+        // Shared timestamps make the slug break ties
+        let memos: Vec<crate::Memo> = (0..25)
+            .map(|i| memo(&format!("m{:02}", i), &format!("2024-01-{:02} 10:00:00", 1 + i / 3)))
+            .collect();
+        temp.db.bulk_upsert_memos(&memos).unwrap();
+
+        let filter = MemoFilter::default();
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = temp.db
+                .get_memos_after(MemoOrder::CreatedAt, "desc", &filter, false, cursor.as_ref(), 10)
+                .unwrap();
+            assert!(page.memos.len() <= 10);
+            seen.extend(page.memos.into_iter().map(|memo| memo.slug));
+            match page.next_cursor {
+                Some(next) => cursor = Some(PageCursor::decode(&next).unwrap()),
+                None => break,
+            }
+        }
+
+        let mut expected: Vec<&crate::Memo> = memos.iter().collect();
+        expected.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.slug.cmp(&a.slug)));
+        let expected: Vec<String> = expected.into_iter().map(|memo| memo.slug.clone()).collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn keyset_pages_see_writes_between_pages() {
+        let temp = TempDatabase::new("keyset-writes");
+        temp.db.bulk_upsert_memos(&[memo("a", "2024-01-03 10:00:00"), memo("b", "2024-01-02 10:00:00")]).unwrap();
+
+        let filter = MemoFilter::default();
+        let first = temp.db.get_memos_after(MemoOrder::CreatedAt, "asc", &filter, false, None, 1).unwrap();
+        assert_eq!(first.memos[0].slug, "b");
+        let cursor = PageCursor::decode(first.next_cursor.as_deref().unwrap()).unwrap();
+
+        // A memo sorting after the cursor shows up on the next page
+        temp.db.bulk_upsert_memos(&[memo("c", "2024-01-04 10:00:00")]).unwrap();
+        let second = temp.db.get_memos_after(MemoOrder::CreatedAt, "asc", &filter, false, Some(&cursor), 10).unwrap();
+        let slugs: Vec<&str> = second.memos.iter().map(|memo| memo.slug.as_str()).collect();
+        assert_eq!(slugs, ["a", "c"]);
+        assert!(second.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,213 @@
+use base64::Engine;
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use crate::error::AppError;
+use crate::export::escape_html;
+use crate::import::inline_tags;
+use crate::{parse_html_to_text, AccountInfo, ApiMemo, LoginResult};
+
+const MEMO_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// Memos generated when demo mode starts.
+const MEMO_COUNT: u64 = 480;
+/// Days back the generated memos reach.
+const HISTORY_DAYS: u64 = 400;
+/// Token handed out by the demo login; any token is accepted.
+pub const DEMO_TOKEN: &str = "demo";
+
+const TAGS: [&str; 12] = [
+    "reading", "reading/books", "reading/articles", "ideas", "ideas/product", "work", "work/meetings",
+    "life", "life/travel", "quotes", "learning/rust", "health",
+];
+const OPENINGS: [&str; 12] = [
+    "Noticed today that", "A thought worth keeping:", "From this morning's walk:", "Reminder to self:",
+    "Something I keep coming back to:", "Small win:", "Question for later:", "Overheard:",
+    "Note from the book:", "Idea:", "Lesson learned:", "Quick observation:",
+];
+const BODIES: [&str; 16] = [
+    "writing things down is how I find out what I actually think",
+    "the best tools disappear while you use them",
+    "short notes compound when they link to each other",
+    "a walk after lunch makes the afternoon twice as productive",
+    "most meetings could be a shared document and a deadline",
+    "rereading old notes is where the surprising connections show up",
+    "constraints make it easier to start, not harder",
+    "the first draft is for me, the second one for the reader",
+    "sleep fixes more bugs than coffee",
+    "explaining an idea to someone else shows where it is still vague",
+    "tags work best when there are few of them",
+    "the hard part of a habit is the first two weeks",
+    "good defaults matter more than many options",
+    "a slow morning without the phone sets the tone for the day",
+    "every project needs a clear \"done\"",
+    "curiosity is a better motivator than discipline",
+];
+const FOLLOW_UPS: [&str; 8] = [
+    "Try it for a week and see.",
+    "Worth turning into a longer note.",
+    "Related to what I read last month.",
+    "Ask the team what they think.",
+    "Still not sure I agree.",
+    "Keep an eye on this.",
+    "Would make a good blog post.",
+    "Compare with last year's notes.",
+];
+
+#[derive(Debug, Clone)]
+struct DemoMemo {
+    slug: String,
+    content: String,
+    tags: Vec<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl DemoMemo {
+    fn to_api(&self, tz: &FixedOffset) -> ApiMemo {
+        ApiMemo {
+            slug: self.slug.clone(),
+            content: self.content.clone(),
+            created_at: self.created_at.with_timezone(tz).format(MEMO_TIME_FORMAT).to_string(),
+            updated_at: self.updated_at.with_timezone(tz).format(MEMO_TIME_FORMAT).to_string(),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+/// Generated memos standing in for a Flomo account, shared by every demo
+/// client so memos created in one are seen by the next sync.
+pub struct DemoBackend {
+    memos: Mutex<Vec<DemoMemo>>,
+    next_id: AtomicU64,
+}
+
+static BACKEND: LazyLock<DemoBackend> = LazyLock::new(|| DemoBackend {
+    memos: Mutex::new(generate(Utc::now().date_naive().and_time(NaiveTime::MIN).and_utc())),
+    next_id: AtomicU64::new(MEMO_COUNT + 1),
+});
+
+/// The demo backend, generating its memos on first use.
+pub fn backend() -> &'static DemoBackend {
+    &BACKEND
+}
+
+impl DemoBackend {
+    /// Memos ordered by update time, like Flomo's `updated` endpoint, after
+    /// the memo with `latest_slug`.
+    pub fn fetch_page(&self, latest_slug: Option<&str>, limit: usize, tz: &FixedOffset) -> Vec<ApiMemo> {
+        let mut memos = self.memos.lock().unwrap().clone();
+        memos.sort_by(|a, b| a.updated_at.cmp(&b.updated_at).then_with(|| a.slug.cmp(&b.slug)));
+
+        let start = latest_slug
+            .and_then(|slug| memos.iter().position(|memo| memo.slug == slug))
+            .map_or(0, |index| index + 1);
+        memos.iter().skip(start).take(limit).map(|memo| memo.to_api(tz)).collect()
+    }
+
+    pub fn account(&self) -> AccountInfo {
+        AccountInfo {
+            id: Some(1),
+            name: Some("Demo".to_string()),
+            email: Some("demo@example.com".to_string()),
+            avatar_url: None,
+        }
+    }
+
+    /// Accepts any email and password.
+    pub fn login(&self, email: &str) -> LoginResult {
+        let mut account = self.account();
+        if !email.trim().is_empty() {
+            account.email = Some(email.trim().to_string());
+        }
+        LoginResult { token: DEMO_TOKEN.to_string(), account }
+    }
+
+    pub fn fetch_memo(&self, slug: &str, tz: &FixedOffset) -> Option<ApiMemo> {
+        self.memos.lock().unwrap().iter().find(|memo| memo.slug == slug).map(|memo| memo.to_api(tz))
+    }
+
+    pub fn create_memo(&self, content_html: &str, tz: &FixedOffset) -> ApiMemo {
+        let mut memos = self.memos.lock().unwrap();
+        let now = Utc::now();
+        let memo = DemoMemo {
+            slug: slug(self.next_id.fetch_add(1, Ordering::Relaxed)),
+            content: content_html.to_string(),
+            tags: tags_of(content_html),
+            created_at: now,
+            updated_at: now,
+        };
+        memos.push(memo.clone());
+        memo.to_api(tz)
+    }
+
+    pub fn update_memo(&self, slug: &str, content_html: &str, tz: &FixedOffset) -> Result<ApiMemo, AppError> {
+        let mut memos = self.memos.lock().unwrap();
+        let memo = memos.iter_mut()
+            .find(|memo| memo.slug == slug)
+            .ok_or_else(|| AppError::NotFound(format!("Memo {}", slug)))?;
+        memo.content = content_html.to_string();
+        memo.tags = tags_of(content_html);
+        memo.updated_at = Utc::now();
+        Ok(memo.to_api(tz))
+    }
+
+    pub fn delete_memo(&self, slug: &str) -> Result<(), AppError> {
+        let mut memos = self.memos.lock().unwrap();
+        let count = memos.len();
+        memos.retain(|memo| memo.slug != slug);
+        if memos.len() == count {
+            return Err(AppError::NotFound(format!("Memo {}", slug)));
+        }
+        Ok(())
+    }
+}
+
+// Flomo slugs are base64-encoded ids
+fn slug(id: u64) -> String {
+    base64::engine::general_purpose::STANDARD_NO_PAD.encode((1_000_000 + id).to_string())
+}
+
+// Flomo picks tags up from the text
+fn tags_of(content_html: &str) -> Vec<String> {
+    let mut tags = inline_tags(&parse_html_to_text(content_html));
+    tags.dedup();
+    tags
+}
+
+// The same memos every time, dated relative to `now`; starting from
+// midnight keeps them identical across runs on the same day
+fn generate(now: DateTime<Utc>) -> Vec<DemoMemo> {
+    // Linear congruential generator; no need for real randomness
+    let mut seed: u64 = 0x5eed;
+    let mut next = move |bound: u64| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) % bound
+    };
+
+    (1..=MEMO_COUNT)
+        .map(|id| {
+            let tag = TAGS[next(TAGS.len() as u64) as usize];
+            let mut lines = vec![format!(
+                "#{} {} {}.",
+                tag,
+                OPENINGS[next(OPENINGS.len() as u64) as usize],
+                BODIES[next(BODIES.len() as u64) as usize]
+            )];
+            if next(3) == 0 {
+                lines.push(FOLLOW_UPS[next(FOLLOW_UPS.len() as u64) as usize].to_string());
+            }
+            let content: String = lines.iter().map(|line| format!("<p>{}</p>", escape_html(line))).collect();
+
+            let age = Duration::days(next(HISTORY_DAYS) as i64) + Duration::minutes(next(24 * 60) as i64);
+            let created_at = now - age;
+            // About one memo in ten was edited later
+            let updated_at = match next(10) {
+                0 => created_at + Duration::minutes(next(age.num_minutes() as u64 + 1) as i64),
+                _ => created_at,
+            };
+
+            DemoMemo { slug: slug(id), tags: tags_of(&content), content, created_at, updated_at }
+        })
+        .collect()
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export::{self, escape_html};
+use crate::i18n::Locale;
+use crate::Memo;
+
+/// Days covered by one digest, ending today.
+const DIGEST_DAYS: i64 = 7;
+/// Tags listed under the summary.
+const TOP_TAGS: usize = 5;
+/// How often the app checks whether the digest is due.
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// TLS from the start, usually port 465
+    #[default]
+    Tls,
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    StartTls,
+    /// Unencrypted, for a relay on this machine
+    None,
+}
+
+/// Mail server and schedule for the weekly digest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub security: SmtpSecurity,
+    pub username: String,
+    pub password: String,
+    /// Sender, e.g. "Flomo Garden <me@example.com>"; defaults to `username`
+    pub from: String,
+    pub to: String,
+    /// Day the digest is sent, 0 = Sunday .. 6 = Saturday
+    pub weekday: u32,
+    /// Hour of that day, 0..23 in the app's timezone
+    pub hour: u32,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            smtp_host: String::new(),
+            smtp_port: 465,
+            security: SmtpSecurity::default(),
+            username: String::new(),
+            password: String::new(),
+            from: String::new(),
+            to: String::new(),
+            weekday: 0,
+            hour: 20,
+        }
+    }
+}
+
+/// A rendered digest email.
+#[derive(Debug, Serialize)]
+pub struct Digest {
+    pub subject: String,
+    pub html: String,
+    pub memo_count: usize,
+}
+
+/// Compiles the memos of the seven days ending `today`, with writing stats,
+/// into an HTML email.
+pub fn build_digest(db: &Database, today: NaiveDate, locale: Locale) -> Result<Digest, AppError> {
+    let first_day = today - Duration::days(DIGEST_DAYS - 1);
+    let filter = MemoFilter {
+        from_date: Some(first_day.format("%Y-%m-%d").to_string()),
+        to_date: Some(today.format("%Y-%m-%d").to_string()),
+        ..Default::default()
+    };
+    let memos = export::load_memos_chronological(db, &filter)?;
+    let stats = db.get_writing_stats(today)?;
+
+    let active_days = memos.iter()
+        .map(|memo| memo.created_at.get(..10).unwrap_or_default())
+        .collect::<BTreeSet<_>>()
+        .len();
+    let subject = locale.digest_subject(&first_day.format("%Y-%m-%d").to_string(), &today.format("%Y-%m-%d").to_string());
+    let summary = locale.digest_summary(memos.len(), active_days, stats.current_streak, stats.total_memos);
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"></head>\n");
+    html.push_str("<body style=\"font-family: -apple-system, 'PingFang SC', sans-serif; max-width: 640px; margin: 0 auto; color: #333;\">\n");
+    html.push_str(&format!("<h1 style=\"font-size: 20px;\">{}</h1>\n", escape_html(&subject)));
+    html.push_str(&format!("<p style=\"color: #666;\">{}</p>\n", escape_html(&summary)));
+
+    let tags = top_tags(&memos);
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags.iter()
+            .map(|(tag, count)| format!("#{} ({})", escape_html(tag), count))
+            .collect();
+        html.push_str(&format!("<p>{}: {}</p>\n", locale.strings().tags, tags.join(" · ")));
+    }
+
+    for memo in &memos {
+        html.push_str("<div style=\"border-top: 1px solid #eee; padding: 12px 0;\">\n");
+        html.push_str(&format!("<div style=\"color: #999; font-size: 12px;\">{}</div>\n", escape_html(&memo.created_at)));
+        html.push_str(&format!("<div style=\"white-space: pre-wrap;\">{}</div>\n", escape_html(memo.content.trim())));
+        html.push_str("</div>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+
+    Ok(Digest { subject, html, memo_count: memos.len() })
+}
+
+/// Sends `digest` through the configured SMTP server.
+pub async fn send(settings: &DigestSettings, digest: &Digest) -> Result<(), AppError> {
+    let mailbox = |address: &str| {
+        address.trim().parse::<Mailbox>()
+            .map_err(|e| AppError::InvalidInput(format!("Invalid email address {:?}: {}", address, e)))
+    };
+    let from = if settings.from.trim().is_empty() { &settings.username } else { &settings.from };
+    let message = lettre::Message::builder()
+        .from(mailbox(from)?)
+        .to(mailbox(&settings.to)?)
+        .subject(&digest.subject)
+        .header(ContentType::TEXT_HTML)
+        .body(digest.html.clone())
+        .map_err(|e| AppError::Internal(format!("Failed to build digest email: {}", e)))?;
+
+    let host = settings.smtp_host.trim();
+    if host.is_empty() {
+        return Err(AppError::InvalidInput("No SMTP server is configured".to_string()));
+    }
+    let builder = match settings.security {
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+        SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+        SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)),
+    }
+    .map_err(|e| AppError::Network(format!("Failed to set up SMTP: {}", e)))?;
+    let mut builder = builder.port(settings.smtp_port);
+    if !settings.username.is_empty() {
+        builder = builder.credentials(Credentials::new(settings.username.clone(), settings.password.clone()));
+    }
+
+    builder.build().send(message).await.map_err(|e| {
+        // 535: authentication credentials invalid
+        if e.status().is_some_and(|code| code.to_string() == "535") {
+            AppError::Auth(format!("SMTP server rejected the login: {}", e))
+        } else {
+            AppError::Network(format!("Failed to send digest: {}", e))
+        }
+    })?;
+    Ok(())
+}
+
+/// Whether the scheduled time has passed since the last digest was sent.
+pub fn is_due(settings: &DigestSettings, now: DateTime<FixedOffset>, last_sent: Option<DateTime<Utc>>) -> bool {
+    let Some(scheduled) = last_scheduled(settings, now) else {
+        return false;
+    };
+    last_sent.is_none_or(|sent| sent < scheduled)
+}
+
+// The most recent send time at or before `now`
+fn last_scheduled(settings: &DigestSettings, now: DateTime<FixedOffset>) -> Option<DateTime<Utc>> {
+    let tz = *now.offset();
+    (0..=DIGEST_DAYS)
+        .map(|days_ago| now.date_naive() - Duration::days(days_ago))
+        .filter(|date| date.weekday().num_days_from_sunday() == settings.weekday % 7)
+        .filter_map(|date| date.and_hms_opt(settings.hour.min(23), 0, 0))
+        .filter_map(|time| tz.from_local_datetime(&time).single())
+        .find(|time| *time <= now)
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Most used tags this week, most frequent first
+fn top_tags(memos: &[Memo]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tag in memos.iter().flat_map(|memo| &memo.tags) {
+        *counts.entry(tag).or_default() += 1;
+    }
+
+    let mut tags: Vec<(String, usize)> = counts.into_iter().map(|(tag, count)| (tag.to_string(), count)).collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    tags.truncate(TOP_TAGS);
+    tags
+}
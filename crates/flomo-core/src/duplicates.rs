@@ -0,0 +1,140 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::Memo;
+
+/// Similarity at or above which `find_duplicate_memos` groups memos when no
+/// threshold is given.
+pub const DEFAULT_THRESHOLD: f64 = 0.9;
+
+/// Memos with the same or nearly the same content. The oldest comes first,
+/// as the one to keep by default.
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub memos: Vec<Memo>,
+    /// Whether every memo has the same text, ignoring case and whitespace
+    pub exact: bool,
+    /// Lowest similarity between memos linked into the group, from 0 to 1
+    pub similarity: f64,
+}
+
+/// Groups memos outside the trash whose content is identical after
+/// normalizing case and whitespace, or whose character trigrams overlap by
+/// at least `threshold` (Jaccard similarity). Largest groups come first.
+pub fn find_duplicate_memos(db: &Database, threshold: f64) -> Result<Vec<DuplicateGroup>, AppError> {
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        return Err(AppError::InvalidInput("Threshold must be above 0 and at most 1".to_string()));
+    }
+
+    let mut memos = Vec::new();
+    db.scan_memos(&MemoFilter::default(), |memo| memos.push(memo))?;
+    memos.retain(|memo| !normalize(&memo.content).is_empty());
+
+    let normalized: Vec<String> = memos.iter().map(|memo| normalize(&memo.content)).collect();
+    let mut groups = UnionFind::new(memos.len());
+
+    let mut by_text: HashMap<&str, usize> = HashMap::new();
+    for (i, text) in normalized.iter().enumerate() {
+        match by_text.get(text.as_str()) {
+            Some(&first) => groups.union(first, i, 1.0),
+            None => {
+                by_text.insert(text, i);
+            }
+        }
+    }
+
+    // Sets this far apart in size can't reach the threshold, so after sorting
+    // by size each memo is only compared with the next few
+    let shingles: Vec<HashSet<&str>> = normalized.iter().map(|text| trigrams(text)).collect();
+    let mut order: Vec<usize> = (0..memos.len()).collect();
+    order.sort_by_key(|&i| shingles[i].len());
+    for (position, &i) in order.iter().enumerate() {
+        for &j in &order[position + 1..] {
+            if (shingles[i].len() as f64) < threshold * shingles[j].len() as f64 {
+                break;
+            }
+            if groups.find(i) == groups.find(j) {
+                continue;
+            }
+            let similarity = jaccard(&shingles[i], &shingles[j]);
+            if similarity >= threshold {
+                groups.union(i, j, similarity);
+            }
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..memos.len() {
+        members.entry(groups.find(i)).or_default().push(i);
+    }
+
+    let mut memos: Vec<Option<Memo>> = memos.into_iter().map(Some).collect();
+    let mut result: Vec<DuplicateGroup> = members
+        .into_iter()
+        .filter(|(_, indexes)| indexes.len() > 1)
+        .map(|(root, indexes)| {
+            let exact = indexes.iter().all(|&i| normalized[i] == normalized[indexes[0]]);
+            let mut group: Vec<Memo> = indexes.iter().filter_map(|&i| memos[i].take()).collect();
+            group.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.slug.cmp(&b.slug)));
+            DuplicateGroup { memos: group, exact, similarity: groups.similarity[root] }
+        })
+        .collect();
+
+    result.sort_by(|a, b| {
+        b.memos.len().cmp(&a.memos.len()).then_with(|| a.memos[0].created_at.cmp(&b.memos[0].created_at))
+    });
+    Ok(result)
+}
+
+// Lowercased, with runs of whitespace collapsed to single spaces
+fn normalize(content: &str) -> String {
+    content.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// Overlapping three-character slices; shorter texts are their own single slice
+fn trigrams(text: &str) -> HashSet<&str> {
+    let boundaries: Vec<usize> = text.char_indices().map(|(i, _)| i).chain([text.len()]).collect();
+    if boundaries.len() <= 4 {
+        return HashSet::from([text]);
+    }
+    boundaries.windows(4).map(|window| &text[window[0]..window[3]]).collect()
+}
+
+fn jaccard(a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+    /// Lowest similarity of the links merged into each root's set
+    similarity: Vec<f64>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self { parent: (0..len).collect(), similarity: vec![1.0; len] }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut node = i;
+        while self.parent[node] != root {
+            node = std::mem::replace(&mut self.parent[node], root);
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize, similarity: f64) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b] = a;
+            self.similarity[a] = self.similarity[a].min(self.similarity[b]).min(similarity);
+        }
+    }
+}
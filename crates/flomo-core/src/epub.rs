@@ -0,0 +1,310 @@
+use chrono::{FixedOffset, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::{Seek, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export::{self, escape_html};
+use crate::i18n::Locale;
+use crate::{format_date, Memo};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChapterGrouping {
+    /// One chapter per calendar month, oldest first
+    Month,
+    /// One chapter per tag; memos with several tags appear in each
+    Tag,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EpubOptions {
+    pub group_by: ChapterGrouping,
+    /// Defaults to the localized "Flomo 备忘录"
+    pub title: Option<String>,
+    pub author: String,
+    pub language: String,
+    pub date_format: String,
+    pub filter: MemoFilter,
+    /// Language of generated labels; set from the app settings
+    #[serde(skip)]
+    pub locale: Locale,
+}
+
+impl EpubOptions {
+    fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(self.locale.strings().title)
+    }
+}
+
+impl Default for EpubOptions {
+    fn default() -> Self {
+        Self {
+            group_by: ChapterGrouping::Month,
+            title: None,
+            author: "Flomo".to_string(),
+            language: "zh".to_string(),
+            date_format: "yyyy-MM-dd HH:mm".to_string(),
+            filter: MemoFilter::default(),
+            locale: Locale::default(),
+        }
+    }
+}
+
+struct Chapter<'a> {
+    title: String,
+    memos: Vec<&'a Memo>,
+}
+
+/// Packages the memos matching `options.filter` into an EPUB 3 book with a
+/// navigation document (and an NCX for older readers). Returns the number of
+/// memos included.
+pub fn export_epub(db: &Database, path: &Path, options: &EpubOptions, tz: FixedOffset) -> Result<usize, AppError> {
+    let memos = export::load_memos_chronological(db, &options.filter)?;
+
+    let chapters = group_chapters(&memos, options.group_by, options.locale.strings().untagged);
+    let file = std::fs::File::create(path)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", path.display(), e)))?;
+
+    let result = write_epub(file, &chapters, options, &tz);
+    if result.is_err() {
+        let _ = std::fs::remove_file(path);
+    }
+    result.map(|_| memos.len())
+}
+
+fn group_chapters<'a>(memos: &'a [Memo], group_by: ChapterGrouping, untagged: &str) -> Vec<Chapter<'a>> {
+    let mut groups: BTreeMap<String, Vec<&Memo>> = BTreeMap::new();
+
+    for memo in memos {
+        match group_by {
+            ChapterGrouping::Month => {
+                let month = memo.created_at.get(..7).unwrap_or(&memo.created_at);
+                groups.entry(month.to_string()).or_default().push(memo);
+            }
+            ChapterGrouping::Tag if memo.tags.is_empty() => {
+                groups.entry(untagged.to_string()).or_default().push(memo);
+            }
+            ChapterGrouping::Tag => {
+                for tag in &memo.tags {
+                    groups.entry(tag.clone()).or_default().push(memo);
+                }
+            }
+        }
+    }
+
+    // Untagged memos go last rather than wherever the key happens to sort
+    let untagged_memos = groups.remove(untagged);
+    let mut chapters: Vec<Chapter> = groups.into_iter()
+        .map(|(title, memos)| Chapter { title, memos })
+        .collect();
+    if let Some(memos) = untagged_memos {
+        chapters.push(Chapter { title: untagged.to_string(), memos });
+    }
+
+    chapters
+}
+
+fn write_epub<W: Write + Seek>(
+    out: W,
+    chapters: &[Chapter],
+    options: &EpubOptions,
+    tz: &FixedOffset,
+) -> Result<(), AppError> {
+    let zip_err = |e: zip::result::ZipError| AppError::Internal(format!("Failed to write EPUB: {}", e));
+    let io_err = |e: std::io::Error| AppError::Internal(format!("Failed to write EPUB: {}", e));
+
+    let mut zip = ZipWriter::new(out);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype entry must come first and be stored uncompressed
+    let add = |zip: &mut ZipWriter<W>, name: &str, content: &str, options: SimpleFileOptions| {
+        zip.start_file(name, options).map_err(zip_err)?;
+        zip.write_all(content.as_bytes()).map_err(io_err)
+    };
+    add(&mut zip, "mimetype", "application/epub+zip", stored)?;
+    add(&mut zip, "META-INF/container.xml", CONTAINER_XML, deflated)?;
+    add(&mut zip, "OEBPS/style.css", STYLE_CSS, deflated)?;
+
+    for (index, chapter) in chapters.iter().enumerate() {
+        add(&mut zip, &format!("OEBPS/{}", chapter_file(index)), &chapter_xhtml(chapter, options, tz), deflated)?;
+    }
+
+    add(&mut zip, "OEBPS/nav.xhtml", &nav_xhtml(chapters, options), deflated)?;
+    add(&mut zip, "OEBPS/toc.ncx", &toc_ncx(chapters, options), deflated)?;
+    add(&mut zip, "OEBPS/content.opf", &content_opf(chapters, options), deflated)?;
+
+    zip.finish().map_err(zip_err)?;
+    Ok(())
+}
+
+fn chapter_file(index: usize) -> String {
+    format!("chapter-{}.xhtml", index + 1)
+}
+
+fn book_id(chapters: &[Chapter], options: &EpubOptions) -> String {
+    let slugs: Vec<&str> = chapters.iter()
+        .flat_map(|chapter| chapter.memos.iter().map(|memo| memo.slug.as_str()))
+        .collect();
+    format!("urn:flomo-garden:{:x}", md5::compute(format!("{}:{}", options.title(), slugs.join(","))))
+}
+
+fn chapter_xhtml(chapter: &Chapter, options: &EpubOptions, tz: &FixedOffset) -> String {
+    let title = escape_html(&chapter.title);
+    let mut body = String::new();
+
+    for memo in &chapter.memos {
+        body.push_str(&format!("<section class=\"memo\" id=\"memo-{}\">\n", escape_html(&memo.slug)));
+        body.push_str(&format!(
+            "  <p class=\"date\">{}</p>\n",
+            escape_html(&format_date(&memo.created_at, &options.date_format, tz))
+        ));
+        for paragraph in memo.content.trim().split("\n\n") {
+            body.push_str(&format!("  <p>{}</p>\n", escape_html(paragraph).replace('\n', "<br/>")));
+        }
+        if !memo.tags.is_empty() {
+            let tags: Vec<String> = memo.tags.iter().map(|tag| format!("#{}", escape_html(tag))).collect();
+            body.push_str(&format!("  <p class=\"tags\">{}</p>\n", tags.join(" ")));
+        }
+        body.push_str("</section>\n");
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="{lang}" lang="{lang}">
+<head>
+<title>{title}</title>
+<link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+<h1>{title}</h1>
+{body}</body>
+</html>
+"#,
+        lang = escape_html(&options.language),
+        title = title,
+        body = body
+    )
+}
+
+fn nav_xhtml(chapters: &[Chapter], options: &EpubOptions) -> String {
+    let items: String = chapters.iter().enumerate()
+        .map(|(index, chapter)| format!(
+            "    <li><a href=\"{}\">{} ({})</a></li>\n",
+            chapter_file(index),
+            escape_html(&chapter.title),
+            chapter.memos.len()
+        ))
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+<title>{title}</title>
+</head>
+<body>
+<nav epub:type="toc" id="toc">
+  <h1>{title}</h1>
+  <ol>
+{items}  </ol>
+</nav>
+</body>
+</html>
+"#,
+        lang = escape_html(&options.language),
+        title = escape_html(options.title()),
+        items = items
+    )
+}
+
+fn toc_ncx(chapters: &[Chapter], options: &EpubOptions) -> String {
+    let points: String = chapters.iter().enumerate()
+        .map(|(index, chapter)| format!(
+            "    <navPoint id=\"nav-{n}\" playOrder=\"{n}\">\n      <navLabel><text>{}</text></navLabel>\n      <content src=\"{}\"/>\n    </navPoint>\n",
+            escape_html(&chapter.title),
+            chapter_file(index),
+            n = index + 1
+        ))
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head>
+    <meta name="dtb:uid" content="{id}"/>
+  </head>
+  <docTitle><text>{title}</text></docTitle>
+  <navMap>
+{points}  </navMap>
+</ncx>
+"#,
+        id = book_id(chapters, options),
+        title = escape_html(options.title()),
+        points = points
+    )
+}
+
+fn content_opf(chapters: &[Chapter], options: &EpubOptions) -> String {
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    for index in 0..chapters.len() {
+        manifest.push_str(&format!(
+            "    <item id=\"chapter-{n}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            chapter_file(index),
+            n = index + 1
+        ));
+        spine.push_str(&format!("    <itemref idref=\"chapter-{}\"/>\n", index + 1));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{id}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:creator>{author}</dc:creator>
+    <dc:language>{lang}</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+{manifest}  </manifest>
+  <spine toc="ncx">
+{spine}  </spine>
+</package>
+"#,
+        id = book_id(chapters, options),
+        title = escape_html(options.title()),
+        author = escape_html(&options.author),
+        lang = escape_html(&options.language),
+        modified = Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        manifest = manifest,
+        spine = spine
+    )
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLE_CSS: &str = r#"body { line-height: 1.6; }
+h1 { font-size: 1.4em; margin-bottom: 1em; }
+.memo { margin-bottom: 1.5em; padding-bottom: 1em; border-bottom: 1px solid #ddd; }
+.date { font-size: 0.85em; color: #888; margin: 0 0 0.4em; }
+.tags { font-size: 0.85em; color: #30a46c; }
+"#;
@@ -0,0 +1,105 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// Error returned by every Tauri command.
+///
+/// Serialized as `{ "code": "...", "message": "..." }` so the frontend can
+/// branch on `code` (e.g. prompt for a new token on `auth`).
+#[derive(Debug, Clone)]
+pub enum AppError {
+    Network(String),
+    /// A request took longer than the configured timeout
+    Timeout(String),
+    Auth(String),
+    CaptchaRequired(String),
+    RateLimited(String),
+    Parse(String),
+    Db(String),
+    Cancelled,
+    /// The database is encrypted and hasn't been unlocked this session
+    Locked,
+    InvalidInput(String),
+    NotFound(String),
+    /// The memo changed elsewhere since it was loaded for editing
+    Conflict(String),
+    Internal(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Network(_) => "network",
+            AppError::Timeout(_) => "timeout",
+            AppError::Auth(_) => "auth",
+            AppError::CaptchaRequired(_) => "captcha_required",
+            AppError::RateLimited(_) => "rate_limited",
+            AppError::Parse(_) => "parse",
+            AppError::Db(_) => "db",
+            AppError::Cancelled => "cancelled",
+            AppError::Locked => "locked",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Network(msg) => write!(f, "Network error: {}", msg),
+            AppError::Timeout(msg) => write!(f, "Request timed out: {}", msg),
+            AppError::Auth(msg) => write!(f, "Authentication failed: {}", msg),
+            AppError::CaptchaRequired(msg) => write!(f, "Captcha required: {}", msg),
+            AppError::RateLimited(msg) => write!(f, "Rate limited: {}", msg),
+            AppError::Parse(msg) => write!(f, "Parse error: {}", msg),
+            AppError::Db(msg) => write!(f, "Database error: {}", msg),
+            AppError::Cancelled => write!(f, "Sync cancelled by user"),
+            AppError::Locked => write!(f, "Database is locked; enter the passphrase to unlock it"),
+            AppError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            AppError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::Db(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AppError::Timeout(e.to_string())
+        } else {
+            AppError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Parse(e.to_string())
+    }
+}
+
+impl From<rust_xlsxwriter::XlsxError> for AppError {
+    fn from(e: rust_xlsxwriter::XlsxError) -> Self {
+        AppError::Internal(format!("Failed to write spreadsheet: {}", e))
+    }
+}
@@ -0,0 +1,1032 @@
+use chrono::{FixedOffset, Utc};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::PathBuf;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::db::{Database, MemoFilter, MemoOrder, PageCursor};
+use crate::error::AppError;
+use crate::i18n::{Locale, Strings};
+use crate::{blog, format_date, parse_memo_time, Memo};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    /// Newline-delimited JSON, one memo object per line
+    Jsonl,
+    Markdown,
+    Table,
+    /// Self-contained HTML page with in-page tag filtering
+    Html,
+    /// Evernote export XML, importable by Evernote, Joplin and others
+    Enex,
+    /// One row per memo under a header row; tags are space-separated
+    Csv,
+}
+
+impl ExportFormat {
+    /// File extension of exports in this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Table => "txt",
+            ExportFormat::Html => "html",
+            ExportFormat::Enex => "enex",
+            ExportFormat::Csv => "csv",
+        }
+    }
+
+    /// Name of the format in file dialogs.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Jsonl => "JSON Lines",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Table => "Text",
+            ExportFormat::Html => "HTML",
+            ExportFormat::Enex => "Evernote",
+            ExportFormat::Csv => "CSV",
+        }
+    }
+}
+
+/// How `<img>` tags in the memo HTML are handled by the HTML export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageMode {
+    /// Keep the remote image URLs
+    #[default]
+    Link,
+    /// Inline images as base64 data URIs
+    Embed,
+    /// Download images into a folder next to the export and link them relatively
+    Files,
+}
+
+/// How the Markdown export splits memos into `#` sections.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// `# 2024-05-12`
+    Day,
+    /// `# 2024-W19` (ISO weeks)
+    Week,
+    /// `# 2024-05`
+    Month,
+    /// `# 读书`; memos with several tags appear under each unless
+    /// `primary_tag_only` is set
+    Tag,
+}
+
+impl GroupBy {
+    /// chrono format of the section heading, for the date groupings
+    fn heading_format(self) -> Option<&'static str> {
+        match self {
+            GroupBy::Day => Some("%Y-%m-%d"),
+            GroupBy::Week => Some("%G-W%V"),
+            GroupBy::Month => Some("%Y-%m"),
+            GroupBy::Tag => None,
+        }
+    }
+}
+
+/// A column of the plain-text table format.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TableColumn {
+    Index,
+    Created,
+    Updated,
+    Slug,
+    Tags,
+    /// CJK characters count one each, other text by whitespace-separated words
+    Words,
+    Content,
+}
+
+impl TableColumn {
+    fn title(self, strings: &Strings) -> &'static str {
+        match self {
+            TableColumn::Index => strings.index,
+            TableColumn::Created => strings.created,
+            TableColumn::Updated => strings.updated,
+            TableColumn::Slug => strings.id,
+            TableColumn::Tags => strings.tags,
+            TableColumn::Words => strings.words,
+            TableColumn::Content => strings.content_preview,
+        }
+    }
+
+    /// Display width the column is padded (or cut) to, in terminal cells
+    fn width(self) -> usize {
+        match self {
+            TableColumn::Index | TableColumn::Words => 4,
+            TableColumn::Created | TableColumn::Updated => 17,
+            TableColumn::Slug => 12,
+            TableColumn::Tags => 20,
+            TableColumn::Content => 30,
+        }
+    }
+}
+
+/// Formatting options shared by the in-memory formatters and `export_to_file`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExportOptions {
+    pub compact: bool,
+    pub url_mode: String,
+    pub date_format: String,
+    pub minimal: bool,
+    /// Export the original API HTML instead of the converted text (JSON Lines only)
+    pub raw_html: bool,
+    pub image_mode: ImageMode,
+    /// Group Markdown memos under date headings instead of a flat numbered list
+    pub group_by: Option<GroupBy>,
+    /// With `GroupBy::Tag`, list each memo only under the first tag it mentions
+    pub primary_tag_only: bool,
+    /// Backslash-escape Markdown syntax in memo text; turn off for memos
+    /// that are written in Markdown
+    pub escape_markdown: bool,
+    /// Columns of the table format, in order
+    pub columns: Vec<TableColumn>,
+    /// Characters of content shown in the table's content column
+    pub preview_length: usize,
+    /// Show the whole memo in the table's content column instead of a preview
+    pub full_content: bool,
+    /// Start the file with a UTF-8 byte order mark, which Excel needs to
+    /// open CSV files with Chinese text correctly
+    pub utf8_bom: bool,
+    /// Language of generated labels; set from the app settings
+    #[serde(skip)]
+    pub locale: Locale,
+    /// Where `ImageMode::Files` writes images; set from the export path
+    #[serde(skip)]
+    pub asset_dir: Option<PathBuf>,
+    /// Which memos to export from the database (tag, date range, search)
+    pub filter: MemoFilter,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            compact: false,
+            url_mode: "full".to_string(),
+            date_format: String::new(),
+            minimal: false,
+            raw_html: false,
+            image_mode: ImageMode::Link,
+            group_by: None,
+            primary_tag_only: false,
+            escape_markdown: true,
+            columns: vec![TableColumn::Index, TableColumn::Created, TableColumn::Content],
+            preview_length: 30,
+            full_content: false,
+            utf8_bom: false,
+            locale: Locale::default(),
+            asset_dir: None,
+            filter: MemoFilter::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub current: usize,
+    pub total: usize,
+}
+
+/// Writes memos one at a time in the chosen format, so large exports never
+/// have to be held in memory as a whole.
+pub struct MemoExporter<W: Write> {
+    out: W,
+    format: ExportFormat,
+    options: ExportOptions,
+    tz: FixedOffset,
+    count: usize,
+    /// Image src -> rewritten src, so repeated images are fetched once
+    images: HashMap<String, String>,
+    http: Option<reqwest::blocking::Client>,
+    /// Heading of the Markdown section currently being written
+    section: Option<String>,
+    /// Memos held back until `finish` when grouping by tag
+    buffered: Vec<Memo>,
+}
+
+impl<W: Write> MemoExporter<W> {
+    pub fn new(out: W, format: ExportFormat, options: ExportOptions, tz: FixedOffset) -> Result<Self, AppError> {
+        let mut exporter = Self { out, format, options, tz, count: 0, images: HashMap::new(), http: None, section: None, buffered: Vec::new() };
+        exporter.write_header()?;
+        Ok(exporter)
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn write_header(&mut self) -> Result<(), AppError> {
+        if self.options.utf8_bom {
+            self.write_str("\u{FEFF}")?;
+        }
+        let strings = self.options.locale.strings();
+        match self.format {
+            ExportFormat::Json => self.write_str("["),
+            ExportFormat::Jsonl => Ok(()),
+            ExportFormat::Markdown if !self.options.minimal => self.write_str(&format!("# {}\n\n", strings.title)),
+            ExportFormat::Markdown => Ok(()),
+            ExportFormat::Table => {
+                let titles: Vec<&str> = self.options.columns.iter().map(|column| column.title(strings)).collect();
+                let header = self.table_row(&titles);
+                let rule = "-".repeat(UnicodeWidthStr::width(header.as_str()).max(50));
+                self.write_str(&format!("{}\n{}\n", header, rule))
+            }
+            ExportFormat::Html => {
+                let head = HTML_HEAD
+                    .replace("{lang}", match self.options.locale { Locale::ZhCn => "zh", Locale::EnUs => "en" })
+                    .replace("{title}", strings.title)
+                    .replace("{filter}", strings.filter)
+                    .replace("{show_all}", strings.show_all);
+                self.write_str(&head)
+            }
+            ExportFormat::Enex => {
+                let header = format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE en-export SYSTEM \"http://xml.evernote.com/pub/evernote-export3.dtd\">\n<en-export export-date=\"{}\" application=\"Flomo Garden\" version=\"1.0\">\n",
+                    Utc::now().format(ENEX_DATE_FORMAT)
+                );
+                self.write_str(&header)
+            }
+            ExportFormat::Csv => {
+                let header = csv_row(&["slug", "created_at", "updated_at", "tags", "content", "url"])?;
+                self.write_str(&header)
+            }
+        }
+    }
+
+    pub fn write_memo(&mut self, memo: &Memo) -> Result<(), AppError> {
+        self.count += 1;
+        if matches!(self.format, ExportFormat::Markdown) && self.options.group_by == Some(GroupBy::Tag) {
+            // A memo's tag sections aren't known to be complete until the end
+            self.buffered.push(memo.clone());
+            return Ok(());
+        }
+        let chunk = match self.format {
+            ExportFormat::Json => self.json_entry(memo)?,
+            ExportFormat::Jsonl => self.jsonl_entry(memo)?,
+            ExportFormat::Markdown => self.markdown_entry(memo),
+            ExportFormat::Table => self.table_entry(memo),
+            ExportFormat::Html => self.html_entry(memo)?,
+            ExportFormat::Enex => self.enex_entry(memo),
+            ExportFormat::Csv => self.csv_entry(memo)?,
+        };
+        self.write_str(&chunk)
+    }
+
+    /// Writes the closing bytes and hands back the underlying writer.
+    pub fn finish(mut self) -> Result<W, AppError> {
+        if !self.buffered.is_empty() {
+            self.write_tag_sections()?;
+        }
+        match self.format {
+            ExportFormat::Json => {
+                let closing = if self.count > 0 && !self.options.compact { "\n]" } else { "]" };
+                self.write_str(closing)?;
+            }
+            ExportFormat::Html => self.write_str(HTML_TAIL)?,
+            ExportFormat::Enex => self.write_str("</en-export>\n")?,
+            _ => {}
+        }
+        self.out.flush()
+            .map_err(|e| AppError::Internal(format!("Failed to write export: {}", e)))?;
+        Ok(self.out)
+    }
+
+    fn write_tag_sections(&mut self) -> Result<(), AppError> {
+        let memos = std::mem::take(&mut self.buffered);
+        let mut sections: BTreeMap<&str, Vec<&Memo>> = BTreeMap::new();
+        let mut untagged = Vec::new();
+
+        for memo in &memos {
+            let tags: Vec<&str> = if self.options.primary_tag_only {
+                primary_tag(memo).into_iter().collect()
+            } else {
+                memo.tags.iter().map(String::as_str).collect()
+            };
+            if tags.is_empty() {
+                untagged.push(memo);
+            }
+            for tag in tags {
+                sections.entry(tag).or_default().push(memo);
+            }
+        }
+
+        // Untagged memos go last rather than wherever the key happens to sort
+        let sections = sections.into_iter().chain((!untagged.is_empty()).then_some((self.options.locale.strings().untagged, untagged)));
+        for (tag, memos) in sections {
+            let mut chunk = format!("# {}\n\n", tag);
+            for memo in memos {
+                chunk.push_str(&self.markdown_entry(memo));
+            }
+            self.write_str(&chunk)?;
+        }
+        Ok(())
+    }
+
+    fn write_str(&mut self, s: &str) -> Result<(), AppError> {
+        self.out.write_all(s.as_bytes())
+            .map_err(|e| AppError::Internal(format!("Failed to write export: {}", e)))
+    }
+
+    fn json_entry(&self, memo: &Memo) -> Result<String, AppError> {
+        let date_format = &self.options.date_format;
+        let mut obj = serde_json::json!({
+            "index": self.count,
+            "content": memo.content,
+            "url": memo.url,
+            "slug": memo.slug,
+            "tags": memo.tags,
+        });
+
+        if !date_format.is_empty() {
+            obj["created_at"] = serde_json::json!(format_date(&memo.created_at, date_format, &self.tz));
+            obj["updated_at"] = serde_json::json!(format_date(&memo.updated_at, date_format, &self.tz));
+        }
+
+        let separator = if self.count > 1 { "," } else { "" };
+        if self.options.compact {
+            Ok(format!("{}{}", separator, serde_json::to_string(&obj)?))
+        } else {
+            // Indent the element by one level, matching to_string_pretty on the whole array
+            let pretty = serde_json::to_string_pretty(&obj)?.replace('\n', "\n  ");
+            Ok(format!("{}\n  {}", separator, pretty))
+        }
+    }
+
+    fn jsonl_entry(&self, memo: &Memo) -> Result<String, AppError> {
+        let date_format = &self.options.date_format;
+        let format_time = |date: &str| {
+            if date_format.is_empty() {
+                date.to_string()
+            } else {
+                format_date(date, date_format, &self.tz)
+            }
+        };
+        let content = match &memo.content_html {
+            Some(html) if self.options.raw_html => html,
+            _ => &memo.content,
+        };
+
+        let line = serde_json::json!({
+            "slug": memo.slug,
+            "content": content,
+            "created_at": format_time(&memo.created_at),
+            "updated_at": format_time(&memo.updated_at),
+            "tags": memo.tags,
+            "url": memo.url,
+            "pinned": memo.pinned,
+        });
+
+        Ok(format!("{}\n", serde_json::to_string(&line)?))
+    }
+
+    fn csv_entry(&self, memo: &Memo) -> Result<String, AppError> {
+        let date_format = &self.options.date_format;
+        let format_time = |date: &str| {
+            if date_format.is_empty() {
+                date.to_string()
+            } else {
+                format_date(date, date_format, &self.tz)
+            }
+        };
+
+        csv_row(&[
+            &memo.slug,
+            &format_time(&memo.created_at),
+            &format_time(&memo.updated_at),
+            &memo.tags.join(" "),
+            &memo.content,
+            memo.url.as_deref().unwrap_or_default(),
+        ])
+    }
+
+    fn html_entry(&mut self, memo: &Memo) -> Result<String, AppError> {
+        let body = match &memo.content_html {
+            Some(html) => self.rewrite_images(html),
+            None => escape_html(&memo.content).replace('\n', "<br>\n"),
+        };
+        let date = if self.options.date_format.is_empty() {
+            memo.created_at.clone()
+        } else {
+            format_date(&memo.created_at, &self.options.date_format, &self.tz)
+        };
+        let slug = escape_html(&memo.slug);
+
+        let mut output = format!(
+            "<article class=\"memo\" id=\"memo-{}\" data-tags=\"{}\">\n",
+            slug,
+            escape_html(&serde_json::to_string(&memo.tags)?)
+        );
+        output.push_str(&format!(
+            "  <div class=\"meta\"><a class=\"anchor\" href=\"#memo-{}\">{}</a></div>\n",
+            slug,
+            escape_html(&date)
+        ));
+        output.push_str(&format!("  <div class=\"content\">{}</div>\n", body));
+
+        if !memo.tags.is_empty() {
+            let links: Vec<String> = memo.tags.iter()
+                .map(|tag| {
+                    let tag = escape_html(tag);
+                    format!("<a class=\"tag\" href=\"#\" data-tag=\"{}\">#{}</a>", tag, tag)
+                })
+                .collect();
+            output.push_str(&format!("  <div class=\"tags\">{}</div>\n", links.join(" ")));
+        }
+
+        output.push_str("</article>\n");
+        Ok(output)
+    }
+
+    fn enex_entry(&self, memo: &Memo) -> String {
+        let enex_date = |date: &str| {
+            parse_memo_time(date, &self.tz)
+                .map(|date| date.with_timezone(&Utc).format(ENEX_DATE_FORMAT).to_string())
+                .unwrap_or_default()
+        };
+
+        // ENML is a strict XHTML subset, so build it from the text rather than
+        // passing through the API HTML
+        let body: String = memo.content.trim().lines()
+            .map(|line| match line.trim() {
+                "" => "<div><br/></div>".to_string(),
+                _ => format!("<div>{}</div>", escape_html(line)),
+            })
+            .collect();
+        let enml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n<!DOCTYPE en-note SYSTEM \"http://xml.evernote.com/pub/enml2.dtd\">\n<en-note>{}</en-note>",
+            body
+        );
+
+        let mut output = String::from("  <note>\n");
+        output.push_str(&format!("    <title>{}</title>\n", escape_html(&blog::post_title(memo))));
+        output.push_str(&format!("    <content><![CDATA[{}]]></content>\n", enml.replace("]]>", "]]]]><![CDATA[>")));
+        output.push_str(&format!("    <created>{}</created>\n", enex_date(&memo.created_at)));
+        output.push_str(&format!("    <updated>{}</updated>\n", enex_date(&memo.updated_at)));
+        for tag in &memo.tags {
+            output.push_str(&format!("    <tag>{}</tag>\n", escape_html(tag)));
+        }
+        if let Some(url) = memo.url.as_deref().filter(|url| !url.is_empty()) {
+            output.push_str(&format!("    <note-attributes><source-url>{}</source-url></note-attributes>\n", escape_html(url)));
+        }
+        output.push_str("  </note>\n");
+        output
+    }
+
+    fn rewrite_images(&mut self, html: &str) -> String {
+        if self.options.image_mode == ImageMode::Link {
+            return html.to_string();
+        }
+
+        let mut output = html.to_string();
+        for src in image_sources(html) {
+            if let Some(replacement) = self.resolve_image(&src) {
+                output = replace_image_src(&output, &src, &replacement);
+            }
+        }
+        output
+    }
+
+    /// Downloads an image for embedding; on failure the remote link is kept.
+    fn resolve_image(&mut self, src: &str) -> Option<String> {
+        if let Some(cached) = self.images.get(src) {
+            return Some(cached.clone());
+        }
+        if !src.starts_with("http://") && !src.starts_with("https://") {
+            return None;
+        }
+
+        let resolved = match self.download_image(src) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                tracing::warn!("Failed to export image {}: {}", src, e);
+                return None;
+            }
+        };
+        self.images.insert(src.to_string(), resolved.clone());
+        Some(resolved)
+    }
+
+    fn download_image(&mut self, src: &str) -> Result<String, AppError> {
+        let client = match &self.http {
+            Some(client) => client.clone(),
+            None => {
+                let client = image_client()?;
+                self.http = Some(client.clone());
+                client
+            }
+        };
+        let (content_type, bytes) = fetch_image(&client, src)?;
+
+        match (self.options.image_mode, &self.options.asset_dir) {
+            (ImageMode::Files, Some(dir)) => {
+                let file_name = format!("{}.{}", self.images.len() + 1, image_extension(&content_type));
+                std::fs::create_dir_all(dir)
+                    .and_then(|_| std::fs::write(dir.join(&file_name), &bytes))
+                    .map_err(|e| AppError::Internal(format!("Failed to save image: {}", e)))?;
+
+                let dir_name = dir.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+                Ok(format!("{}/{}", dir_name, file_name))
+            }
+            _ => Ok(format!(
+                "data:{};base64,{}",
+                content_type,
+                base64::engine::general_purpose::STANDARD.encode(&bytes)
+            )),
+        }
+    }
+
+    fn markdown_entry(&mut self, memo: &Memo) -> String {
+        let mut output = String::new();
+        if let Some(heading_format) = self.options.group_by.and_then(GroupBy::heading_format) {
+            let time = parse_memo_time(&memo.created_at, &self.tz);
+            let section = match time {
+                Some(time) => time.format(heading_format).to_string(),
+                None => memo.created_at.get(..10).unwrap_or(&memo.created_at).to_string(),
+            };
+            if self.section.as_ref() != Some(&section) {
+                output.push_str(&format!("# {}\n\n", section));
+                self.section = Some(section);
+            }
+        }
+
+        let ExportOptions { url_mode, date_format, minimal, group_by, escape_markdown, locale, .. } = &self.options;
+        let strings = locale.strings();
+        let index = self.count;
+
+        if *minimal {
+            // Minimal mode: one line per memo
+            let date = if date_format.is_empty() {
+                String::new()
+            } else {
+                format_date(&memo.created_at, date_format, &self.tz)
+            };
+            let content = memo.content.replace('\n', " ");
+            if date.is_empty() {
+                output.push_str(&format!("{}|{}\n", index, content));
+            } else {
+                output.push_str(&format!("{}|{}|{}\n", index, date, content));
+            }
+            return output;
+        }
+
+        if let Some(group_by) = group_by {
+            // The section heading carries the date, so entries aren't numbered
+            let label = match (date_format.is_empty(), parse_memo_time(&memo.created_at, &self.tz)) {
+                (false, _) => format_date(&memo.created_at, date_format, &self.tz),
+                (true, Some(time)) => match group_by {
+                    GroupBy::Day => time.format("%H:%M").to_string(),
+                    GroupBy::Week | GroupBy::Month => time.format("%m-%d %H:%M").to_string(),
+                    GroupBy::Tag => time.format("%Y-%m-%d %H:%M").to_string(),
+                },
+                (true, None) => memo.created_at.clone(),
+            };
+            output.push_str(&format!("## {}\n\n", label));
+        } else if !date_format.is_empty() {
+            let formatted_date = format_date(&memo.created_at, date_format, &self.tz);
+            output.push_str(&format!("## {}. {}\n\n", index, formatted_date));
+        } else {
+            output.push_str(&format!("## {}\n\n", index));
+        }
+
+        let escape = |text: &str| if *escape_markdown { escape_markdown_text(text) } else { text.to_string() };
+        output.push_str(&format!("{}\n", escape(memo.content.trim())));
+
+        // URL handling
+        match url_mode.as_str() {
+            "full" => {
+                if let Some(url) = &memo.url {
+                    output.push_str(&format!("**{}**: {}\n", strings.link, url));
+                }
+            },
+            "id" => {
+                output.push_str(&format!("**{}**: {}\n", strings.id, memo.slug));
+            },
+            _ => {} // "none" or any other value
+        }
+
+        // Tags
+        if !memo.tags.is_empty() {
+            output.push_str(&format!("**{}**: {}\n", strings.tags, escape(&memo.tags.join(", "))));
+        }
+
+        output.push_str("\n---\n\n");
+        output
+    }
+
+    fn table_entry(&self, memo: &Memo) -> String {
+        let date = |value: &str| {
+            if self.options.date_format.is_empty() {
+                value.split(' ').next().unwrap_or(value).to_string()
+            } else {
+                format_date(value, &self.options.date_format, &self.tz)
+            }
+        };
+
+        let cells: Vec<String> = self.options.columns.iter()
+            .map(|column| match column {
+                TableColumn::Index => format!("{:>2}", self.count),
+                TableColumn::Created => date(&memo.created_at),
+                TableColumn::Updated => date(&memo.updated_at),
+                TableColumn::Slug => memo.slug.clone(),
+                TableColumn::Tags => memo.tags.join(","),
+                TableColumn::Words => word_count(&memo.content).to_string(),
+                TableColumn::Content => {
+                    let content = memo.content.trim().replace('\n', " ");
+                    let preview_length = self.options.preview_length;
+                    if self.options.full_content || content.chars().count() <= preview_length {
+                        content
+                    } else {
+                        format!("{}...", content.chars().take(preview_length).collect::<String>())
+                    }
+                }
+            })
+            .collect();
+        let cells: Vec<&str> = cells.iter().map(String::as_str).collect();
+
+        format!("{}\n", self.table_row(&cells))
+    }
+
+    /// Joins cells with " | ", padding each but the last to its column's
+    /// display width so CJK text lines up.
+    fn table_row(&self, cells: &[&str]) -> String {
+        let last = cells.len().saturating_sub(1);
+        cells.iter().zip(&self.options.columns).enumerate()
+            .map(|(index, (cell, column))| {
+                if index == last {
+                    cell.to_string()
+                } else {
+                    pad_to_width(cell, column.width())
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+/// Pulls memos matching `options.filter` from the database in pages and
+/// writes them to `out`, calling `on_page` with the running count.
+pub fn export_from_db<W: Write>(
+    db: &Database,
+    out: W,
+    format: ExportFormat,
+    options: ExportOptions,
+    tz: FixedOffset,
+    mut on_page: impl FnMut(usize),
+) -> Result<(W, usize), AppError> {
+    const PAGE_SIZE: i64 = 500;
+    let filter = options.filter.clone();
+    let raw_html = options.raw_html || matches!(format, ExportFormat::Html);
+    let mut exporter = MemoExporter::new(out, format, options, tz)?;
+    let mut cursor = None;
+
+    loop {
+        let page = db.get_memos_after(MemoOrder::CreatedAt, "desc", &filter, false, cursor.as_ref(), PAGE_SIZE)?;
+        let mut memos = page.memos;
+        if raw_html {
+            attach_content_html(db, &mut memos)?;
+        }
+        for memo in &memos {
+            exporter.write_memo(memo)?;
+        }
+        on_page(exporter.count());
+
+        match page.next_cursor {
+            Some(next) => cursor = Some(PageCursor::decode(&next)?),
+            None => break,
+        }
+    }
+
+    let written = exporter.count();
+    Ok((exporter.finish()?, written))
+}
+
+/// Loads every memo matching `filter`, oldest first, for formats that need
+/// the whole set up front (PDF, EPUB).
+pub fn load_memos_chronological(db: &Database, filter: &MemoFilter) -> Result<Vec<Memo>, AppError> {
+    const PAGE_SIZE: i64 = 500;
+    let mut memos = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let page = db.get_memos_after(MemoOrder::CreatedAt, "asc", filter, false, cursor.as_ref(), PAGE_SIZE)?;
+        memos.extend(page.memos);
+        match page.next_cursor {
+            Some(next) => cursor = Some(PageCursor::decode(&next)?),
+            None => return Ok(memos),
+        }
+    }
+}
+
+/// Fills in `content_html` from the database for memos that lack it.
+pub fn attach_content_html(db: &Database, memos: &mut [Memo]) -> Result<(), AppError> {
+    let slugs: Vec<String> = memos.iter()
+        .filter(|memo| memo.content_html.is_none())
+        .map(|memo| memo.slug.clone())
+        .collect();
+    let mut html = db.get_content_html(&slugs)?;
+
+    for memo in memos.iter_mut().filter(|memo| memo.content_html.is_none()) {
+        memo.content_html = html.remove(&memo.slug);
+    }
+    Ok(())
+}
+
+/// Formats an in-memory list of memos, for the preview commands.
+pub fn format_to_string(memos: &[Memo], format: ExportFormat, options: ExportOptions, tz: FixedOffset) -> String {
+    let run = || -> Result<String, AppError> {
+        let mut exporter = MemoExporter::new(Vec::new(), format, options, tz)?;
+        for memo in memos {
+            exporter.write_memo(memo)?;
+        }
+        Ok(String::from_utf8_lossy(&exporter.finish()?).into_owned())
+    };
+    run().unwrap_or_default()
+}
+
+/// The memos' own HTML, separated by rules, for pasting as rich text. Memos
+/// without stored HTML fall back to their text.
+pub fn clipboard_html(memos: &[Memo]) -> String {
+    memos.iter()
+        .map(|memo| match &memo.content_html {
+            Some(html) => html.clone(),
+            None => format!("<p>{}</p>", escape_html(&memo.content).replace('\n', "<br>")),
+        })
+        .collect::<Vec<_>>()
+        .join("\n<hr>\n")
+}
+
+/// The `src` of every `<img>` in a memo's HTML, in document order.
+pub(crate) fn image_sources(html: &str) -> Vec<String> {
+    let selector = scraper::Selector::parse("img").unwrap();
+    scraper::Html::parse_fragment(html)
+        .select(&selector)
+        .filter_map(|img| img.value().attr("src").map(str::to_string))
+        .collect()
+}
+
+pub(crate) fn replace_image_src(html: &str, src: &str, replacement: &str) -> String {
+    let mut output = html.to_string();
+    // The raw markup may carry the src either verbatim or entity-escaped
+    for original in [src.to_string(), escape_html(src)] {
+        output = output.replace(&format!("\"{}\"", original), &format!("\"{}\"", replacement));
+    }
+    output
+}
+
+pub(crate) fn image_client() -> Result<reqwest::blocking::Client, AppError> {
+    Ok(reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?)
+}
+
+/// Downloads an image, returning its content type and bytes.
+pub(crate) fn fetch_image(client: &reqwest::blocking::Client, src: &str) -> Result<(String, Vec<u8>), AppError> {
+    let response = client.get(src).send()?.error_for_status()?;
+    let content_type = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("image/png")
+        .to_string();
+    Ok((content_type, response.bytes()?.to_vec()))
+}
+
+/// File extension for an image content type, e.g. "image/jpeg" -> "jpeg".
+pub(crate) fn image_extension(content_type: &str) -> &str {
+    content_type.rsplit('/').next().unwrap_or("png").split(';').next().unwrap_or("png").trim()
+}
+
+/// Backslash-escapes characters that Markdown would read as formatting, so
+/// memo text renders literally. Bare URLs are left untouched.
+pub(crate) fn escape_markdown_text(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let escaped = line.trim_start()
+                .split(' ')
+                .map(|word| {
+                    if word.starts_with("http://") || word.starts_with("https://") {
+                        return word.to_string();
+                    }
+                    let mut escaped = String::with_capacity(word.len());
+                    for ch in word.chars() {
+                        if matches!(ch, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~') {
+                            escaped.push('\\');
+                        }
+                        escaped.push(ch);
+                    }
+                    escaped
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{}{}", indent, escape_line_start(&escaped))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escapes block syntax at the start of a line: headings (including a
+/// leading #tag), list markers, numbered lists and setext/rule lines.
+fn escape_line_start(line: &str) -> String {
+    let first = line.chars().next();
+    let is_rule = line.len() >= 3 && line.chars().all(|ch| ch == '-' || ch == '=');
+    let is_bullet = matches!(first, Some('-' | '+')) && (line.len() == 1 || line[1..].starts_with(' '));
+
+    if first == Some('#') || is_rule || is_bullet {
+        return format!("\\{}", line);
+    }
+
+    // "1. item" / "1) item"
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && matches!(line[digits..].chars().next(), Some('.' | ')')) {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+
+    line.to_string()
+}
+
+/// Cuts `text` to `width` terminal cells (marking the cut with "…") and pads
+/// it with spaces to exactly that width.
+fn pad_to_width(text: &str, width: usize) -> String {
+    let mut output = String::new();
+    let mut used = 0;
+    if UnicodeWidthStr::width(text) > width {
+        for ch in text.chars() {
+            let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
+            if used + ch_width + 1 > width {
+                break;
+            }
+            output.push(ch);
+            used += ch_width;
+        }
+        output.push('…');
+        used += 1;
+    } else {
+        output.push_str(text);
+        used = UnicodeWidthStr::width(text);
+    }
+    output.push_str(&" ".repeat(width.saturating_sub(used)));
+    output
+}
+
+/// Counts CJK characters individually and other text by whitespace-separated
+/// words, which is how Chinese word counts are usually given.
+pub(crate) fn word_count(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for ch in text.chars() {
+        if is_cjk(ch) {
+            count += 1;
+            in_word = false;
+        } else if ch.is_alphanumeric() {
+            if !in_word {
+                count += 1;
+            }
+            in_word = true;
+        } else {
+            in_word = false;
+        }
+    }
+    count
+}
+
+/// Characters other than whitespace.
+pub(crate) fn char_count(text: &str) -> usize {
+    text.chars().filter(|ch| !ch.is_whitespace()).count()
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32,
+        0x3040..=0x30ff      // Hiragana, Katakana
+        | 0x3400..=0x4dbf    // CJK Extension A
+        | 0x4e00..=0x9fff    // CJK Unified Ideographs
+        | 0xac00..=0xd7af    // Hangul syllables
+        | 0xf900..=0xfaff    // CJK Compatibility Ideographs
+        | 0x20000..=0x2fa1f  // CJK Extensions B-F
+    )
+}
+
+/// The tag mentioned first in the memo text, falling back to the first
+/// tag the API listed.
+fn primary_tag(memo: &Memo) -> Option<&str> {
+    memo.tags.iter()
+        .filter_map(|tag| memo.content.find(&format!("#{}", tag)).map(|position| (position, tag)))
+        .min_by_key(|(position, _)| *position)
+        .map(|(_, tag)| tag.as_str())
+        .or_else(|| memo.tags.first().map(String::as_str))
+}
+
+// One CSV record with quoting as needed, ending in a newline
+fn csv_row(fields: &[&str]) -> Result<String, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields)
+        .map_err(|e| AppError::Internal(format!("Failed to write CSV: {}", e)))?;
+    let bytes = writer.into_inner()
+        .map_err(|e| AppError::Internal(format!("Failed to write CSV: {}", e)))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub(crate) const ENEX_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+const HTML_HEAD: &str = r##"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body { max-width: 720px; margin: 0 auto; padding: 24px 16px; font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", "PingFang SC", sans-serif; color: #1f2328; background: #f6f7f9; line-height: 1.6; }
+header { margin-bottom: 24px; }
+#filter { font-size: 14px; color: #555; }
+.memo { background: #fff; border-radius: 8px; padding: 16px 20px; margin-bottom: 16px; box-shadow: 0 1px 2px rgba(0, 0, 0, 0.06); }
+.memo.hidden { display: none; }
+.meta { font-size: 13px; margin-bottom: 8px; }
+.meta a { color: #8a8f98; text-decoration: none; }
+.content p { margin: 0 0 8px; }
+.content img { max-width: 100%; border-radius: 4px; }
+.tags { margin-top: 8px; }
+.tag { display: inline-block; margin-right: 6px; padding: 0 8px; font-size: 13px; color: #30a46c; background: #e9f6ef; border-radius: 10px; text-decoration: none; }
+</style>
+</head>
+<body>
+<header>
+<h1>{title}</h1>
+<div id="filter" hidden>{filter}<strong id="filter-tag"></strong> <a href="#" id="filter-clear">{show_all}</a></div>
+</header>
+<main>
+"##;
+
+const HTML_TAIL: &str = r##"</main>
+<script>
+(function () {
+  var memos = document.querySelectorAll(".memo");
+  function filterBy(tag) {
+    memos.forEach(function (memo) {
+      var tags = JSON.parse(memo.dataset.tags || "[]");
+      var match = !tag || tags.some(function (t) { return t === tag || t.indexOf(tag + "/") === 0; });
+      memo.classList.toggle("hidden", !match);
+    });
+    document.getElementById("filter").hidden = !tag;
+    document.getElementById("filter-tag").textContent = tag ? "#" + tag : "";
+  }
+  document.addEventListener("click", function (event) {
+    var link = event.target.closest(".tag");
+    if (link) {
+      event.preventDefault();
+      filterBy(link.dataset.tag);
+    } else if (event.target.id === "filter-clear") {
+      event.preventDefault();
+      filterBy(null);
+    }
+  });
+})();
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_markdown_text_escapes_inline_syntax() {
+        assert_eq!(escape_markdown_text("a *b* _c_ `d` [e](f) <g> x|y ~z~"), r"a \*b\* \_c\_ \`d\` \[e\](f) \<g\> x\|y \~z\~");
+        assert_eq!(escape_markdown_text(r"C:\path"), r"C:\\path");
+    }
+
+    #[test]
+    fn escape_markdown_text_keeps_bare_urls() {
+        assert_eq!(
+            escape_markdown_text("see https://example.com/a_b*c and *this*"),
+            r"see https://example.com/a_b*c and \*this\*"
+        );
+    }
+
+    #[test]
+    fn escape_markdown_text_escapes_block_syntax_per_line() {
+        assert_eq!(escape_markdown_text("#tag note\n- item\n  + nested\n1. one\n2) two\n---"), "\\#tag note\n\\- item\n  \\+ nested\n1\\. one\n2\\) two\n\\---");
+        // A dash only starts a list when a space follows
+        assert_eq!(escape_markdown_text("-5 degrees"), "-5 degrees");
+    }
+}
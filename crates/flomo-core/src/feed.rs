@@ -0,0 +1,131 @@
+use chrono::{FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::{Database, MemoFilter, MemoOrder};
+use crate::error::AppError;
+use crate::export::escape_html;
+use crate::i18n::Locale;
+use crate::{blog, parse_memo_time, Memo};
+
+const DEFAULT_LIMIT: i64 = 50;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    #[default]
+    Atom,
+    Rss,
+}
+
+/// Feed written to `path` after every successful sync.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedSettings {
+    pub path: String,
+    pub format: FeedFormat,
+    pub limit: Option<i64>,
+    pub tag: Option<String>,
+}
+
+/// Builds a feed of the `limit` most recent memos, optionally limited to a tag.
+pub fn generate_feed(
+    db: &Database,
+    format: FeedFormat,
+    limit: Option<i64>,
+    tag: Option<String>,
+    tz: &FixedOffset,
+    locale: Locale,
+) -> Result<String, AppError> {
+    let filter = MemoFilter { tag, ..Default::default() };
+    let memos = db.get_memos_page(MemoOrder::CreatedAt, "desc", &filter, false, 0, limit.unwrap_or(DEFAULT_LIMIT))?;
+
+    Ok(match format {
+        FeedFormat::Atom => atom(&memos, tz, locale.strings().title),
+        FeedFormat::Rss => rss(&memos, tz, locale.strings().title),
+    })
+}
+
+pub fn write_feed(db: &Database, settings: &FeedSettings, tz: &FixedOffset, locale: Locale) -> Result<(), AppError> {
+    let xml = generate_feed(db, settings.format, settings.limit, settings.tag.clone(), tz, locale)?;
+    std::fs::write(&settings.path, xml)
+        .map_err(|e| AppError::Internal(format!("Failed to write feed {}: {}", settings.path, e)))
+}
+
+fn entry_id(memo: &Memo) -> String {
+    format!("urn:flomo:memo:{}", memo.slug)
+}
+
+fn atom(memos: &[Memo], tz: &FixedOffset, title: &str) -> String {
+    let rfc3339 = |date: &str| {
+        parse_memo_time(date, tz).map(|date| date.to_rfc3339()).unwrap_or_else(|| date.to_string())
+    };
+    let updated = memos.iter()
+        .map(|memo| rfc3339(&memo.updated_at))
+        .max()
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+    let mut entries = String::new();
+    for memo in memos {
+        entries.push_str("  <entry>\n");
+        entries.push_str(&format!("    <id>{}</id>\n", escape_html(&entry_id(memo))));
+        entries.push_str(&format!("    <title>{}</title>\n", escape_html(&blog::post_title(memo))));
+        entries.push_str(&format!("    <published>{}</published>\n", rfc3339(&memo.created_at)));
+        entries.push_str(&format!("    <updated>{}</updated>\n", rfc3339(&memo.updated_at)));
+        if let Some(url) = memo.url.as_deref().filter(|url| !url.is_empty()) {
+            entries.push_str(&format!("    <link href=\"{}\"/>\n", escape_html(url)));
+        }
+        for tag in &memo.tags {
+            entries.push_str(&format!("    <category term=\"{}\"/>\n", escape_html(tag)));
+        }
+        entries.push_str(&format!("    <content type=\"text\">{}</content>\n", escape_html(memo.content.trim())));
+        entries.push_str("  </entry>\n");
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:flomo:memos</id>
+  <title>{}</title>
+  <updated>{}</updated>
+  <author><name>Flomo</name></author>
+{}</feed>
+"#,
+        escape_html(title), updated, entries
+    )
+}
+
+fn rss(memos: &[Memo], tz: &FixedOffset, title: &str) -> String {
+    let rfc2822 = |date: &str| {
+        parse_memo_time(date, tz).map(|date| date.to_rfc2822()).unwrap_or_else(|| date.to_string())
+    };
+
+    let mut items = String::new();
+    for memo in memos {
+        items.push_str("    <item>\n");
+        items.push_str(&format!("      <title>{}</title>\n", escape_html(&blog::post_title(memo))));
+        if let Some(url) = memo.url.as_deref().filter(|url| !url.is_empty()) {
+            items.push_str(&format!("      <link>{}</link>\n", escape_html(url)));
+        }
+        items.push_str(&format!("      <guid isPermaLink=\"false\">{}</guid>\n", escape_html(&entry_id(memo))));
+        items.push_str(&format!("      <pubDate>{}</pubDate>\n", rfc2822(&memo.created_at)));
+        for tag in &memo.tags {
+            items.push_str(&format!("      <category>{}</category>\n", escape_html(tag)));
+        }
+        items.push_str(&format!("      <description>{}</description>\n", escape_html(memo.content.trim())));
+        items.push_str("    </item>\n");
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0">
+  <channel>
+    <title>{}</title>
+    <link>https://v.flomoapp.com/mine</link>
+    <description>{}</description>
+    <lastBuildDate>{}</lastBuildDate>
+{}  </channel>
+</rss>
+"#,
+        escape_html(title), escape_html(title), Utc::now().to_rfc2822(), items
+    )
+}
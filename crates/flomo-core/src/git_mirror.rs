@@ -0,0 +1,211 @@
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::blog::{post_title, yaml_string};
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export;
+use crate::{parse_memo_time, Memo};
+
+/// Folder inside the repository holding the memo files; everything else in
+/// the repository is left alone.
+const MEMO_DIR: &str = "memos";
+/// Memo titles listed in a commit message before the rest are counted.
+const MAX_LISTED: usize = 20;
+
+/// A local git repository mirroring memos as Markdown, committed after
+/// every successful sync.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitMirrorSettings {
+    /// Repository directory; initialized if it isn't one yet
+    pub path: String,
+    /// Only mirror memos with this tag or a tag below it
+    pub tag: Option<String>,
+    /// Push to `remote` after committing
+    pub push: bool,
+    pub remote: String,
+}
+
+impl Default for GitMirrorSettings {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            tag: None,
+            push: false,
+            remote: "origin".to_string(),
+        }
+    }
+}
+
+/// What a mirror run changed.
+#[derive(Debug, Default, Serialize)]
+pub struct GitMirrorReport {
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    /// Hash of the new commit; `None` when nothing changed
+    pub commit: Option<String>,
+    pub pushed: bool,
+}
+
+/// Writes one Markdown file per memo to `memos/YYYY/MM/<slug>.md`, deletes
+/// files of memos that are gone, and commits the difference with a message
+/// listing what changed.
+pub fn mirror(db: &Database, settings: &GitMirrorSettings, tz: &FixedOffset) -> Result<GitMirrorReport, AppError> {
+    let repo = PathBuf::from(settings.path.trim());
+    if repo.as_os_str().is_empty() {
+        return Err(AppError::InvalidInput("Git mirror path is empty".to_string()));
+    }
+    std::fs::create_dir_all(&repo)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", repo.display(), e)))?;
+    if !repo.join(".git").exists() {
+        git(&repo, &["init", "--quiet"])?;
+    }
+
+    let filter = MemoFilter { tag: settings.tag.clone(), ..Default::default() };
+    let memos = export::load_memos_chronological(db, &filter)?;
+
+    let mut report = GitMirrorReport::default();
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut removed = Vec::new();
+    let mut expected = HashSet::new();
+    for memo in &memos {
+        let relative = memo_path(memo, tz);
+        let path = repo.join(&relative);
+        let content = render(memo);
+        expected.insert(relative);
+
+        match std::fs::read_to_string(&path) {
+            Ok(existing) if existing == content => continue,
+            Ok(_) => changed.push(post_title(memo)),
+            Err(_) => added.push(post_title(memo)),
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        std::fs::write(&path, content)
+            .map_err(|e| AppError::Internal(format!("Failed to write {}: {}", path.display(), e)))?;
+    }
+
+    // A memo moves when its date changes, so stale files are found by path
+    for relative in markdown_files(&repo, Path::new(MEMO_DIR))? {
+        if !expected.contains(&relative) {
+            let path = repo.join(&relative);
+            std::fs::remove_file(&path)
+                .map_err(|e| AppError::Internal(format!("Failed to remove {}: {}", path.display(), e)))?;
+            removed.push(relative.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+        }
+    }
+    report.added = added.len();
+    report.changed = changed.len();
+    report.removed = removed.len();
+
+    git(&repo, &["add", "--all", "--", MEMO_DIR])?;
+    if git(&repo, &["status", "--porcelain", "--", MEMO_DIR])?.trim().is_empty() {
+        return Ok(report);
+    }
+    let message = commit_message(&added, &changed, &removed);
+    let mut commit = vec!["commit", "--quiet", "-m", &message, "--", MEMO_DIR];
+    // Commits still work on machines without a git identity
+    if git(&repo, &["config", "user.email"]).is_err() {
+        commit.splice(0..0, ["-c", "user.name=Flomo Garden", "-c", "user.email=flomo-garden@localhost"]);
+    }
+    git(&repo, &commit)?;
+    report.commit = Some(git(&repo, &["rev-parse", "HEAD"])?.trim().to_string());
+
+    if settings.push {
+        git(&repo, &["push", "--quiet", &settings.remote, "HEAD"])?;
+        report.pushed = true;
+    }
+    Ok(report)
+}
+
+fn memo_path(memo: &Memo, tz: &FixedOffset) -> PathBuf {
+    let dir = match parse_memo_time(&memo.created_at, tz) {
+        Some(date) => Path::new(MEMO_DIR).join(date.format("%Y").to_string()).join(date.format("%m").to_string()),
+        None => Path::new(MEMO_DIR).join("undated"),
+    };
+    dir.join(format!("{}.md", memo.slug))
+}
+
+fn render(memo: &Memo) -> String {
+    let tags: Vec<String> = memo.tags.iter().map(|tag| yaml_string(tag)).collect();
+    let mut lines = vec![
+        "---".to_string(),
+        format!("slug: {}", yaml_string(&memo.slug)),
+        format!("created: {}", yaml_string(&memo.created_at)),
+        format!("updated: {}", yaml_string(&memo.updated_at)),
+        format!("tags: [{}]", tags.join(", ")),
+    ];
+    if let Some(url) = memo.url.as_deref().filter(|url| !url.is_empty()) {
+        lines.push(format!("url: {}", yaml_string(url)));
+    }
+    lines.push("---".to_string());
+
+    format!("{}\n\n{}\n", lines.join("\n"), memo.content.trim())
+}
+
+// Subject with the counts, then the titles of the first few memos
+fn commit_message(added: &[String], changed: &[String], removed: &[String]) -> String {
+    let counts: Vec<String> = [(added, "added"), (changed, "updated"), (removed, "removed")]
+        .iter()
+        .filter(|(titles, _)| !titles.is_empty())
+        .map(|(titles, label)| format!("{} {}", titles.len(), label))
+        .collect();
+    let mut message = format!("Sync memos: {}\n", counts.join(", "));
+
+    let listed = [("A", added), ("M", changed), ("D", removed)]
+        .into_iter()
+        .flat_map(|(mark, titles)| titles.iter().map(move |title| format!("{} {}", mark, title)));
+    let total = added.len() + changed.len() + removed.len();
+    message.push('\n');
+    for line in listed.take(MAX_LISTED) {
+        message.push_str(&line);
+        message.push('\n');
+    }
+    if total > MAX_LISTED {
+        message.push_str(&format!("… and {} more\n", total - MAX_LISTED));
+    }
+    message
+}
+
+// Markdown files under `dir`, relative to `repo`
+fn markdown_files(repo: &Path, dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let entries = match std::fs::read_dir(repo.join(dir)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::Internal(format!("Failed to read {}: {}", dir.display(), e))),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let relative = dir.join(entry.file_name());
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            files.extend(markdown_files(repo, &relative)?);
+        } else if relative.extension().is_some_and(|extension| extension == "md") {
+            files.push(relative);
+        }
+    }
+    Ok(files)
+}
+
+// Runs git in `repo`, returning its standard output
+fn git(repo: &Path, args: &[&str]) -> Result<String, AppError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| AppError::Internal(format!("Failed to run git (is it installed?): {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::Internal(format!("git {} failed: {}", args.join(" "), stderr.trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::TagGraph;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    /// Graphviz DOT, for `dot -Tsvg` and friends
+    Dot,
+    /// Mermaid flowchart, renders in Markdown on GitHub, Obsidian and others
+    Mermaid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagGraphResult {
+    #[serde(flatten)]
+    pub graph: TagGraph,
+    /// DOT or Mermaid source, when a format was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rendered: Option<String>,
+}
+
+pub fn render(graph: &TagGraph, format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Dot => to_dot(graph),
+        GraphFormat::Mermaid => to_mermaid(graph),
+    }
+}
+
+fn to_dot(graph: &TagGraph) -> String {
+    let mut output = String::from("graph tags {\n");
+    output.push_str("    node [shape=ellipse];\n");
+
+    for node in &graph.nodes {
+        output.push_str(&format!(
+            "    \"{}\" [label=\"{} ({})\"];\n",
+            escape_dot(&node.tag),
+            escape_dot(&node.tag),
+            node.count
+        ));
+    }
+    for edge in &graph.edges {
+        output.push_str(&format!(
+            "    \"{}\" -- \"{}\" [weight={}, penwidth={:.1}];\n",
+            escape_dot(&edge.source),
+            escape_dot(&edge.target),
+            edge.weight,
+            // Keep heavy edges visible without drowning out the rest
+            (edge.weight as f64).sqrt().clamp(1.0, 8.0)
+        ));
+    }
+
+    output.push_str("}\n");
+    output
+}
+
+fn to_mermaid(graph: &TagGraph) -> String {
+    let mut output = String::from("graph LR\n");
+
+    // Mermaid ids must be plain identifiers, so tags only appear in labels
+    let ids: std::collections::HashMap<&str, String> = graph.nodes.iter()
+        .enumerate()
+        .map(|(i, node)| (node.tag.as_str(), format!("t{}", i)))
+        .collect();
+
+    for node in &graph.nodes {
+        output.push_str(&format!(
+            "    {}[\"#{} ({})\"]\n",
+            ids[node.tag.as_str()],
+            escape_mermaid(&node.tag),
+            node.count
+        ));
+    }
+    for edge in &graph.edges {
+        if let (Some(source), Some(target)) = (ids.get(edge.source.as_str()), ids.get(edge.target.as_str())) {
+            output.push_str(&format!("    {} ---|{}| {}\n", source, edge.weight, target));
+        }
+    }
+
+    output
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
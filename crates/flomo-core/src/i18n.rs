@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+/// Language of the labels and headings written into exported files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en-US")]
+    EnUs,
+}
+
+/// Generated output strings for one locale.
+pub struct Strings {
+    pub title: &'static str,
+    pub link: &'static str,
+    pub id: &'static str,
+    pub tags: &'static str,
+    /// Section or chapter for memos without tags
+    pub untagged: &'static str,
+    pub index: &'static str,
+    pub created: &'static str,
+    pub updated: &'static str,
+    pub words: &'static str,
+    pub content: &'static str,
+    pub content_preview: &'static str,
+    pub month: &'static str,
+    pub count: &'static str,
+    pub memos_sheet: &'static str,
+    pub summary_sheet: &'static str,
+    pub filter: &'static str,
+    pub show_all: &'static str,
+}
+
+const ZH_CN: Strings = Strings {
+    title: "Flomo 备忘录",
+    link: "链接",
+    id: "ID",
+    tags: "标签",
+    untagged: "未分类",
+    index: "序号",
+    created: "创建时间",
+    updated: "更新时间",
+    words: "字数",
+    content: "内容",
+    content_preview: "内容预览",
+    month: "月份",
+    count: "数量",
+    memos_sheet: "备忘录",
+    summary_sheet: "统计",
+    filter: "筛选：",
+    show_all: "显示全部",
+};
+
+const EN_US: Strings = Strings {
+    title: "Flomo Memos",
+    link: "Link",
+    id: "ID",
+    tags: "Tags",
+    untagged: "Untagged",
+    index: "No.",
+    created: "Created",
+    updated: "Updated",
+    words: "Words",
+    content: "Content",
+    content_preview: "Preview",
+    month: "Month",
+    count: "Count",
+    memos_sheet: "Memos",
+    summary_sheet: "Summary",
+    filter: "Filter: ",
+    show_all: "Show all",
+};
+
+impl Locale {
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Locale::ZhCn => &ZH_CN,
+            Locale::EnUs => &EN_US,
+        }
+    }
+
+    /// Summary line on the PDF cover page.
+    pub fn cover_stats(self, memos: usize, tags: usize, characters: usize) -> String {
+        match self {
+            Locale::ZhCn => format!("{} 条备忘录 · {} 个标签 · {} 字", memos, tags, characters),
+            Locale::EnUs => format!("{} memos · {} tags · {} characters", memos, tags, characters),
+        }
+    }
+
+    /// Subject of the weekly digest email.
+    pub fn digest_subject(self, first_day: &str, last_day: &str) -> String {
+        match self {
+            Locale::ZhCn => format!("Flomo 每周回顾 · {} – {}", first_day, last_day),
+            Locale::EnUs => format!("Your Flomo week · {} – {}", first_day, last_day),
+        }
+    }
+
+    /// Stats line at the top of the weekly digest.
+    pub fn digest_summary(self, memos: usize, active_days: usize, streak: i64, total: i64) -> String {
+        match self {
+            Locale::ZhCn => format!(
+                "本周 {} 条备忘录，{} 天有记录 · 连续记录 {} 天 · 共 {} 条",
+                memos, active_days, streak, total
+            ),
+            Locale::EnUs => format!(
+                "{} memos on {} days this week · {}-day streak · {} in total",
+                memos, active_days, streak, total
+            ),
+        }
+    }
+}
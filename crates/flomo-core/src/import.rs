@@ -0,0 +1,823 @@
+use base64::Engine;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::db::{normalize_tag, Database};
+use crate::error::AppError;
+use crate::export::{escape_html, image_extension, ENEX_DATE_FORMAT};
+use crate::Memo;
+
+const MEMO_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// Header names tried when the column mapping leaves a field unset
+const CONTENT_COLUMNS: &[&str] = &["content", "text", "body", "note", "内容"];
+const TITLE_COLUMNS: &[&str] = &["title", "name", "标题"];
+const CREATED_COLUMNS: &[&str] = &["created_at", "created", "created time", "date", "创建时间"];
+const UPDATED_COLUMNS: &[&str] = &["updated_at", "updated", "last edited time", "修改时间", "更新时间"];
+const TAGS_COLUMNS: &[&str] = &["tags", "labels", "标签"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MarkdownImportOptions {
+    /// Descend into subfolders (hidden ones such as .obsidian are skipped)
+    pub recursive: bool,
+    /// Tags added to every imported memo, e.g. "imported"
+    pub extra_tags: Vec<String>,
+    /// Create the memos on Flomo as well instead of keeping them local only
+    pub push_to_flomo: bool,
+    pub merge: MergeStrategy,
+}
+
+impl Default for MarkdownImportOptions {
+    fn default() -> Self {
+        Self {
+            recursive: true,
+            extra_tags: Vec::new(),
+            push_to_flomo: false,
+            merge: MergeStrategy::default(),
+        }
+    }
+}
+
+/// What to do with an imported memo that matches a stored one, either by
+/// slug (the same file or row imported before) or by content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeStrategy {
+    /// Keep the stored memo and drop the import
+    #[default]
+    Skip,
+    /// Replace the stored memo with the import
+    Overwrite,
+    /// Keep both, giving the import a suffixed slug if its own is taken
+    Duplicate,
+}
+
+/// Imported memos sorted by what the merge strategy does with them.
+#[derive(Debug, Default)]
+pub struct ImportPlan {
+    /// Memos with no stored match, and duplicates kept alongside one
+    pub new: Vec<Memo>,
+    /// Imports carrying the slug of the stored memo they replace
+    pub overwrite: Vec<Memo>,
+    pub skipped: usize,
+}
+
+/// Counts shown to the user after an import.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    /// Memos added (or that would be, in a dry run)
+    pub created: usize,
+    /// Stored memos replaced by an import
+    pub updated: usize,
+    /// Imports dropped as duplicates or identical to what's stored
+    pub skipped: usize,
+    /// Memos created on Flomo
+    pub pushed: usize,
+    /// Files or rows that couldn't be imported, with the reason
+    pub errors: Vec<String>,
+}
+
+/// Matches `memos` against the stored memos and each other by slug and by
+/// content hash, and applies `strategy` to the matches.
+pub fn plan_import(db: &Database, memos: Vec<Memo>, strategy: MergeStrategy) -> Result<ImportPlan, AppError> {
+    let stored = db.get_memo_contents()?;
+    let mut slugs: HashSet<String> = stored.iter().map(|(slug, _)| slug.clone()).collect();
+    let mut by_content: HashMap<String, String> = stored.into_iter()
+        .map(|(slug, content)| (content_hash(&content), slug))
+        .collect();
+
+    let mut plan = ImportPlan::default();
+    for mut memo in memos {
+        let hash = content_hash(&memo.content);
+        let existing = match slugs.contains(&memo.slug) {
+            true => Some(memo.slug.clone()),
+            false => by_content.get(&hash).cloned(),
+        };
+
+        match (existing, strategy) {
+            (None, _) => {}
+            (Some(_), MergeStrategy::Skip) => {
+                plan.skipped += 1;
+                continue;
+            }
+            (Some(slug), MergeStrategy::Overwrite) => {
+                memo.slug = slug;
+                plan.overwrite.push(memo);
+                continue;
+            }
+            (Some(_), MergeStrategy::Duplicate) => {
+                if slugs.contains(&memo.slug) {
+                    memo.slug = (2..)
+                        .map(|n| format!("{}-{}", memo.slug, n))
+                        .find(|slug| !slugs.contains(slug))
+                        .unwrap_or_default();
+                }
+            }
+        }
+
+        slugs.insert(memo.slug.clone());
+        by_content.insert(hash, memo.slug.clone());
+        plan.new.push(memo);
+    }
+
+    Ok(plan)
+}
+
+// Whitespace differences between tools shouldn't make a memo look new
+pub(crate) fn content_hash(content: &str) -> String {
+    let normalized = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{:x}", md5::compute(normalized.as_bytes()))
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EnexImportOptions {
+    /// Tags added to every imported note, e.g. "evernote"
+    pub extra_tags: Vec<String>,
+    /// Create the notes on Flomo as well instead of keeping them local only
+    pub push_to_flomo: bool,
+    pub merge: MergeStrategy,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TwitterImportOptions {
+    /// Also import replies to other accounts, which read oddly without context
+    pub include_replies: bool,
+    /// Create the tweets on Flomo as well instead of keeping them local only
+    pub push_to_flomo: bool,
+    pub merge: MergeStrategy,
+}
+
+#[derive(Deserialize)]
+struct ArchivedTweet {
+    tweet: Tweet,
+}
+
+#[derive(Deserialize)]
+struct Tweet {
+    id_str: String,
+    full_text: String,
+    created_at: String,
+    #[serde(default)]
+    retweeted: bool,
+    #[serde(default)]
+    in_reply_to_user_id_str: Option<String>,
+    #[serde(default)]
+    entities: TweetEntities,
+}
+
+#[derive(Default, Deserialize)]
+struct TweetEntities {
+    #[serde(default)]
+    hashtags: Vec<TweetHashtag>,
+    #[serde(default)]
+    urls: Vec<TweetUrl>,
+}
+
+#[derive(Deserialize)]
+struct TweetHashtag {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct TweetUrl {
+    url: String,
+    expanded_url: String,
+}
+
+/// Header names of the CSV columns that hold each memo field. Fields left
+/// unset are looked up under common names such as "content" or "Created time".
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CsvColumnMapping {
+    pub content: Option<String>,
+    /// Written as the memo's first line
+    pub title: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    /// Tags separated by commas or semicolons, or as "#a #b"
+    pub tags: Option<String>,
+}
+
+/// Reads every .md file under `dir` into a memo. Dates and tags come from
+/// YAML front matter when present; otherwise a date on the first line is
+/// used, falling back to the file's modification time. Inline #tags are
+/// picked up from the text.
+pub fn read_markdown_dir(
+    dir: &Path,
+    options: &MarkdownImportOptions,
+    tz: FixedOffset,
+) -> Result<(Vec<Memo>, Vec<String>), AppError> {
+    let mut files = Vec::new();
+    collect_markdown_files(dir, options.recursive, &mut files)?;
+    files.sort();
+
+    let mut memos = Vec::new();
+    let mut errors = Vec::new();
+    for path in files {
+        match read_markdown_file(&path, &options.extra_tags, &tz) {
+            Ok(memo) => memos.push(memo),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    Ok((memos, errors))
+}
+
+fn collect_markdown_files(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<(), AppError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", dir.display(), e)))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if path.is_dir() {
+            if recursive && !hidden {
+                collect_markdown_files(&path, recursive, files)?;
+            }
+        } else if !hidden && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("md")) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn read_markdown_file(path: &Path, extra_tags: &[String], tz: &FixedOffset) -> Result<Memo, AppError> {
+    let text = std::fs::read_to_string(path).map_err(|e| AppError::Internal(e.to_string()))?;
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+
+    let (front_matter, body) = split_front_matter(&text);
+    let field = |keys: &[&str]| {
+        front_matter.iter()
+            .find(|(key, _)| keys.contains(&key.as_str()))
+            .map(|(_, value)| value.as_slice())
+    };
+
+    let mut created_at = field(&["date", "created", "created_at"])
+        .and_then(|values| values.first())
+        .and_then(|value| parse_import_date(value, tz));
+    let mut body = body.trim();
+
+    // "# 2024-05-12 09:30" or a bare date on the first line
+    if created_at.is_none() {
+        if let Some((first, rest)) = body.split_once('\n').or(Some((body, ""))) {
+            if let Some(date) = parse_import_date(first.trim_start_matches('#').trim(), tz) {
+                created_at = Some(date);
+                body = rest.trim();
+            }
+        }
+    }
+
+    if body.is_empty() {
+        return Err(AppError::InvalidInput("File has no content".to_string()));
+    }
+
+    let created_at = match created_at {
+        Some(date) => date,
+        None => file_time(path, tz)?,
+    };
+    let updated_at = field(&["updated", "updated_at", "lastmod"])
+        .and_then(|values| values.first())
+        .and_then(|value| parse_import_date(value, tz))
+        .unwrap_or_else(|| created_at.clone());
+
+    let mut tags: Vec<String> = Vec::new();
+    let front_matter_tags = field(&["tags", "tag"]).unwrap_or_default();
+    for tag in front_matter_tags.iter().chain(&inline_tags(body)).chain(extra_tags) {
+        let tag = normalize_tag(tag);
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    // The same file always maps to the same memo, so re-imports update it
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let slug = format!("local-{:x}", md5::compute(canonical.to_string_lossy().as_bytes()));
+
+    Ok(Memo {
+        slug,
+        content: body.to_string(),
+        created_at,
+        updated_at,
+        tags,
+        url: None,
+        pinned: false,
+        local: true,
+        content_html: None,
+        word_count: None,
+        char_count: None,
+    })
+}
+
+/// Splits a leading `---` YAML block into `key: values` pairs. Only the flat
+/// subset blog front matter uses is understood: scalars, `[a, b]` lists and
+/// `- item` lists.
+fn split_front_matter(text: &str) -> (Vec<(String, Vec<String>)>, &str) {
+    let Some(rest) = text.strip_prefix("---\n") else {
+        return (Vec::new(), text);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (Vec::new(), text);
+    };
+    let body = rest[end + 4..].trim_start_matches('-');
+
+    let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+    for line in rest[..end].lines() {
+        let unquote = |value: &str| value.trim().trim_matches('"').trim_matches('\'').to_string();
+
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some((_, values)) = fields.last_mut() {
+                values.push(unquote(item));
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let value = value.trim();
+        let values = match value.strip_prefix('[').and_then(|list| list.strip_suffix(']')) {
+            Some(list) => list.split(',').map(unquote).filter(|item| !item.is_empty()).collect(),
+            None if value.is_empty() => Vec::new(),
+            None => vec![unquote(value)],
+        };
+        fields.push((key.trim().to_lowercase(), values));
+    }
+
+    (fields, body)
+}
+
+/// `#tag` words in the text; a lone `#` (a heading marker) isn't a tag.
+pub fn inline_tags(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .map(|tag| tag.trim_end_matches(|ch: char| ",.;:!?，。；：！？)）".contains(ch)))
+        .filter(|tag| !tag.is_empty() && !tag.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses the date formats other tools commonly write and returns it in the
+/// memo format ("2024-05-12 09:30:00") in the local timezone `tz`.
+pub(crate) fn parse_import_date(value: &str, tz: &FixedOffset) -> Option<String> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(tz).format(MEMO_TIME_FORMAT).to_string());
+    }
+    if let Ok(date) = DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S %z") {
+        return Some(date.with_timezone(tz).format(MEMO_TIME_FORMAT).to_string());
+    }
+
+    // Notion writes "March 5, 2024 10:30 AM"
+    let date_times = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+        "%B %d, %Y %I:%M %p",
+    ];
+    let dates = ["%Y-%m-%d", "%Y/%m/%d", "%Y_%m_%d", "%Y年%m月%d日", "%B %d, %Y"];
+    date_times.iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            dates.iter()
+                .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|date| date.format(MEMO_TIME_FORMAT).to_string())
+}
+
+fn file_time(path: &Path, tz: &FixedOffset) -> Result<String, AppError> {
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let modified: DateTime<chrono::Utc> = modified.into();
+    Ok(tz.from_utc_datetime(&modified.naive_utc()).format(MEMO_TIME_FORMAT).to_string())
+}
+
+/// HTML for creating `memo` on Flomo: one paragraph per line, with tags that
+/// only came from front matter or options appended so Flomo sees them.
+pub fn memo_to_flomo_html(memo: &Memo) -> String {
+    flomo_html(&memo.content, &memo.tags)
+}
+
+/// HTML for `content` with any of `tags` not already written in it appended.
+pub fn flomo_html(content: &str, tags: &[String]) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let inline = inline_tags(content);
+    let missing: Vec<String> = tags.iter()
+        .filter(|tag| !inline.contains(tag))
+        .map(|tag| format!("#{}", tag))
+        .collect();
+    if !missing.is_empty() {
+        lines.push(missing.join(" "));
+    }
+
+    lines.iter().map(|line| format!("<p>{}</p>", escape_html(line))).collect()
+}
+
+/// Reads the rows of a CSV export from another tool into memos, one per row.
+pub fn read_csv(path: &Path, mapping: &CsvColumnMapping, tz: &FixedOffset) -> Result<(Vec<Memo>, Vec<String>), AppError> {
+    let read_err = |e: csv::Error| AppError::Internal(format!("Failed to read {}: {}", path.display(), e));
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .map_err(read_err)?;
+    let headers = reader.headers().map_err(read_err)?.clone();
+
+    let column = |name: &Option<String>, defaults: &[&str]| match name {
+        Some(name) => headers.iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(name.trim()))
+            .map(Some)
+            .ok_or_else(|| AppError::InvalidInput(format!("Column \"{}\" not found", name))),
+        None => Ok(headers.iter()
+            .position(|header| defaults.iter().any(|default| header.trim().eq_ignore_ascii_case(default)))),
+    };
+    let content_column = column(&mapping.content, CONTENT_COLUMNS)?
+        .ok_or_else(|| AppError::InvalidInput("No content column found; choose one in the column mapping".to_string()))?;
+    let title_column = column(&mapping.title, TITLE_COLUMNS)?;
+    let created_column = column(&mapping.created_at, CREATED_COLUMNS)?;
+    let updated_column = column(&mapping.updated_at, UPDATED_COLUMNS)?;
+    let tags_column = column(&mapping.tags, TAGS_COLUMNS)?;
+
+    let now = Utc::now().with_timezone(tz).format(MEMO_TIME_FORMAT).to_string();
+    let mut memos = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, record) in reader.records().enumerate() {
+        // Line 1 is the header
+        let row = i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(format!("Row {}: {}", row, e));
+                continue;
+            }
+        };
+        let field = |column: Option<usize>| {
+            column.and_then(|column| record.get(column))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let content = match (field(title_column), field(Some(content_column))) {
+            (Some(title), Some(content)) => format!("{}\n{}", title, content),
+            (title, content) => title.or(content).unwrap_or_default().to_string(),
+        };
+        if content.is_empty() {
+            errors.push(format!("Row {}: no content", row));
+            continue;
+        }
+
+        let created = field(created_column);
+        let created_at = match created.map(|value| (value, parse_import_date(value, tz))) {
+            Some((_, Some(date))) => date,
+            Some((value, None)) => {
+                errors.push(format!("Row {}: unrecognized date \"{}\"", row, value));
+                continue;
+            }
+            None => now.clone(),
+        };
+        let updated_at = field(updated_column)
+            .and_then(|value| parse_import_date(value, tz))
+            .unwrap_or_else(|| created_at.clone());
+
+        let mut tags: Vec<String> = Vec::new();
+        let listed = field(tags_column).unwrap_or_default()
+            .split([',', ';', '，', '；'])
+            .flat_map(|part| match part.trim() {
+                part if part.starts_with('#') => inline_tags(part),
+                part => vec![part.to_string()],
+            });
+        for tag in listed.chain(inline_tags(&content)) {
+            let tag = normalize_tag(&tag);
+            if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+
+        // Derived from the row itself so re-importing the same file matches by slug
+        let key = format!("{}\n{}", created.unwrap_or_default(), content);
+        let slug = format!("local-{:x}", md5::compute(key.as_bytes()));
+
+        memos.push(Memo {
+            slug,
+            content,
+            created_at,
+            updated_at,
+            tags,
+            url: None,
+            pinned: false,
+            local: true,
+            content_html: None,
+            word_count: None,
+            char_count: None,
+        });
+    }
+
+    Ok((memos, errors))
+}
+
+/// Reads the notes of an Evernote export into memos. Attachments are saved
+/// to `attachments_dir` as `<md5>.<ext>` and linked from the memo HTML where
+/// the note embedded them.
+pub fn read_enex(
+    path: &Path,
+    options: &EnexImportOptions,
+    attachments_dir: &Path,
+    tz: FixedOffset,
+) -> Result<(Vec<Memo>, Vec<String>), AppError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+    let parsing = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+    let document = roxmltree::Document::parse_with_options(&text, parsing)
+        .map_err(|e| AppError::Parse(format!("Invalid ENEX file: {}", e)))?;
+
+    let mut memos = Vec::new();
+    let mut errors = Vec::new();
+    let notes = document.root_element().children().filter(|node| node.has_tag_name("note"));
+    for (i, note) in notes.enumerate() {
+        match read_enex_note(note, &options.extra_tags, attachments_dir, &tz) {
+            Ok(memo) => memos.push(memo),
+            Err(e) => errors.push(format!("Note {}: {}", i + 1, e)),
+        }
+    }
+
+    Ok((memos, errors))
+}
+
+fn read_enex_note(
+    note: roxmltree::Node,
+    extra_tags: &[String],
+    attachments_dir: &Path,
+    tz: &FixedOffset,
+) -> Result<Memo, AppError> {
+    let child_text = |name: &str| {
+        note.children()
+            .find(|child| child.has_tag_name(name))
+            .and_then(|child| child.text())
+            .map(str::trim)
+            .unwrap_or_default()
+    };
+    let enex_date = |value: &str| {
+        NaiveDateTime::parse_from_str(value, ENEX_DATE_FORMAT)
+            .ok()
+            .map(|date| tz.from_utc_datetime(&date).format(MEMO_TIME_FORMAT).to_string())
+    };
+
+    let title = child_text("title");
+    let enml = child_text("content");
+    let created = child_text("created");
+    let created_at = match created {
+        "" => Utc::now().with_timezone(tz).format(MEMO_TIME_FORMAT).to_string(),
+        value => enex_date(value).ok_or_else(|| AppError::Parse(format!("Unrecognized date \"{}\"", value)))?,
+    };
+    let updated_at = enex_date(child_text("updated")).unwrap_or_else(|| created_at.clone());
+
+    let attachments = note.children()
+        .filter(|child| child.has_tag_name("resource"))
+        .map(|resource| save_attachment(resource, attachments_dir))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut html = enml_to_html(enml, &attachments);
+    if !title.is_empty() {
+        html = format!("<p>{}</p>{}", escape_html(title), html);
+    }
+    // Attachments the note body never embedded still belong to the memo
+    for (_, embed) in &attachments {
+        if !html.contains(embed.as_str()) {
+            html.push_str(embed);
+        }
+    }
+
+    let content = crate::parse_html_to_text(&html).trim().to_string();
+    if content.is_empty() {
+        return Err(AppError::InvalidInput("Note has no content".to_string()));
+    }
+
+    let mut tags: Vec<String> = Vec::new();
+    let note_tags = note.children()
+        .filter(|child| child.has_tag_name("tag"))
+        .filter_map(|child| child.text())
+        .map(str::to_string);
+    for tag in note_tags.chain(inline_tags(&content)).chain(extra_tags.iter().cloned()) {
+        let tag = normalize_tag(&tag);
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    let key = format!("{}\n{}\n{}", created, title, enml);
+    let slug = format!("local-{:x}", md5::compute(key.as_bytes()));
+
+    Ok(Memo {
+        slug,
+        content,
+        created_at,
+        updated_at,
+        tags,
+        url: None,
+        pinned: false,
+        local: true,
+        content_html: Some(html),
+        word_count: None,
+        char_count: None,
+    })
+}
+
+/// Writes a note resource to `dir` and returns its MD5 hash (what ENML
+/// `<en-media>` elements refer to) with the HTML that embeds it.
+fn save_attachment(resource: roxmltree::Node, dir: &Path) -> Result<(String, String), AppError> {
+    let child_text = |name: &str| {
+        resource.descendants()
+            .find(|child| child.has_tag_name(name))
+            .and_then(|child| child.text())
+            .map(str::trim)
+            .unwrap_or_default()
+    };
+
+    let data: String = child_text("data").split_whitespace().collect();
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data)
+        .map_err(|e| AppError::Parse(format!("Invalid attachment data: {}", e)))?;
+    let mime = child_text("mime");
+    let file_name = child_text("file-name");
+    let hash = format!("{:x}", md5::compute(&bytes));
+
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_else(|| image_extension(mime));
+    let path = dir.join(format!("{}.{}", hash, extension));
+    let write_err = |e: std::io::Error| AppError::Internal(format!("Failed to write {}: {}", path.display(), e));
+    std::fs::create_dir_all(dir).map_err(write_err)?;
+    std::fs::write(&path, &bytes).map_err(write_err)?;
+
+    let src = escape_html(&path.to_string_lossy());
+    let embed = if mime.starts_with("image/") {
+        format!("<p><img src=\"{}\"></p>", src)
+    } else {
+        let name = if file_name.is_empty() { hash.as_str() } else { file_name };
+        format!("<p><a href=\"{}\">{}</a></p>", src, escape_html(name))
+    };
+
+    Ok((hash, embed))
+}
+
+/// Unwraps the `<en-note>` body and replaces `<en-media>` embeds with the
+/// HTML for the matching saved attachment.
+fn enml_to_html(enml: &str, attachments: &[(String, String)]) -> String {
+    let start = enml.find("<en-note")
+        .and_then(|i| enml[i..].find('>').map(|j| i + j + 1))
+        .unwrap_or(0);
+    let end = enml.rfind("</en-note>").filter(|&end| end >= start).unwrap_or(enml.len());
+
+    let mut body = &enml[start..end];
+    let mut html = String::new();
+    while let Some(pos) = body.find("<en-media") {
+        html.push_str(&body[..pos]);
+        let rest = &body[pos..];
+        let tag_end = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+        let tag = &rest[..tag_end];
+
+        let hash = tag.split("hash=\"").nth(1).and_then(|value| value.split('"').next()).unwrap_or_default();
+        if let Some((_, embed)) = attachments.iter().find(|(h, _)| h == hash) {
+            html.push_str(embed);
+        }
+
+        body = &rest[tag_end..];
+        if !tag.ends_with("/>") {
+            body = body.strip_prefix("</en-media>").unwrap_or(body);
+        }
+    }
+    html.push_str(body);
+
+    html
+}
+
+/// Reads the account's own tweets from an X/Twitter data export into memos
+/// tagged #twitter. `path` may be the export zip, its extracted folder or the
+/// tweets.js file itself. Retweets are always skipped.
+pub fn read_twitter_archive(
+    path: &Path,
+    options: &TwitterImportOptions,
+    tz: FixedOffset,
+) -> Result<(Vec<Memo>, Vec<String>), AppError> {
+    let mut memos = Vec::new();
+    let mut errors = Vec::new();
+
+    for (name, script) in tweet_scripts(path)? {
+        // "window.YTD.tweets.part0 = [ ... ]"
+        let json = script.find('[').map(|start| &script[start..]).unwrap_or_default();
+        let tweets: Vec<ArchivedTweet> = match serde_json::from_str(json) {
+            Ok(tweets) => tweets,
+            Err(e) => {
+                errors.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+
+        for ArchivedTweet { tweet } in tweets {
+            if tweet.retweeted || tweet.full_text.starts_with("RT @") {
+                continue;
+            }
+            if tweet.in_reply_to_user_id_str.is_some() && !options.include_replies {
+                continue;
+            }
+            match tweet_to_memo(tweet, &tz) {
+                Ok(memo) => memos.push(memo),
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            }
+        }
+    }
+
+    Ok((memos, errors))
+}
+
+/// The tweets.js file(s) of an export, which splits large archives into
+/// tweets-part1.js and so on. Older exports call it tweet.js.
+fn tweet_scripts(path: &Path) -> Result<Vec<(String, String)>, AppError> {
+    let read_err = |e: std::io::Error| AppError::Internal(format!("Failed to read {}: {}", path.display(), e));
+    let is_tweet_script = |name: &str| {
+        let name = name.rsplit('/').next().unwrap_or(name);
+        (name.starts_with("tweets") || name == "tweet.js") && name.ends_with(".js")
+    };
+
+    let mut scripts = Vec::new();
+    if path.is_dir() {
+        let data_dir = path.join("data");
+        let dir = if data_dir.is_dir() { data_dir } else { path.to_path_buf() };
+        for entry in std::fs::read_dir(&dir).map_err(read_err)?.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if is_tweet_script(&name) {
+                scripts.push((name, std::fs::read_to_string(entry.path()).map_err(read_err)?));
+            }
+        }
+    } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip")) {
+        let zip_err = |e: zip::result::ZipError| AppError::Internal(format!("Failed to read {}: {}", path.display(), e));
+        let file = std::fs::File::open(path).map_err(read_err)?;
+        let mut archive = zip::ZipArchive::new(file).map_err(zip_err)?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).map_err(zip_err)?;
+            let name = entry.name().to_string();
+            if name.starts_with("data/") && is_tweet_script(&name) {
+                let mut script = String::new();
+                std::io::Read::read_to_string(&mut entry, &mut script).map_err(read_err)?;
+                scripts.push((name, script));
+            }
+        }
+    } else {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        scripts.push((name, std::fs::read_to_string(path).map_err(read_err)?));
+    }
+
+    if scripts.is_empty() {
+        return Err(AppError::InvalidInput("No tweets.js found in the archive".to_string()));
+    }
+    scripts.sort();
+    Ok(scripts)
+}
+
+fn tweet_to_memo(tweet: Tweet, tz: &FixedOffset) -> Result<Memo, AppError> {
+    // "Wed Oct 10 20:19:24 +0000 2018"
+    let created_at = DateTime::parse_from_str(&tweet.created_at, "%a %b %d %H:%M:%S %z %Y")
+        .map_err(|_| AppError::Parse(format!("Unrecognized date \"{}\"", tweet.created_at)))?
+        .with_timezone(tz)
+        .format(MEMO_TIME_FORMAT)
+        .to_string();
+
+    // The text is HTML-escaped and uses t.co short links
+    let mut content = tweet.full_text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    for url in &tweet.entities.urls {
+        content = content.replace(&url.url, &url.expanded_url);
+    }
+
+    let mut tags = vec!["twitter".to_string()];
+    for hashtag in &tweet.entities.hashtags {
+        if !tags.iter().any(|tag| tag == &hashtag.text) {
+            tags.push(hashtag.text.clone());
+        }
+    }
+
+    Ok(Memo {
+        slug: format!("local-twitter-{}", tweet.id_str),
+        content,
+        updated_at: created_at.clone(),
+        created_at,
+        tags,
+        url: Some(format!("https://x.com/i/web/status/{}", tweet.id_str)),
+        pinned: false,
+        local: true,
+        content_html: None,
+        word_count: None,
+        char_count: None,
+    })
+}
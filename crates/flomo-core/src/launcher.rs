@@ -0,0 +1,71 @@
+use serde::Serialize;
+
+use crate::blog::post_title;
+use crate::db::{Database, MemoFilter, MemoOrder};
+use crate::error::AppError;
+use crate::Memo;
+
+/// Scheme the app registers for deep links; see the app's `deeplink` module.
+const DEEP_LINK_SCHEME: &str = "openflomo";
+
+/// Script Filter output understood by Alfred and by Raycast script
+/// commands: `{"items": [...]}`.
+#[derive(Debug, Serialize)]
+pub struct LauncherResults {
+    pub items: Vec<LauncherItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LauncherItem {
+    /// The memo slug, so the launcher can learn which results get picked
+    pub uid: String,
+    pub title: String,
+    /// Creation time and tags
+    pub subtitle: String,
+    /// Deep link that opens the memo in the app
+    pub arg: String,
+    /// Memo on the Flomo website, previewed with Shift or ⌘Y in Alfred
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quicklookurl: Option<String>,
+    pub text: LauncherText,
+}
+
+/// Full memo text for Alfred's copy (⌘C) and large type (⌘L) actions.
+#[derive(Debug, Serialize)]
+pub struct LauncherText {
+    pub copy: String,
+    pub largetype: String,
+}
+
+impl From<&Memo> for LauncherItem {
+    fn from(memo: &Memo) -> Self {
+        let mut subtitle = memo.created_at.clone();
+        if !memo.tags.is_empty() {
+            let tags: Vec<String> = memo.tags.iter().map(|tag| format!("#{}", tag)).collect();
+            subtitle.push_str(" · ");
+            subtitle.push_str(&tags.join(" "));
+        }
+        let content = memo.content.trim().to_string();
+
+        Self {
+            uid: memo.slug.clone(),
+            title: post_title(memo),
+            subtitle,
+            arg: memo_link(&memo.slug),
+            quicklookurl: memo.url.clone().filter(|url| !url.is_empty()),
+            text: LauncherText { copy: content.clone(), largetype: content },
+        }
+    }
+}
+
+/// `openflomo://memo/<slug>`, which opens the memo in the app.
+pub fn memo_link(slug: &str) -> String {
+    format!("{}://memo/{}", DEEP_LINK_SCHEME, slug)
+}
+
+/// Searches memos with the app's query syntax, newest first; an empty query
+/// lists the latest memos.
+pub fn query(db: &Database, query: &str, filter: &MemoFilter, limit: i64) -> Result<LauncherResults, AppError> {
+    let memos = db.search_memos(query, MemoOrder::CreatedAt, "desc", filter, 0, limit.max(0))?;
+    Ok(LauncherResults { items: memos.iter().map(LauncherItem::from).collect() })
+}
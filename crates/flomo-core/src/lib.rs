@@ -0,0 +1,833 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use regex::{Captures, Regex};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+pub mod archive;
+pub mod auto_export;
+pub mod backup;
+pub mod blog;
+pub mod date_format;
+pub mod db;
+pub mod demo;
+pub mod digest;
+pub mod duplicates;
+pub mod epub;
+pub mod error;
+pub mod export;
+pub mod feed;
+pub mod git_mirror;
+pub mod graph;
+pub mod i18n;
+pub mod import;
+pub mod launcher;
+pub mod links;
+pub mod llm;
+pub mod logging;
+pub mod logseq;
+pub mod outbox;
+pub mod pdf;
+pub mod proxy;
+pub mod readwise;
+pub mod search;
+pub mod settings;
+pub mod similar;
+pub mod suggest;
+pub mod sync;
+pub mod telegram;
+pub mod templates;
+pub mod todos;
+pub mod webhooks;
+pub mod xlsx;
+use date_format::format_date;
+use error::AppError;
+
+/// Parses a memo timestamp. Flomo returns naive "YYYY-MM-DD HH:MM:SS" strings
+/// in the timezone requested via the `tz` parameter, so those are read in `tz`.
+pub fn parse_memo_time(date_str: &str, tz: &FixedOffset) -> Option<DateTime<FixedOffset>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(date_str) {
+        return Some(dt.with_timezone(tz));
+    }
+
+    NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+        .and_then(|dt| tz.from_local_datetime(&dt).single())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Memo {
+    pub slug: String,
+    pub content: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Imported or written in the app rather than synced from Flomo
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub local: bool,
+    /// Original HTML from the API; only set while syncing, so it is stored
+    /// without being shipped to the frontend on every list query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_html: Option<String>,
+    /// Words, counting CJK characters one each, and non-whitespace
+    /// characters; counted when the memo is stored, so only set on memos
+    /// read from the database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_count: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub char_count: Option<i64>,
+}
+
+impl From<ApiMemo> for Memo {
+    fn from(api_memo: ApiMemo) -> Self {
+        Memo {
+            slug: api_memo.slug.clone(),
+            content: parse_html_to_text(&api_memo.content),
+            created_at: api_memo.created_at,
+            updated_at: api_memo.updated_at,
+            tags: api_memo.tags,
+            url: Some(format!("https://v.flomoapp.com/mine/?memo_id={}", api_memo.slug)),
+            pinned: false,
+            local: false,
+            content_html: Some(api_memo.content),
+            word_count: None,
+            char_count: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ApiResponse<T> {
+    code: i32,
+    #[serde(default)]
+    message: Option<String>,
+    data: Option<T>,
+}
+
+/// A memo as the Flomo API returns it, with HTML content.
+///
+/// Parsing is lenient so small API changes don't break syncs: unknown
+/// fields are ignored, nulls read as empty, camelCase names are accepted,
+/// and dates may be Unix timestamps (see `localized`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiMemo {
+    #[serde(deserialize_with = "api_string")]
+    pub slug: String,
+    #[serde(default, deserialize_with = "api_string")]
+    pub content: String,
+    #[serde(alias = "createdAt", deserialize_with = "api_time")]
+    pub created_at: String,
+    /// Same as `created_at` for memos that never changed when missing
+    #[serde(default, alias = "updatedAt", deserialize_with = "api_time")]
+    pub updated_at: String,
+    #[serde(default, deserialize_with = "api_tags")]
+    pub tags: Vec<String>,
+}
+
+impl ApiMemo {
+    /// Rewrites timestamp dates in the memo format, in `tz`.
+    pub fn localized(mut self, tz: &FixedOffset) -> Self {
+        for time in [&mut self.created_at, &mut self.updated_at] {
+            if let Ok(dt) = DateTime::parse_from_rfc3339(time) {
+                *time = dt.with_timezone(tz).format("%Y-%m-%d %H:%M:%S").to_string();
+            }
+        }
+        if self.updated_at.is_empty() {
+            self.updated_at = self.created_at.clone();
+        }
+        self
+    }
+}
+
+// Strings, or numbers written as strings; null reads as empty
+fn api_string<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => Ok(String::new()),
+        Some(serde_json::Value::String(text)) => Ok(text),
+        Some(serde_json::Value::Number(number)) => Ok(number.to_string()),
+        Some(other) => Err(serde::de::Error::custom(format!("expected a string, got {}", other))),
+    }
+}
+
+// Date strings as they are; Unix timestamps in seconds or milliseconds
+// become RFC 3339, which `ApiMemo::localized` moves into the client's timezone
+fn api_time<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => Ok(String::new()),
+        Some(serde_json::Value::String(text)) => Ok(text),
+        Some(serde_json::Value::Number(number)) => {
+            let timestamp = number.as_f64().unwrap_or_default() as i64;
+            // Seconds won't reach 10^11 until the year 5138
+            let millis = if timestamp.abs() >= 100_000_000_000 { timestamp } else { timestamp * 1000 };
+            DateTime::from_timestamp_millis(millis)
+                .map(|dt| dt.to_rfc3339())
+                .ok_or_else(|| serde::de::Error::custom(format!("timestamp {} out of range", number)))
+        }
+        Some(other) => Err(serde::de::Error::custom(format!("expected a date, got {}", other))),
+    }
+}
+
+// A list of tag names; null reads as no tags
+fn api_tags<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(Option::<Vec<String>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// One page from `fetch_page`. Memos that couldn't be read are set aside
+/// rather than failing the page.
+#[derive(Debug, Default)]
+pub struct ApiPage {
+    pub memos: Vec<ApiMemo>,
+    pub unparsed: Vec<UnparsedRecord>,
+}
+
+/// A record from a Flomo response that didn't parse, kept for inspection.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnparsedRecord {
+    pub error: String,
+    /// The record as JSON
+    pub raw: String,
+}
+
+/// Account details returned by `validate_token`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountInfo {
+    #[serde(default)]
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+}
+
+/// Token and account returned by `login`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginResult {
+    pub token: String,
+    pub account: AccountInfo,
+}
+
+/// How requests to the Flomo API are made. The defaults match the web
+/// app; change them when Flomo starts rejecting old clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    /// Sent as `app_version` in every signed request
+    pub app_version: String,
+    /// `User-Agent` header; empty sends none
+    pub user_agent: String,
+    pub connect_timeout_secs: u64,
+    /// Limit on a whole request, including reading the response
+    pub request_timeout_secs: u64,
+    /// Least time between two requests, so large syncs don't hammer Flomo
+    pub request_interval_ms: u64,
+    /// How often a request Flomo answers with HTTP 429 is retried, after
+    /// waiting as long as its Retry-After header asks
+    pub rate_limit_retries: u32,
+    /// Memos per sync request, between `MIN_PAGE_SIZE` and `MAX_PAGE_SIZE`;
+    /// smaller pages time out less on flaky networks, larger ones make full
+    /// syncs faster
+    pub page_size: usize,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            app_version: "5.25.64".to_string(),
+            user_agent: String::new(),
+            connect_timeout_secs: 10,
+            request_timeout_secs: 60,
+            request_interval_ms: 300,
+            rate_limit_retries: 3,
+            page_size: FlomoClient::DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+pub struct FlomoClient {
+    token: String,
+    client: reqwest::Client,
+    cancel: Option<CancellationToken>,
+    timezone: FixedOffset,
+    api: ApiSettings,
+    proxy: Option<reqwest::Proxy>,
+    /// Serves generated memos instead of calling Flomo
+    demo: Option<&'static demo::DemoBackend>,
+    /// When the last request was sent, for `request_interval_ms`
+    last_request: Arc<tokio::sync::Mutex<Option<Instant>>>,
+}
+
+impl FlomoClient {
+    /// Default page size of `fetch_page`
+    pub const DEFAULT_PAGE_SIZE: usize = 200;
+    pub const MIN_PAGE_SIZE: usize = 20;
+    pub const MAX_PAGE_SIZE: usize = 500;
+    const URL_UPDATED: &'static str = "https://flomoapp.com/api/v1/memo/updated/";
+    const URL_USER_ME: &'static str = "https://flomoapp.com/api/v1/user/me";
+    const URL_LOGIN: &'static str = "https://flomoapp.com/api/v1/user/login_by_email";
+    const URL_MEMO: &'static str = "https://flomoapp.com/api/v1/memo";
+    /// Longest wait for a rate limit to clear before the request is retried.
+    const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+    // API codes Flomo returns when the session is missing or expired
+    const AUTH_ERROR_CODES: &'static [i32] = &[-10];
+    const SALT: &'static str = "dbbc3dd73364b4084c3a69346e0ce2b2";
+
+    pub fn new(token: String) -> Self {
+        let api = ApiSettings::default();
+        let client = Self::build_client(&api, None)
+            .unwrap_or_else(|_| reqwest::Client::new());
+        let token = if token.starts_with("Bearer ") {
+            token
+        } else {
+            format!("Bearer {}", token)
+        };
+        logging::register_secret(&token);
+        
+        Self {
+            token,
+            client,
+            cancel: None,
+            timezone: date_format::system_timezone(),
+            api,
+            proxy: None,
+            demo: None,
+            last_request: Arc::new(tokio::sync::Mutex::new(None)),
+        }
+    }
+
+    /// Client without a token, for endpoints like login that don't need one.
+    pub fn anonymous() -> Self {
+        let mut client = Self::new(String::new());
+        client.token.clear();
+        client
+    }
+
+    /// Timezone the API reads and writes memo times in; defaults to the
+    /// system timezone.
+    pub fn with_timezone(mut self, timezone: FixedOffset) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Sends every request through `proxy`; `None` connects directly, or
+    /// through the system proxy variables.
+    pub fn with_proxy(mut self, proxy: Option<&proxy::ProxySettings>) -> Result<Self, AppError> {
+        self.proxy = proxy
+            .filter(|proxy| !proxy.url.trim().is_empty())
+            .map(proxy::ProxySettings::to_proxy)
+            .transpose()?;
+        self.client = Self::build_client(&self.api, self.proxy.clone())?;
+        Ok(self)
+    }
+
+    /// Applies the app version, user agent and timeouts from `api`.
+    pub fn with_api_settings(mut self, api: &ApiSettings) -> Result<Self, AppError> {
+        if api.app_version.trim().is_empty() {
+            return Err(AppError::InvalidInput("App version cannot be empty".to_string()));
+        }
+        self.api = api.clone();
+        self.client = Self::build_client(&self.api, self.proxy.clone())?;
+        Ok(self)
+    }
+
+    fn build_client(api: &ApiSettings, proxy: Option<reqwest::Proxy>) -> Result<reqwest::Client, AppError> {
+        // Zero would fail every request, so it falls back to the default
+        let defaults = ApiSettings::default();
+        let seconds = |value: u64, default: u64| Duration::from_secs(if value == 0 { default } else { value });
+
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(seconds(api.connect_timeout_secs, defaults.connect_timeout_secs))
+            .timeout(seconds(api.request_timeout_secs, defaults.request_timeout_secs));
+        if !api.user_agent.trim().is_empty() {
+            builder = builder.user_agent(api.user_agent.trim());
+        }
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(proxy);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Answers every call from the generated demo account instead of the
+    /// Flomo API, so no token or network is needed.
+    pub fn with_demo(mut self, enabled: bool) -> Self {
+        self.demo = enabled.then(demo::backend);
+        self
+    }
+
+    /// Aborts any in-flight request as soon as the token is cancelled.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Memos asked for per page; a page with fewer was the last one.
+    pub fn page_size(&self) -> usize {
+        self.api.page_size.clamp(Self::MIN_PAGE_SIZE, Self::MAX_PAGE_SIZE)
+    }
+
+    pub fn get_params(&self, latest_slug: Option<&str>, latest_updated_at: Option<i64>) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("limit".to_string(), self.page_size().to_string());
+
+        // Add pagination parameters if available
+        // Note: We should add slug even if updated_at is missing
+        if let Some(slug) = latest_slug {
+            params.insert("latest_slug".to_string(), slug.to_string());
+        }
+        
+        if let Some(updated_at) = latest_updated_at {
+            params.insert("latest_updated_at".to_string(), updated_at.to_string());
+        }
+
+        // Log pagination parameters for debugging
+        if latest_slug.is_some() || latest_updated_at.is_some() {
+            tracing::debug!("Pagination params: latest_slug={:?}, latest_updated_at={:?}",
+                            latest_slug, latest_updated_at);
+        }
+        
+        self.sign_params(params)
+    }
+
+    // Flomo expects the UTC offset as "hours:minutes", e.g. "8:0" or "-3:30"
+    fn tz_param(&self) -> String {
+        let seconds = self.timezone.local_minus_utc();
+        format!("{}:{}", seconds / 3600, (seconds.abs() % 3600) / 60)
+    }
+
+    /// Converts a memo's `updated_at` into the unix timestamp used as the pagination cursor.
+    pub fn cursor_timestamp(&self, updated_at: &str) -> Option<i64> {
+        parse_memo_time(updated_at, &self.timezone).map(|dt| dt.timestamp())
+    }
+
+    /// Adds the common client parameters and the MD5 `sign` Flomo requires on every request.
+    fn sign_params(&self, mut params: HashMap<String, String>) -> HashMap<String, String> {
+        params.insert("tz".to_string(), self.tz_param());
+        params.insert("timestamp".to_string(), chrono::Utc::now().timestamp().to_string());
+        params.insert("api_key".to_string(), "flomo_web".to_string());
+        params.insert("app_version".to_string(), self.api.app_version.trim().to_string());
+        params.insert("platform".to_string(), "mac".to_string());
+        params.insert("webp".to_string(), "1".to_string());
+
+        // Generate sign (using MD5 to match Python implementation)
+        let mut sorted_params: Vec<(&String, &String)> = params.iter().collect();
+        sorted_params.sort_by_key(|&(k, _)| k);
+        
+        let param_str = sorted_params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+        
+        let sign_str = format!("{}{}", param_str, Self::SALT);
+        
+        
+        let sign = format!("{:x}", md5::compute(sign_str.as_bytes()));
+        
+        params.insert("sign".to_string(), sign);
+        
+        params
+    }
+
+    pub async fn get_all_memos(&self) -> Result<Vec<Memo>, AppError> {
+        let mut all_memos = Vec::new();
+        let mut latest_slug: Option<String> = None;
+        let mut latest_updated_at: Option<i64> = None;
+
+        loop {
+            let page = self.fetch_page(latest_slug.as_deref(), latest_updated_at).await?;
+            // Skipped records still count towards a full page
+            let page_size = page.memos.len() + page.unparsed.len();
+            let memos = page.memos;
+            
+            if memos.is_empty() {
+                break;
+            }
+
+            let should_continue = page_size >= self.page_size();
+            
+            if should_continue {
+                let last_memo = &memos[memos.len() - 1];
+                latest_slug = Some(last_memo.slug.clone());
+                
+                latest_updated_at = self.cursor_timestamp(&last_memo.updated_at);
+            }
+
+            // The HTML is only kept when memos are stored
+            all_memos.extend(memos.into_iter().map(|api_memo| Memo { content_html: None, ..Memo::from(api_memo) }));
+
+            if !should_continue {
+                break;
+            }
+        }
+
+        Ok(all_memos)
+    }
+
+    /// Fetches a single page of memos after the given pagination cursor.
+    pub async fn fetch_page(
+        &self,
+        latest_slug: Option<&str>,
+        latest_updated_at: Option<i64>,
+    ) -> Result<ApiPage, AppError> {
+        if let Some(demo) = self.demo {
+            let memos = demo.fetch_page(latest_slug, self.page_size(), &self.timezone);
+            return Ok(ApiPage { memos, unparsed: Vec::new() });
+        }
+        let params = self.get_params(latest_slug, latest_updated_at);
+        let records = match self.get_json(Self::URL_UPDATED, &params).await? {
+            Some(serde_json::Value::Array(records)) => records,
+            None | Some(serde_json::Value::Null) => Vec::new(),
+            Some(other) => return Err(AppError::Parse(format!("Expected a list of memos, got: {}", other))),
+        };
+
+        let mut page = ApiPage::default();
+        for record in records {
+            match serde_json::from_value::<ApiMemo>(record.clone()) {
+                Ok(memo) => page.memos.push(memo.localized(&self.timezone)),
+                Err(e) => {
+                    tracing::warn!("Skipping a memo Flomo returned that couldn't be read: {}", e);
+                    page.unparsed.push(UnparsedRecord { error: e.to_string(), raw: record.to_string() });
+                }
+            }
+        }
+        Ok(page)
+    }
+
+    /// Round-trip time of a request to Flomo, to check the network or proxy.
+    /// Any HTTP response counts as reachable.
+    pub async fn ping(&self) -> Result<Duration, AppError> {
+        if self.demo.is_some() {
+            return Ok(Duration::ZERO);
+        }
+        let started = Instant::now();
+        self.client.head(Self::URL_USER_ME).send().await?;
+        Ok(started.elapsed())
+    }
+
+    /// Checks the token against the API and returns the account it belongs to.
+    pub async fn get_account_info(&self) -> Result<AccountInfo, AppError> {
+        if let Some(demo) = self.demo {
+            return Ok(demo.account());
+        }
+        let params = self.sign_params(HashMap::new());
+        self.get_json(Self::URL_USER_ME, &params)
+            .await?
+            .ok_or_else(|| AppError::Parse("Account info missing from response".to_string()))
+    }
+
+    /// Exchanges email and password for an access token.
+    pub async fn login(&self, email: &str, password: &str) -> Result<LoginResult, AppError> {
+        if let Some(demo) = self.demo {
+            return Ok(demo.login(email));
+        }
+        let mut params = HashMap::new();
+        params.insert("email".to_string(), email.to_string());
+        params.insert("password".to_string(), password.to_string());
+        let params = self.sign_params(params);
+
+        let response = self.send(self.client.post(Self::URL_LOGIN).json(&params)).await?;
+        if response.code != 0 {
+            let message = response.message
+                .unwrap_or_else(|| format!("API error: code {}", response.code));
+            if message.contains("验证码") || message.to_lowercase().contains("captcha") {
+                return Err(AppError::CaptchaRequired(message));
+            }
+            return Err(AppError::Auth(message));
+        }
+
+        let data = response.data
+            .ok_or_else(|| AppError::Parse("Login data missing from response".to_string()))?;
+        let token = data.get("access_token")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| AppError::Parse("Access token missing from login response".to_string()))?
+            .to_string();
+        let account: AccountInfo = serde_json::from_value(data)?;
+
+        Ok(LoginResult { token, account })
+    }
+
+    /// Fetches a single memo, or `None` if the API doesn't return one.
+    pub async fn fetch_memo(&self, slug: &str) -> Result<Option<ApiMemo>, AppError> {
+        if let Some(demo) = self.demo {
+            return Ok(demo.fetch_memo(slug, &self.timezone));
+        }
+        let params = self.sign_params(HashMap::new());
+        let memo: Option<ApiMemo> = self.get_json(&format!("{}/{}", Self::URL_MEMO, slug), &params).await?;
+        Ok(memo.map(|memo| memo.localized(&self.timezone)))
+    }
+
+    /// Creates a memo from HTML content. Flomo picks up #tags from the text
+    /// and stamps its own creation time.
+    pub async fn create_memo(&self, content_html: &str) -> Result<ApiMemo, AppError> {
+        if let Some(demo) = self.demo {
+            return Ok(demo.create_memo(content_html, &self.timezone));
+        }
+        let mut params = HashMap::new();
+        params.insert("content".to_string(), content_html.to_string());
+        params.insert("source".to_string(), "web".to_string());
+
+        self.put_memo(Self::URL_MEMO, params).await
+    }
+
+    /// Replaces the content of an existing memo; tags again come from the text.
+    pub async fn update_memo(&self, slug: &str, content_html: &str) -> Result<ApiMemo, AppError> {
+        if let Some(demo) = self.demo {
+            return demo.update_memo(slug, content_html, &self.timezone);
+        }
+        let mut params = HashMap::new();
+        params.insert("content".to_string(), content_html.to_string());
+        params.insert("source".to_string(), "web".to_string());
+        params.insert("local_updated_at".to_string(), chrono::Utc::now().timestamp().to_string());
+
+        self.put_memo(&format!("{}/{}", Self::URL_MEMO, slug), params).await
+    }
+
+    /// Deletes a memo on Flomo.
+    pub async fn delete_memo(&self, slug: &str) -> Result<(), AppError> {
+        if let Some(demo) = self.demo {
+            return demo.delete_memo(slug);
+        }
+        let params = self.sign_params(HashMap::new());
+        let url = format!("{}/{}", Self::URL_MEMO, slug);
+
+        let response = self.send(self.client.delete(&url).query(&params)).await?;
+        if response.code != 0 {
+            return Err(AppError::Network(format!(
+                "API error: code {} - {}",
+                response.code,
+                response.message.unwrap_or_default()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Sends a signed PUT to a memo endpoint and returns the memo Flomo stored.
+    async fn put_memo(&self, url: &str, params: HashMap<String, String>) -> Result<ApiMemo, AppError> {
+        let params = self.sign_params(params);
+
+        let response = self.send(self.client.put(url).json(&params)).await?;
+        if response.code != 0 {
+            return Err(AppError::Network(format!(
+                "API error: code {} - {}",
+                response.code,
+                response.message.unwrap_or_default()
+            )));
+        }
+
+        let data = response.data
+            .ok_or_else(|| AppError::Parse("Memo missing from response".to_string()))?;
+        let memo: ApiMemo = serde_json::from_value(data)?;
+        Ok(memo.localized(&self.timezone))
+    }
+
+    /// Sends a signed GET request and unwraps Flomo's `{ code, message, data }` envelope.
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<Option<T>, AppError> {
+        let response = self.send(self.client.get(url).query(params)).await?;
+
+        if response.code != 0 {
+            return Err(AppError::Network(format!(
+                "API error: code {} - {}",
+                response.code,
+                response.message.unwrap_or_default()
+            )));
+        }
+
+        response.data
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| AppError::Parse(e.to_string()))
+    }
+
+    /// Sends a request with the token attached, honouring cancellation, the
+    /// request interval and rate limits, and mapping HTTP-level and session
+    /// failures onto `AppError`.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<ApiResponse<serde_json::Value>, AppError> {
+        let mut headers = HeaderMap::new();
+        if !self.token.is_empty() {
+            headers.insert(
+                "authorization",
+                HeaderValue::from_str(&self.token)
+                    .map_err(|e| AppError::Auth(format!("Invalid token: {}", e)))?,
+            );
+        }
+        let request = request.headers(headers);
+
+        let mut retries = 0;
+        let (status, response_text) = loop {
+            let attempt = request.try_clone()
+                .ok_or_else(|| AppError::Internal("Request body can't be sent twice".to_string()))?;
+            self.throttle().await?;
+            let (status, retry_after, text) = self.cancellable(async {
+                let response = attempt.send().await?;
+                let status = response.status();
+                let retry_after = response.headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_retry_after);
+                let text = response.text().await?;
+                Ok::<_, AppError>((status, retry_after, text))
+            })
+            .await??;
+
+            if status.as_u16() == 429 && retries < self.api.rate_limit_retries {
+                retries += 1;
+                // Without Retry-After: 5, 10, 20... seconds
+                let delay = retry_after
+                    .unwrap_or_else(|| Duration::from_secs(5 << (retries - 1).min(5)))
+                    .min(Self::MAX_RETRY_AFTER);
+                tracing::warn!("Flomo is rate limiting requests; retrying in {} seconds", delay.as_secs());
+                self.cancellable(tokio::time::sleep(delay)).await?;
+                continue;
+            }
+            break (status, text);
+        };
+
+        match status.as_u16() {
+            401 | 403 => return Err(AppError::Auth(format!("HTTP {}", status))),
+            429 => return Err(AppError::RateLimited(format!("HTTP {}", status))),
+            _ => {}
+        }
+
+        let api_response: ApiResponse<serde_json::Value> = serde_json::from_str(&response_text)
+            .map_err(|e| AppError::Parse(format!("{} - Response was: {}", e, response_text)))?;
+
+        if Self::AUTH_ERROR_CODES.contains(&api_response.code) {
+            return Err(AppError::Auth(
+                api_response.message.unwrap_or_else(|| format!("API error: code {}", api_response.code)),
+            ));
+        }
+
+        Ok(api_response)
+    }
+
+    // Waits until `request_interval_ms` has passed since the previous request
+    async fn throttle(&self) -> Result<(), AppError> {
+        let mut last_request = self.last_request.lock().await;
+        if let Some(last) = *last_request {
+            let interval = Duration::from_millis(self.api.request_interval_ms);
+            self.cancellable(tokio::time::sleep_until((last + interval).into())).await?;
+        }
+        *last_request = Some(Instant::now());
+        Ok(())
+    }
+
+    // Runs `future` unless the cancellation token fires first; dropping a
+    // request future aborts the underlying connection
+    async fn cancellable<T>(&self, future: impl Future<Output = T>) -> Result<T, AppError> {
+        match &self.cancel {
+            Some(cancel) => tokio::select! {
+                _ = cancel.cancelled() => Err(AppError::Cancelled),
+                result = future => Ok(result),
+            },
+            None => Ok(future.await),
+        }
+    }
+}
+
+// Retry-After holds either seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+/// Narrowest column memo text can be wrapped at.
+pub const MIN_TEXT_WIDTH: usize = 20;
+// Wider than any line of a memo, so nothing gets wrapped
+const NO_WRAP_WIDTH: usize = 1_000_000;
+/// Column `parse_html_to_text` wraps at; 0 leaves lines unwrapped.
+static TEXT_WIDTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the column `parse_html_to_text` wraps at from now on; `None` keeps
+/// each paragraph on one line.
+pub fn set_text_width(width: Option<usize>) {
+    TEXT_WIDTH.store(width.map_or(0, |width| width.max(MIN_TEXT_WIDTH)), Ordering::Relaxed);
+}
+
+/// Plain text of memo HTML, wrapped as set by `set_text_width`.
+pub fn parse_html_to_text(html: &str) -> String {
+    let width = TEXT_WIDTH.load(Ordering::Relaxed);
+    html_to_text(html, (width > 0).then_some(width))
+}
+
+static PRE_BLOCK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<pre\b[^>]*>(.*?)</pre>").unwrap());
+static CODE_LANGUAGE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)class\s*=\s*["'][^"']*\blang(?:uage)?-([\w+#-]+)"#).unwrap()
+});
+static LINE_BREAK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<br\s*/?>").unwrap());
+// Stands in for a code block while the rest of the memo is converted;
+// a private use character can't clash with memo text
+const CODE_PLACEHOLDER: &str = "\u{E000}code";
+
+/// Plain text of memo HTML, wrapped at `width` columns if given. Code
+/// blocks become fenced blocks that are never wrapped, and quotes keep
+/// their `>` prefix.
+pub fn html_to_text(html: &str, width: Option<usize>) -> String {
+    let width = width.map_or(NO_WRAP_WIDTH, |width| width.max(MIN_TEXT_WIDTH));
+    
+    // html2text would wrap code and render it as inline code
+    let mut blocks = Vec::new();
+    let html = PRE_BLOCK.replace_all(html, |caps: &Captures| {
+        blocks.push(fenced_code(&caps[0], &caps[1]));
+        format!("<p>{}{}</p>", CODE_PLACEHOLDER, blocks.len() - 1)
+    });
+    let text = html2text::from_read(html.as_bytes(), width);
+    if blocks.is_empty() {
+        return text;
+    }
+    
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let block = line.find(CODE_PLACEHOLDER).and_then(|at| {
+            let index: usize = line[at + CODE_PLACEHOLDER.len()..].trim().parse().ok()?;
+            Some((&line[..at], blocks.get(index)?))
+        });
+        match block {
+            // Repeat the line's prefix, e.g. "> " inside a quote, on every code line
+            Some((prefix, block)) => lines.extend(block.lines().map(|code| match code {
+                "" => prefix.trim_end().to_string(),
+                code => format!("{}{}", prefix, code),
+            })),
+            None => lines.push(line.to_string()),
+        }
+    }
+    let mut converted = lines.join("\n");
+    if text.ends_with('\n') {
+        converted.push('\n');
+    }
+    converted
+}
+
+// A Markdown code fence around the text of a <pre> element
+fn fenced_code(pre: &str, inner: &str) -> String {
+    let language = CODE_LANGUAGE.captures(pre).map_or("", |caps| caps.get(1).unwrap().as_str());
+    let inner = LINE_BREAK.replace_all(inner, "\n");
+    let code: String = scraper::Html::parse_fragment(&inner).root_element().text().collect();
+    let code = code.trim_matches('\n');
+    
+    // The fence has to be longer than any run of backticks in the code
+    let longest_run = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
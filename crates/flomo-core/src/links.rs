@@ -0,0 +1,147 @@
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Links checked at the same time by `check_links`.
+const CHECK_CONCURRENCY: usize = 8;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+// Stops at whitespace, quotes and full-width punctuation, which Chinese text
+// often puts right after a URL without a space
+static URL_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"https?://[^\s<>"'`，。、；：！？）】」』《》]+"#).unwrap()
+});
+
+// Flomo memo links ("https://v.flomoapp.com/mine/?memo_id=MTIzNDU")
+static MEMO_URL_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"flomoapp\.com/mine/?\?(?:[^\s#]*&)?memo_id=([A-Za-z0-9_-]+)").unwrap()
+});
+
+// Wiki-style references: "[[MTIzNDU]]" or "[[first line of a memo]]"
+static WIKI_LINK_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[([^\[\]\n]+)\]\]").unwrap());
+
+/// Result of requesting one link.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkCheck {
+    pub url: String,
+    /// HTTP status, if the server answered at all
+    pub status: Option<u16>,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// Distinct http(s) URLs in a memo, from the plain text and from the hrefs
+/// of the API HTML, in order of appearance.
+pub fn extract_links(content: &str, content_html: Option<&str>) -> Vec<String> {
+    let mut links: Vec<String> = URL_PATTERN.find_iter(content)
+        .map(|found| trim_url(found.as_str()).to_string())
+        .collect();
+
+    if let Some(html) = content_html {
+        let selector = scraper::Selector::parse("a[href]").unwrap();
+        links.extend(
+            scraper::Html::parse_fragment(html)
+                .select(&selector)
+                .filter_map(|a| a.value().attr("href"))
+                .filter(|href| href.starts_with("http://") || href.starts_with("https://"))
+                .map(str::to_string),
+        );
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    links.retain(|link| seen.insert(link.clone()));
+    links
+}
+
+/// Other memos a memo refers to, by pasted Flomo memo URL or `[[...]]`.
+/// URL references yield the slug; `[[...]]` yields the text inside, which
+/// may be a slug or a memo's first line and is resolved when queried.
+pub fn memo_references(content: &str, content_html: Option<&str>) -> Vec<String> {
+    let mut references: Vec<String> = MEMO_URL_PATTERN.captures_iter(content)
+        .map(|captures| captures[1].to_string())
+        .collect();
+    if let Some(html) = content_html {
+        references.extend(MEMO_URL_PATTERN.captures_iter(html).map(|captures| captures[1].to_string()));
+    }
+    references.extend(
+        WIKI_LINK_PATTERN.captures_iter(content)
+            .map(|captures| captures[1].trim().to_string())
+            .filter(|reference| !reference.is_empty()),
+    );
+
+    let mut seen = std::collections::HashSet::new();
+    references.retain(|reference| seen.insert(reference.clone()));
+    references
+}
+
+/// Host of `url` without a leading "www.", lowercased. A bare host such as
+/// "github.com" is accepted too.
+pub fn domain(url: &str) -> Option<String> {
+    let url = url.trim();
+    let parsed = reqwest::Url::parse(url).or_else(|_| reqwest::Url::parse(&format!("https://{}", url))).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+// Sentence punctuation right after a link is almost never part of it; a
+// closing paren is kept when the URL itself opened one (Wikipedia links)
+fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let Some(last) = url.chars().last() else { return url };
+        let unbalanced_paren = last == ')' && url.matches('(').count() < url.matches(')').count();
+        if matches!(last, '.' | ',' | ';' | ':' | '!' | '?' | ']' | '}') || unbalanced_paren {
+            url = &url[..url.len() - last.len_utf8()];
+        } else {
+            return url;
+        }
+    }
+}
+
+/// Requests every URL, a few at a time, and reports which ones still
+/// resolve. Servers that reject HEAD are retried with GET.
+pub async fn check_links(urls: Vec<String>) -> Result<Vec<LinkCheck>, reqwest::Error> {
+    let client = reqwest::Client::builder()
+        .timeout(CHECK_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .build()?;
+
+    let mut results = Vec::with_capacity(urls.len());
+    for batch in urls.chunks(CHECK_CONCURRENCY) {
+        let mut tasks = JoinSet::new();
+        for url in batch {
+            let client = client.clone();
+            let url = url.clone();
+            tasks.spawn(async move { check_link(&client, url).await });
+        }
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(check) = result {
+                results.push(check);
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+async fn check_link(client: &reqwest::Client, url: String) -> LinkCheck {
+    let mut response = client.head(&url).send().await;
+    if matches!(&response, Ok(r) if r.status().as_u16() == 405 || r.status().as_u16() == 403) {
+        response = client.get(&url).send().await;
+    }
+
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            LinkCheck {
+                url,
+                status: Some(status.as_u16()),
+                ok: status.is_success(),
+                error: None,
+            }
+        }
+        Err(e) => LinkCheck { url, status: None, ok: false, error: Some(e.to_string()) },
+    }
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::i18n::Locale;
+use crate::similar::SimilarMemo;
+use crate::Memo;
+
+/// Memo text sent in one request; larger sets are summarized in chunks and
+/// the chunk summaries summarized again.
+const MAX_CHUNK_CHARS: usize = 12_000;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+/// Longest excerpt of a single memo given as context to `ask`.
+const MAX_SOURCE_CHARS: usize = 2_000;
+
+/// An OpenAI-compatible chat completions endpoint. Works with OpenAI itself
+/// and with local servers such as Ollama or LM Studio.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmSettings {
+    /// Base URL up to and including the version, e.g. "https://api.openai.com/v1"
+    pub base_url: String,
+    /// Sent as a bearer token; local servers usually don't need one
+    pub api_key: String,
+    pub model: String,
+}
+
+impl Default for LlmSettings {
+    fn default() -> Self {
+        Self {
+            base_url: "https://api.openai.com/v1".to_string(),
+            api_key: String::new(),
+            model: "gpt-4o-mini".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub role: &'static str,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self { role: "system", content: content.into() }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self { role: "user", content: content.into() }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: StreamDelta,
+}
+
+#[derive(Default, Deserialize)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
+pub struct LlmClient {
+    client: reqwest::Client,
+    settings: LlmSettings,
+}
+
+impl LlmClient {
+    /// Fails with `InvalidInput` when no endpoint has been configured.
+    pub fn new(settings: Option<LlmSettings>) -> Result<Self, AppError> {
+        let settings = settings
+            .filter(|settings| !settings.base_url.trim().is_empty() && !settings.model.trim().is_empty())
+            .ok_or_else(|| AppError::InvalidInput("No LLM endpoint is configured".to_string()))?;
+        let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+
+        Ok(Self { client, settings })
+    }
+
+    /// Runs a chat completion, calling `on_delta` with each piece of the
+    /// answer as it streams in, and returns the whole answer.
+    pub async fn complete<F: FnMut(&str)>(&self, messages: &[ChatMessage], mut on_delta: F) -> Result<String, AppError> {
+        let url = format!("{}/chat/completions", self.settings.base_url.trim_end_matches('/'));
+        let mut request = self.client.post(&url).json(&ChatRequest {
+            model: &self.settings.model,
+            messages,
+            stream: true,
+        });
+        if !self.settings.api_key.is_empty() {
+            request = request.bearer_auth(&self.settings.api_key);
+        }
+
+        let mut response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                401 | 403 => AppError::Auth(format!("LLM endpoint rejected the API key: {}", body)),
+                429 => AppError::RateLimited(format!("LLM endpoint: {}", body)),
+                _ => AppError::Network(format!("LLM endpoint returned {}: {}", status, body)),
+            });
+        }
+
+        // Server-sent events: "data: {json}" lines, ending with "data: [DONE]"
+        let mut answer = String::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            while let Some(newline) = buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=newline).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                    continue;
+                };
+                if data == "[DONE]" {
+                    return Ok(answer);
+                }
+
+                let chunk: StreamChunk = serde_json::from_str(data)
+                    .map_err(|e| AppError::Parse(format!("Unexpected LLM response: {}", e)))?;
+                for choice in chunk.choices {
+                    if let Some(delta) = choice.delta.content.filter(|delta| !delta.is_empty()) {
+                        on_delta(&delta);
+                        answer.push_str(&delta);
+                    }
+                }
+            }
+        }
+
+        Ok(answer)
+    }
+}
+
+/// Writes a digest of `memos`. Only the final pass streams through
+/// `on_delta`; sets too large for one request are first summarized chunk by
+/// chunk.
+pub async fn summarize<F: FnMut(&str)>(
+    client: &LlmClient,
+    memos: &[Memo],
+    locale: Locale,
+    on_delta: F,
+) -> Result<String, AppError> {
+    let mut sections: Vec<String> = memos.iter()
+        .map(|memo| format!("[{}] {}", memo.created_at, memo.content.trim()))
+        .collect();
+
+    loop {
+        let chunks = chunk_sections(&sections);
+        if chunks.len() <= 1 {
+            let notes = chunks.into_iter().next().unwrap_or_default();
+            let messages = [ChatMessage::system(summary_prompt(locale)), ChatMessage::user(notes)];
+            return client.complete(&messages, on_delta).await;
+        }
+
+        let mut partials = Vec::with_capacity(chunks.len());
+        for notes in chunks {
+            let messages = [ChatMessage::system(summary_prompt(locale)), ChatMessage::user(notes)];
+            partials.push(client.complete(&messages, |_| {}).await?);
+        }
+        sections = partials;
+    }
+}
+
+/// An answer from `ask` with the memos it was based on.
+#[derive(Debug, Serialize)]
+pub struct AskAnswer {
+    pub answer: String,
+    /// Slugs of the sources the answer cites, in order of first citation
+    pub citations: Vec<String>,
+    /// Every memo given to the model as context, most relevant first
+    pub sources: Vec<SimilarMemo>,
+}
+
+/// Answers `question` from `sources` only, streaming the answer through
+/// `on_delta`. The model is asked to cite memos as `[slug]`.
+pub async fn ask<F: FnMut(&str)>(
+    client: &LlmClient,
+    question: &str,
+    mut sources: Vec<SimilarMemo>,
+    locale: Locale,
+    on_delta: F,
+) -> Result<AskAnswer, AppError> {
+    let mut context = String::new();
+    let mut included = 0;
+    for source in &sources {
+        let excerpt: String = source.memo.content.trim().chars().take(MAX_SOURCE_CHARS).collect();
+        let entry = format!("[{}] ({})\n{}\n\n", source.memo.slug, source.memo.created_at, excerpt);
+        if !context.is_empty() && context.chars().count() + entry.chars().count() > MAX_CHUNK_CHARS {
+            break;
+        }
+        context.push_str(&entry);
+        included += 1;
+    }
+    sources.truncate(included);
+
+    let messages = [
+        ChatMessage::system(ask_prompt(locale)),
+        ChatMessage::user(format!("{}\n---\n{}", context, question.trim())),
+    ];
+    let answer = client.complete(&messages, on_delta).await?;
+
+    let mut cited: Vec<(usize, String)> = sources.iter()
+        .filter_map(|source| {
+            answer.find(&format!("[{}]", source.memo.slug)).map(|position| (position, source.memo.slug.clone()))
+        })
+        .collect();
+    cited.sort();
+
+    Ok(AskAnswer {
+        answer,
+        citations: cited.into_iter().map(|(_, slug)| slug).collect(),
+        sources,
+    })
+}
+
+// Groups sections into request-sized blocks; a section longer than a block
+// on its own is cut so every pass makes progress
+fn chunk_sections(sections: &[String]) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for section in sections {
+        let section: String = section.chars().take(MAX_CHUNK_CHARS).collect();
+        if !current.is_empty() && current.chars().count() + section.chars().count() > MAX_CHUNK_CHARS {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&section);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+fn ask_prompt(locale: Locale) -> &'static str {
+    match locale {
+        Locale::ZhCn => "你是用户的笔记助手。下面是从用户笔记中检索到的若干条，每条以 [编号] 和日期开头，最后是用户的问题。只根据这些笔记用中文回答；引用笔记时在句末写上它的 [编号]。如果笔记中没有答案，请直接说明。",
+        Locale::EnUs => "You are the user's note assistant. Below are notes retrieved from the user's collection, each starting with its [id] and date, followed by the user's question. Answer in English using only these notes, and cite a note by writing its [id] after the sentence that uses it. If the notes don't contain the answer, say so.",
+    }
+}
+
+fn summary_prompt(locale: Locale) -> &'static str {
+    match locale {
+        Locale::ZhCn => "你是一位笔记整理助手。下面是用户的一组笔记，每条以日期开头。请用中文写一份简洁的摘要：概括主要主题、值得注意的想法和待办事项，用 Markdown 列表组织，不要编造笔记中没有的内容。",
+        Locale::EnUs => "You are a note-taking assistant. Below is a set of the user's notes, each starting with its date. Write a concise digest in English: the main themes, notable ideas and open action items, organized as Markdown lists. Do not invent anything the notes don't say.",
+    }
+}
@@ -0,0 +1,201 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{LazyLock, OnceLock, RwLock};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+
+use crate::error::AppError;
+
+/// Directory in the app data directory holding the log files.
+pub const LOG_DIR: &str = "logs";
+const FILE_PREFIX: &str = "flomo-garden";
+const FILE_SUFFIX: &str = "log";
+/// Daily log files kept before the oldest is deleted.
+const MAX_LOG_FILES: usize = 7;
+/// Crates whose events are logged at the configured level; everything else
+/// (HTTP, TLS, ...) only logs warnings.
+const OWN_TARGETS: [&str; 3] = ["flomo_core", "flomo_garden_lib", "flomo"];
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+static FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+/// Tokens seen by API clients, masked wherever they show up in a log line.
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+static PATTERNS: LazyLock<[(Regex, &str); 2]> = LazyLock::new(|| [
+    // Authorization headers and anything else that looks like one
+    (Regex::new(r"(?i)\b(bearer\s+)[^\s\x22',;)]+").unwrap(), "${1}[redacted]"),
+    // Telegram puts the bot token in the request path
+    (Regex::new(r"/bot\d+:[\w-]+").unwrap(), "/bot[redacted]"),
+]);
+
+/// Sends log events to stderr and, with `log_dir`, to a file there that
+/// rotates daily. Only the first call installs the logger.
+pub fn init(log_dir: Option<&Path>, level: LogLevel) -> Result<(), AppError> {
+    let file = match log_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", dir.display(), e)))?;
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(FILE_PREFIX)
+                .filename_suffix(FILE_SUFFIX)
+                .max_log_files(MAX_LOG_FILES)
+                .build(dir)
+                .map_err(|e| AppError::Internal(format!("Failed to open log directory: {}", e)))?;
+            Some(tracing_subscriber::fmt::layer().with_ansi(false).with_writer(Redacting(appender)))
+        }
+        None => None,
+    };
+    let (filter, handle) = reload::Layer::new(targets(level));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(file)
+        .with(tracing_subscriber::fmt::layer().with_writer(Redacting(io::stderr)))
+        .try_init()
+        .map_err(|e| AppError::Internal(format!("Failed to set up logging: {}", e)))?;
+    let _ = FILTER.set(handle);
+    Ok(())
+}
+
+/// Changes the level of the running logger.
+pub fn set_level(level: LogLevel) -> Result<(), AppError> {
+    match FILTER.get() {
+        Some(handle) => handle.reload(targets(level))
+            .map_err(|e| AppError::Internal(format!("Failed to change log level: {}", e))),
+        None => Ok(()),
+    }
+}
+
+fn targets(level: LogLevel) -> Targets {
+    OWN_TARGETS.iter().fold(
+        Targets::new().with_default(LevelFilter::WARN.min(level.into())),
+        |targets, target| targets.with_target(*target, LevelFilter::from(level)),
+    )
+}
+
+/// Masks `secret` in everything logged from now on.
+pub fn register_secret(secret: &str) {
+    let secret = secret.trim_start_matches("Bearer ").trim();
+    // Short values would mask ordinary words
+    if secret.len() < 8 {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap();
+    if !secrets.iter().any(|known| known == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// `text` with tokens and registered secrets replaced by "[redacted]".
+pub fn redact(text: &str) -> String {
+    let mut text = text.to_string();
+    for secret in SECRETS.read().unwrap().iter() {
+        text = text.replace(secret.as_str(), REDACTED);
+    }
+    for (pattern, replacement) in PATTERNS.iter() {
+        text = pattern.replace_all(&text, *replacement).into_owned();
+    }
+    text
+}
+
+/// The last `lines` lines logged to `log_dir`, oldest first, reaching back
+/// into earlier days' files when today's is shorter.
+pub fn recent_lines(log_dir: &Path, lines: usize) -> Result<Vec<String>, AppError> {
+    let mut files: Vec<_> = match std::fs::read_dir(log_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name().and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX))
+            })
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::Internal(format!("Failed to read log directory: {}", e))),
+    };
+    // Names end in the date, so they sort oldest to newest
+    files.sort();
+
+    let mut recent: Vec<String> = Vec::new();
+    for path in files.iter().rev() {
+        if recent.len() >= lines {
+            break;
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+        let mut older: Vec<String> = content.lines().rev().take(lines - recent.len()).map(str::to_string).collect();
+        older.reverse();
+        older.append(&mut recent);
+        recent = older;
+    }
+    Ok(recent)
+}
+
+// Writer that redacts each log line before passing it on
+struct Redacting<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter { inner: self.0.make_writer(), buffer: Vec::new() }
+    }
+}
+
+// Collects one event, which the formatter may write in pieces, and redacts
+// it as a whole when dropped
+struct RedactingWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let text = redact(&String::from_utf8_lossy(&self.buffer));
+            self.buffer.clear();
+            self.inner.write_all(text.as_bytes())?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for RedactingWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
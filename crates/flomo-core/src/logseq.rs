@@ -0,0 +1,112 @@
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export;
+use crate::{parse_memo_time, Memo};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LogseqOptions {
+    /// chrono format for journal file names; Logseq's default is "yyyy_MM_dd"
+    pub file_name_format: String,
+    /// Prefix each block with the memo's time, e.g. "09:30"
+    pub include_time: bool,
+    /// Replace journal pages that already exist instead of skipping them
+    pub overwrite: bool,
+    pub filter: MemoFilter,
+}
+
+impl Default for LogseqOptions {
+    fn default() -> Self {
+        Self {
+            file_name_format: "%Y_%m_%d".to_string(),
+            include_time: true,
+            overwrite: false,
+            filter: MemoFilter::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct LogseqExportResult {
+    pub memos: usize,
+    pub files_written: usize,
+    /// Existing journal pages left untouched because `overwrite` was off
+    pub files_skipped: usize,
+}
+
+/// Writes one Logseq journal page per day under `<dir>/journals`, each memo
+/// a top-level block.
+pub fn export_logseq(db: &Database, dir: &Path, options: &LogseqOptions, tz: FixedOffset) -> Result<LogseqExportResult, AppError> {
+    let memos = export::load_memos_chronological(db, &options.filter)?;
+    let journals = dir.join("journals");
+    std::fs::create_dir_all(&journals)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", journals.display(), e)))?;
+
+    let mut days: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for memo in &memos {
+        let time = parse_memo_time(&memo.created_at, &tz);
+        let file_name = match time {
+            Some(time) => time.format(&options.file_name_format).to_string(),
+            None => memo.created_at.get(..10).unwrap_or(&memo.created_at).replace('-', "_"),
+        };
+        let time_label = time.filter(|_| options.include_time).map(|time| time.format("%H:%M").to_string());
+
+        days.entry(file_name).or_default().push(block(memo, time_label.as_deref()));
+    }
+
+    let mut result = LogseqExportResult { memos: memos.len(), ..Default::default() };
+    for (file_name, blocks) in days {
+        let path = journals.join(format!("{}.md", file_name));
+        if path.exists() && !options.overwrite {
+            result.files_skipped += 1;
+            continue;
+        }
+
+        std::fs::write(&path, blocks.concat())
+            .map_err(|e| AppError::Internal(format!("Failed to write {}: {}", path.display(), e)))?;
+        result.files_written += 1;
+    }
+
+    Ok(result)
+}
+
+/// Formats a memo as a Logseq block: the first line after "- ", the rest
+/// indented so they stay inside the block, and any tags the text doesn't
+/// already mention appended as #tags.
+fn block(memo: &Memo, time: Option<&str>) -> String {
+    let mut lines: Vec<String> = memo.content.trim().lines().map(str::to_string).collect();
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    let missing_tags: Vec<String> = memo.tags.iter()
+        .filter(|tag| !memo.content.contains(&format!("#{}", tag)))
+        .map(|tag| logseq_tag(tag))
+        .collect();
+    if !missing_tags.is_empty() {
+        lines.push(missing_tags.join(" "));
+    }
+
+    if let Some(time) = time {
+        lines[0] = format!("{} {}", time, lines[0]);
+    }
+
+    let mut output = format!("- {}\n", lines[0]);
+    for line in &lines[1..] {
+        output.push_str(&format!("  {}\n", line));
+    }
+    output
+}
+
+fn logseq_tag(tag: &str) -> String {
+    if tag.contains(char::is_whitespace) {
+        format!("#[[{}]]", tag)
+    } else {
+        format!("#{}", tag)
+    }
+}
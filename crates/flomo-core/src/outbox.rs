@@ -0,0 +1,154 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::db::{Database, PendingMemo};
+use crate::error::AppError;
+use crate::import::{content_hash, flomo_html};
+use crate::{FlomoClient, Memo};
+
+/// How often the background task looks for due memos.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+const FIRST_RETRY: Duration = Duration::from_secs(30);
+const MAX_RETRY: Duration = Duration::from_secs(60 * 60);
+
+/// What to do with a queued memo whose content is already stored, usually
+/// because an earlier push reached Flomo but its response was lost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutboxConflictPolicy {
+    /// Drop the queued memo
+    #[default]
+    Skip,
+    /// Create it on Flomo anyway
+    Duplicate,
+}
+
+/// Outcome of one pass over the outbox.
+#[derive(Debug, Default, Serialize)]
+pub struct FlushReport {
+    /// Memos created on Flomo, as stored locally
+    pub pushed: Vec<Memo>,
+    /// Queued memos dropped by the conflict policy
+    pub skipped: usize,
+    /// Memos whose push failed and were rescheduled
+    pub failed: usize,
+    /// Memos still queued after this pass
+    pub remaining: usize,
+}
+
+/// Result of `compose`: the memo as created on Flomo, or its outbox entry
+/// when it couldn't be pushed right away.
+#[derive(Debug, Serialize)]
+pub struct ComposeResult {
+    pub memo: Option<Memo>,
+    pub queued: Option<PendingMemo>,
+}
+
+/// Creates a memo on Flomo, or queues it in the outbox when there is no
+/// client or Flomo can't be reached.
+pub async fn compose(
+    db: &Database,
+    client: Option<&FlomoClient>,
+    content: String,
+    tags: Vec<String>,
+) -> Result<ComposeResult, AppError> {
+    if content.trim().is_empty() {
+        return Err(AppError::InvalidInput("Memo content is empty".to_string()));
+    }
+
+    if let Some(client) = client {
+        match client.create_memo(&flomo_html(&content, &tags)).await {
+            Ok(api_memo) => {
+                let mut memo = Memo::from(api_memo);
+                let stored = memo.clone();
+                db.run(move |db| db.upsert_memo(&stored)).await?;
+                memo.content_html = None;
+                return Ok(ComposeResult { memo: Some(memo), queued: None });
+            }
+            Err(AppError::Network(_)) | Err(AppError::Timeout(_)) | Err(AppError::RateLimited(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let queued = db.run(move |db| db.add_pending_memo(&content, &tags)).await?;
+    Ok(ComposeResult { memo: None, queued: Some(queued) })
+}
+
+/// Delay before the next automatic push after `attempts` failures:
+/// 30 seconds, doubling up to an hour.
+pub fn retry_delay(attempts: i64) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.clamp(0, 16) as u32);
+    FIRST_RETRY.saturating_mul(factor).min(MAX_RETRY)
+}
+
+/// Pushes queued memos in order, all of them when `due_only` is false.
+/// A network failure ends the pass, since the rest would fail the same way;
+/// an auth failure ends it with an error.
+pub async fn flush(
+    db: &Database,
+    client: &FlomoClient,
+    policy: OutboxConflictPolicy,
+    due_only: bool,
+) -> Result<FlushReport, AppError> {
+    let now = Utc::now().to_rfc3339();
+    let queued = db.run(move |db| db.get_pending_memos(due_only.then_some(now.as_str()))).await?;
+    let mut report = FlushReport::default();
+    if queued.is_empty() {
+        report.remaining = db.run(|db| db.get_pending_memos(None)).await?.len();
+        return Ok(report);
+    }
+
+    // Pushed in this pass; Flomo may store their text slightly differently
+    let mut pushed: HashSet<String> = HashSet::new();
+    for pending in queued {
+        let hash = content_hash(&pending.content);
+        if policy == OutboxConflictPolicy::Skip {
+            let content = pending.content.clone();
+            let stored = pushed.contains(&hash) || db.run(move |db| db.has_memo_with_content(&content)).await?;
+            if stored {
+                let id = pending.id;
+                db.run(move |db| db.remove_pending_memo(id)).await?;
+                report.skipped += 1;
+                continue;
+            }
+        }
+
+        match client.create_memo(&flomo_html(&pending.content, &pending.tags)).await {
+            Ok(api_memo) => {
+                let memo = Memo::from(api_memo);
+                let (stored, id) = (memo.clone(), pending.id);
+                db.run(move |db| {
+                    db.upsert_memo(&stored)?;
+                    db.remove_pending_memo(id).map(drop)
+                })
+                .await?;
+                pushed.insert(hash);
+                report.pushed.push(memo);
+            }
+            Err(e) => {
+                let message = e.to_string();
+                db.run(move |db| reschedule(db, &pending, &message)).await?;
+                report.failed += 1;
+                match e {
+                    AppError::Auth(_) => return Err(e),
+                    AppError::Network(_) | AppError::Timeout(_) | AppError::RateLimited(_) => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    report.remaining = db.run(|db| db.get_pending_memos(None)).await?.len();
+    for memo in &mut report.pushed {
+        memo.content_html = None;
+    }
+    Ok(report)
+}
+
+fn reschedule(db: &Database, pending: &PendingMemo, error: &str) -> Result<(), AppError> {
+    let delay = chrono::Duration::from_std(retry_delay(pending.attempts)).unwrap_or_else(|_| chrono::Duration::hours(1));
+    let next_attempt_at = (Utc::now() + delay).to_rfc3339();
+    db.record_push_failure(pending.id, error, &next_attempt_at)
+}
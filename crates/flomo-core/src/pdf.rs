@@ -0,0 +1,271 @@
+use chrono::FixedOffset;
+use printpdf::{Color, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Rgb};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::i18n::Locale;
+use crate::export;
+use crate::{format_date, Memo};
+
+const MARGIN: f32 = 20.0;
+const PT_TO_MM: f32 = 0.3528;
+
+/// CJK-capable fonts with TrueType outlines (printpdf can't embed CFF),
+/// tried in order when no `fontPath` is given.
+const SYSTEM_FONTS: &[&str] = &[
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Light.ttc",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/arphic/uming.ttc",
+];
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    A4,
+    A5,
+    Letter,
+}
+
+impl PageSize {
+    /// Width and height in millimetres
+    fn dimensions(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PdfOptions {
+    pub page_size: PageSize,
+    /// TrueType font covering CJK; falls back to a known system font
+    pub font_path: Option<String>,
+    /// Start with a cover page showing the date range and tag stats
+    pub cover: bool,
+    /// Defaults to the localized "Flomo 备忘录"
+    pub title: Option<String>,
+    pub date_format: String,
+    /// Which memos to include, e.g. a from/to date range for a yearly journal
+    pub filter: MemoFilter,
+    /// Language of generated labels; set from the app settings
+    #[serde(skip)]
+    pub locale: Locale,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            font_path: None,
+            cover: true,
+            title: None,
+            date_format: "yyyy-MM-dd HH:mm".to_string(),
+            filter: MemoFilter::default(),
+            locale: Locale::default(),
+        }
+    }
+}
+
+/// Renders the memos matching `options.filter`, oldest first, into a PDF at
+/// `path`. Returns the number of memos written.
+pub fn export_pdf(db: &Database, path: &Path, options: &PdfOptions, tz: FixedOffset) -> Result<usize, AppError> {
+    let memos = export::load_memos_chronological(db, &options.filter)?;
+
+    let font_path = resolve_font(options.font_path.as_deref())?;
+    let font_bytes = std::fs::read(&font_path)
+        .map_err(|e| AppError::Internal(format!("Failed to read font {}: {}", font_path.display(), e)))?;
+
+    let title = options.title.as_deref().unwrap_or(options.locale.strings().title);
+    let mut writer = PdfWriter::new(title, options.page_size, &font_bytes)?;
+    if options.cover {
+        writer.cover_page(title, &memos, &options.date_format, &tz, options.locale);
+    }
+    for memo in &memos {
+        writer.memo(memo, &options.date_format, &tz);
+    }
+
+    let bytes = writer.finish()?;
+    std::fs::write(path, bytes)
+        .map_err(|e| AppError::Internal(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    Ok(memos.len())
+}
+
+fn resolve_font(font_path: Option<&str>) -> Result<PathBuf, AppError> {
+    if let Some(path) = font_path {
+        return Ok(PathBuf::from(path));
+    }
+
+    SYSTEM_FONTS.iter()
+        .map(PathBuf::from)
+        .find(|path| path.exists())
+        .ok_or_else(|| AppError::InvalidInput("No CJK font found; set fontPath to a TrueType font".to_string()))
+}
+
+struct PdfWriter<'a> {
+    doc: PdfDocumentReference,
+    font: IndirectFontRef,
+    face: ttf_parser::Face<'a>,
+    width: f32,
+    height: f32,
+    layers: Vec<PdfLayerReference>,
+    y: f32,
+}
+
+impl<'a> PdfWriter<'a> {
+    fn new(title: &str, page_size: PageSize, font_bytes: &'a [u8]) -> Result<Self, AppError> {
+        let (width, height) = page_size.dimensions();
+        let (doc, page, layer) = PdfDocument::new(title, Mm(width), Mm(height), "Layer 1");
+        let font = doc.add_external_font(font_bytes)
+            .map_err(|e| AppError::Internal(format!("Failed to load font: {}", e)))?;
+        let face = ttf_parser::Face::parse(font_bytes, 0)
+            .map_err(|e| AppError::Internal(format!("Failed to parse font: {}", e)))?;
+        let layer = doc.get_page(page).get_layer(layer);
+
+        Ok(Self { doc, font, face, width, height, layers: vec![layer], y: height - MARGIN })
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(self.width), Mm(self.height), "Layer 1");
+        self.layers.push(self.doc.get_page(page).get_layer(layer));
+        self.y = self.height - MARGIN;
+    }
+
+    fn layer(&self) -> &PdfLayerReference {
+        self.layers.last().unwrap()
+    }
+
+    fn text_width(&self, text: &str, font_size: f32) -> f32 {
+        let units = self.face.units_per_em() as f32;
+        let advance: f32 = text.chars()
+            .map(|c| {
+                self.face.glyph_index(c)
+                    .and_then(|glyph| self.face.glyph_hor_advance(glyph))
+                    .unwrap_or(0) as f32
+            })
+            .sum();
+        advance / units * font_size * PT_TO_MM
+    }
+
+    /// Greedy line wrapping; breaks Latin text at spaces and CJK anywhere.
+    fn wrap(&self, text: &str, font_size: f32) -> Vec<String> {
+        let max_width = self.width - 2.0 * MARGIN;
+        let mut lines = Vec::new();
+
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for c in paragraph.chars() {
+                line.push(c);
+                if self.text_width(&line, font_size) <= max_width {
+                    continue;
+                }
+
+                line.pop();
+                let break_at = line.rfind(' ').filter(|_| c.is_ascii_alphanumeric());
+                match break_at {
+                    Some(index) if index > 0 => {
+                        let rest = line.split_off(index + 1);
+                        lines.push(line.trim_end().to_string());
+                        line = rest;
+                    }
+                    _ => lines.push(std::mem::take(&mut line)),
+                }
+                line.push(c);
+            }
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    fn line_height(font_size: f32) -> f32 {
+        font_size * PT_TO_MM * 1.5
+    }
+
+    fn ensure_space(&mut self, needed: f32) {
+        if self.y - needed < MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn write_line(&mut self, text: &str, font_size: f32, color: (f32, f32, f32)) {
+        let height = Self::line_height(font_size);
+        self.ensure_space(height);
+        self.y -= height;
+
+        let layer = self.layer();
+        layer.set_fill_color(Color::Rgb(Rgb::new(color.0, color.1, color.2, None)));
+        layer.use_text(text, font_size, Mm(MARGIN), Mm(self.y), &self.font);
+    }
+
+    fn cover_page(&mut self, title: &str, memos: &[Memo], date_format: &str, tz: &FixedOffset, locale: Locale) {
+        let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+        for tag in memos.iter().flat_map(|memo| &memo.tags) {
+            *tag_counts.entry(tag.as_str()).or_default() += 1;
+        }
+        let mut top_tags: Vec<_> = tag_counts.iter().collect();
+        top_tags.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let characters: usize = memos.iter().map(|memo| memo.content.chars().count()).sum();
+
+        self.y = self.height * 0.65;
+        self.write_line(title, 26.0, (0.1, 0.1, 0.1));
+        self.y -= 6.0;
+
+        if let (Some(first), Some(last)) = (memos.first(), memos.last()) {
+            let range = format!(
+                "{} — {}",
+                format_date(&first.created_at, date_format, tz),
+                format_date(&last.created_at, date_format, tz)
+            );
+            self.write_line(&range, 12.0, (0.4, 0.4, 0.4));
+        }
+        self.write_line(&locale.cover_stats(memos.len(), tag_counts.len(), characters), 12.0, (0.4, 0.4, 0.4));
+        self.y -= 6.0;
+
+        for (tag, count) in top_tags.into_iter().take(10) {
+            self.write_line(&format!("#{}  {}", tag, count), 11.0, (0.19, 0.64, 0.42));
+        }
+
+        self.new_page();
+    }
+
+    fn memo(&mut self, memo: &Memo, date_format: &str, tz: &FixedOffset) {
+        let lines = self.wrap(memo.content.trim(), 11.0);
+
+        // Keep the date with at least the first line of content
+        self.ensure_space(Self::line_height(9.0) + Self::line_height(11.0));
+        self.write_line(&format_date(&memo.created_at, date_format, tz), 9.0, (0.55, 0.55, 0.55));
+        for line in &lines {
+            self.write_line(line, 11.0, (0.12, 0.12, 0.12));
+        }
+        if !memo.tags.is_empty() {
+            let tags: Vec<String> = memo.tags.iter().map(|tag| format!("#{}", tag)).collect();
+            self.write_line(&tags.join("  "), 9.0, (0.19, 0.64, 0.42));
+        }
+        self.y -= 5.0;
+    }
+
+    fn finish(self) -> Result<Vec<u8>, AppError> {
+        let total = self.layers.len();
+        for (index, layer) in self.layers.iter().enumerate() {
+            let label = format!("{} / {}", index + 1, total);
+            let x = (self.width - self.text_width(&label, 9.0)) / 2.0;
+            layer.set_fill_color(Color::Rgb(Rgb::new(0.55, 0.55, 0.55, None)));
+            layer.use_text(label, 9.0, Mm(x), Mm(MARGIN / 2.0), &self.font);
+        }
+
+        self.doc.save_to_bytes()
+            .map_err(|e| AppError::Internal(format!("Failed to render PDF: {}", e)))
+    }
+}
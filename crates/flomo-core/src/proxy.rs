@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+const SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+/// Proxy the Flomo API is reached through.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    /// "http://host:port", "https://host:port" or "socks5://host:port";
+    /// "socks5h://" also resolves host names through the proxy
+    pub url: String,
+    /// Leave empty for proxies without authentication
+    pub username: String,
+    pub password: String,
+}
+
+impl ProxySettings {
+    pub fn to_proxy(&self) -> Result<reqwest::Proxy, AppError> {
+        let invalid = |reason: String| AppError::InvalidInput(format!("Invalid proxy {}: {}", self.url, reason));
+        let mut url = reqwest::Url::parse(self.url.trim()).map_err(|e| invalid(e.to_string()))?;
+        if !SCHEMES.contains(&url.scheme()) {
+            return Err(invalid("use http://, https://, socks5:// or socks5h://".to_string()));
+        }
+        if url.host_str().is_none_or(str::is_empty) {
+            return Err(invalid("missing host".to_string()));
+        }
+
+        if self.username.is_empty() {
+            return reqwest::Proxy::all(url.as_str()).map_err(|e| invalid(e.to_string()));
+        }
+        // SOCKS credentials are only read from the URL
+        if url.scheme().starts_with("socks") {
+            url.set_username(&self.username).map_err(|_| invalid("can't hold a username".to_string()))?;
+            url.set_password(Some(&self.password)).map_err(|_| invalid("can't hold a password".to_string()))?;
+            return reqwest::Proxy::all(url.as_str()).map_err(|e| invalid(e.to_string()));
+        }
+        Ok(reqwest::Proxy::all(url.as_str())
+            .map_err(|e| invalid(e.to_string()))?
+            .basic_auth(&self.username, &self.password))
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::FixedOffset;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export;
+use crate::{parse_memo_time, Memo};
+
+const HIGHLIGHTS_URL: &str = "https://readwise.io/api/v2/highlights/";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Highlights sent per request.
+const BATCH_SIZE: usize = 100;
+/// Readwise rejects highlight text and notes longer than this.
+const MAX_TEXT_CHARS: usize = 8191;
+/// Every memo lands in one Readwise "book" with this title.
+const SOURCE_TITLE: &str = "Flomo";
+
+/// Counts shown to the user after a push.
+#[derive(Debug, Default, Serialize)]
+pub struct ReadwiseReport {
+    /// Memos sent, including ones edited since their last push
+    pub pushed: usize,
+    /// Memos already on Readwise as they are now, and empty ones
+    pub skipped: usize,
+}
+
+#[derive(Serialize)]
+struct Highlight<'a> {
+    text: String,
+    title: &'static str,
+    source_type: &'static str,
+    category: &'static str,
+    /// Readwise turns ".tag" words in the note into highlight tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    highlighted_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    highlight_url: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct HighlightsRequest<'a> {
+    highlights: Vec<Highlight<'a>>,
+}
+
+/// Sends memos to Readwise as highlights, skipping memos already pushed
+/// unless they were edited since. `tag` limits the push to memos with that
+/// tag or a tag below it, e.g. "highlight"; `None` pushes every memo.
+pub async fn push(
+    db: &Database,
+    token: &str,
+    tag: Option<String>,
+    tz: FixedOffset,
+) -> Result<ReadwiseReport, AppError> {
+    if token.trim().is_empty() {
+        return Err(AppError::InvalidInput("No Readwise access token is configured".to_string()));
+    }
+    let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+
+    let filter = MemoFilter { tag, ..Default::default() };
+    let (memos, pushes) = db
+        .run(move |db| Ok((export::load_memos_chronological(db, &filter)?, db.get_readwise_pushes()?)))
+        .await?;
+    let total = memos.len();
+    let pending: Vec<Memo> = memos.into_iter()
+        .filter(|memo| pushes.get(&memo.slug) != Some(&memo.updated_at))
+        .filter(|memo| !memo.content.trim().is_empty())
+        .collect();
+
+    let mut report = ReadwiseReport { pushed: 0, skipped: total - pending.len() };
+    for batch in pending.chunks(BATCH_SIZE) {
+        let request = HighlightsRequest {
+            highlights: batch.iter().map(|memo| highlight(memo, &tz)).collect(),
+        };
+        let response = client.post(HIGHLIGHTS_URL)
+            .header("Authorization", format!("Token {}", token.trim()))
+            .json(&request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                401 | 403 => AppError::Auth(format!("Readwise rejected the access token: {}", body)),
+                429 => AppError::RateLimited(format!("Readwise: {}", body)),
+                _ => AppError::Network(format!("Readwise returned {}: {}", status, body)),
+            });
+        }
+
+        // Recorded per batch so a failure later on doesn't resend these
+        let sent = batch.to_vec();
+        db.run(move |db| db.record_readwise_pushes(&sent)).await?;
+        report.pushed += batch.len();
+    }
+
+    Ok(report)
+}
+
+fn highlight<'a>(memo: &'a Memo, tz: &FixedOffset) -> Highlight<'a> {
+    let note = memo.tags.iter()
+        .map(|tag| format!(".{}", tag.replace(['/', ' '], "-")))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Highlight {
+        text: memo.content.trim().chars().take(MAX_TEXT_CHARS).collect(),
+        title: SOURCE_TITLE,
+        source_type: "flomo_garden",
+        category: "books",
+        note: (!note.is_empty()).then(|| note.chars().take(MAX_TEXT_CHARS).collect()),
+        highlighted_at: parse_memo_time(&memo.created_at, tz).map(|date| date.to_rfc3339()),
+        highlight_url: memo.url.as_deref().filter(|url| !url.is_empty()),
+    }
+}
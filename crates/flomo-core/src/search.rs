@@ -0,0 +1,644 @@
+use chrono::NaiveDate;
+use pinyin::ToPinyin;
+use regex::{Regex, RegexBuilder};
+use rusqlite::types::Value;
+use serde::Serialize;
+
+use crate::db::{tag_clause, Database, MemoFilter};
+use crate::error::AppError;
+use crate::export::escape_html;
+use crate::Memo;
+
+/// Characters of context kept before the first match in a snippet
+const SNIPPET_LEAD: usize = 30;
+const SNIPPET_LENGTH: usize = 120;
+/// Most results a regex search returns per call, since every row it skips
+/// still has to be scanned
+pub const REGEX_RESULT_CAP: usize = 200;
+// Keeps pathological patterns from using unbounded memory
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// A parsed search box query. Words must all appear (`AND` between them is
+/// optional), `"quoted text"` matches as a phrase, `OR` matches either side
+/// and a leading `-` excludes memos containing the term. `AND` binds tighter
+/// than `OR`, so `a b OR c` means `(a AND b) OR c`.
+///
+/// Field operators narrow the results instead of matching text:
+/// `tag:读书` (including nested tags), `before:2024-01-01` and `after:2023-06`
+/// (a year, month or day; `after` includes the period itself) and
+/// `has:link`, `has:image` or `has:tag`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchExpr {
+    /// Substring of the content or tags
+    Text(String),
+    Tag(String),
+    /// Created before this "YYYY-MM-DD HH:MM:SS" time
+    Before(String),
+    /// Created at or after this "YYYY-MM-DD HH:MM:SS" time
+    After(String),
+    Has(HasFeature),
+    Not(Box<SearchExpr>),
+    And(Vec<SearchExpr>),
+    Or(Vec<SearchExpr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HasFeature {
+    Link,
+    Image,
+    Tag,
+}
+
+enum Token {
+    /// `quoted` terms are always text, never field operators
+    Term { text: String, negated: bool, quoted: bool },
+    And,
+    Or,
+}
+
+// Chinese input methods produce curly quotes
+fn is_quote(ch: char) -> bool {
+    matches!(ch, '"' | '“' | '”')
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // A lone "-" is just text
+        let negated = chars[i] == '-' && chars.get(i + 1).is_some_and(|ch| !ch.is_whitespace());
+        if negated {
+            i += 1;
+        }
+
+        if is_quote(chars[i]) {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && !is_quote(chars[i]) {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Term { text, negated, quoted: true });
+            i += 1;
+            continue;
+        }
+
+        // Quotes inside a word keep spaces in operator values: tag:"reading list"
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() {
+            if is_quote(chars[i]) {
+                i += 1;
+                while i < chars.len() && !is_quote(chars[i]) {
+                    i += 1;
+                }
+            }
+            i += 1;
+        }
+        let word: String = chars[start..i.min(chars.len())].iter().filter(|&&ch| !is_quote(ch)).collect();
+        tokens.push(match (word.as_str(), negated) {
+            ("AND", false) => Token::And,
+            ("OR", false) => Token::Or,
+            _ => Token::Term { text: word, negated, quoted: false },
+        });
+    }
+
+    tokens
+}
+
+impl SearchExpr {
+    /// Parses a search box query; `None` when it has no terms.
+    pub fn parse(query: &str) -> Option<SearchExpr> {
+        let mut groups: Vec<Vec<SearchExpr>> = vec![Vec::new()];
+
+        for token in tokenize(query) {
+            match token {
+                Token::And => {}
+                Token::Or => {
+                    if groups.last().is_some_and(|group| !group.is_empty()) {
+                        groups.push(Vec::new());
+                    }
+                }
+                Token::Term { text, negated, quoted } => {
+                    if text.trim().is_empty() {
+                        continue;
+                    }
+                    let expr = match quoted {
+                        true => SearchExpr::Text(text),
+                        false => SearchExpr::term(text),
+                    };
+                    let expr = if negated { SearchExpr::Not(Box::new(expr)) } else { expr };
+                    if let Some(group) = groups.last_mut() {
+                        group.push(expr);
+                    }
+                }
+            }
+        }
+
+        let mut alternatives: Vec<SearchExpr> = groups.into_iter()
+            .filter(|group| !group.is_empty())
+            .map(|mut group| if group.len() == 1 { group.remove(0) } else { SearchExpr::And(group) })
+            .collect();
+
+        match alternatives.len() {
+            0 => None,
+            1 => alternatives.pop(),
+            _ => Some(SearchExpr::Or(alternatives)),
+        }
+    }
+
+    /// Text the query looks for, excluding negated terms, for highlighting.
+    pub fn highlight_terms(&self) -> Vec<String> {
+        match self {
+            SearchExpr::Text(text) => vec![text.clone()],
+            SearchExpr::And(exprs) | SearchExpr::Or(exprs) => {
+                exprs.iter().flat_map(SearchExpr::highlight_terms).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// A field operator such as `tag:x`, or plain text when the word isn't one
+    /// or its value doesn't parse.
+    fn term(word: String) -> SearchExpr {
+        let Some((field, value)) = word.split_once(':') else {
+            return SearchExpr::Text(word);
+        };
+
+        let expr = match field.to_lowercase().as_str() {
+            "tag" if !value.is_empty() => Some(SearchExpr::Tag(value.to_string())),
+            "before" => period(value).map(SearchExpr::Before),
+            "after" => period(value).map(SearchExpr::After),
+            "has" => match value.to_lowercase().as_str() {
+                "link" => Some(SearchExpr::Has(HasFeature::Link)),
+                "image" => Some(SearchExpr::Has(HasFeature::Image)),
+                "tag" => Some(SearchExpr::Has(HasFeature::Tag)),
+                _ => None,
+            },
+            _ => None,
+        };
+        expr.unwrap_or(SearchExpr::Text(word))
+    }
+
+    /// A predicate on the `memos` table, appending its bind values to `params`.
+    /// With `pinyin`, Latin text terms also match the pinyin of Chinese text.
+    pub fn to_sql(&self, params: &mut Vec<Value>, pinyin: bool) -> String {
+        let join = |exprs: &[SearchExpr], params: &mut Vec<Value>, op: &str| {
+            let clauses: Vec<String> = exprs.iter().map(|expr| expr.to_sql(params, pinyin)).collect();
+            format!("({})", clauses.join(op))
+        };
+
+        match self {
+            SearchExpr::Text(text) => {
+                let pattern = format!("%{}%", escape_like(text));
+                params.push(Value::from(pattern.clone()));
+                params.push(Value::from(pattern));
+                match pinyin_query(text).filter(|_| pinyin) {
+                    Some(query) => {
+                        params.push(Value::from(query));
+                        "(content LIKE ? ESCAPE '\\' OR tags LIKE ? ESCAPE '\\' OR pinyin_match(content_pinyin, ?))".to_string()
+                    }
+                    None => "(content LIKE ? ESCAPE '\\' OR tags LIKE ? ESCAPE '\\')".to_string(),
+                }
+            }
+            SearchExpr::Tag(tag) => {
+                let (clause, values) = tag_clause(tag);
+                params.extend(values);
+                clause.to_string()
+            }
+            SearchExpr::Before(time) => {
+                params.push(Value::from(time.clone()));
+                "created_at < ?".to_string()
+            }
+            SearchExpr::After(time) => {
+                params.push(Value::from(time.clone()));
+                "created_at >= ?".to_string()
+            }
+            SearchExpr::Has(HasFeature::Link) => "(content LIKE '%http://%' OR content LIKE '%https://%')".to_string(),
+            SearchExpr::Has(HasFeature::Image) => "COALESCE(content_html, '') LIKE '%<img%'".to_string(),
+            SearchExpr::Has(HasFeature::Tag) => "slug IN (SELECT slug FROM memo_tags)".to_string(),
+            SearchExpr::Not(expr) => format!("NOT {}", expr.to_sql(params, pinyin)),
+            SearchExpr::And(exprs) => join(exprs, params, " AND "),
+            SearchExpr::Or(exprs) => join(exprs, params, " OR "),
+        }
+    }
+}
+
+/// One page of search results.
+#[derive(Debug, Serialize)]
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    /// Pass back to get the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// A search result with where the query matched its content.
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub memo: Memo,
+    /// Excerpt around the first match, HTML-escaped with matches in `<mark>`
+    pub snippet: String,
+    /// `[start, end)` of each match in `content`, in UTF-16 code units so
+    /// they can be passed straight to `String.prototype.slice`
+    pub matches: Vec<[usize; 2]>,
+    /// Relevance from 0 to 1, only set by fuzzy search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+impl SearchHit {
+    pub fn new(memo: Memo, terms: &[String]) -> Self {
+        let ranges = find_matches(&memo.content, terms);
+        Self::with_ranges(memo, ranges)
+    }
+
+    pub fn for_regex(memo: Memo, pattern: &Regex) -> Self {
+        let ranges = pattern.find_iter(&memo.content)
+            .filter(|found| !found.is_empty())
+            .map(|found| (found.start(), found.end()))
+            .collect();
+        Self::with_ranges(memo, ranges)
+    }
+
+    fn with_ranges(memo: Memo, ranges: Vec<(usize, usize)>) -> Self {
+        let snippet = snippet(&memo.content, &ranges);
+        let utf16 = |byte: usize| memo.content[..byte].encode_utf16().count();
+        let matches = ranges.iter().map(|&(start, end)| [utf16(start), utf16(end)]).collect();
+
+        SearchHit { memo, snippet, matches, score: None }
+    }
+}
+
+/// Typo-tolerant search: every word of `query` must appear in the content
+/// within a few edits, and hits are ranked by how close the matches are.
+/// All memos matching `filter` are scored, then `offset` and `limit` apply.
+pub fn fuzzy_search(
+    db: &Database,
+    query: &str,
+    filter: &MemoFilter,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<SearchHit>, AppError> {
+    let words: Vec<Vec<char>> = query.split_whitespace()
+        .map(|word| word.chars().map(|ch| ch.to_ascii_lowercase()).collect())
+        .collect();
+    if words.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut hits = Vec::new();
+    db.scan_memos(filter, |memo| {
+        if let Some((score, ranges)) = fuzzy_score(&memo.content, &words) {
+            let mut hit = SearchHit::with_ranges(memo, ranges);
+            hit.score = Some(score);
+            hits.push(hit);
+        }
+    })?;
+
+    hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(hits.into_iter().skip(offset).take(limit).collect())
+}
+
+/// Edits tolerated in a word of `len` characters. Chinese words carry more
+/// per character, so they get fewer.
+fn allowed_edits(len: usize, cjk: bool) -> usize {
+    match (cjk, len) {
+        (true, 0..=2) | (false, 0..=2) => 0,
+        (true, _) => len / 3,
+        (false, 3..=4) => 1,
+        (false, 5..=8) => 2,
+        (false, _) => len / 4,
+    }
+}
+
+/// Average closeness of each query word's best match in `content`, with the
+/// byte ranges matched; `None` if any word has no match within its edits.
+fn fuzzy_score(content: &str, words: &[Vec<char>]) -> Option<(f64, Vec<(usize, usize)>)> {
+    // Byte offset and ASCII-lowercased char, so ranges map back to `content`
+    let chars: Vec<(usize, char)> = content.char_indices().map(|(i, ch)| (i, ch.to_ascii_lowercase())).collect();
+    let byte_end = |index: usize| chars.get(index).map(|&(i, _)| i).unwrap_or(content.len());
+
+    let mut total = 0.0;
+    let mut ranges = Vec::new();
+    for word in words {
+        let cjk = word.iter().any(|ch| !ch.is_ascii());
+        let max_edits = allowed_edits(word.len(), cjk);
+
+        // Candidate spans: whole alphanumeric tokens for Latin words, and
+        // windows of about the word's length for Chinese ones
+        let mut best: Option<(usize, usize, usize)> = None;
+        let mut consider = |start: usize, end: usize| {
+            let candidate: Vec<char> = chars[start..end].iter().map(|&(_, ch)| ch).collect();
+            if let Some(distance) = bounded_levenshtein(word, &candidate, max_edits) {
+                if best.is_none_or(|(d, _, _)| distance < d) {
+                    best = Some((distance, start, end));
+                }
+            }
+        };
+
+        if cjk {
+            // Skip content that doesn't even share enough characters
+            let shared = word.iter().filter(|ch| chars.iter().any(|(_, c)| c == *ch)).count();
+            if shared + max_edits < word.len() {
+                return None;
+            }
+            for start in 0..chars.len() {
+                let shortest = word.len().saturating_sub(max_edits).max(1);
+                for len in shortest..=word.len() + max_edits {
+                    if start + len <= chars.len() {
+                        consider(start, start + len);
+                    }
+                }
+            }
+        } else {
+            let mut start = 0;
+            while start < chars.len() {
+                if !chars[start].1.is_alphanumeric() {
+                    start += 1;
+                    continue;
+                }
+                let mut end = start;
+                while end < chars.len() && chars[end].1.is_ascii_alphanumeric() {
+                    end += 1;
+                }
+                if end == start {
+                    end += 1;
+                }
+                consider(start, end);
+                // A word can also sit inside a longer token ("flomo" in "flomoapp")
+                if end - start > word.len() {
+                    for offset in 1..=end - start - word.len() {
+                        consider(start + offset, start + offset + word.len());
+                    }
+                    consider(start, start + word.len());
+                }
+                start = end;
+            }
+        }
+
+        let (distance, start, end) = best?;
+        total += 1.0 - distance as f64 / word.len().max(1) as f64;
+        ranges.push((chars[start].0, byte_end(end)));
+    }
+
+    Some((total / words.len() as f64, merge_ranges(ranges)))
+}
+
+/// Edit distance between `a` and `b`, or `None` once it exceeds `max`.
+fn bounded_levenshtein(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().min().is_some_and(|&lowest| lowest > max) {
+            return None;
+        }
+        previous = current;
+    }
+
+    Some(previous[b.len()]).filter(|&distance| distance <= max)
+}
+
+/// Compiles a user-supplied regex for searching memo content.
+pub fn compile_regex(pattern: &str) -> Result<Regex, AppError> {
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| AppError::InvalidInput(format!("Invalid regex: {}", e)))
+}
+
+/// Byte ranges of `terms` in `content`, merged where they overlap. Matching
+/// ignores ASCII case, like SQLite's LIKE.
+fn find_matches(content: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    for term in terms.iter().filter(|term| !term.is_empty()) {
+        for (start, _) in content.char_indices() {
+            let mut rest = content[start..].chars();
+            let matched = term.chars().all(|ch| rest.next().is_some_and(|c| c.eq_ignore_ascii_case(&ch)));
+            if matched {
+                let end = content.len() - rest.as_str().len();
+                ranges.push((start, end));
+            }
+        }
+    }
+
+    merge_ranges(ranges)
+}
+
+/// Sorts byte ranges and joins the ones that overlap or touch.
+fn merge_ranges(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Up to `SNIPPET_LENGTH` characters of `content` on one line, starting a
+/// little before the first match, with the matches inside it marked.
+fn snippet(content: &str, matches: &[(usize, usize)]) -> String {
+    let first = matches.first().map(|&(start, _)| start).unwrap_or(0);
+    let lead_start = content[..first].char_indices().rev().nth(SNIPPET_LEAD - 1).map(|(i, _)| i).unwrap_or(0);
+    let end = content[lead_start..].char_indices().nth(SNIPPET_LENGTH).map(|(i, _)| lead_start + i).unwrap_or(content.len());
+
+    let mut output = String::new();
+    if lead_start > 0 {
+        output.push('…');
+    }
+    let mut pos = lead_start;
+    for &(start, match_end) in matches {
+        // Ranges are merged, but never slice backwards past what's written
+        if match_end <= lead_start || start >= end || start < pos {
+            continue;
+        }
+        let match_end = match_end.min(end);
+        output.push_str(&escape_html(&content[pos..start]));
+        output.push_str(&format!("<mark>{}</mark>", escape_html(&content[start..match_end])));
+        pos = match_end;
+    }
+    output.push_str(&escape_html(&content[pos..end]));
+    if end < content.len() {
+        output.push('…');
+    }
+
+    output.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Users searching for "100%" or "a_b" mean those characters literally
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Start of the year, month or day in `value` ("2024", "2024-06",
+/// "2024-06-15", also with '/') as a "YYYY-MM-DD HH:MM:SS" time.
+fn period(value: &str) -> Option<String> {
+    let value = value.replace('/', "-");
+    let padded = match value.split('-').count() {
+        1 => format!("{}-01-01", value),
+        2 => format!("{}-01", value),
+        _ => value,
+    };
+
+    NaiveDate::parse_from_str(&padded, "%Y-%m-%d")
+        .ok()
+        .map(|date| format!("{} 00:00:00", date.format("%Y-%m-%d")))
+}
+
+/// Pinyin of the Chinese text in `content` for `pinyin_match`: runs of
+/// Chinese characters become space-separated syllables without tones, with
+/// runs separated by '|' so matches don't span other text.
+/// "今天读书, ok 笔记" gives "jin tian du shu|bi ji".
+pub fn pinyin_index(content: &str) -> String {
+    let mut runs: Vec<Vec<String>> = vec![Vec::new()];
+    for ch in content.chars() {
+        match ch.to_pinyin() {
+            // Users type ü as v
+            Some(syllable) => {
+                if let Some(run) = runs.last_mut() {
+                    run.push(syllable.plain().replace('ü', "v"));
+                }
+            }
+            None => {
+                if runs.last().is_some_and(|run| !run.is_empty()) {
+                    runs.push(Vec::new());
+                }
+            }
+        }
+    }
+
+    runs.iter()
+        .filter(|run| !run.is_empty())
+        .map(|run| run.join(" "))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// A search term worth matching against pinyin: Latin letters only, such as
+/// "dushu" or "dsbiji", ignoring the apostrophes people type as separators.
+fn pinyin_query(text: &str) -> Option<String> {
+    let query: String = text.chars().filter(|&ch| ch != '\'').collect::<String>().to_lowercase();
+    (query.len() >= 2 && query.chars().all(|ch| ch.is_ascii_lowercase())).then_some(query)
+}
+
+/// Whether `query` spells consecutive syllables of `index`, each written in
+/// full or as a prefix, so "dushu", "ds" and "dsbiji" all match "du shu bi ji".
+pub fn pinyin_matches(index: &str, query: &str) -> bool {
+    let query = query.as_bytes();
+
+    for run in index.split('|') {
+        let syllables: Vec<&[u8]> = run.split(' ').map(str::as_bytes).collect();
+        for start in 0..syllables.len() {
+            // Positions in the query reachable after consuming syllables start..=i
+            let mut positions = vec![0];
+            for syllable in &syllables[start..] {
+                let mut next = Vec::new();
+                for &pos in &positions {
+                    let rest = &query[pos..];
+                    let common = rest.iter().zip(syllable.iter()).take_while(|(a, b)| a == b).count();
+                    next.extend((1..=common).map(|len| pos + len));
+                }
+                if next.contains(&query.len()) {
+                    return true;
+                }
+                next.sort_unstable();
+                next.dedup();
+                if next.is_empty() {
+                    break;
+                }
+                positions = next;
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> SearchExpr {
+        SearchExpr::Text(text.to_string())
+    }
+
+    #[test]
+    fn parse_binds_and_tighter_than_or() {
+        assert_eq!(
+            SearchExpr::parse("a b OR c"),
+            Some(SearchExpr::Or(vec![SearchExpr::And(vec![text("a"), text("b")]), text("c")]))
+        );
+        assert_eq!(SearchExpr::parse("a AND b"), Some(SearchExpr::And(vec![text("a"), text("b")])));
+    }
+
+    #[test]
+    fn parse_phrases_and_negation() {
+        assert_eq!(
+            SearchExpr::parse("“reading list” -draft"),
+            Some(SearchExpr::And(vec![text("reading list"), SearchExpr::Not(Box::new(text("draft")))]))
+        );
+        // A lone dash is plain text
+        assert_eq!(SearchExpr::parse("-"), Some(text("-")));
+    }
+
+    #[test]
+    fn parse_field_operators() {
+        assert_eq!(SearchExpr::parse("tag:\"reading list\""), Some(SearchExpr::Tag("reading list".to_string())));
+        assert_eq!(SearchExpr::parse("after:2023-06"), Some(SearchExpr::After("2023-06-01 00:00:00".to_string())));
+        assert_eq!(SearchExpr::parse("before:2024"), Some(SearchExpr::Before("2024-01-01 00:00:00".to_string())));
+        assert_eq!(SearchExpr::parse("HAS:Link"), Some(SearchExpr::Has(HasFeature::Link)));
+        // Quoted or unparseable operators stay text
+        assert_eq!(SearchExpr::parse("\"tag:x\""), Some(text("tag:x")));
+        assert_eq!(SearchExpr::parse("before:soon"), Some(text("before:soon")));
+    }
+
+    #[test]
+    fn parse_without_terms() {
+        assert_eq!(SearchExpr::parse(""), None);
+        assert_eq!(SearchExpr::parse("  OR AND \"\" "), None);
+        assert_eq!(SearchExpr::parse("OR a OR"), Some(text("a")));
+    }
+
+    #[test]
+    fn pinyin_matches_full_and_prefix_syllables() {
+        let index = pinyin_index("今天读书, ok 笔记");
+        assert_eq!(index, "jin tian du shu|bi ji");
+        assert!(pinyin_matches(&index, "dushu"));
+        assert!(pinyin_matches(&index, "ds"));
+        assert!(pinyin_matches(&index, "tiandu"));
+        assert!(pinyin_matches(&index, "biji"));
+    }
+
+    #[test]
+    fn pinyin_matches_stay_within_runs() {
+        let index = pinyin_index("今天读书, ok 笔记");
+        // "shu" and "bi" are separated by other text
+        assert!(!pinyin_matches(&index, "shubi"));
+        assert!(!pinyin_matches(&index, "jintiandushux"));
+        assert!(!pinyin_matches(&index, "dsj"));
+    }
+}
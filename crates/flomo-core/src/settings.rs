@@ -0,0 +1,175 @@
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::auto_export::AutoExportRule;
+use crate::backup::BackupSettings;
+use crate::date_format::system_timezone;
+use crate::digest::DigestSettings;
+use crate::feed::FeedSettings;
+use crate::git_mirror::GitMirrorSettings;
+use crate::i18n::Locale;
+use crate::llm::LlmSettings;
+use crate::logging::LogLevel;
+use crate::telegram::TelegramSettings;
+use crate::outbox::OutboxConflictPolicy;
+use crate::proxy::ProxySettings;
+use crate::sync::PROGRESS_INTERVAL;
+use crate::templates::CaptureTemplate;
+use crate::webhooks::WebhookSettings;
+use crate::ApiSettings;
+
+/// Store file in the app data directory holding the token and settings.
+pub const CONFIG_FILE: &str = "config.json";
+/// Key of the `AppSettings` object in `CONFIG_FILE`.
+pub const SETTINGS_KEY: &str = "settings";
+/// Key of the Flomo token in `CONFIG_FILE`.
+pub const TOKEN_KEY: &str = "authorization";
+/// Local database in the app data directory.
+pub const DATABASE_FILE: &str = "flomo.db";
+/// Database used in demo mode, so generated memos never mix with real ones.
+pub const DEMO_DATABASE_FILE: &str = "flomo-demo.db";
+
+/// User preferences persisted next to the token in `config.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// UTC offset in minutes; `None` follows the system timezone
+    pub timezone_offset_minutes: Option<i32>,
+    /// Regenerate an Atom/RSS feed file after every sync
+    pub feed: Option<FeedSettings>,
+    /// Language of labels and headings in exported files
+    pub locale: Locale,
+    /// Let Latin search terms such as "dushu" or "dsbj" find "读书笔记"
+    pub pinyin_search: bool,
+    /// Endpoint used for summaries; LLM features are off while unset
+    pub llm: Option<LlmSettings>,
+    /// Whether queued memos already stored (e.g. pushed before a lost
+    /// response) are dropped or pushed again
+    pub outbox_conflict_policy: OutboxConflictPolicy,
+    /// Templates offered by quick capture
+    pub capture_templates: Vec<CaptureTemplate>,
+    /// WebDAV folder for database or JSON backups
+    pub backup: Option<BackupSettings>,
+    /// Git repository mirroring memos as Markdown, committed after every sync
+    pub git_mirror: Option<GitMirrorSettings>,
+    /// Access token from readwise.io/access_token for pushing highlights
+    pub readwise_token: Option<String>,
+    /// Telegram bot polled while the app runs; its messages become memos
+    pub telegram: Option<TelegramSettings>,
+    /// SMTP server and weekday for the weekly digest email
+    pub digest: Option<DigestSettings>,
+    /// URLs notified after syncs, for automations
+    pub webhooks: Vec<WebhookSettings>,
+    /// Exports written after every successful sync
+    pub auto_exports: Vec<AutoExportRule>,
+    /// HTTP or SOCKS5 proxy for the Flomo API
+    pub proxy: Option<ProxySettings>,
+    /// App version, user agent and timeouts for Flomo API requests
+    pub api: ApiSettings,
+    /// Least severe events written to the log file
+    pub log_level: LogLevel,
+    /// Sync generated memos instead of a Flomo account, into a separate
+    /// database; takes effect after a restart
+    pub demo_mode: bool,
+    /// Least time between sync progress updates sent to the window;
+    /// `None` uses `sync::PROGRESS_INTERVAL`
+    pub progress_interval_ms: Option<u64>,
+    /// Column memo text converted from Flomo's HTML is wrapped at; `None`
+    /// keeps each paragraph on one line
+    pub text_width: Option<usize>,
+    /// Folder holding the database and attachments, e.g. a synced folder or
+    /// one next to the executable; `None` keeps them in the app data
+    /// directory. Changed through the app's `set_data_dir`, which moves them.
+    pub data_dir: Option<PathBuf>,
+}
+
+impl AppSettings {
+    /// Reads settings saved under `SETTINGS_KEY`. A field that doesn't
+    /// parse, e.g. one written by another version, falls back to its
+    /// default instead of resetting every setting; why each was dropped is
+    /// returned for logging.
+    pub fn from_json(value: serde_json::Value) -> (Self, Vec<String>) {
+        let saved = match value {
+            serde_json::Value::Object(saved) => saved,
+            other => return (Self::default(), vec![format!("expected an object, found {}", other)]),
+        };
+        if let Ok(settings) = serde_json::from_value(serde_json::Value::Object(saved.clone())) {
+            return (settings, Vec::new());
+        }
+
+        // Missing fields take their defaults, so fields are kept one by one
+        let mut kept = serde_json::Map::new();
+        let mut dropped = Vec::new();
+        for (key, field) in saved {
+            let mut candidate = kept.clone();
+            candidate.insert(key.clone(), field);
+            match serde_json::from_value::<Self>(serde_json::Value::Object(candidate.clone())) {
+                Ok(_) => kept = candidate,
+                Err(e) => dropped.push(format!("{}: {}", key, e)),
+            }
+        }
+        let settings = serde_json::from_value(serde_json::Value::Object(kept)).unwrap_or_default();
+        (settings, dropped)
+    }
+
+    pub fn timezone(&self) -> FixedOffset {
+        self.timezone_offset_minutes
+            .and_then(|minutes| FixedOffset::east_opt(minutes * 60))
+            .unwrap_or_else(system_timezone)
+    }
+
+    pub fn progress_interval(&self) -> Duration {
+        self.progress_interval_ms.map_or(PROGRESS_INTERVAL, Duration::from_millis)
+    }
+
+    /// Folder holding the database, given the app data directory.
+    pub fn database_dir(&self, app_data_dir: &Path) -> PathBuf {
+        self.data_dir.clone().unwrap_or_else(|| app_data_dir.to_path_buf())
+    }
+
+    /// Database file in the data directory for the current mode.
+    pub fn database_file(&self) -> &'static str {
+        if self.demo_mode { DEMO_DATABASE_FILE } else { DATABASE_FILE }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn from_json_keeps_valid_fields() {
+        let (settings, dropped) = AppSettings::from_json(json!({
+            "pinyin_search": true,
+            "locale": "fr-FR",
+            "timezone_offset_minutes": 480,
+            "text_width": "wide",
+        }));
+
+        assert!(settings.pinyin_search);
+        assert_eq!(settings.timezone_offset_minutes, Some(480));
+        assert_eq!(settings.locale, Locale::default());
+        assert_eq!(settings.text_width, None);
+        assert_eq!(dropped.len(), 2);
+        assert!(dropped.iter().any(|reason| reason.starts_with("locale: ")));
+        assert!(dropped.iter().any(|reason| reason.starts_with("text_width: ")));
+    }
+
+    #[test]
+    fn from_json_reads_valid_settings_whole() {
+        let (settings, dropped) = AppSettings::from_json(json!({ "demo_mode": true, "locale": "en-US" }));
+        assert!(settings.demo_mode);
+        assert_eq!(settings.locale, Locale::EnUs);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn from_json_rejects_non_objects() {
+        let (settings, dropped) = AppSettings::from_json(json!([1, 2]));
+        assert!(!settings.pinyin_search);
+        assert_eq!(dropped.len(), 1);
+    }
+}
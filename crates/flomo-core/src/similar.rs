@@ -0,0 +1,162 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::Memo;
+
+/// A memo related to the one being viewed.
+#[derive(Debug, Serialize)]
+pub struct SimilarMemo {
+    #[serde(flatten)]
+    pub memo: Memo,
+    /// Cosine similarity of the TF-IDF vectors, from 0 to 1
+    pub score: f64,
+}
+
+/// The `top_k` memos most similar to `slug` by TF-IDF cosine similarity
+/// over all memos outside the trash. Memos sharing no terms are left out.
+pub fn similar_memos(db: &Database, slug: &str, top_k: usize) -> Result<Vec<SimilarMemo>, AppError> {
+    let corpus = Corpus::load(db)?;
+    let Some(target) = corpus.memos.iter().position(|memo| memo.slug == slug) else {
+        return Err(AppError::NotFound(format!("Memo {}", slug)));
+    };
+
+    let query = corpus.term_counts[target].clone();
+    Ok(corpus.rank(&query, Some(target), top_k))
+}
+
+/// The `top_k` memos most relevant to free text, such as a question, scored
+/// the same way as `similar_memos`.
+pub fn relevant_memos(db: &Database, text: &str, top_k: usize) -> Result<Vec<SimilarMemo>, AppError> {
+    let corpus = Corpus::load(db)?;
+    Ok(corpus.rank(&count_terms(text), None, top_k))
+}
+
+struct Corpus {
+    memos: Vec<Memo>,
+    term_counts: Vec<HashMap<String, usize>>,
+    document_frequency: HashMap<String, usize>,
+}
+
+impl Corpus {
+    fn load(db: &Database) -> Result<Self, AppError> {
+        let mut memos = Vec::new();
+        let mut term_counts = Vec::new();
+        db.scan_memos(&MemoFilter::default(), |memo| {
+            term_counts.push(count_terms(&memo.content));
+            memos.push(memo);
+        })?;
+
+        let mut document_frequency: HashMap<String, usize> = HashMap::new();
+        for counts in &term_counts {
+            for term in counts.keys() {
+                *document_frequency.entry(term.clone()).or_default() += 1;
+            }
+        }
+
+        Ok(Self { memos, term_counts, document_frequency })
+    }
+
+    // Smoothed IDF, so terms in every memo still count for a little
+    fn idf(&self, term: &str) -> f64 {
+        let frequency = self.document_frequency.get(term).copied().unwrap_or(0);
+        ((self.memos.len() as f64 + 1.0) / (frequency as f64 + 1.0)).ln() + 1.0
+    }
+
+    fn vector<'a>(&self, counts: &'a HashMap<String, usize>) -> HashMap<&'a str, f64> {
+        counts.iter()
+            .map(|(term, &count)| (term.as_str(), (1.0 + (count as f64).ln()) * self.idf(term)))
+            .collect()
+    }
+
+    fn rank(self, query: &HashMap<String, usize>, exclude: Option<usize>, top_k: usize) -> Vec<SimilarMemo> {
+        let query_vector = self.vector(query);
+        let query_norm = norm(&query_vector);
+        if query_norm == 0.0 {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(usize, f64)> = self.term_counts.iter()
+            .enumerate()
+            .filter(|&(i, _)| Some(i) != exclude)
+            .filter_map(|(i, counts)| {
+                let vector = self.vector(counts);
+                let vector_norm = norm(&vector);
+                if vector_norm == 0.0 {
+                    return None;
+                }
+                // Iterate the smaller vector for the dot product
+                let (small, large) = if vector.len() < query_vector.len() {
+                    (&vector, &query_vector)
+                } else {
+                    (&query_vector, &vector)
+                };
+                let dot: f64 = small.iter()
+                    .filter_map(|(term, weight)| large.get(term).map(|other| weight * other))
+                    .sum();
+                Some((i, dot / (vector_norm * query_norm)))
+            })
+            .filter(|&(_, score)| score > 0.0)
+            .collect();
+
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(top_k);
+
+        let mut memos: Vec<Option<Memo>> = self.memos.into_iter().map(Some).collect();
+        scored.into_iter()
+            .filter_map(|(i, score)| memos[i].take().map(|memo| SimilarMemo { memo, score }))
+            .collect()
+    }
+}
+
+fn norm(vector: &HashMap<&str, f64>) -> f64 {
+    vector.values().map(|weight| weight * weight).sum::<f64>().sqrt()
+}
+
+// Latin words of two or more characters, lowercased, and overlapping
+// bigrams of Chinese text, which has no spaces to split words on
+fn count_terms(content: &str) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut word = String::new();
+    let mut han: Vec<char> = Vec::new();
+
+    for ch in content.chars() {
+        if is_han(ch) {
+            add_word(&mut word, &mut counts);
+            han.push(ch);
+        } else {
+            add_han(&mut han, &mut counts);
+            if ch.is_alphanumeric() {
+                word.push(ch);
+            } else {
+                add_word(&mut word, &mut counts);
+            }
+        }
+    }
+    add_word(&mut word, &mut counts);
+    add_han(&mut han, &mut counts);
+
+    counts
+}
+
+fn add_word(word: &mut String, counts: &mut HashMap<String, usize>) {
+    if word.chars().count() >= 2 {
+        *counts.entry(word.to_lowercase()).or_default() += 1;
+    }
+    word.clear();
+}
+
+fn add_han(han: &mut Vec<char>, counts: &mut HashMap<String, usize>) {
+    if han.len() == 1 {
+        *counts.entry(han[0].to_string()).or_default() += 1;
+    }
+    for pair in han.windows(2) {
+        *counts.entry(pair.iter().collect()).or_default() += 1;
+    }
+    han.clear();
+}
+
+fn is_han(ch: char) -> bool {
+    matches!(ch, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}')
+}
@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::{normalize_tag, Database};
+use crate::error::AppError;
+use crate::import::inline_tags;
+
+/// Weight of tags reached only through co-occurrence, relative to a
+/// keyword match.
+const CO_OCCURRENCE_WEIGHT: f64 = 0.5;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionReason {
+    /// The tag, or one of its path segments, appears in the text
+    Keyword,
+    /// Often used together with a tag already in or matched by the text
+    CoOccurrence,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagSuggestion {
+    pub tag: String,
+    pub score: f64,
+    pub reason: SuggestionReason,
+}
+
+/// Existing tags that fit `content`, best first. Tags the text already
+/// carries are not suggested again.
+pub fn suggest_tags(db: &Database, content: &str, limit: usize) -> Result<Vec<TagSuggestion>, AppError> {
+    let tags = db.get_all_tags()?;
+    let graph = db.get_tag_graph(1)?;
+
+    let text = content.to_lowercase();
+    let present: Vec<String> = inline_tags(content).iter()
+        .map(|tag| normalize_tag(tag).to_lowercase())
+        .collect();
+    let usage: HashMap<&str, i64> = tags.iter().map(|tag| (tag.tag.as_str(), tag.count)).collect();
+
+    let mut keyword_scores: HashMap<&str, f64> = HashMap::new();
+    for tag in &tags {
+        if present.contains(&tag.tag.to_lowercase()) {
+            continue;
+        }
+        let path = tag.tag.to_lowercase();
+        // The full path counts most, then the leaf, then parent segments
+        let segments: Vec<&str> = path.split('/').collect();
+        let mut score: f64 = 0.0;
+        if contains_keyword(&text, &path) {
+            score = 1.0;
+        } else if let Some((leaf, parents)) = segments.split_last() {
+            if contains_keyword(&text, leaf) {
+                score = 0.8;
+            } else if parents.iter().any(|segment| contains_keyword(&text, segment)) {
+                score = 0.4;
+            }
+        }
+        if score > 0.0 {
+            // Break ties toward tags that are actually used
+            keyword_scores.insert(tag.tag.as_str(), score + (tag.count as f64).ln_1p() / 100.0);
+        }
+    }
+
+    // Tags already in the text and keyword matches both pull in their neighbours
+    let mut seeds: Vec<(&str, f64)> = tags.iter()
+        .filter(|tag| present.contains(&tag.tag.to_lowercase()))
+        .map(|tag| (tag.tag.as_str(), 1.0))
+        .collect();
+    seeds.extend(keyword_scores.iter().map(|(&tag, &score)| (tag, score)));
+
+    let mut related_scores: HashMap<&str, f64> = HashMap::new();
+    for (seed, seed_score) in &seeds {
+        let seed_count = usage.get(seed).copied().unwrap_or(1).max(1) as f64;
+        for edge in &graph.edges {
+            let other = if edge.source == *seed {
+                edge.target.as_str()
+            } else if edge.target == *seed {
+                edge.source.as_str()
+            } else {
+                continue;
+            };
+            // How often memos with the seed also carry the other tag
+            let share = edge.weight as f64 / seed_count;
+            *related_scores.entry(other).or_default() += CO_OCCURRENCE_WEIGHT * share * seed_score;
+        }
+    }
+
+    let mut suggestions: Vec<TagSuggestion> = keyword_scores.iter()
+        .map(|(&tag, &score)| TagSuggestion {
+            tag: tag.to_string(),
+            score: score + related_scores.get(tag).copied().unwrap_or(0.0),
+            reason: SuggestionReason::Keyword,
+        })
+        .collect();
+    suggestions.extend(
+        related_scores.into_iter()
+            .filter(|(tag, _)| !keyword_scores.contains_key(tag) && !present.contains(&tag.to_lowercase()))
+            .map(|(tag, score)| TagSuggestion {
+                tag: tag.to_string(),
+                score,
+                reason: SuggestionReason::CoOccurrence,
+            }),
+    );
+
+    suggestions.sort_by(|a, b| {
+        b.score.partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
+
+// Substring match; Latin keywords must also sit on word boundaries so "art"
+// doesn't match "start". Single letters and digits are too noisy to match.
+fn contains_keyword(text: &str, keyword: &str) -> bool {
+    if keyword.chars().count() < 2 {
+        return false;
+    }
+    if !keyword.is_ascii() {
+        return text.contains(keyword);
+    }
+
+    let is_word_char = |ch: Option<char>| ch.is_some_and(|ch| ch.is_ascii_alphanumeric());
+    text.match_indices(keyword).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + keyword.len()..].chars().next();
+        !is_word_char(before) && !is_word_char(after)
+    })
+}
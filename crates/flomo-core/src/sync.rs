@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::backup::{self, BackupEntry};
+use crate::db::{Database, MemoChanges, UpsertStats};
+use crate::error::AppError;
+use crate::git_mirror::{self, GitMirrorReport};
+use crate::settings::AppSettings;
+use crate::{auto_export, feed, webhooks};
+use crate::{ApiPage, FlomoClient, Memo};
+
+/// Source of sync failures in the API diagnostics.
+const DIAGNOSTIC_SOURCE: &str = "memo page";
+/// Default of `AppSettings::progress_interval_ms`.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// Progress jump, in percentage points, reported even within the interval.
+const PROGRESS_STEP_PERCENT: usize = 10;
+/// Pages fetched ahead while earlier ones are written to the database.
+const PREFETCH_PAGES: usize = 2;
+/// Memos a full sync fetches before giving up on reaching the last page.
+const MAX_SYNCED_MEMOS: usize = 500_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncProgress {
+    pub total: usize,
+    pub current: usize,
+    pub status: String,
+    pub message: String,
+    /// Memos inserted, changed, and skipped as identical so far in this sync
+    pub new_count: usize,
+    pub updated_count: usize,
+    pub unchanged_count: usize,
+    pub pages_fetched: usize,
+    pub elapsed_secs: f64,
+    /// Memos fetched per second so far
+    pub memos_per_sec: f64,
+    /// Seconds until the sync should finish, going by the previous sync's
+    /// memo count; `None` until there's a rate to go by
+    pub eta_secs: Option<f64>,
+}
+
+/// Lets through at most one progress report per interval, plus reports
+/// that change the status or move the bar by `PROGRESS_STEP_PERCENT`. The
+/// final result of a sync isn't a progress report and is always sent.
+pub struct ProgressThrottle {
+    interval: Duration,
+    // Time, status and percentage of the last report let through
+    last: Option<(Instant, String, usize)>,
+}
+
+impl ProgressThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, last: None }
+    }
+
+    /// Whether `progress` should be reported; if so it counts as the last one.
+    pub fn ready(&mut self, progress: &SyncProgress) -> bool {
+        let percent = (progress.current * 100).checked_div(progress.total).unwrap_or(0);
+        let ready = match &self.last {
+            None => true,
+            Some((at, status, last_percent)) => {
+                at.elapsed() >= self.interval
+                    || *status != progress.status
+                    || percent.abs_diff(*last_percent) >= PROGRESS_STEP_PERCENT
+            }
+        };
+        if ready {
+            self.last = Some((Instant::now(), progress.status.clone(), percent));
+        }
+        ready
+    }
+}
+
+/// Pulls every memo from Flomo into `db`, recording the run in the sync
+/// history. `on_progress` is called after each page; the completed progress
+/// is returned rather than reported, so callers can finish their own
+/// post-sync work first, along with the memos the sync added or changed.
+/// The next page is requested while the previous one is being written.
+///
+/// `client` should be built `with_cancellation(cancel)` so cancelling also
+/// aborts the request in flight.
+pub async fn sync_all(
+    db: &Database,
+    client: &FlomoClient,
+    cancel: &CancellationToken,
+    mut on_progress: impl FnMut(&SyncProgress) -> Result<(), AppError>,
+) -> Result<(SyncProgress, MemoChanges), AppError> {
+    // Every sync pulls all memos, so the last one's count is what to expect
+    let expected_total = db.run(|db| {
+        let status = db.get_sync_status()?;
+        if status.total_memos > 0 { Ok(status.total_memos) } else { db.get_memo_count() }
+    })
+    .await
+    .unwrap_or(0) as usize;
+    let started = Instant::now();
+
+    // Update status to syncing
+    db.run(|db| db.update_sync_status("syncing", None, None)).await?;
+    let run_id = db.run(|db| db.begin_sync_run()).await?;
+
+    let mut run = SyncRunProgress { id: run_id, pages: 0, stats: UpsertStats::default(), unparsed: 0 };
+    let mut changes = MemoChanges::default();
+    // Every sync walks the whole list, so what it didn't see is gone from Flomo
+    let mut synced_slugs = HashSet::new();
+    let mut records = 0;
+    let mut fetched = 0;
+
+    let (sender, receiver) = mpsc::channel(PREFETCH_PAGES);
+    let writing = async {
+        let mut receiver = receiver;
+        while let Some(FetchedPage { page, more, seen }) = receiver.recv().await {
+            // Pages already fetched are dropped once the sync is cancelled
+            if cancel.is_cancelled() {
+                break;
+            }
+            run.pages += 1;
+            run.unparsed += page.unparsed.len();
+            if !page.unparsed.is_empty() {
+                let unparsed = page.unparsed;
+                db.run(move |db| {
+                    for record in &unparsed {
+                        db.record_api_diagnostic(DIAGNOSTIC_SOURCE, &record.error, &record.raw)?;
+                    }
+                    Ok(())
+                })
+                .await?;
+            }
+
+            let batch: Vec<Memo> = page.memos.into_iter().map(Memo::from).collect();
+            synced_slugs.extend(batch.iter().map(|memo| memo.slug.clone()));
+
+            // Save batch to database
+            let batch_size = batch.len();
+            let (stats, batch_changes) = db.run(move |db| db.bulk_upsert_memos_tracked(&batch)).await?;
+            run.stats += stats;
+            changes += batch_changes;
+            records += batch_size;
+            fetched = seen;
+
+            debug!("Total API records so far: {}", records);
+
+            let elapsed = started.elapsed().as_secs_f64();
+            let memos_per_sec = if elapsed > 0.0 { fetched as f64 / elapsed } else { 0.0 };
+            // Once past the expected count, assume one more full page
+            let total = match more {
+                true if fetched < expected_total => expected_total,
+                true => fetched + batch_size,
+                false => fetched,
+            };
+            let eta_secs = (memos_per_sec > 0.0).then(|| (total - fetched) as f64 / memos_per_sec);
+
+            let progress = SyncProgress {
+                total,
+                current: fetched,
+                status: "syncing".to_string(),
+                message: format!("Synced {} unique memos...", fetched),
+                new_count: run.stats.new,
+                updated_count: run.stats.updated,
+                unchanged_count: run.stats.unchanged,
+                pages_fetched: run.pages,
+                elapsed_secs: elapsed,
+                memos_per_sec,
+                eta_secs,
+            };
+            on_progress(&progress)?;
+        }
+        Ok::<_, AppError>(())
+    };
+    let (fetching, writing) = tokio::join!(fetch_pages(client, cancel, sender), writing);
+
+    // Either side stops early when the sync is cancelled
+    if cancel.is_cancelled() {
+        return Err(mark_sync_cancelled(db, &run, records).await);
+    }
+    if let Err(e) = writing {
+        return Err(mark_sync_failed(db, &run, e).await);
+    }
+    let iteration_count = match fetching {
+        Ok(iterations) => iterations,
+        Err(AppError::Parse(message)) => {
+            // The message carries the response text
+            let diagnostic = message.clone();
+            let _ = db.run(move |db| db.record_api_diagnostic(DIAGNOSTIC_SOURCE, &diagnostic, "")).await;
+            return Err(mark_sync_failed(db, &run, AppError::Parse(message)).await);
+        }
+        Err(e) => return Err(mark_sync_failed(db, &run, e).await),
+    };
+
+    let elapsed = started.elapsed().as_secs_f64();
+    // Get final count from database
+    let final_count = db.run(|db| db.get_memo_count()).await.unwrap_or(0);
+    // A record that couldn't be parsed has no slug, so its memo would be
+    // miscounted as deleted
+    let deleted = match run.unparsed {
+        0 => db.run(move |db| db.count_memos_missing_from(&synced_slugs)).await?,
+        _ => 0,
+    };
+
+    info!("Sync completed: {} iterations, {} total API records fetched, {} unique slugs seen, {} unique memos in database, {} no longer on Flomo",
+          iteration_count, records, fetched, final_count, deleted);
+
+    // Update sync status to completed
+    db.run(move |db| db.update_sync_status("completed", Some(final_count), None)).await?;
+    db.run(move |db| db.finish_sync_run(run.id, "completed", run.pages, run.stats, deleted, None)).await?;
+
+    let mut message = format!(
+        "Successfully synced {} unique memos ({} new, {} updated, {} unchanged)",
+        final_count, run.stats.new, run.stats.updated, run.stats.unchanged
+    );
+    if run.unparsed > 0 {
+        message.push_str(&format!("; skipped {} that couldn't be read, see the API diagnostics", run.unparsed));
+    }
+    let progress = SyncProgress {
+        total: final_count as usize,
+        current: final_count as usize,
+        status: "completed".to_string(),
+        message,
+        new_count: run.stats.new,
+        updated_count: run.stats.updated,
+        unchanged_count: run.stats.unchanged,
+        pages_fetched: run.pages,
+        elapsed_secs: elapsed,
+        memos_per_sec: if elapsed > 0.0 { fetched as f64 / elapsed } else { 0.0 },
+        eta_secs: Some(0.0),
+    };
+    Ok((progress, changes))
+}
+
+/// What `run_after_sync` did that a front end may want to announce.
+#[derive(Debug, Default)]
+pub struct AfterSyncReport {
+    /// Set when the git mirror made a new commit
+    pub git_mirror: Option<GitMirrorReport>,
+    /// The backup uploaded after the sync, if one is configured
+    pub backup: Option<BackupEntry>,
+}
+
+/// Keeps the feed, git mirror, webhooks, after-sync backup and auto-exports
+/// configured in `settings` current after a successful sync. None of them
+/// is worth failing the sync over, so failures are only logged.
+pub async fn run_after_sync(
+    db: &Database,
+    settings: AppSettings,
+    progress: &SyncProgress,
+    changes: &MemoChanges,
+) -> AfterSyncReport {
+    let mut report = AfterSyncReport::default();
+    let (tz, locale) = (settings.timezone(), settings.locale);
+    if let Some(feed) = settings.feed {
+        if let Err(e) = db.run(move |db| feed::write_feed(db, &feed, &tz, locale)).await {
+            error!("Failed to update feed: {}", e);
+        }
+    }
+    if let Some(mirror) = settings.git_mirror {
+        match db.run(move |db| git_mirror::mirror(db, &mirror, &tz)).await {
+            Ok(mirrored) => report.git_mirror = mirrored.commit.is_some().then_some(mirrored),
+            Err(e) => error!("Failed to update git mirror: {}", e),
+        }
+    }
+    for (url, e) in webhooks::deliver(db, &settings.webhooks, progress, changes).await {
+        error!("Failed to call webhook {}: {}", url, e);
+    }
+    if let Some(backup) = settings.backup.filter(|backup| backup.after_sync) {
+        match backup::backup(db, &backup).await {
+            Ok(entry) => report.backup = Some(entry),
+            Err(e) => error!("Failed to back up after sync: {}", e),
+        }
+    }
+    if !settings.auto_exports.is_empty() {
+        let rules = settings.auto_exports;
+        match db.run(move |db| auto_export::run_rules(db, &rules, tz, locale)).await {
+            Ok(failed) => {
+                for (rule, e) in failed {
+                    error!("Auto-export {} failed: {}", rule, e);
+                }
+            }
+            Err(e) => error!("Failed to run auto-exports: {}", e),
+        }
+    }
+    report
+}
+
+// A page handed from the fetch loop to the database writer
+struct FetchedPage {
+    page: ApiPage,
+    /// Whether another page follows
+    more: bool,
+    /// Unique memos fetched so far, this page included
+    seen: usize,
+}
+
+// Requests pages one after another and queues them for the writer, until
+// the last page or until the writer stops taking them. Returns the number
+// of requests made.
+async fn fetch_pages(
+    client: &FlomoClient,
+    cancel: &CancellationToken,
+    pages: mpsc::Sender<FetchedPage>,
+) -> Result<usize, AppError> {
+    let mut latest_slug: Option<String> = None;
+    let mut latest_updated_at: Option<i64> = None;
+    let mut seen_slugs = HashSet::new();
+    let mut consecutive_empty_batches = 0;
+    // Safety limit against pagination that never ends, sized so that small
+    // pages still reach MAX_SYNCED_MEMOS
+    let max_iterations = MAX_SYNCED_MEMOS.div_ceil(client.page_size()) + 1;
+    let mut iteration_count = 0;
+
+    loop {
+        iteration_count += 1;
+        if iteration_count > max_iterations {
+            warn!("Reached maximum iteration limit of {}", max_iterations);
+            // Reported as a failure, since the sync would otherwise look complete
+            return Err(AppError::Internal(format!(
+                "Stopped after {} pages and {} memos without reaching the last page",
+                max_iterations - 1,
+                seen_slugs.len()
+            )));
+        }
+        // Check if sync was cancelled
+        if cancel.is_cancelled() {
+            return Err(AppError::Cancelled);
+        }
+
+        let page = client.fetch_page(latest_slug.as_deref(), latest_updated_at).await?;
+        // Skipped records still count towards a full page
+        let page_size = page.memos.len() + page.unparsed.len();
+        let memos = &page.memos;
+        let mut done = false;
+
+        debug!("API returned {} memos in this batch (iteration {})", memos.len(), iteration_count);
+
+        if memos.is_empty() {
+            consecutive_empty_batches += 1;
+            if consecutive_empty_batches >= 2 {
+                debug!("No more memos to fetch after {} empty batches, ending sync", consecutive_empty_batches);
+                done = true;
+            }
+        } else {
+            consecutive_empty_batches = 0;
+        }
+
+        // Check for duplicates - if we've seen all memos in this batch before, we're looping
+        let new_memos_count = memos.iter()
+            .filter(|memo| !seen_slugs.contains(&memo.slug))
+            .count();
+
+        // Only break if we have no timestamp AND we're seeing duplicates
+        // With proper timestamp, duplicates shouldn't happen
+        if new_memos_count == 0 && !memos.is_empty() && latest_updated_at.is_none() {
+            warn!("All {} memos in this batch are duplicates and pagination timestamp is missing.", memos.len());
+            warn!("This usually means we've fetched all available memos. Total unique memos: {}", seen_slugs.len());
+            // Don't break immediately - the API might still have more data
+            // Only break if we've seen this multiple times
+            consecutive_empty_batches += 1;
+            if consecutive_empty_batches >= 2 {
+                warn!("Breaking after {} duplicate batches to prevent infinite loop.", consecutive_empty_batches);
+                done = true;
+            }
+        } else if new_memos_count > 0 {
+            consecutive_empty_batches = 0;
+            debug!("Found {} new memos in this batch", new_memos_count);
+        }
+
+        // Add new slugs to our seen set
+        for memo in memos {
+            seen_slugs.insert(memo.slug.clone());
+        }
+
+        let should_continue = !done && page_size >= client.page_size();
+
+        if should_continue && !memos.is_empty() {
+            let last_memo = &memos[memos.len() - 1];
+            latest_slug = Some(last_memo.slug.clone());
+
+            // API returns "YYYY-MM-DD HH:MM:SS" in the requested timezone
+            let date_str = &last_memo.updated_at;
+
+            if let Some(timestamp) = client.cursor_timestamp(date_str) {
+                latest_updated_at = Some(timestamp);
+                debug!("Successfully parsed date: {} -> timestamp: {}", date_str, timestamp);
+            } else {
+                error!("Failed to parse date format: '{}'", date_str);
+                // Don't break - continue with just slug pagination
+            }
+
+            debug!("Next page will use slug: {} and updated_at: {:?}",
+                   latest_slug.as_ref().unwrap(), latest_updated_at);
+        }
+
+        let fetched = FetchedPage { page, more: should_continue, seen: seen_slugs.len() };
+        // The writer only hangs up after a failure, which it reports itself
+        if pages.send(fetched).await.is_err() || !should_continue {
+            break;
+        }
+    }
+    Ok(iteration_count)
+}
+
+// Counters for the sync_runs entry of the sync in progress
+#[derive(Clone, Copy)]
+struct SyncRunProgress {
+    id: i64,
+    pages: usize,
+    stats: UpsertStats,
+    /// Records skipped because they couldn't be parsed
+    unparsed: usize,
+}
+
+// Records a failed sync and hands the error back for the caller
+async fn mark_sync_failed(db: &Database, run: &SyncRunProgress, error: AppError) -> AppError {
+    let status_msg = error.to_string();
+    let run = *run;
+    let _ = db
+        .run(move |db| {
+            db.update_sync_status("failed", None, Some(&status_msg))?;
+            db.finish_sync_run(run.id, "failed", run.pages, run.stats, 0, Some(&status_msg))
+        })
+        .await;
+    error
+}
+
+// Records a cancelled sync with the number of memos fetched before it stopped
+async fn mark_sync_cancelled(db: &Database, run: &SyncRunProgress, fetched: usize) -> AppError {
+    let run = *run;
+    let _ = db
+        .run(move |db| {
+            db.update_sync_status("cancelled", Some(fetched as i64), None)?;
+            db.finish_sync_run(run.id, "cancelled", run.pages, run.stats, 0, None)
+        })
+        .await;
+    AppError::Cancelled
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::outbox::{self, ComposeResult};
+use crate::FlomoClient;
+
+const API_URL: &str = "https://api.telegram.org";
+/// How long one getUpdates call waits for a message before returning empty.
+const POLL_TIMEOUT_SECS: u64 = 30;
+/// How often to check whether the bridge was turned on while it's off.
+pub const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+/// Pause after a failed poll before trying again.
+pub const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// A Telegram bot whose messages become memos.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelegramSettings {
+    /// Token from @BotFather
+    pub bot_token: String,
+    /// Chats whose messages are saved; anyone else who finds the bot is
+    /// told their chat id and ignored
+    pub allowed_chat_ids: Vec<i64>,
+    /// Added to every captured memo, e.g. "inbox"
+    pub tags: Vec<String>,
+}
+
+/// A text message (or a photo/file caption) sent to the bot.
+#[derive(Debug, Clone)]
+pub struct IncomingMessage {
+    pub chat_id: i64,
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+    caption: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+pub struct TelegramBot {
+    client: reqwest::Client,
+    token: String,
+    /// Id of the next update to fetch; asking for it confirms the earlier ones
+    offset: i64,
+}
+
+impl TelegramBot {
+    pub fn new(token: &str) -> Result<Self, AppError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(POLL_TIMEOUT_SECS + 15))
+            .build()?;
+        Ok(Self { client, token: token.trim().to_string(), offset: 0 })
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Waits up to 30 seconds for new messages.
+    pub async fn poll(&mut self) -> Result<Vec<IncomingMessage>, AppError> {
+        let updates: Vec<Update> = self.call("getUpdates", &serde_json::json!({
+            "offset": self.offset,
+            "timeout": POLL_TIMEOUT_SECS,
+            "allowed_updates": ["message"],
+        }))
+        .await?;
+
+        if let Some(last) = updates.last() {
+            self.offset = last.update_id + 1;
+        }
+        Ok(updates.into_iter()
+            .filter_map(|update| update.message)
+            .filter_map(|message| {
+                let text = message.text.or(message.caption)?;
+                Some(IncomingMessage { chat_id: message.chat.id, text })
+            })
+            .collect())
+    }
+
+    pub async fn reply(&self, chat_id: i64, text: &str) -> Result<(), AppError> {
+        self.call::<serde_json::Value>("sendMessage", &serde_json::json!({ "chat_id": chat_id, "text": text }))
+            .await
+            .map(|_| ())
+    }
+
+    async fn call<T: serde::de::DeserializeOwned>(&self, method: &str, body: &serde_json::Value) -> Result<T, AppError> {
+        let response = self.client
+            .post(format!("{}/bot{}/{}", API_URL, self.token, method))
+            .json(body)
+            .send()
+            .await?;
+        let status = response.status();
+        let response: ApiResponse<T> = response.json().await
+            .map_err(|e| AppError::Parse(format!("Unexpected Telegram response: {}", e)))?;
+
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => {
+                let description = response.description.unwrap_or_default();
+                Err(match status.as_u16() {
+                    401 | 404 => AppError::Auth(format!("Telegram rejected the bot token: {}", description)),
+                    429 => AppError::RateLimited(format!("Telegram: {}", description)),
+                    _ => AppError::Network(format!("Telegram returned {}: {}", status, description)),
+                })
+            }
+        }
+    }
+}
+
+/// Saves a message as a memo, queueing it when Flomo can't be reached, and
+/// tells the sender how it went. Messages from chats that aren't allowed
+/// are answered with the chat id and dropped.
+pub async fn capture(
+    bot: &TelegramBot,
+    db: &Database,
+    client: Option<&FlomoClient>,
+    settings: &TelegramSettings,
+    message: IncomingMessage,
+) -> Result<Option<ComposeResult>, AppError> {
+    if !settings.allowed_chat_ids.contains(&message.chat_id) {
+        let notice = format!("This chat isn't allowed to save memos. Add chat id {} in Flomo Garden's settings.", message.chat_id);
+        bot.reply(message.chat_id, &notice).await?;
+        return Ok(None);
+    }
+    // Telegram clients send /start when a chat with the bot is opened
+    if message.text.trim() == "/start" {
+        bot.reply(message.chat_id, "Send me anything and it becomes a memo.").await?;
+        return Ok(None);
+    }
+
+    let chat_id = message.chat_id;
+    match outbox::compose(db, client, message.text, settings.tags.clone()).await {
+        Ok(result) => {
+            let reply = if result.memo.is_some() { "Saved" } else { "Queued; it will be saved once Flomo is reachable" };
+            if let Err(e) = bot.reply(chat_id, reply).await {
+                tracing::warn!("Failed to answer Telegram message: {}", e);
+            }
+            Ok(Some(result))
+        }
+        Err(e) => {
+            let _ = bot.reply(chat_id, &format!("Couldn't save this memo: {}", e)).await;
+            Err(e)
+        }
+    }
+}
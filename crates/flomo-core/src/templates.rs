@@ -0,0 +1,85 @@
+use chrono::{DateTime, Datelike, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use crate::date_format::format_date_in;
+use crate::error::AppError;
+use crate::i18n::Locale;
+
+const MEMO_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// A reusable memo skeleton for quick capture, e.g. a daily review or a
+/// book note. `body` may contain the variables listed on `render`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureTemplate {
+    pub id: String,
+    pub name: String,
+    pub body: String,
+    /// Added to the memo unless the rendered body already carries them
+    pub tags: Vec<String>,
+}
+
+/// Values available to a template when it is rendered.
+pub struct TemplateContext {
+    pub now: DateTime<FixedOffset>,
+    pub locale: Locale,
+    /// Clipboard text; only read when the template uses `{{clipboard}}`
+    pub clipboard: Option<String>,
+}
+
+impl CaptureTemplate {
+    /// Whether rendering needs the clipboard, which is read lazily.
+    pub fn uses_clipboard(&self) -> bool {
+        self.body.contains("{{clipboard}}")
+    }
+
+    /// Expands the template variables in `body`:
+    ///
+    /// - `{{date}}`, `{{time}}`, `{{datetime}}`: "2024-05-12", "09:30", both
+    /// - `{{date:FORMAT}}`: any `format_date` pattern, e.g. `{{date:yyyy年M月d日}}`
+    /// - `{{weekday}}`: "星期日" or "Sunday", following the app language
+    /// - `{{week}}`: ISO week number; `{{year}}`, `{{month}}`, `{{day}}`
+    /// - `{{clipboard}}`: the current clipboard text
+    ///
+    /// Unknown variables are left as written.
+    pub fn render(&self, context: &TemplateContext) -> Result<String, AppError> {
+        let now = context.now.format(MEMO_TIME_FORMAT).to_string();
+        let tz = *context.now.offset();
+        let zh = context.locale == Locale::ZhCn;
+        let format = |pattern: &str| format_date_in(&now, pattern, &tz, zh);
+
+        let mut output = String::new();
+        let mut rest = self.body.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let name = &rest[start + 2..start + 2 + length];
+            output.push_str(&rest[..start]);
+
+            let value = match name {
+                "date" => Some(format("yyyy-MM-dd")),
+                "time" => Some(format("HH:mm")),
+                "datetime" => Some(format("yyyy-MM-dd HH:mm")),
+                "weekday" => Some(format("EEEE")),
+                "week" => Some(context.now.iso_week().week().to_string()),
+                "year" => Some(context.now.year().to_string()),
+                "month" => Some(format("MM")),
+                "day" => Some(format("dd")),
+                "clipboard" => Some(context.clipboard.clone().unwrap_or_default()),
+                other => other.strip_prefix("date:").map(format),
+            };
+            match value {
+                Some(value) => output.push_str(&value),
+                None => output.push_str(&rest[start..start + length + 4]),
+            }
+            rest = &rest[start + length + 4..];
+        }
+        output.push_str(rest);
+
+        if output.trim().is_empty() {
+            return Err(AppError::InvalidInput(format!("Template \"{}\" rendered an empty memo", self.name)));
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,28 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+// "[ ] buy milk", "- [x] done", "* [ ] ...", "1. [ ] ..."
+static CHECKBOX_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:[-*+•]\s*|\d+[.)]\s*)?\[([ xX✓✔])\]\s*(.+?)\s*$").unwrap()
+});
+
+/// A checklist item found in a memo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Todo {
+    pub slug: String,
+    /// Index of the item within its memo, counting from 0
+    pub position: i64,
+    pub text: String,
+    pub done: bool,
+    /// Creation time of the memo the item belongs to
+    pub created_at: String,
+}
+
+/// Checkbox lines in `content` as (text, done), in order.
+pub fn extract_todos(content: &str) -> Vec<(String, bool)> {
+    content.lines()
+        .filter_map(|line| CHECKBOX_PATTERN.captures(line))
+        .map(|captures| (captures[2].to_string(), &captures[1] != " "))
+        .collect()
+}
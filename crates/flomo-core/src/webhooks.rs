@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::db::{Database, MemoChanges};
+use crate::error::AppError;
+use crate::sync::SyncProgress;
+use crate::Memo;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A URL that gets a JSON POST after syncs, e.g. an n8n or IFTTT webhook.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookSettings {
+    pub url: String,
+    /// Only call the hook when new memos with this tag (or a tag below it)
+    /// arrive; without one it's called after every completed sync
+    pub tag: Option<String>,
+}
+
+/// Body of a webhook request.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload<'a> {
+    /// "sync.completed", or "memos.tagged" for hooks with a tag
+    pub event: &'static str,
+    pub total_memos: usize,
+    pub new_count: usize,
+    pub updated_count: usize,
+    pub new_slugs: &'a [String],
+    pub updated_slugs: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<&'a str>,
+    /// The new memos with the hook's tag
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub memos: Vec<Memo>,
+}
+
+/// Calls every configured hook for a completed sync. A failing hook doesn't
+/// stop the others; failures are returned with the hook's URL.
+pub async fn deliver(
+    db: &Database,
+    hooks: &[WebhookSettings],
+    progress: &SyncProgress,
+    changes: &MemoChanges,
+) -> Vec<(String, AppError)> {
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            let error = AppError::from(e);
+            return hooks.iter().map(|hook| (hook.url.clone(), error.clone())).collect();
+        }
+    };
+
+    let mut failures = Vec::new();
+    for hook in hooks.iter().filter(|hook| !hook.url.trim().is_empty()) {
+        let tag = hook.tag.as_deref().map(str::trim).filter(|tag| !tag.is_empty());
+        let memos = match tag {
+            Some(tag) => match tagged_memos(db, &changes.new, tag).await {
+                Ok(memos) if memos.is_empty() => continue,
+                Ok(memos) => memos,
+                Err(e) => {
+                    failures.push((hook.url.clone(), e));
+                    continue;
+                }
+            },
+            None => Vec::new(),
+        };
+        let payload = WebhookPayload {
+            event: if tag.is_some() { "memos.tagged" } else { "sync.completed" },
+            total_memos: progress.total,
+            new_count: changes.new.len(),
+            updated_count: changes.updated.len(),
+            new_slugs: &changes.new,
+            updated_slugs: &changes.updated,
+            tag,
+            memos,
+        };
+
+        let result = match client.post(hook.url.trim()).json(&payload).send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(AppError::Network(format!("Webhook returned {}", response.status()))),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            failures.push((hook.url.clone(), e));
+        }
+    }
+    failures
+}
+
+// New memos tagged `tag` or a tag below it
+async fn tagged_memos(db: &Database, slugs: &[String], tag: &str) -> Result<Vec<Memo>, AppError> {
+    let (slugs, tag) = (slugs.to_vec(), tag.to_string());
+    db.run(move |db| {
+        let nested = format!("{}/", tag);
+        let mut memos = Vec::new();
+        for slug in &slugs {
+            if let Some(memo) = db.get_memo_by_slug(slug)? {
+                if memo.tags.iter().any(|memo_tag| *memo_tag == tag || memo_tag.starts_with(&nested)) {
+                    memos.push(memo);
+                }
+            }
+        }
+        Ok(memos)
+    })
+    .await
+}
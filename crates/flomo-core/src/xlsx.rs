@@ -0,0 +1,124 @@
+use chrono::FixedOffset;
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export;
+use crate::i18n::Locale;
+use crate::parse_memo_time;
+
+/// Widest a column may be autofitted to, in pixels; longer memo text wraps
+const MAX_COLUMN_WIDTH: u32 = 480;
+/// Character width fitting "yyyy-mm-dd hh:mm"
+const DATE_COLUMN_WIDTH: f64 = 17.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct XlsxOptions {
+    /// Excel number format for the date columns
+    pub date_format: String,
+    pub filter: MemoFilter,
+    /// Language of generated labels; set from the app settings
+    #[serde(skip)]
+    pub locale: Locale,
+}
+
+impl Default for XlsxOptions {
+    fn default() -> Self {
+        Self {
+            date_format: "yyyy-mm-dd hh:mm".to_string(),
+            filter: MemoFilter::default(),
+            locale: Locale::default(),
+        }
+    }
+}
+
+/// Writes the memos matching `options.filter` to an Excel workbook: one row
+/// per memo with real datetime cells, plus a summary sheet counting memos per
+/// month and per tag. Returns the number of memos written.
+pub fn export_xlsx(db: &Database, path: &Path, options: &XlsxOptions, tz: FixedOffset) -> Result<usize, AppError> {
+    let memos = export::load_memos_chronological(db, &options.filter)?;
+    let strings = options.locale.strings();
+
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format(&options.date_format);
+    let wrap = Format::new().set_text_wrap();
+
+    let mut sheet = Worksheet::new();
+    sheet.set_name(strings.memos_sheet)?;
+    let titles = [strings.created, strings.updated, strings.content, strings.tags, strings.link];
+    for (col, title) in titles.into_iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, title, &header)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+
+    let mut months: BTreeMap<String, u32> = BTreeMap::new();
+    let mut tags: BTreeMap<String, u32> = BTreeMap::new();
+
+    for (index, memo) in memos.iter().enumerate() {
+        let row = index as u32 + 1;
+        for (col, value) in [(0, &memo.created_at), (1, &memo.updated_at)] {
+            match parse_memo_time(value, &tz) {
+                Some(time) => sheet.write_datetime_with_format(row, col, time.naive_local(), &date)?,
+                None => sheet.write_string(row, col, value)?,
+            };
+        }
+        sheet.write_string_with_format(row, 2, memo.content.trim(), &wrap)?;
+        sheet.write_string(row, 3, memo.tags.join(", "))?;
+        if let Some(url) = &memo.url {
+            sheet.write_string(row, 4, url)?;
+        }
+
+        let month = memo.created_at.get(..7).unwrap_or(&memo.created_at);
+        *months.entry(month.to_string()).or_default() += 1;
+        for tag in &memo.tags {
+            *tags.entry(tag.clone()).or_default() += 1;
+        }
+    }
+
+    if !memos.is_empty() {
+        sheet.autofilter(0, 0, memos.len() as u32, 4)?;
+    }
+    sheet.set_autofit_max_width(MAX_COLUMN_WIDTH).autofit();
+    // Autofit measures dates as numbers, which would show "####" in Excel
+    sheet.set_column_range_width(0, 1, DATE_COLUMN_WIDTH)?;
+
+    let mut summary = Worksheet::new();
+    summary.set_name(strings.summary_sheet)?;
+    let months: Vec<(String, u32)> = months.into_iter().collect();
+    write_counts(&mut summary, 0, strings.month, strings.count, &months, &header)?;
+    // Most used tags first
+    let mut tags: Vec<(String, u32)> = tags.into_iter().collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    write_counts(&mut summary, 3, strings.tags, strings.count, &tags, &header)?;
+    summary.autofit();
+
+    let mut workbook = Workbook::new();
+    workbook.push_worksheet(sheet);
+    workbook.push_worksheet(summary);
+    workbook.save(path)?;
+
+    Ok(memos.len())
+}
+
+/// Writes a two-column "<label> | <count_label>" table starting at column `col`.
+fn write_counts(
+    sheet: &mut Worksheet,
+    col: u16,
+    label: &str,
+    count_label: &str,
+    counts: &[(String, u32)],
+    header: &Format,
+) -> Result<(), AppError> {
+    sheet.write_string_with_format(0, col, label, header)?;
+    sheet.write_string_with_format(0, col + 1, count_label, header)?;
+    for (index, (key, count)) in counts.iter().enumerate() {
+        let row = index as u32 + 1;
+        sheet.write_string(row, col, key)?;
+        sheet.write_number(row, col + 1, *count)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use serde::Serialize;
+use tauri::Url;
+
+use crate::error::AppError;
+
+/// URL scheme registered for the app, see `plugins.deep-link` in tauri.conf.json.
+pub const SCHEME: &str = "openflomo";
+
+/// What an `openflomo://` URL asks the app to do. Links can come from any
+/// web page, so none of them changes data on its own: capture links only
+/// prefill the editor and the user still posts the memo.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum DeepLink {
+    /// `openflomo://memo/<slug>`
+    OpenMemo { slug: String },
+    /// `openflomo://new?content=...&tags=a,b`
+    NewMemo { content: String, tags: Vec<String> },
+    /// `openflomo://search?q=...`
+    Search { query: String },
+}
+
+impl DeepLink {
+    pub fn parse(url: &Url) -> Result<Self, AppError> {
+        let invalid = |reason: &str| AppError::InvalidInput(format!("Unsupported link {}: {}", url, reason));
+        if url.scheme() != SCHEME {
+            return Err(invalid("unknown scheme"));
+        }
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let segments: Vec<&str> = url.path_segments()
+            .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+            .unwrap_or_default();
+
+        match url.host_str().unwrap_or_default() {
+            "memo" => match segments.as_slice() {
+                [slug] => Ok(Self::OpenMemo { slug: slug.to_string() }),
+                _ => Err(invalid("expected openflomo://memo/<slug>")),
+            },
+            "new" => Ok(Self::NewMemo {
+                content: param("content").unwrap_or_default(),
+                tags: param("tags")
+                    .map(|tags| {
+                        tags.split(',')
+                            .map(|tag| tag.trim().trim_start_matches('#').to_string())
+                            .filter(|tag| !tag.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            }),
+            "search" => Ok(Self::Search { query: param("q").unwrap_or_default() }),
+            _ => Err(invalid("unknown action")),
+        }
+    }
+}
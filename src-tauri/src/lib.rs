@@ -1,318 +1,202 @@
-use chrono::{DateTime, NaiveDateTime, Utc, TimeZone};
-use reqwest::header::{HeaderMap, HeaderValue};
+use flomo_core::{
+    archive, backup, blog, db, demo, digest, duplicates, epub, error, export, feed, git_mirror, graph, i18n, import,
+    launcher, links, llm, logging, logseq, outbox, pdf, readwise, search, similar, suggest, sync, telegram, templates, todos, xlsx,
+};
+use flomo_core::{AccountInfo, FlomoClient, LoginResult, Memo};
 use serde::{Deserialize, Serialize};
-use md5;
-use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tauri::{Emitter, Manager, State};
 use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
-mod db;
-use db::Database;
-
-// Helper function to format dates according to the given format string
-fn format_date(date_str: &str, format: &str) -> String {
-    // Parse the date string
-    let parsed = DateTime::parse_from_rfc3339(date_str)
-        .map(|dt| dt.with_timezone(&Utc))
-        .or_else(|_| {
-            NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S")
-                .map(|dt| Utc.from_utc_datetime(&dt))
-        });
-    
-    if let Ok(parsed) = parsed {
-        // Convert common date format patterns to chrono format
-        let chrono_format = format
-            .replace("yyyy", "%Y")
-            .replace("MM", "%m")
-            .replace("dd", "%d")
-            .replace("HH", "%H")
-            .replace("mm", "%M")
-            .replace("ss", "%S")
-            .replace("MMM", "%b");
-        
-        // Handle special formats
-        if format.contains("年") {
-            // Chinese date format
-            return parsed.format("%Y年%m月%d日 %H:%M").to_string();
-        }
-        
-        parsed.format(&chrono_format).to_string()
-    } else {
-        // If parsing fails, return the original string
-        date_str.to_string()
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Memo {
-    pub slug: String,
-    pub content: String,
-    pub created_at: String,
-    pub updated_at: String,
-    pub tags: Vec<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub url: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ApiResponse {
-    code: i32,
-    data: Option<Vec<ApiMemo>>,
-}
+mod deeplink;
+mod publish;
+mod settings;
+use archive::ArchiveOptions;
+use blog::BlogOptions;
+use db::{Database, MemoFilter, MemoOrder, MemoPage, PageCursor};
+use deeplink::DeepLink;
+use epub::EpubOptions;
+use error::AppError;
+use export::{ExportFormat, ExportOptions, ExportProgress, ImageMode};
+use import::{
+    CsvColumnMapping, EnexImportOptions, ImportPlan, ImportReport, MarkdownImportOptions, MergeStrategy,
+    TwitterImportOptions,
+};
+use feed::FeedFormat;
+use graph::{GraphFormat, TagGraphResult};
+use logseq::LogseqOptions;
+use outbox::ComposeResult;
+use pdf::PdfOptions;
+use search::{SearchExpr, SearchHit, SearchPage};
+use settings::{AppSettings, SettingsStore};
+use xlsx::XlsxOptions;
 
 #[derive(Debug, Serialize, Deserialize)]
-struct ApiMemo {
-    slug: String,
-    content: String,
-    created_at: String,
-    updated_at: String,
-    tags: Vec<String>,
+pub struct PagedResponse {
+    memos: Vec<Memo>,
+    has_more: bool,
+    next_slug: Option<String>,
+    next_updated_at: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AppConfig {
-    authorization: Option<String>,
-}
+/// How long `AppState::close_db` waits for other database handles to go away.
+const DB_CLOSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
-pub struct FlomoClient {
-    token: String,
-    client: reqwest::Client,
+pub struct AppState {
+    pub db: Arc<Mutex<Option<Database>>>,
+    /// Where the database is; changes when `set_data_dir` moves it
+    pub db_path: Arc<Mutex<PathBuf>>,
+    /// Where logging writes; fixed at startup, even if the database moves
+    pub log_dir: PathBuf,
+    pub sync_cancel: Arc<Mutex<CancellationToken>>,
+    pub settings: Arc<Mutex<AppSettings>>,
+    /// Confirmation nonces handed out by `request_memo_deletion`, by slug
+    pub pending_deletions: Arc<Mutex<HashMap<String, (String, std::time::Instant)>>>,
+    /// The read-only web server started by `start_publishing`, if running
+    pub publisher: Arc<Mutex<Option<publish::Publisher>>>,
+    /// Demo mode as it was at startup, which picked the database
+    pub demo: bool,
 }
 
-impl FlomoClient {
-    const LIMIT: usize = 200;
-    const URL_UPDATED: &'static str = "https://flomoapp.com/api/v1/memo/updated/";
-    const SALT: &'static str = "dbbc3dd73364b4084c3a69346e0ce2b2";
-
-    pub fn new(token: String) -> Self {
-        let client = reqwest::Client::new();
-        let token = if token.starts_with("Bearer ") {
-            token
-        } else {
-            format!("Bearer {}", token)
-        };
-        
-        Self { token, client }
-    }
-
-    pub fn get_params(&self, latest_slug: Option<&str>, latest_updated_at: Option<i64>) -> HashMap<String, String> {
-        let mut params = HashMap::new();
-        params.insert("limit".to_string(), Self::LIMIT.to_string());
-        params.insert("tz".to_string(), "8:0".to_string());
-        params.insert("timestamp".to_string(), chrono::Utc::now().timestamp().to_string());
-        params.insert("api_key".to_string(), "flomo_web".to_string());
-        params.insert("app_version".to_string(), "5.25.64".to_string());
-        params.insert("platform".to_string(), "mac".to_string());
-        params.insert("webp".to_string(), "1".to_string());
-        
-
-        // Add pagination parameters if available
-        // Note: We should add slug even if updated_at is missing
-        if let Some(slug) = latest_slug {
-            params.insert("latest_slug".to_string(), slug.to_string());
-        }
-        
-        if let Some(updated_at) = latest_updated_at {
-            params.insert("latest_updated_at".to_string(), updated_at.to_string());
-        }
-
-        // Generate sign (using MD5 to match Python implementation)
-        let mut sorted_params: Vec<(&String, &String)> = params.iter().collect();
-        sorted_params.sort_by_key(|&(k, _)| k);
-        
-        let param_str = sorted_params
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, v))
-            .collect::<Vec<_>>()
-            .join("&");
-        
-        let sign_str = format!("{}{}", param_str, Self::SALT);
-        
-        
-        let sign = format!("{:x}", md5::compute(sign_str.as_bytes()));
-        
-        params.insert("sign".to_string(), sign);
-        
-        // Log pagination parameters for debugging
-        if latest_slug.is_some() || latest_updated_at.is_some() {
-            println!("Pagination params: latest_slug={:?}, latest_updated_at={:?}", 
-                     latest_slug, latest_updated_at);
-        }
-        
-        params
-    }
-
-    pub async fn get_all_memos(&self) -> Result<Vec<Memo>, String> {
-        let mut all_memos = Vec::new();
-        let mut latest_slug: Option<String> = None;
-        let mut latest_updated_at: Option<i64> = None;
-
-        loop {
-            let params = self.get_params(latest_slug.as_deref(), latest_updated_at);
-            
-            
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "authorization",
-                HeaderValue::from_str(&self.token).map_err(|e| {
-                        e.to_string()
-                })?,
-            );
-
-
-            let response = self.client
-                .get(Self::URL_UPDATED)
-                .headers(headers)
-                .query(&params)
-                .send()
-                .await
-                .map_err(|e| {
-                        e.to_string()
-                })?;
-
-            
-            // Get response text first for debugging
-            let response_text = response.text().await.map_err(|e| {
-                e.to_string()
-            })?;
-            
-            
-            // Parse the response
-            let api_response: ApiResponse = serde_json::from_str(&response_text).map_err(|e| {
-                format!("JSON parse error: {} - Response was: {}", e, response_text)
-            })?;
-
-
-            if api_response.code != 0 {
-                return Err(format!("API error: code {} - Response: {}", api_response.code, response_text));
-            }
-
-            let memos = api_response.data.unwrap_or_default();
-            
-            if memos.is_empty() {
-                break;
-            }
-
-            let should_continue = memos.len() >= Self::LIMIT;
-            
-            if should_continue {
-                let last_memo = &memos[memos.len() - 1];
-                latest_slug = Some(last_memo.slug.clone());
-                
-                if let Ok(dt) = DateTime::parse_from_rfc3339(&last_memo.updated_at) {
-                    latest_updated_at = Some(dt.timestamp());
+impl AppState {
+    /// Clones the database handle out of the state so the lock isn't held across awaits.
+    fn db(&self) -> Result<Database, AppError> {
+        let db_lock = self.db.lock().unwrap();
+        db_lock
+            .as_ref()
+            .cloned()
+            .ok_or_else(|| {
+                if db::is_encrypted(&self.db_path()) {
+                    AppError::Locked
+                } else {
+                    AppError::Db("Database not initialized".to_string())
                 }
-            }
-
-            // Convert API memos to our Memo struct
-            for api_memo in memos {
-                let memo = Memo {
-                    slug: api_memo.slug.clone(),
-                    content: parse_html_to_text(&api_memo.content),
-                    created_at: api_memo.created_at,
-                    updated_at: api_memo.updated_at,
-                    tags: api_memo.tags,
-                    url: Some(format!("https://v.flomoapp.com/mine/?memo_id={}", api_memo.slug)),
-                };
-                all_memos.push(memo);
-            }
+            })
+    }
 
-            if !should_continue {
-                break;
+    /// Takes the database out of the state and waits for every other handle
+    /// to be dropped, so its files can be replaced. New callers get an error
+    /// meanwhile. Puts it back and fails if it is still in use after
+    /// `DB_CLOSE_TIMEOUT`, e.g. during a sync.
+    async fn close_db(&self) -> Result<Database, AppError> {
+        let db = self.db()?;
+        self.db.lock().unwrap().take();
+        
+        let deadline = std::time::Instant::now() + DB_CLOSE_TIMEOUT;
+        while db.handle_count() > 1 {
+            if std::time::Instant::now() >= deadline {
+                *self.db.lock().unwrap() = Some(db);
+                return Err(AppError::Conflict("The database is in use, e.g. by a sync; try again when it's done".to_string()));
             }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
         }
+        Ok(db)
+    }
+    
+    fn db_path(&self) -> PathBuf {
+        self.db_path.lock().unwrap().clone()
+    }
+    
+    fn settings(&self) -> AppSettings {
+        self.settings.lock().unwrap().clone()
+    }
 
-        Ok(all_memos)
+    /// Builds an API client configured from the current settings.
+    fn client(&self, token: String) -> Result<FlomoClient, AppError> {
+        let settings = self.settings();
+        FlomoClient::new(token)
+            .with_demo(self.demo)
+            .with_timezone(settings.timezone())
+            .with_api_settings(&settings.api)?
+            .with_proxy(settings.proxy.as_ref())
     }
-}
 
-fn parse_html_to_text(html: &str) -> String {
-    // Simple HTML to text conversion
-    html2text::from_read(html.as_bytes(), 80)
+    /// Where imported note attachments are saved, next to the database.
+    fn attachments_dir(&self) -> PathBuf {
+        self.db_path().with_file_name("attachments")
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PagedResponse {
-    memos: Vec<Memo>,
-    has_more: bool,
-    next_slug: Option<String>,
-    next_updated_at: Option<i64>,
+// Tauri commands
+// Lets the frontend prompt for a new token whenever the API rejects the current one
+fn notify_auth_expired(app: &tauri::AppHandle, error: AppError) -> AppError {
+    if let AppError::Auth(message) = &error {
+        let _ = app.emit("auth-expired", message);
+    }
+    error
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SyncProgress {
-    pub total: usize,
-    pub current: usize,
-    pub status: String,
-    pub message: String,
+#[tauri::command]
+async fn validate_token(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<AccountInfo, AppError> {
+    let client = state.client(token)?;
+    client.get_account_info()
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))
 }
 
-pub struct AppState {
-    pub db: Arc<Mutex<Option<Database>>>,
-    pub sync_cancelled: Arc<AtomicBool>,
+/// Checks that Flomo can be reached through `proxy`, or through the saved
+/// proxy setting when none is given, and returns the round trip in
+/// milliseconds.
+#[tauri::command]
+async fn test_connection(state: State<'_, AppState>, proxy: Option<flomo_core::proxy::ProxySettings>) -> Result<u64, AppError> {
+    let settings = state.settings();
+    let proxy = proxy.or(settings.proxy);
+    let client = FlomoClient::anonymous()
+        .with_demo(state.demo)
+        .with_api_settings(&settings.api)?
+        .with_proxy(proxy.as_ref())?;
+    
+    let elapsed = client.ping().await?;
+    Ok(elapsed.as_millis() as u64)
 }
 
-// Tauri commands
 #[tauri::command]
-async fn get_memos(token: String) -> Result<Vec<Memo>, String> {
-    let client = FlomoClient::new(token);
-    client.get_all_memos().await
+async fn get_memos(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<Vec<Memo>, AppError> {
+    let client = state.client(token)?;
+    client.get_all_memos()
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))
 }
 
 #[tauri::command]
 async fn get_memos_page(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
     token: String,
     latest_slug: Option<String>,
     latest_updated_at: Option<i64>,
-) -> Result<PagedResponse, String> {
-    let client = FlomoClient::new(token);
-    
-    let params = client.get_params(latest_slug.as_deref(), latest_updated_at);
-    
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "authorization",
-        HeaderValue::from_str(&client.token).map_err(|e| e.to_string())?,
-    );
-
-    let response = client.client
-        .get(FlomoClient::URL_UPDATED)
-        .headers(headers)
-        .query(&params)
-        .send()
+) -> Result<PagedResponse, AppError> {
+    let client = state.client(token)?;
+    let page = client.fetch_page(latest_slug.as_deref(), latest_updated_at)
         .await
-        .map_err(|e| e.to_string())?;
-
-    let response_text = response.text().await.map_err(|e| e.to_string())?;
-    let api_response: ApiResponse = serde_json::from_str(&response_text)
-        .map_err(|e| format!("JSON parse error: {}", e))?;
-
-    if api_response.code != 0 {
-        return Err(format!("API error: code {}", api_response.code));
-    }
-
-    let api_memos = api_response.data.unwrap_or_default();
-    let has_more = api_memos.len() >= FlomoClient::LIMIT;
+        .map_err(|e| notify_auth_expired(&app, e))?;
+    let has_more = page.memos.len() + page.unparsed.len() >= client.page_size();
+    let api_memos = page.memos;
     
     let (next_slug, next_updated_at) = if has_more && !api_memos.is_empty() {
         let last_memo = &api_memos[api_memos.len() - 1];
-        let updated_at = DateTime::parse_from_rfc3339(&last_memo.updated_at)
-            .ok()
-            .map(|dt| dt.timestamp());
+        let updated_at = client.cursor_timestamp(&last_memo.updated_at);
         (Some(last_memo.slug.clone()), updated_at)
     } else {
         (None, None)
     };
 
-    let memos: Vec<Memo> = api_memos.into_iter().map(|api_memo| Memo {
-        slug: api_memo.slug.clone(),
-        content: parse_html_to_text(&api_memo.content),
-        created_at: api_memo.created_at,
-        updated_at: api_memo.updated_at,
-        tags: api_memo.tags,
-        url: Some(format!("https://v.flomoapp.com/mine/?memo_id={}", api_memo.slug)),
-    }).collect();
+    // The HTML is only kept when memos are stored
+    let memos: Vec<Memo> = api_memos.into_iter()
+        .map(|api_memo| Memo { content_html: None, ..Memo::from(api_memo) })
+        .collect();
 
     Ok(PagedResponse {
         memos,
@@ -323,9 +207,16 @@ async fn get_memos_page(
 }
 
 #[tauri::command]
-async fn search_memos(token: String, query: String) -> Result<Vec<Memo>, String> {
-    let client = FlomoClient::new(token);
-    let all_memos = client.get_all_memos().await?;
+async fn search_memos(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+    query: String,
+) -> Result<Vec<Memo>, AppError> {
+    let client = state.client(token)?;
+    let all_memos = client.get_all_memos()
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))?;
     
     let filtered: Vec<Memo> = all_memos
         .into_iter()
@@ -340,13 +231,17 @@ async fn search_memos(token: String, query: String) -> Result<Vec<Memo>, String>
 
 #[tauri::command]
 async fn search_memos_page(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
     token: String,
     query: String,
     offset: usize,
     limit: usize,
-) -> Result<PagedResponse, String> {
-    let client = FlomoClient::new(token);
-    let all_memos = client.get_all_memos().await?;
+) -> Result<PagedResponse, AppError> {
+    let client = state.client(token)?;
+    let all_memos = client.get_all_memos()
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))?;
     
     let filtered: Vec<Memo> = all_memos
         .into_iter()
@@ -374,23 +269,92 @@ async fn search_memos_page(
 }
 
 #[tauri::command]
-async fn save_config(app: tauri::AppHandle, token: String) -> Result<(), String> {
+async fn save_config(app: tauri::AppHandle, token: String) -> Result<(), AppError> {
+    store_token(&app, token)
+}
+
+fn store_token(app: &tauri::AppHandle, token: String) -> Result<(), AppError> {
     use tauri_plugin_store::StoreExt;
     
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    store.set("authorization", serde_json::Value::String(token));
-    store.save().map_err(|e| e.to_string())?;
+    // Keep the real token for when demo mode is turned off
+    if app.state::<AppState>().demo {
+        return Ok(());
+    }
+    let store = app.store(flomo_core::settings::CONFIG_FILE).map_err(|e| AppError::Internal(e.to_string()))?;
+    store.set(flomo_core::settings::TOKEN_KEY, serde_json::Value::String(token));
+    store.save().map_err(|e| AppError::Internal(e.to_string()))?;
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn get_settings(state: State<'_, AppState>) -> AppSettings {
+    state.settings()
+}
+
+#[tauri::command]
+fn save_settings(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    settings: AppSettings,
+) -> Result<(), AppError> {
+    settings.save(&app)?;
+    logging::set_level(settings.log_level)?;
+    flomo_core::set_text_width(settings.text_width);
+    *state.settings.lock().unwrap() = settings;
     
     Ok(())
 }
 
+/// Converts synced memos to text again with the current wrap setting.
+/// Returns how many memos changed.
 #[tauri::command]
-async fn load_config(app: tauri::AppHandle) -> Result<Option<String>, String> {
+async fn reconvert_memo_text(state: State<'_, AppState>) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let width = state.settings().text_width;
+    
+    db.run(move |db| db.reconvert_memo_text(width)).await
+}
+
+/// The last `lines` lines of the app's log, oldest first.
+#[tauri::command]
+fn get_recent_logs(state: State<'_, AppState>, lines: usize) -> Result<Vec<String>, AppError> {
+    logging::recent_lines(&state.log_dir, lines)
+}
+
+#[tauri::command]
+async fn login(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    email: String,
+    password: String,
+) -> Result<LoginResult, AppError> {
+    let settings = state.settings();
+    let client = FlomoClient::anonymous()
+        .with_demo(state.demo)
+        .with_timezone(settings.timezone())
+        .with_api_settings(&settings.api)?
+        .with_proxy(settings.proxy.as_ref())?;
+    let result = client.login(&email, &password).await?;
+    store_token(&app, result.token.clone())?;
+    
+    Ok(result)
+}
+
+#[tauri::command]
+async fn load_config(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    stored_token(&app)
+}
+
+fn stored_token(app: &tauri::AppHandle) -> Result<Option<String>, AppError> {
     use tauri_plugin_store::StoreExt;
     
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
+    if app.state::<AppState>().demo {
+        return Ok(Some(demo::DEMO_TOKEN.to_string()));
+    }
+    let store = app.store(flomo_core::settings::CONFIG_FILE).map_err(|e| AppError::Internal(e.to_string()))?;
     
-    if let Some(value) = store.get("authorization") {
+    if let Some(value) = store.get(flomo_core::settings::TOKEN_KEY) {
         if let Some(token) = value.as_str() {
             return Ok(Some(token.to_string()));
         }
@@ -405,53 +369,19 @@ fn format_memos_json(memos: Vec<Memo>) -> String {
 }
 
 #[tauri::command]
-fn format_memos_markdown(memos: Vec<Memo>) -> String {
-    let mut output = String::from("# Flomo 备忘录\n\n");
+fn format_memos_markdown(state: State<'_, AppState>, memos: Vec<Memo>) -> String {
+    let settings = state.settings();
+    let options = ExportOptions { locale: settings.locale, ..Default::default() };
     
-    for (index, memo) in memos.iter().enumerate() {
-        output.push_str(&format!("## {}. {}\n\n", index + 1, memo.created_at));
-        output.push_str(&format!("{}\n\n", memo.content));
-        
-        if let Some(url) = &memo.url {
-            output.push_str(&format!("**链接**: {}\n", url));
-        }
-        
-        if !memo.tags.is_empty() {
-            output.push_str(&format!("**标签**: {}\n", memo.tags.join(", ")));
-        }
-        
-        output.push_str("\n---\n\n");
-    }
-    
-    output
+    export::format_to_string(&memos, ExportFormat::Markdown, options, settings.timezone())
 }
 
 #[tauri::command]
-fn format_memos_table(memos: Vec<Memo>) -> String {
-    let mut output = String::from("序号 | 创建时间          | 内容预览\n");
-    output.push_str(&"-".repeat(50));
-    output.push('\n');
-    
-    for (index, memo) in memos.iter().enumerate() {
-        let content_preview = memo.content
-            .replace('\n', " ")
-            .chars()
-            .take(30)
-            .collect::<String>();
-        
-        let created_at = memo.created_at.split(' ').next().unwrap_or(&memo.created_at);
-        output.push_str(&format!("{:2}   | {:17} | {}\n", 
-            index + 1, 
-            created_at,
-            if content_preview.len() >= 30 {
-                format!("{}...", content_preview)
-            } else {
-                content_preview
-            }
-        ));
-    }
+fn format_memos_table(state: State<'_, AppState>, memos: Vec<Memo>) -> String {
+    let settings = state.settings();
+    let options = ExportOptions { locale: settings.locale, ..Default::default() };
     
-    output
+    export::format_to_string(&memos, ExportFormat::Table, options, settings.timezone())
 }
 
 #[derive(Debug, Deserialize)]
@@ -463,30 +393,11 @@ struct JsonFormatArgs {
 }
 
 #[tauri::command]
-fn format_memos_json_with_options(args: JsonFormatArgs) -> String {
+fn format_memos_json_with_options(state: State<'_, AppState>, args: JsonFormatArgs) -> String {
     let JsonFormatArgs { memos, compact, date_format } = args;
-    let processed_memos: Vec<serde_json::Value> = memos.iter().enumerate().map(|(index, memo)| {
-        let mut obj = serde_json::json!({
-            "index": index + 1,
-            "content": memo.content,
-            "url": memo.url,
-            "slug": memo.slug,
-            "tags": memo.tags,
-        });
-        
-        if !date_format.is_empty() {
-            obj["created_at"] = serde_json::json!(format_date(&memo.created_at, &date_format));
-            obj["updated_at"] = serde_json::json!(format_date(&memo.updated_at, &date_format));
-        }
-        
-        obj
-    }).collect();
+    let options = ExportOptions { compact, date_format, ..Default::default() };
     
-    if compact {
-        serde_json::to_string(&processed_memos).unwrap_or_default()
-    } else {
-        serde_json::to_string_pretty(&processed_memos).unwrap_or_default()
-    }
+    export::format_to_string(&memos, ExportFormat::Json, options, state.settings().timezone())
 }
 
 #[derive(Debug, Deserialize)]
@@ -500,62 +411,12 @@ struct MarkdownFormatArgs {
 }
 
 #[tauri::command]
-fn format_memos_markdown_with_options(args: MarkdownFormatArgs) -> String {
+fn format_memos_markdown_with_options(state: State<'_, AppState>, args: MarkdownFormatArgs) -> String {
     let MarkdownFormatArgs { memos, url_mode, date_format, minimal } = args;
-    let mut output = String::new();
-    
-    if !minimal {
-        output.push_str("# Flomo 备忘录\n\n");
-    }
-    
-    for (index, memo) in memos.iter().enumerate() {
-        if minimal {
-            // Minimal mode: one line per memo
-            let date = if date_format.is_empty() {
-                String::new()
-            } else {
-                format_date(&memo.created_at, &date_format)
-            };
-            let content = memo.content.replace('\n', " ");
-            if date.is_empty() {
-                output.push_str(&format!("{}|{}\n", index + 1, content));
-            } else {
-                output.push_str(&format!("{}|{}|{}\n", index + 1, date, content));
-            }
-        } else {
-            // Normal mode
-            if !date_format.is_empty() {
-                let formatted_date = format_date(&memo.created_at, &date_format);
-                output.push_str(&format!("## {}. {}\n\n", index + 1, formatted_date));
-            } else {
-                output.push_str(&format!("## {}\n\n", index + 1));
-            }
-            
-            output.push_str(&format!("{}\n", memo.content.trim()));
-            
-            // URL handling
-            match url_mode.as_str() {
-                "full" => {
-                    if let Some(url) = &memo.url {
-                        output.push_str(&format!("**链接**: {}\n", url));
-                    }
-                },
-                "id" => {
-                    output.push_str(&format!("**ID**: {}\n", memo.slug));
-                },
-                _ => {} // "none" or any other value
-            }
-            
-            // Tags
-            if !memo.tags.is_empty() {
-                output.push_str(&format!("**标签**: {}\n", memo.tags.join(", ")));
-            }
-            
-            output.push_str("\n---\n\n");
-        }
-    }
+    let settings = state.settings();
+    let options = ExportOptions { url_mode, date_format, minimal, locale: settings.locale, ..Default::default() };
     
-    output
+    export::format_to_string(&memos, ExportFormat::Markdown, options, settings.timezone())
 }
 
 #[derive(Debug, Deserialize)]
@@ -566,139 +427,1820 @@ struct TableFormatArgs {
 }
 
 #[tauri::command]
-fn format_memos_table_with_options(args: TableFormatArgs) -> String {
+fn format_memos_table_with_options(state: State<'_, AppState>, args: TableFormatArgs) -> String {
     let TableFormatArgs { memos, date_format } = args;
-    let mut output = String::from("序号 | 创建时间          | 内容预览\n");
-    output.push_str(&"-".repeat(50));
-    output.push('\n');
-    
-    for (index, memo) in memos.iter().enumerate() {
-        let content_preview = memo.content
-            .replace('\n', " ")
-            .chars()
-            .take(30)
-            .collect::<String>();
-        
-        let date_str = if date_format.is_empty() {
-            memo.created_at.split(' ').next().unwrap_or(&memo.created_at).to_string()
-        } else {
-            format_date(&memo.created_at, &date_format)
-        };
-        
-        output.push_str(&format!("{:2}   | {:17} | {}\n", 
-            index + 1, 
-            date_str,
-            if content_preview.len() >= 30 {
-                format!("{}...", content_preview)
-            } else {
-                content_preview
-            }
-        ));
-    }
+    let settings = state.settings();
+    let options = ExportOptions { date_format, locale: settings.locale, ..Default::default() };
     
-    output
-}
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_store::Builder::new().build())
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_updater::Builder::new().build())
-        .plugin(tauri_plugin_process::init())
-        // Removed tauri_plugin_sql since we're using rusqlite directly
-        .setup(|app| {
-            let app_handle = app.handle();
-            let app_data_dir = app_handle
-                .path()
-                .app_data_dir()
-                .expect("Failed to get app data dir");
-            
-            // Ensure the directory exists
-            std::fs::create_dir_all(&app_data_dir).ok();
-            
-            let db_path = app_data_dir.join("flomo.db");
-            
-            // Initialize database asynchronously
-            let app_state = AppState {
-                db: Arc::new(Mutex::new(None)),
-                sync_cancelled: Arc::new(AtomicBool::new(false)),
-            };
-            
-            app.manage(app_state);
-            
-            let db_state = app.state::<AppState>().db.clone();
-            match Database::new(&db_path) {
-                Ok(db) => {
-                    let mut db_lock = db_state.lock().unwrap();
-                    *db_lock = Some(db);
-                    println!("Database initialized successfully");
-                }
-                Err(e) => {
-                    eprintln!("Failed to initialize database: {}", e);
-                }
-            }
-            
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
-            get_memos,
-            get_memos_page,
-            get_memos_from_db,
-            search_memos,
-            search_memos_page,
-            search_memos_from_db,
-            sync_all_memos,
-            cancel_sync,
-            get_sync_status,
-            clear_local_data,
-            save_config,
-            load_config,
-            format_memos_json,
-            format_memos_markdown,
-            format_memos_table,
-            format_memos_json_with_options,
-            format_memos_markdown_with_options,
-            format_memos_table_with_options
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+    export::format_to_string(&memos, ExportFormat::Table, options, settings.timezone())
 }
 
-// Database-backed commands
+/// One JSON object per line, for piping into jq, DuckDB and the like. With
+/// `raw_html` the original HTML stored at sync time replaces the text content.
 #[tauri::command]
-async fn get_memos_from_db(
+async fn format_memos_jsonl(
     state: State<'_, AppState>,
-    order_by: String,
-    order_dir: String,
-    offset: i64,
-    limit: i64,
-) -> Result<Vec<Memo>, String> {
-    let db = {
-        let db_lock = state.db.lock().unwrap();
-        db_lock.as_ref().ok_or("Database not initialized")?.clone()
-    };
+    mut memos: Vec<Memo>,
+    raw_html: Option<bool>,
+) -> Result<String, AppError> {
+    let options = ExportOptions { raw_html: raw_html.unwrap_or(false), ..Default::default() };
+    let tz = state.settings().timezone();
+    
+    if options.raw_html {
+        let db = state.db()?;
+        memos = db.run(move |db| export::attach_content_html(db, &mut memos).map(|_| memos)).await?;
+    }
     
-    db.get_memos_page(&order_by, &order_dir, offset, limit)
+    Ok(export::format_to_string(&memos, ExportFormat::Jsonl, options, tz))
 }
 
+/// Streams memos from the database straight into `path`, page by page,
+/// emitting `export-progress` events along the way.
 #[tauri::command]
+async fn export_to_file(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let mut options = options.unwrap_or_default();
+    options.locale = settings.locale;
+    let tz = settings.timezone();
+    
+    // Downloaded images go to "<name>_files" next to the HTML file
+    if matches!(format, ExportFormat::Html) && options.image_mode == ImageMode::Files {
+        let path = std::path::Path::new(&path);
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+        options.asset_dir = Some(path.with_file_name(format!("{}_files", stem)));
+    }
+    
+    db.run(move |db| {
+        let total = db.count_memos(&options.filter)? as usize;
+        let file = std::fs::File::create(&path)
+            .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", path, e)))?;
+        
+        let result = export::export_from_db(db, std::io::BufWriter::new(file), format, options, tz, |current| {
+            let _ = app.emit("export-progress", ExportProgress { current, total });
+        });
+        
+        // Don't leave a truncated file behind
+        if result.is_err() {
+            let _ = std::fs::remove_file(&path);
+        }
+        result.map(|(_, written)| written)
+    })
+    .await
+}
+
+/// Writes just the memos in a multi-selection to `path`, like
+/// `export_to_file` with `options.filter.slugs` set.
+#[tauri::command]
+async fn export_selected_memos(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    slugs: Vec<String>,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+) -> Result<usize, AppError> {
+    let mut options = options.unwrap_or_default();
+    options.filter.slugs = Some(slugs);
+    export_to_file(app, state, path, format, Some(options)).await
+}
+
+/// Asks where to save with the native save dialog, then writes the export
+/// there straight from the database like `export_to_file`. Returns the
+/// chosen path, or `None` when the dialog was cancelled.
+#[tauri::command]
+async fn export_with_dialog(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+) -> Result<Option<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+    
+    let file_name = format!(
+        "flomo_export_{}.{}",
+        chrono::Local::now().format("%Y%m%d_%H%M%S"),
+        format.extension()
+    );
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_file_name(&file_name)
+        .add_filter(format.display_name(), &[format.extension()])
+        .save_file(move |path| {
+            let _ = sender.send(path);
+        });
+    
+    let Some(path) = receiver.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let mut path = path.into_path()
+        .map_err(|e| AppError::Internal(format!("Invalid save location: {}", e)))?;
+    // Some platforms hand back the typed name without the filter's extension
+    if path.extension().is_none() {
+        path.set_extension(format.extension());
+    }
+    let path = path.to_string_lossy().into_owned();
+    
+    export_to_file(app, state, path.clone(), format, options).await?;
+    Ok(Some(path))
+}
+
+/// Writes a single self-contained HTML page with per-memo anchors and
+/// clickable tags that filter the page.
+#[tauri::command]
+async fn export_memos_html(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<usize, AppError> {
+    export_to_file(app, state, path, ExportFormat::Html, options).await
+}
+
+/// Writes an Evernote .enex file for migrating into Evernote, Joplin and
+/// other ENEX importers.
+#[tauri::command]
+async fn export_memos_enex(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<usize, AppError> {
+    export_to_file(app, state, path, ExportFormat::Enex, options).await
+}
+
+/// Writes a portable zip backup: the memos as Markdown and JSON plus their
+/// images under `assets/`, linked relatively.
+#[tauri::command]
+async fn export_archive(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<ArchiveOptions>,
+) -> Result<archive::ArchiveExportResult, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let mut options = options.unwrap_or_default();
+    options.locale = settings.locale;
+    let tz = settings.timezone();
+    
+    db.run(move |db| archive::export_archive(db, std::path::Path::new(&path), &options, tz)).await
+}
+
+/// Writes the memos matching the filter to an Excel workbook with a summary
+/// sheet of counts per month and tag.
+#[tauri::command]
+async fn export_memos_xlsx(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<XlsxOptions>,
+) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let mut options = options.unwrap_or_default();
+    options.locale = settings.locale;
+    let tz = settings.timezone();
+    
+    db.run(move |db| xlsx::export_xlsx(db, std::path::Path::new(&path), &options, tz)).await
+}
+
+/// Summarizes the memos matching `filter` with the configured LLM endpoint.
+/// The digest streams to the frontend as `summary-delta` events and is also
+/// returned whole.
+#[tauri::command]
+async fn summarize_memos(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    filter: Option<MemoFilter>,
+) -> Result<String, AppError> {
+    let mut filter = filter.unwrap_or_default();
+    filter.pinyin = state.settings().pinyin_search;
+    summarize(&app, &state, filter).await
+}
+
+/// Summarizes one month of memos; `month` is "YYYY-MM".
+#[tauri::command]
+async fn summarize_period(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    month: String,
+) -> Result<String, AppError> {
+    let first_day = chrono::NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+        .map_err(|_| AppError::InvalidInput(format!("Invalid month: {}", month)))?;
+    let last_day = first_day.checked_add_months(chrono::Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(first_day);
+    
+    let filter = MemoFilter {
+        from_date: Some(first_day.format("%Y-%m-%d").to_string()),
+        to_date: Some(last_day.format("%Y-%m-%d").to_string()),
+        ..Default::default()
+    };
+    summarize(&app, &state, filter).await
+}
+
+async fn summarize(app: &tauri::AppHandle, state: &AppState, filter: MemoFilter) -> Result<String, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let client = llm::LlmClient::new(settings.llm)?;
+    
+    let memos = db.run(move |db| export::load_memos_chronological(db, &filter)).await?;
+    if memos.is_empty() {
+        return Err(AppError::InvalidInput("No memos to summarize".to_string()));
+    }
+    
+    llm::summarize(&client, &memos, settings.locale, |delta| {
+        let _ = app.emit("summary-delta", delta);
+    })
+    .await
+}
+
+/// Answers `question` from the most relevant memos using the configured LLM
+/// endpoint. Retrieval runs locally; only the question and the retrieved
+/// memos are sent to the endpoint. The answer streams as `ask-delta` events.
+#[tauri::command]
+async fn ask_memos(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    question: String,
+    source_count: Option<usize>,
+) -> Result<llm::AskAnswer, AppError> {
+    if question.trim().is_empty() {
+        return Err(AppError::InvalidInput("Question is empty".to_string()));
+    }
+    let db = state.db()?;
+    let settings = state.settings();
+    let client = llm::LlmClient::new(settings.llm)?;
+    let source_count = source_count.unwrap_or(8);
+    
+    let query = question.clone();
+    let sources = db.run(move |db| similar::relevant_memos(db, &query, source_count)).await?;
+    
+    llm::ask(&client, &question, sources, settings.locale, |delta| {
+        let _ = app.emit("ask-delta", delta);
+    })
+    .await
+}
+
+/// Imports a folder of Markdown files as local memos. With `pushToFlomo` each
+/// memo is also created on Flomo and stored as the synced copy; memos that
+/// fail to push are kept locally and reported in `errors`.
+#[tauri::command]
+async fn import_markdown_dir(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<MarkdownImportOptions>,
+    token: Option<String>,
+) -> Result<ImportReport, AppError> {
+    let db = state.db()?;
+    let options = options.unwrap_or_default();
+    let client = import_client(&state, options.push_to_flomo, token)?;
+    let tz = state.settings().timezone();
+    
+    let (plan, errors) = db.run(move |db| {
+        let (memos, errors) = import::read_markdown_dir(std::path::Path::new(&path), &options, tz)?;
+        Ok((import::plan_import(db, memos, options.merge)?, errors))
+    }).await?;
+    save_imported_memos(&app, &db, client, plan, errors).await
+}
+
+/// Imports the notes of an Evernote .enex export as local memos, saving their
+/// attachments to the app's attachments folder. With `pushToFlomo` the notes
+/// are created on Flomo too, as with `import_markdown_dir`.
+#[tauri::command]
+async fn import_enex(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<EnexImportOptions>,
+    token: Option<String>,
+) -> Result<ImportReport, AppError> {
+    let db = state.db()?;
+    let options = options.unwrap_or_default();
+    let client = import_client(&state, options.push_to_flomo, token)?;
+    let attachments_dir = state.attachments_dir();
+    let tz = state.settings().timezone();
+    
+    let (plan, errors) = db.run(move |db| {
+        let (memos, errors) = import::read_enex(std::path::Path::new(&path), &options, &attachments_dir, tz)?;
+        Ok((import::plan_import(db, memos, options.merge)?, errors))
+    }).await?;
+    save_imported_memos(&app, &db, client, plan, errors).await
+}
+
+/// Imports the account's own tweets from an X/Twitter data export as memos
+/// tagged #twitter, keeping their original timestamps. Retweets are skipped.
+#[tauri::command]
+async fn import_twitter_archive(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<TwitterImportOptions>,
+    token: Option<String>,
+) -> Result<ImportReport, AppError> {
+    let db = state.db()?;
+    let options = options.unwrap_or_default();
+    let client = import_client(&state, options.push_to_flomo, token)?;
+    let tz = state.settings().timezone();
+    
+    let (plan, errors) = db.run(move |db| {
+        let (memos, errors) = import::read_twitter_archive(std::path::Path::new(&path), &options, tz)?;
+        Ok((import::plan_import(db, memos, options.merge)?, errors))
+    }).await?;
+    save_imported_memos(&app, &db, client, plan, errors).await
+}
+
+// Importers push to Flomo only when asked, which needs a token
+fn import_client(state: &AppState, push_to_flomo: bool, token: Option<String>) -> Result<Option<FlomoClient>, AppError> {
+    match (push_to_flomo, token) {
+        (false, _) => Ok(None),
+        (true, Some(token)) => state.client(token).map(Some),
+        (true, None) => Err(AppError::InvalidInput("A token is required to push memos to Flomo".to_string())),
+    }
+}
+
+// Memos created per batch when importers push to Flomo, and the pause after each batch
+const PUSH_BATCH_SIZE: usize = 20;
+const PUSH_BATCH_PAUSE: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Stores an import plan, first creating the new memos on Flomo when a
+/// client is given. Memos are pushed in batches with a pause in between;
+/// those that fail to push are kept locally and reported. Overwrites only
+/// change the local copy.
+async fn save_imported_memos(
+    app: &tauri::AppHandle,
+    db: &Database,
+    mut client: Option<FlomoClient>,
+    plan: ImportPlan,
+    errors: Vec<String>,
+) -> Result<ImportReport, AppError> {
+    let mut report = ImportReport { errors, ..Default::default() };
+    let mut local = plan.overwrite;
+    let mut pushed = Vec::new();
+    
+    for (index, memo) in plan.new.into_iter().enumerate() {
+        let Some(flomo) = &client else {
+            local.push(memo);
+            continue;
+        };
+        if index > 0 && index % PUSH_BATCH_SIZE == 0 {
+            tokio::time::sleep(PUSH_BATCH_PAUSE).await;
+        }
+        
+        match flomo.create_memo(&import::memo_to_flomo_html(&memo)).await {
+            Ok(api_memo) => pushed.push(Memo::from(api_memo)),
+            Err(e) => {
+                let preview: String = memo.content.chars().take(30).collect();
+                // Pushing the rest would fail the same way
+                if matches!(e, AppError::Auth(_) | AppError::RateLimited(_)) {
+                    client = None;
+                }
+                report.errors.push(format!("Failed to push \"{}\": {}", preview, notify_auth_expired(app, e)));
+                local.push(memo);
+            }
+        }
+    }
+    
+    report.pushed = pushed.len();
+    let stats = db.run(move |db| {
+        let mut stats = db.import_local_memos(&local)?;
+        stats += db.bulk_upsert_memos(&pushed)?;
+        Ok(stats)
+    }).await?;
+    report.created = stats.new;
+    report.updated = stats.updated;
+    report.skipped = plan.skipped + stats.unchanged;
+    
+    Ok(report)
+}
+
+/// Imports memos from a CSV file, merging rows that match stored memos per
+/// `merge`. With `dryRun` nothing is written; the report says what would be
+/// created, updated and skipped.
+#[tauri::command]
+async fn import_csv(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    column_mapping: Option<CsvColumnMapping>,
+    merge: Option<MergeStrategy>,
+    dry_run: Option<bool>,
+) -> Result<ImportReport, AppError> {
+    let db = state.db()?;
+    let mapping = column_mapping.unwrap_or_default();
+    let merge = merge.unwrap_or_default();
+    let tz = state.settings().timezone();
+    
+    let (plan, errors) = db.run(move |db| {
+        let (memos, errors) = import::read_csv(std::path::Path::new(&path), &mapping, &tz)?;
+        Ok((import::plan_import(db, memos, merge)?, errors))
+    }).await?;
+    
+    if dry_run.unwrap_or(false) {
+        return Ok(ImportReport {
+            created: plan.new.len(),
+            updated: plan.overwrite.len(),
+            skipped: plan.skipped,
+            errors,
+            ..Default::default()
+        });
+    }
+    save_imported_memos(&app, &db, None, plan, errors).await
+}
+
+/// Renders the memos matching the filter into a printable PDF, oldest first.
+#[tauri::command]
+async fn export_memos_pdf(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<PdfOptions>,
+) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let mut options = options.unwrap_or_default();
+    options.locale = settings.locale;
+    let tz = settings.timezone();
+    
+    db.run(move |db| pdf::export_pdf(db, std::path::Path::new(&path), &options, tz)).await
+}
+
+/// Packages the memos matching the filter into an EPUB, one chapter per
+/// month or per tag.
+#[tauri::command]
+async fn export_memos_epub(
+    state: State<'_, AppState>,
+    path: String,
+    options: Option<EpubOptions>,
+) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let mut options = options.unwrap_or_default();
+    options.locale = settings.locale;
+    let tz = settings.timezone();
+    
+    db.run(move |db| epub::export_epub(db, std::path::Path::new(&path), &options, tz)).await
+}
+
+/// Writes the memos matching the filter as Logseq daily journal pages under
+/// `<dir>/journals`.
+#[tauri::command]
+async fn export_logseq(
+    state: State<'_, AppState>,
+    dir: String,
+    options: Option<LogseqOptions>,
+) -> Result<logseq::LogseqExportResult, AppError> {
+    let db = state.db()?;
+    let options = options.unwrap_or_default();
+    let tz = state.settings().timezone();
+    
+    db.run(move |db| logseq::export_logseq(db, std::path::Path::new(&dir), &options, tz)).await
+}
+
+/// Writes the memos matching the filter as Hugo or Jekyll posts under the
+/// site directory `dir`.
+#[tauri::command]
+async fn export_blog_posts(
+    state: State<'_, AppState>,
+    dir: String,
+    options: Option<BlogOptions>,
+) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let options = options.unwrap_or_default();
+    let tz = state.settings().timezone();
+    
+    db.run(move |db| blog::export_blog(db, std::path::Path::new(&dir), &options, tz)).await
+}
+
+/// Mirrors memos into the configured git repository now, committing any
+/// changes.
+#[tauri::command]
+async fn update_git_mirror(state: State<'_, AppState>) -> Result<git_mirror::GitMirrorReport, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let tz = settings.timezone();
+    let mirror = settings.git_mirror
+        .ok_or_else(|| AppError::InvalidInput("No git mirror repository is configured".to_string()))?;
+    
+    db.run(move |db| git_mirror::mirror(db, &mirror, &tz)).await
+}
+
+/// The weekly digest as it would be sent today, for a preview.
+#[tauri::command]
+async fn preview_digest(state: State<'_, AppState>) -> Result<digest::Digest, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let today = chrono::Utc::now().with_timezone(&settings.timezone()).date_naive();
+    let locale = settings.locale;
+    
+    db.run(move |db| digest::build_digest(db, today, locale)).await
+}
+
+/// Emails this week's digest now, regardless of the schedule.
+#[tauri::command]
+async fn send_digest_now(state: State<'_, AppState>) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let digest_settings = settings.digest.clone()
+        .ok_or_else(|| AppError::InvalidInput("No SMTP server is configured".to_string()))?;
+    
+    send_digest(&db, &settings, &digest_settings).await
+}
+
+// Builds, sends and records this week's digest; returns its memo count
+async fn send_digest(db: &Database, settings: &AppSettings, digest_settings: &digest::DigestSettings) -> Result<usize, AppError> {
+    let today = chrono::Utc::now().with_timezone(&settings.timezone()).date_naive();
+    let locale = settings.locale;
+    
+    let digest = db.run(move |db| digest::build_digest(db, today, locale)).await?;
+    digest::send(digest_settings, &digest).await?;
+    let memo_count = digest.memo_count;
+    db.run(move |db| db.record_digest(memo_count)).await?;
+    Ok(memo_count)
+}
+
+// Sends the weekly digest once its scheduled time has passed
+async fn run_digests(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(digest::CHECK_INTERVAL).await;
+        
+        let state = app.state::<AppState>();
+        let settings = state.settings();
+        let Some(digest_settings) = settings.digest.clone() else { continue };
+        let Ok(db) = state.db() else { continue };
+        
+        let now = chrono::Utc::now().with_timezone(&settings.timezone());
+        match db.run(|db| db.last_digest_sent_at()).await {
+            Ok(last_sent) if digest::is_due(&digest_settings, now, last_sent) => {}
+            Ok(_) => continue,
+            Err(e) => {
+                error!("Failed to check the weekly digest: {}", e);
+                continue;
+            }
+        }
+        if let Err(e) = send_digest(&db, &settings, &digest_settings).await {
+            error!("Failed to send the weekly digest: {}", e);
+        }
+    }
+}
+
+/// Sends memos with `tag_filter` (e.g. "highlight"), or every memo, to
+/// Readwise as highlights. Memos pushed before are only sent again once
+/// edited.
+#[tauri::command]
+async fn push_to_readwise(
+    state: State<'_, AppState>,
+    tag_filter: Option<String>,
+) -> Result<readwise::ReadwiseReport, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let tz = settings.timezone();
+    let token = settings.readwise_token.unwrap_or_default();
+    
+    readwise::push(&db, &token, tag_filter, tz).await
+}
+
+/// Atom (default) or RSS XML of the most recent memos, optionally for one tag.
+#[tauri::command]
+async fn generate_feed(
+    state: State<'_, AppState>,
+    limit: Option<i64>,
+    tag_filter: Option<String>,
+    format: Option<FeedFormat>,
+) -> Result<String, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let (tz, locale) = (settings.timezone(), settings.locale);
+    
+    db.run(move |db| feed::generate_feed(db, format.unwrap_or_default(), limit, tag_filter, &tz, locale)).await
+}
+
+/// Formats the memos `memo_slugs`, in that order, and puts the result on
+/// the clipboard as plain text, with the memos' HTML alongside for apps that
+/// paste rich text. Returns how many memos were copied.
+#[tauri::command]
+async fn copy_memos_to_clipboard(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    memo_slugs: Vec<String>,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+) -> Result<usize, AppError> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+    
+    let db = state.db()?;
+    let settings = state.settings();
+    let mut options = options.unwrap_or_default();
+    options.locale = settings.locale;
+    let tz = settings.timezone();
+    
+    let (text, html, count) = db.run(move |db| {
+        let mut memos = db.get_memos_by_slugs(&memo_slugs)?;
+        export::attach_content_html(db, &mut memos)?;
+        let text = export::format_to_string(&memos, format, options, tz);
+        Ok((text, export::clipboard_html(&memos), memos.len()))
+    })
+    .await?;
+    
+    app.clipboard().write_html(html, Some(text))
+        .map_err(|e| AppError::Internal(format!("Failed to write clipboard: {}", e)))?;
+    Ok(count)
+}
+
+/// Formats the memos matching `options.filter` straight from the database,
+/// so exports don't have to ship every memo to the frontend and back.
+#[tauri::command]
+async fn format_memos_from_db(
+    state: State<'_, AppState>,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+) -> Result<String, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let mut options = options.unwrap_or_default();
+    options.locale = settings.locale;
+    let tz = settings.timezone();
+    
+    db.run(move |db| {
+        let (bytes, _) = export::export_from_db(db, Vec::new(), format, options, tz, |_| {})?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    })
+    .await
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        // Must come first: a second launch, e.g. from an openflomo:// link,
+        // forwards its URL to the running app through the deep-link plugin
+        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+            open_deep_links(app, &[]);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_store::Builder::new().build())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        // Removed tauri_plugin_sql since we're using rusqlite directly
+        .setup(|app| {
+            let app_handle = app.handle();
+            let app_data_dir = app_handle
+                .path()
+                .app_data_dir()
+                .expect("Failed to get app data dir");
+            
+            // Ensure the directory exists
+            std::fs::create_dir_all(&app_data_dir).ok();
+            
+            let (settings, dropped_settings) = AppSettings::load(app_handle).unwrap_or_default();
+            let data_dir = settings.database_dir(&app_data_dir);
+            std::fs::create_dir_all(&data_dir).ok();
+            let log_dir = data_dir.join(logging::LOG_DIR);
+            if let Err(e) = logging::init(Some(&log_dir), settings.log_level) {
+                // Nothing to log it to yet
+                eprintln!("{}", e);
+            }
+            for reason in dropped_settings {
+                warn!("Ignoring saved setting {}", reason);
+            }
+            flomo_core::set_text_width(settings.text_width);
+            
+            let db_path = data_dir.join(settings.database_file());
+            let demo = settings.demo_mode;
+            if demo {
+                info!("Demo mode: syncing generated memos into {}", db_path.display());
+            }
+            
+            // Initialize database asynchronously
+            let app_state = AppState {
+                db: Arc::new(Mutex::new(None)),
+                db_path: Arc::new(Mutex::new(db_path.clone())),
+                log_dir,
+                sync_cancel: Arc::new(Mutex::new(CancellationToken::new())),
+                settings: Arc::new(Mutex::new(settings)),
+                pending_deletions: Arc::new(Mutex::new(HashMap::new())),
+                publisher: Arc::new(Mutex::new(None)),
+                demo,
+            };
+            
+            app.manage(app_state);
+            tauri::async_runtime::spawn(run_outbox(app_handle.clone()));
+            tauri::async_runtime::spawn(run_backups(app_handle.clone()));
+            tauri::async_runtime::spawn(run_telegram(app_handle.clone()));
+            tauri::async_runtime::spawn(run_digests(app_handle.clone()));
+            
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                
+                // Installers register the scheme on macOS and Windows; Linux
+                // and Windows dev builds register it at runtime
+                #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+                if let Err(e) = app.deep_link().register_all() {
+                    error!("Failed to register URL scheme: {}", e);
+                }
+                
+                let deep_link_handle = app_handle.clone();
+                app.deep_link().on_open_url(move |event| {
+                    open_deep_links(&deep_link_handle, &event.urls());
+                });
+            }
+            
+            // Encrypted databases stay closed until the frontend unlocks them
+            if db::is_encrypted(&db_path) {
+                info!("Database is encrypted, waiting for passphrase");
+                return Ok(());
+            }
+            
+            let db_state = app.state::<AppState>().db.clone();
+            match Database::new(&db_path) {
+                Ok(db) => {
+                    let mut db_lock = db_state.lock().unwrap();
+                    *db_lock = Some(db);
+                    info!("Database initialized successfully");
+                }
+                Err(e) => {
+                    error!("Failed to initialize database: {}", e);
+                }
+            }
+            
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            validate_token,
+            test_connection,
+            login,
+            get_memos,
+            get_memos_page,
+            get_memos_from_db,
+            search_memos,
+            search_memos_page,
+            search_memos_from_db,
+            query_for_launcher,
+            get_search_history,
+            clear_search_history,
+            get_memo_by_slug,
+            update_memo,
+            compose_memo,
+            get_pending_memos,
+            remove_pending_memo,
+            flush_outbox,
+            render_capture_template,
+            quick_capture_with_template,
+            get_launch_deep_links,
+            start_publishing,
+            stop_publishing,
+            get_publish_status,
+            request_memo_deletion,
+            delete_memo_remote,
+            get_audit_log,
+            count_memos,
+            get_total_memo_count,
+            get_memos_by_tag,
+            get_random_memos,
+            get_memos_on_this_day,
+            get_writing_stats,
+            get_all_tags,
+            get_tag_tree,
+            get_tag_graph,
+            suggest_tags,
+            get_memos_with_links,
+            get_backlinks,
+            get_similar_memos,
+            find_duplicate_memos,
+            merge_duplicate_memos,
+            get_open_todos,
+            get_link_stats,
+            check_dead_links,
+            get_dead_links,
+            rename_tag,
+            merge_tags,
+            get_tag_mappings,
+            delete_memo_local,
+            restore_memo,
+            empty_trash,
+            pin_memo,
+            unpin_memo,
+            pin_memos,
+            unpin_memos,
+            add_local_tag,
+            remove_local_tag,
+            get_local_tags,
+            create_collection,
+            rename_collection,
+            add_to_collection,
+            remove_from_collection,
+            get_collections,
+            get_collection_memos,
+            delete_collection,
+            get_pinned_memos,
+            sync_all_memos,
+            cancel_sync,
+            get_sync_status,
+            get_sync_history,
+            get_export_runs,
+            get_api_diagnostics,
+            clear_api_diagnostics,
+            get_app_stats,
+            check_database,
+            get_database_status,
+            get_data_dir,
+            set_data_dir,
+            unlock_database,
+            encrypt_database,
+            backup_now,
+            list_backups,
+            restore_backup,
+            clear_local_data,
+            save_config,
+            load_config,
+            get_settings,
+            save_settings,
+            get_recent_logs,
+            reconvert_memo_text,
+            format_memos_json,
+            format_memos_markdown,
+            format_memos_table,
+            format_memos_json_with_options,
+            format_memos_markdown_with_options,
+            format_memos_table_with_options,
+            format_memos_jsonl,
+            export_to_file,
+            export_selected_memos,
+            export_with_dialog,
+            export_memos_html,
+            export_memos_enex,
+            export_archive,
+            export_memos_xlsx,
+            summarize_memos,
+            summarize_period,
+            ask_memos,
+            import_markdown_dir,
+            import_csv,
+            import_enex,
+            import_twitter_archive,
+            export_memos_pdf,
+            export_memos_epub,
+            export_logseq,
+            export_blog_posts,
+            generate_feed,
+            update_git_mirror,
+            push_to_readwise,
+            preview_digest,
+            send_digest_now,
+            format_memos_from_db,
+            copy_memos_to_clipboard
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+// Database-backed commands
+/// A page of memos; pass the returned `next_cursor` back as `cursor` for
+/// the next one.
+#[tauri::command]
+async fn get_memos_from_db(
+    state: State<'_, AppState>,
+    order_by: MemoOrder,
+    order_dir: String,
+    cursor: Option<String>,
+    limit: i64,
+    filter: Option<MemoFilter>,
+    pinned_first: Option<bool>,
+) -> Result<MemoPage, AppError> {
+    let db = state.db()?;
+    let mut filter = filter.unwrap_or_default();
+    filter.pinyin = state.settings().pinyin_search;
+    let pinned_first = pinned_first.unwrap_or(false);
+    let cursor = cursor.as_deref().map(PageCursor::decode).transpose()?;
+    
+    db.run(move |db| db.get_memos_after(order_by, &order_dir, &filter, pinned_first, cursor.as_ref(), limit))
+        .await
+}
+
+/// Searches memos with the query syntax described on `SearchExpr`, or with
+/// `regex` as a regular expression over the content (at most
+/// `REGEX_RESULT_CAP` results per call), or with `fuzzy` tolerating typos and
+/// ranking hits by score. Each hit carries a highlighted snippet and the
+/// match offsets in its content. First-page searches are added to the
+/// search history.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // Tauri maps each argument to a frontend parameter
 async fn search_memos_from_db(
     state: State<'_, AppState>,
-    query: String,
-    order_by: String,
-    order_dir: String,
-    offset: i64,
+    query: String,
+    order_by: MemoOrder,
+    order_dir: String,
+    cursor: Option<String>,
+    limit: i64,
+    filter: Option<MemoFilter>,
+    regex: Option<bool>,
+    fuzzy: Option<bool>,
+) -> Result<SearchPage, AppError> {
+    let db = state.db()?;
+    let mut filter = filter.unwrap_or_default();
+    filter.pinyin = state.settings().pinyin_search;
+    let cursor = cursor.as_deref().map(PageCursor::decode).transpose()?;
+    
+    let record = cursor.is_none() && !query.trim().is_empty();
+    let history_query = query.trim().to_string();
+    
+    let (page, total) = if fuzzy.unwrap_or(false) {
+        // Fuzzy hits are ranked by score, so they are paged by position
+        let offset = PageCursor::offset(cursor.as_ref())?;
+        let limit = limit.max(0) as usize;
+        // Every memo is scored anyway, so the first page keeps all hits to count them
+        let fetch = if record { usize::MAX } else { limit + 1 };
+        let mut hits = db.run(move |db| search::fuzzy_search(db, &query, &filter, offset, fetch)).await?;
+        let total = hits.len() as i64;
+        let next_cursor = (hits.len() > limit).then(|| PageCursor::Offset(offset + limit).encode());
+        hits.truncate(limit);
+        (SearchPage { hits, next_cursor }, total)
+    } else if regex.unwrap_or(false) {
+        // Regex matches are filtered after the query, so they are paged by position too
+        let pattern = search::compile_regex(&query)?;
+        let offset = PageCursor::offset(cursor.as_ref())?;
+        let limit = (limit.max(0) as usize).min(search::REGEX_RESULT_CAP);
+        let matcher = pattern.clone();
+        let (mut memos, total) = db.run(move |db| {
+            let memos = db.search_memos_regex(&matcher, order_by, &order_dir, &filter, offset, limit + 1)?;
+            // Only the first page needs the full count, for the history entry
+            let total = if record { db.count_memos_regex(&matcher, &filter)? } else { 0 };
+            Ok((memos, total))
+        })
+        .await?;
+        let next_cursor = (memos.len() > limit).then(|| PageCursor::Offset(offset + limit).encode());
+        memos.truncate(limit);
+        let hits = memos.into_iter().map(|memo| SearchHit::for_regex(memo, &pattern)).collect();
+        (SearchPage { hits, next_cursor }, total)
+    } else {
+        let terms = SearchExpr::parse(&query)
+            .map(|expr| expr.highlight_terms())
+            .unwrap_or_default();
+        
+        let (page, total) = db.run(move |db| {
+            let page = db.search_memos_after(&query, order_by, &order_dir, &filter, cursor.as_ref(), limit)?;
+            // Only the first page needs the full count, for the history entry
+            let total = if record {
+                db.count_memos(&MemoFilter { search: Some(query), ..filter })?
+            } else {
+                0
+            };
+            Ok((page, total))
+        })
+        .await?;
+        let hits = page.memos.into_iter().map(|memo| SearchHit::new(memo, &terms)).collect();
+        (SearchPage { hits, next_cursor: page.next_cursor }, total)
+    };
+    
+    if record {
+        let result = db.run(move |db| db.record_search(&history_query, total)).await;
+        if let Err(e) = result {
+            warn!("Failed to record search: {}", e);
+        }
+    }
+    
+    Ok(page)
+}
+
+/// Search results shaped for Alfred Script Filters and Raycast script
+/// commands; each item's `arg` is an `openflomo://memo/<slug>` link.
+#[tauri::command]
+async fn query_for_launcher(
+    state: State<'_, AppState>,
+    q: String,
+    limit: Option<i64>,
+) -> Result<launcher::LauncherResults, AppError> {
+    let db = state.db()?;
+    let filter = MemoFilter { pinyin: state.settings().pinyin_search, ..Default::default() };
+    let limit = limit.unwrap_or(20);
+    
+    db.run(move |db| launcher::query(db, &q, &filter, limit)).await
+}
+
+/// Most recent distinct searches, newest first, for search suggestions.
+#[tauri::command]
+async fn get_search_history(
+    state: State<'_, AppState>,
+    limit: Option<i64>,
+) -> Result<Vec<db::SearchHistoryEntry>, AppError> {
+    let db = state.db()?;
+    let limit = limit.unwrap_or(20);
+    
+    db.run(move |db| db.get_search_history(limit)).await
+}
+
+/// Removes every search history entry and returns how many were removed.
+#[tauri::command]
+async fn clear_search_history(state: State<'_, AppState>) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.clear_search_history()).await
+}
+
+/// Number of memos matching `filter`, using the same predicates as
+/// `get_memos_from_db` and `search_memos_from_db`, for pagination totals.
+#[tauri::command]
+async fn count_memos(state: State<'_, AppState>, filter: Option<MemoFilter>) -> Result<i64, AppError> {
+    let db = state.db()?;
+    let mut filter = filter.unwrap_or_default();
+    filter.pinyin = state.settings().pinyin_search;
+    
+    db.run(move |db| db.count_memos(&filter)).await
+}
+
+/// Number of memos outside the trash, regardless of any filter.
+#[tauri::command]
+async fn get_total_memo_count(state: State<'_, AppState>) -> Result<i64, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.count_memos(&MemoFilter::default())).await
+}
+
+/// Looks up one memo for deep links and detail views. Memos that haven't
+/// been synced yet are fetched from the API when a token is given, and cached.
+#[tauri::command]
+async fn get_memo_by_slug(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    slug: String,
+    token: Option<String>,
+) -> Result<Option<Memo>, AppError> {
+    let db = state.db()?;
+    let cached_slug = slug.clone();
+    if let Some(memo) = db.run(move |db| db.get_memo_by_slug(&cached_slug)).await? {
+        return Ok(Some(memo));
+    }
+    
+    let Some(token) = token else {
+        return Ok(None);
+    };
+    let api_memo = state.client(token)?.fetch_memo(&slug)
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))?;
+    let Some(api_memo) = api_memo else {
+        return Ok(None);
+    };
+    
+    let mut memo = Memo::from(api_memo);
+    let cached = memo.clone();
+    db.run(move |db| db.upsert_memo(&cached)).await?;
+    // Like list queries, the HTML stays in the database
+    memo.content_html = None;
+    
+    Ok(Some(memo))
+}
+
+/// Edits a memo's content and pushes the change to Flomo. `tags` not written
+/// in the content are appended to it. The edit is refused with a `conflict`
+/// error when the memo's `updated_at` on Flomo no longer matches
+/// `expected_updated_at` (by default the local copy's), in which case the
+/// local copy is refreshed from Flomo. Memos that only exist locally are
+/// edited in place.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // Tauri maps each argument to a frontend parameter
+async fn update_memo(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    slug: String,
+    content: String,
+    tags: Option<Vec<String>>,
+    expected_updated_at: Option<String>,
+    token: Option<String>,
+) -> Result<Memo, AppError> {
+    if content.trim().is_empty() {
+        return Err(AppError::InvalidInput("Memo content is empty".to_string()));
+    }
+    let db = state.db()?;
+    let lookup_slug = slug.clone();
+    let mut memo = db.run(move |db| db.get_memo_by_slug(&lookup_slug))
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Memo {}", slug)))?;
+    let expected_updated_at = expected_updated_at.unwrap_or_else(|| memo.updated_at.clone());
+    let tags = tags.unwrap_or_default();
+    let content_html = import::flomo_html(&content, &tags);
+    
+    if memo.local {
+        if memo.updated_at != expected_updated_at {
+            return Err(AppError::Conflict(format!("Memo {} was changed at {}", slug, memo.updated_at)));
+        }
+        let mut all_tags = import::inline_tags(&content);
+        for tag in tags {
+            if !all_tags.contains(&tag) {
+                all_tags.push(tag);
+            }
+        }
+        memo.content = content;
+        memo.tags = all_tags;
+        memo.content_html = Some(content_html);
+        memo.updated_at = chrono::Utc::now()
+            .with_timezone(&state.settings().timezone())
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        
+        let stored = memo.clone();
+        db.run(move |db| db.import_local_memos(&[stored])).await?;
+        memo.content_html = None;
+        return Ok(memo);
+    }
+    
+    let token = token.ok_or_else(|| AppError::InvalidInput("A token is required to edit Flomo memos".to_string()))?;
+    let client = state.client(token)?;
+    let remote = client.fetch_memo(&slug)
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))?
+        .ok_or_else(|| AppError::NotFound(format!("Memo {} on Flomo", slug)))?;
+    
+    if remote.updated_at != expected_updated_at {
+        let changed_at = remote.updated_at.clone();
+        let latest = Memo::from(remote);
+        db.run(move |db| db.upsert_memo(&latest)).await?;
+        return Err(AppError::Conflict(format!("Memo {} was changed on Flomo at {}", slug, changed_at)));
+    }
+    
+    let updated = client.update_memo(&slug, &content_html)
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))?;
+    let mut memo = Memo::from(updated);
+    let stored = memo.clone();
+    db.run(move |db| db.upsert_memo(&stored)).await?;
+    memo.content_html = None;
+    
+    Ok(memo)
+}
+
+/// Creates a memo on Flomo, or queues it in the outbox when there is no
+/// token or Flomo can't be reached. Queued memos are pushed in the
+/// background once the connection is back.
+#[tauri::command]
+async fn compose_memo(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    content: String,
+    tags: Option<Vec<String>>,
+    token: Option<String>,
+) -> Result<ComposeResult, AppError> {
+    compose(&app, &state, content, tags.unwrap_or_default(), token).await
+}
+
+async fn compose(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    content: String,
+    tags: Vec<String>,
+    token: Option<String>,
+) -> Result<ComposeResult, AppError> {
+    let db = state.db()?;
+    let client = token.map(|token| state.client(token)).transpose()?;
+    
+    outbox::compose(&db, client.as_ref(), content, tags)
+        .await
+        .map_err(|e| notify_auth_expired(app, e))
+}
+
+/// Expands the capture template `template_id` without posting it, for a
+/// preview the user can edit.
+#[tauri::command]
+fn render_capture_template(app: tauri::AppHandle, state: State<'_, AppState>, template_id: String) -> Result<String, AppError> {
+    render_template(&app, &state, &template_id).map(|(content, _)| content)
+}
+
+/// Expands the capture template `template_id` and posts the result like
+/// `compose_memo`, queueing it when Flomo can't be reached.
+#[tauri::command]
+async fn quick_capture_with_template(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    template_id: String,
+    token: Option<String>,
+) -> Result<ComposeResult, AppError> {
+    let (content, tags) = render_template(&app, &state, &template_id)?;
+    compose(&app, &state, content, tags, token).await
+}
+
+fn render_template(app: &tauri::AppHandle, state: &AppState, template_id: &str) -> Result<(String, Vec<String>), AppError> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+    
+    let settings = state.settings();
+    let template = settings.capture_templates.iter()
+        .find(|template| template.id == template_id)
+        .ok_or_else(|| AppError::NotFound(format!("Capture template {}", template_id)))?;
+    
+    let clipboard = if template.uses_clipboard() {
+        // An empty or non-text clipboard just renders as nothing
+        app.clipboard().read_text().ok()
+    } else {
+        None
+    };
+    let context = templates::TemplateContext {
+        now: chrono::Utc::now().with_timezone(&settings.timezone()),
+        locale: settings.locale,
+        clipboard,
+    };
+    
+    Ok((template.render(&context)?, template.tags.clone()))
+}
+
+/// Memos waiting in the outbox, oldest first.
+#[tauri::command]
+async fn get_pending_memos(state: State<'_, AppState>) -> Result<Vec<db::PendingMemo>, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.get_pending_memos(None)).await
+}
+
+/// Discards a queued memo without pushing it.
+#[tauri::command]
+async fn remove_pending_memo(state: State<'_, AppState>, id: i64) -> Result<bool, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.remove_pending_memo(id)).await
+}
+
+/// Pushes every queued memo now, ignoring the retry schedule.
+#[tauri::command]
+async fn flush_outbox(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<outbox::FlushReport, AppError> {
+    let db = state.db()?;
+    let client = state.client(token)?;
+    let policy = state.settings().outbox_conflict_policy;
+    
+    outbox::flush(&db, &client, policy, false)
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))
+}
+
+// Pushes due outbox memos in the background for as long as the app runs
+async fn run_outbox(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(outbox::FLUSH_INTERVAL).await;
+        
+        let state = app.state::<AppState>();
+        let Ok(db) = state.db() else { continue };
+        let Ok(Some(token)) = stored_token(&app) else { continue };
+        let Ok(client) = state.client(token) else { continue };
+        
+        match outbox::flush(&db, &client, state.settings().outbox_conflict_policy, true).await {
+            Ok(report) if !report.pushed.is_empty() || report.skipped > 0 => {
+                let _ = app.emit("outbox-flushed", &report);
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to push queued memos: {}", e),
+        }
+    }
+}
+
+// Turns messages sent to the configured Telegram bot into memos for as
+// long as the app runs
+async fn run_telegram(app: tauri::AppHandle) {
+    let mut bot: Option<telegram::TelegramBot> = None;
+    loop {
+        let state = app.state::<AppState>();
+        let settings = state.settings().telegram.filter(|settings| !settings.bot_token.trim().is_empty());
+        // Messages wait on Telegram's side while the database is locked
+        let Some(settings) = settings.filter(|_| state.db().is_ok()) else {
+            bot = None;
+            tokio::time::sleep(telegram::IDLE_INTERVAL).await;
+            continue;
+        };
+        
+        // A new token in the settings means a different bot
+        if bot.as_ref().is_none_or(|bot| bot.token() != settings.bot_token.trim()) {
+            match telegram::TelegramBot::new(&settings.bot_token) {
+                Ok(new_bot) => bot = Some(new_bot),
+                Err(e) => {
+                    error!("Failed to start Telegram bot: {}", e);
+                    tokio::time::sleep(telegram::RETRY_DELAY).await;
+                    continue;
+                }
+            }
+        }
+        let Some(bot) = bot.as_mut() else { continue };
+        
+        let messages = match bot.poll().await {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!("Failed to fetch Telegram messages: {}", e);
+                tokio::time::sleep(telegram::RETRY_DELAY).await;
+                continue;
+            }
+        };
+        // Only taken after the long poll, so the database can be closed
+        // meanwhile; the fetched messages wait until it's back
+        let db = loop {
+            match state.db() {
+                Ok(db) => break db,
+                Err(_) => tokio::time::sleep(telegram::IDLE_INTERVAL).await,
+            }
+        };
+        for message in messages {
+            let client = stored_token(&app).ok().flatten().and_then(|token| state.client(token).ok());
+            match telegram::capture(bot, &db, client.as_ref(), &settings, message).await {
+                Ok(Some(result)) => {
+                    let _ = app.emit("telegram-captured", &result);
+                }
+                Ok(None) => {}
+                Err(e) => error!("Failed to save Telegram message: {}", notify_auth_expired(&app, e)),
+            }
+        }
+    }
+}
+
+/// Serves the memos matching `options.filter`, the "public" tag unless
+/// given, as a read-only web page, on this computer or, with `lan`, to
+/// other devices on the network. A filter matching every memo is refused.
+/// The page is regenerated after every sync. Replaces a server already
+/// running.
+#[tauri::command]
+async fn start_publishing(state: State<'_, AppState>, options: Option<publish::PublishOptions>) -> Result<publish::PublishStatus, AppError> {
+    let db = state.db()?;
+    let settings = state.settings();
+    let mut options = options.unwrap_or_default();
+    options.filter.pinyin = settings.pinyin_search;
+    
+    // Free the port before binding it again
+    state.publisher.lock().unwrap().take();
+    let publisher = publish::Publisher::start(&db, options, settings.timezone(), settings.locale).await?;
+    let status = publisher.status();
+    *state.publisher.lock().unwrap() = Some(publisher);
+    
+    Ok(status)
+}
+
+#[tauri::command]
+fn stop_publishing(state: State<'_, AppState>) {
+    state.publisher.lock().unwrap().take();
+}
+
+#[tauri::command]
+fn get_publish_status(state: State<'_, AppState>) -> Option<publish::PublishStatus> {
+    state.publisher.lock().unwrap().as_ref().map(publish::Publisher::status)
+}
+
+async fn refresh_published(state: &AppState, db: &Database) -> Result<(), AppError> {
+    let Some(options) = state.publisher.lock().unwrap().as_ref().map(|publisher| publisher.options().clone()) else {
+        return Ok(());
+    };
+    let settings = state.settings();
+    let (tz, locale) = (settings.timezone(), settings.locale);
+    
+    let (page, memo_count) = db.run(move |db| publish::render(db, &options, tz, locale)).await?;
+    if let Some(publisher) = state.publisher.lock().unwrap().as_mut() {
+        publisher.replace_page(page, memo_count);
+    }
+    Ok(())
+}
+
+/// Parses `openflomo://` URLs and hands them to the frontend as
+/// "deep-link" events, bringing the window to the front.
+fn open_deep_links(app: &tauri::AppHandle, urls: &[tauri::Url]) {
+    for url in urls {
+        match DeepLink::parse(url) {
+            Ok(link) => {
+                let _ = app.emit("deep-link", &link);
+            }
+            Err(e) => warn!("Ignoring deep link: {}", e),
+        }
+    }
+    
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Links the app was launched with, which arrive before the frontend can
+/// listen for "deep-link" events.
+#[tauri::command]
+fn get_launch_deep_links(app: tauri::AppHandle) -> Result<Vec<DeepLink>, AppError> {
+    use tauri_plugin_deep_link::DeepLinkExt;
+    
+    let urls = app.deep_link().get_current()
+        .map_err(|e| AppError::Internal(format!("Failed to read launch URL: {}", e)))?
+        .unwrap_or_default();
+    Ok(urls.iter().filter_map(|url| DeepLink::parse(url).ok()).collect())
+}
+
+// How long a deletion nonce stays valid
+const DELETION_NONCE_TTL: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// First step of deleting a memo on Flomo: returns a nonce that
+/// `delete_memo_remote` must echo back within two minutes, so a stray call
+/// can't delete anything without the user confirming.
+#[tauri::command]
+async fn request_memo_deletion(state: State<'_, AppState>, slug: String) -> Result<String, AppError> {
+    let db = state.db()?;
+    let lookup_slug = slug.clone();
+    let memo = db.run(move |db| db.get_memo_by_slug(&lookup_slug))
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Memo {}", slug)))?;
+    if memo.local {
+        return Err(AppError::InvalidInput(format!("Memo {} only exists locally", slug)));
+    }
+    
+    let issued_at = std::time::Instant::now();
+    // Only needs to be unguessable by accident, not cryptographically
+    let seed = format!("{}:{:?}:{:?}", slug, issued_at, chrono::Utc::now());
+    let nonce = format!("{:x}", md5::compute(seed));
+    let mut pending = state.pending_deletions.lock().unwrap();
+    pending.retain(|_, (_, issued)| issued.elapsed() < DELETION_NONCE_TTL);
+    pending.insert(slug, (nonce.clone(), issued_at));
+    
+    Ok(nonce)
+}
+
+/// Deletes a memo on Flomo after `request_memo_deletion`, then removes it
+/// locally and records the deletion, with the memo's content, in the audit log.
+#[tauri::command]
+async fn delete_memo_remote(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    slug: String,
+    nonce: String,
+    token: String,
+) -> Result<(), AppError> {
+    let db = state.db()?;
+    {
+        let mut pending = state.pending_deletions.lock().unwrap();
+        match pending.remove(&slug) {
+            Some((expected, issued)) if expected == nonce && issued.elapsed() < DELETION_NONCE_TTL => {}
+            _ => return Err(AppError::InvalidInput("Deletion was not confirmed or the confirmation expired".to_string())),
+        }
+    }
+    
+    state.client(token)?.delete_memo(&slug)
+        .await
+        .map_err(|e| notify_auth_expired(&app, e))?;
+    
+    db.run(move |db| db.remove_deleted_memo(&slug)).await
+}
+
+/// Recent remote deletions, newest first.
+#[tauri::command]
+async fn get_audit_log(state: State<'_, AppState>, limit: Option<i64>) -> Result<Vec<db::AuditEntry>, AppError> {
+    let db = state.db()?;
+    let limit = limit.unwrap_or(100);
+    
+    db.run(move |db| db.get_audit_log(limit)).await
+}
+
+#[tauri::command]
+async fn get_memos_by_tag(
+    state: State<'_, AppState>,
+    tag: String,
+    cursor: Option<String>,
+    limit: i64,
+) -> Result<MemoPage, AppError> {
+    let db = state.db()?;
+    let filter = MemoFilter { tag: Some(tag), ..Default::default() };
+    let cursor = cursor.as_deref().map(PageCursor::decode).transpose()?;
+    
+    db.run(move |db| db.get_memos_after(MemoOrder::CreatedAt, "desc", &filter, false, cursor.as_ref(), limit))
+        .await
+}
+
+/// Up to `count` random memos, optionally limited to `tag_filter` and its
+/// nested tags, for daily review.
+#[tauri::command]
+async fn get_random_memos(
+    state: State<'_, AppState>,
+    count: Option<i64>,
+    tag_filter: Option<String>,
+) -> Result<Vec<Memo>, AppError> {
+    let db = state.db()?;
+    let count = count.unwrap_or(1).max(0);
+    let filter = MemoFilter { tag: tag_filter, ..Default::default() };
+    
+    db.run(move |db| db.get_random_memos(count, &filter)).await
+}
+
+/// Memos written on today's date in previous years, with "today" taken in
+/// the configured timezone.
+#[tauri::command]
+async fn get_memos_on_this_day(state: State<'_, AppState>) -> Result<Vec<Memo>, AppError> {
+    let db = state.db()?;
+    let today = chrono::Utc::now().with_timezone(&state.settings().timezone()).date_naive();
+    
+    db.run(move |db| db.get_memos_on_this_day(today)).await
+}
+
+/// Streaks, averages and the busiest weekday and hour, for the stats
+/// dashboard. Streaks count days in the configured timezone.
+#[tauri::command]
+async fn get_writing_stats(state: State<'_, AppState>) -> Result<db::WritingStats, AppError> {
+    let db = state.db()?;
+    let today = chrono::Utc::now().with_timezone(&state.settings().timezone()).date_naive();
+    
+    db.run(move |db| db.get_writing_stats(today)).await
+}
+
+#[tauri::command]
+async fn get_all_tags(state: State<'_, AppState>) -> Result<Vec<db::TagCount>, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.get_all_tags()).await
+}
+
+#[tauri::command]
+async fn get_tag_tree(state: State<'_, AppState>) -> Result<Vec<db::TagNode>, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.get_tag_tree()).await
+}
+
+/// Memos containing links, newest first; `domain` ("github.com" or any URL
+/// on it) narrows them to one site.
+#[tauri::command]
+async fn get_memos_with_links(
+    state: State<'_, AppState>,
+    domain: Option<String>,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<Memo>, AppError> {
+    let db = state.db()?;
+    let domain = domain.map(|domain| links::domain(&domain).unwrap_or(domain));
+    
+    db.run(move |db| db.get_memos_with_links(domain.as_deref(), offset, limit)).await
+}
+
+/// Unchecked `[ ]` items across memos matching `filter`, newest first.
+#[tauri::command]
+async fn get_open_todos(state: State<'_, AppState>, filter: Option<MemoFilter>) -> Result<Vec<todos::Todo>, AppError> {
+    let db = state.db()?;
+    let mut filter = filter.unwrap_or_default();
+    filter.pinyin = state.settings().pinyin_search;
+    
+    db.run(move |db| db.get_open_todos(&filter)).await
+}
+
+/// Notes related to `slug` by shared vocabulary, most similar first.
+#[tauri::command]
+async fn get_similar_memos(
+    state: State<'_, AppState>,
+    slug: String,
+    top_k: Option<usize>,
+) -> Result<Vec<similar::SimilarMemo>, AppError> {
+    let db = state.db()?;
+    let top_k = top_k.unwrap_or(5);
+    
+    db.run(move |db| similar::similar_memos(db, &slug, top_k)).await
+}
+
+/// Groups of memos with the same or nearly the same content, for cleaning
+/// up after re-imports. `threshold` is the similarity from 0 to 1 at which
+/// memos count as duplicates.
+#[tauri::command]
+async fn find_duplicate_memos(
+    state: State<'_, AppState>,
+    threshold: Option<f64>,
+) -> Result<Vec<duplicates::DuplicateGroup>, AppError> {
+    let db = state.db()?;
+    let threshold = threshold.unwrap_or(duplicates::DEFAULT_THRESHOLD);
+    
+    db.run(move |db| duplicates::find_duplicate_memos(db, threshold)).await
+}
+
+/// Keeps `keep` and moves `duplicates` to the trash, recorded as its
+/// duplicates. Returns how many were merged.
+#[tauri::command]
+async fn merge_duplicate_memos(
+    state: State<'_, AppState>,
+    keep: String,
+    duplicates: Vec<String>,
+) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.merge_duplicates(&keep, &duplicates)).await
+}
+
+/// Memos mentioning `slug`, for the "mentioned in" list of the detail view.
+#[tauri::command]
+async fn get_backlinks(state: State<'_, AppState>, slug: String) -> Result<Vec<Memo>, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.get_backlinks(&slug)).await
+}
+
+/// Most linked domains with their link, memo and dead-link counts.
+#[tauri::command]
+async fn get_link_stats(state: State<'_, AppState>, limit: Option<i64>) -> Result<Vec<db::DomainStats>, AppError> {
+    let db = state.db()?;
+    let limit = limit.unwrap_or(50);
+    
+    db.run(move |db| db.get_link_stats(limit)).await
+}
+
+/// Requests every link (or those on `domain`), stores the results and
+/// returns the links that are now dead.
+#[tauri::command]
+async fn check_dead_links(state: State<'_, AppState>, domain: Option<String>) -> Result<Vec<db::DeadLink>, AppError> {
+    let db = state.db()?;
+    let domain = domain.map(|domain| links::domain(&domain).unwrap_or(domain));
+    
+    let urls = db.run(move |db| db.get_link_urls(domain.as_deref())).await?;
+    let checks = links::check_links(urls).await?;
+    db.run(move |db| {
+        db.save_link_checks(&checks)?;
+        db.get_dead_links()
+    })
+    .await
+}
+
+/// Dead links found by the last `check_dead_links` run, without
+/// re-checking anything.
+#[tauri::command]
+async fn get_dead_links(state: State<'_, AppState>) -> Result<Vec<db::DeadLink>, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.get_dead_links()).await
+}
+
+/// Tags from the existing vocabulary that fit `content`, for the
+/// quick-capture window.
+#[tauri::command]
+async fn suggest_tags(
+    state: State<'_, AppState>,
+    content: String,
+    limit: Option<usize>,
+) -> Result<Vec<suggest::TagSuggestion>, AppError> {
+    let db = state.db()?;
+    let limit = limit.unwrap_or(5);
+    
+    db.run(move |db| suggest::suggest_tags(db, &content, limit)).await
+}
+
+/// Tag co-occurrence graph for the knowledge graph view. With `format` set,
+/// the graph is also rendered as DOT or Mermaid source in `rendered`.
+#[tauri::command]
+async fn get_tag_graph(
+    state: State<'_, AppState>,
+    min_weight: Option<i64>,
+    format: Option<GraphFormat>,
+) -> Result<TagGraphResult, AppError> {
+    let db = state.db()?;
+    let min_weight = min_weight.unwrap_or(1).max(1);
+    let graph = db.run(move |db| db.get_tag_graph(min_weight)).await?;
+    let rendered = format.map(|format| graph::render(&graph, format));
+    
+    Ok(TagGraphResult { graph, rendered })
+}
+
+#[tauri::command]
+async fn rename_tag(
+    state: State<'_, AppState>,
+    old_tag: String,
+    new_tag: String,
+    queue_remote: Option<bool>,
+) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let queue_remote = queue_remote.unwrap_or(false);
+    db.run(move |db| db.rename_tag(&old_tag, &new_tag, queue_remote)).await
+}
+
+#[tauri::command]
+async fn merge_tags(
+    state: State<'_, AppState>,
+    sources: Vec<String>,
+    target: String,
+    queue_remote: Option<bool>,
+) -> Result<usize, AppError> {
+    let db = state.db()?;
+    let queue_remote = queue_remote.unwrap_or(false);
+    db.run(move |db| db.merge_tags(&sources, &target, queue_remote)).await
+}
+
+#[tauri::command]
+async fn get_tag_mappings(state: State<'_, AppState>) -> Result<Vec<db::TagMapping>, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.get_tag_mappings()).await
+}
+
+#[tauri::command]
+async fn delete_memo_local(state: State<'_, AppState>, slug: String) -> Result<(), AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.delete_memo_local(&slug)).await
+}
+
+#[tauri::command]
+async fn restore_memo(state: State<'_, AppState>, slug: String) -> Result<(), AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.restore_memo(&slug)).await
+}
+
+#[tauri::command]
+async fn empty_trash(state: State<'_, AppState>) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.empty_trash()).await
+}
+
+#[tauri::command]
+async fn pin_memo(state: State<'_, AppState>, slug: String) -> Result<(), AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.set_pinned(&slug, true)).await
+}
+
+#[tauri::command]
+async fn unpin_memo(state: State<'_, AppState>, slug: String) -> Result<(), AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.set_pinned(&slug, false)).await
+}
+
+/// Pins every memo in a multi-selection. Returns how many were updated.
+#[tauri::command]
+async fn pin_memos(state: State<'_, AppState>, slugs: Vec<String>) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.set_pinned_bulk(&slugs, true)).await
+}
+
+#[tauri::command]
+async fn unpin_memos(state: State<'_, AppState>, slugs: Vec<String>) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.set_pinned_bulk(&slugs, false)).await
+}
+
+/// Adds a tag kept only in the app to every memo in `slugs`; filter on it
+/// with `MemoFilter::local_tag`. Returns how many memos were tagged.
+#[tauri::command]
+async fn add_local_tag(state: State<'_, AppState>, slugs: Vec<String>, tag: String) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.set_local_tag(&slugs, &tag, true)).await
+}
+
+#[tauri::command]
+async fn remove_local_tag(state: State<'_, AppState>, slugs: Vec<String>, tag: String) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.set_local_tag(&slugs, &tag, false)).await
+}
+
+#[tauri::command]
+async fn get_local_tags(state: State<'_, AppState>) -> Result<Vec<db::TagCount>, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.get_local_tags()).await
+}
+
+#[tauri::command]
+async fn create_collection(state: State<'_, AppState>, name: String) -> Result<db::Collection, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.create_collection(&name)).await
+}
+
+#[tauri::command]
+async fn rename_collection(state: State<'_, AppState>, name: String, new_name: String) -> Result<(), AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.rename_collection(&name, &new_name)).await
+}
+
+/// Adds `slugs` to the collection `name`, creating it on first use.
+/// Returns how many memos were newly added.
+#[tauri::command]
+async fn add_to_collection(state: State<'_, AppState>, name: String, slugs: Vec<String>) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.add_to_collection(&name, &slugs)).await
+}
+
+#[tauri::command]
+async fn remove_from_collection(state: State<'_, AppState>, name: String, slugs: Vec<String>) -> Result<usize, AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.remove_from_collection(&name, &slugs)).await
+}
+
+#[tauri::command]
+async fn get_collections(state: State<'_, AppState>) -> Result<Vec<db::Collection>, AppError> {
+    let db = state.db()?;
+    db.run(|db| db.get_collections()).await
+}
+
+/// A page of a collection's memos, most recently added first.
+#[tauri::command]
+async fn get_collection_memos(
+    state: State<'_, AppState>,
+    name: String,
+    cursor: Option<String>,
     limit: i64,
-) -> Result<Vec<Memo>, String> {
-    let db = {
-        let db_lock = state.db.lock().unwrap();
-        db_lock.as_ref().ok_or("Database not initialized")?.clone()
-    };
+) -> Result<MemoPage, AppError> {
+    let db = state.db()?;
+    let cursor = cursor.as_deref().map(PageCursor::decode).transpose()?;
+    
+    db.run(move |db| db.get_collection_memos(&name, cursor.as_ref(), limit)).await
+}
+
+#[tauri::command]
+async fn delete_collection(state: State<'_, AppState>, name: String) -> Result<(), AppError> {
+    let db = state.db()?;
+    db.run(move |db| db.delete_collection(&name)).await
+}
+
+#[tauri::command]
+async fn get_pinned_memos(state: State<'_, AppState>) -> Result<Vec<Memo>, AppError> {
+    let db = state.db()?;
+    let filter = MemoFilter { pinned_only: true, ..Default::default() };
     
-    db.search_memos(&query, &order_by, &order_dir, offset, limit)
+    // LIMIT -1 means no limit in SQLite; the pinned set is small
+    db.run(move |db| db.get_memos_page(MemoOrder::CreatedAt, "desc", &filter, false, 0, -1))
+        .await
 }
 
 #[tauri::command]
@@ -706,230 +2248,499 @@ async fn sync_all_memos(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
     token: String,
-) -> Result<(), String> {
-    // Clone the database to avoid holding the lock across await
-    let db = {
-        let db_lock = state.db.lock().unwrap();
-        db_lock.as_ref().ok_or("Database not initialized")?.clone()
-    };
+) -> Result<(), AppError> {
+    let db = state.db()?;
     
-    // Reset cancellation flag
-    state.sync_cancelled.store(false, Ordering::Relaxed);
+    // Fresh token per run so a previous cancel doesn't abort this one
+    let cancel = CancellationToken::new();
+    *state.sync_cancel.lock().unwrap() = cancel.clone();
     
-    // Update status to syncing
-    db.update_sync_status("syncing", None, None)?;
+    let client = state.client(token)?.with_cancellation(cancel.clone());
+    let mut throttle = sync::ProgressThrottle::new(state.settings().progress_interval());
+    let (progress, changes) = sync::sync_all(&db, &client, &cancel, |progress| {
+        if !throttle.ready(progress) {
+            return Ok(());
+        }
+        app.emit("sync-progress", progress)
+            .map_err(|e| AppError::Internal(format!("Failed to emit progress: {}", e)))
+    })
+    .await
+    .map_err(|e| notify_auth_expired(&app, e))?;
     
-    let client = FlomoClient::new(token);
-    let mut all_memos = Vec::new();
-    let mut latest_slug: Option<String> = None;
-    let mut latest_updated_at: Option<i64> = None;
-    let mut seen_slugs = HashSet::new();
-    let mut consecutive_empty_batches = 0;
-    const MAX_ITERATIONS: usize = 100; // Safety limit to prevent infinite loops
-    let mut iteration_count = 0;
+    // The published page isn't worth failing the sync over either
+    if let Err(e) = refresh_published(&state, &db).await {
+        error!("Failed to update published memos: {}", e);
+    }
+    let report = sync::run_after_sync(&db, state.settings(), &progress, &changes).await;
+    if let Some(mirrored) = report.git_mirror {
+        let _ = app.emit("git-mirror-updated", &mirrored);
+    }
+    if let Some(entry) = report.backup {
+        let _ = app.emit("backup-completed", &entry);
+    }
     
-    loop {
-        iteration_count += 1;
-        if iteration_count > MAX_ITERATIONS {
-            println!("WARNING: Reached maximum iteration limit of {}", MAX_ITERATIONS);
-            break;
+    // Emit completion event
+    app.emit("sync-progress", &progress)
+        .map_err(|e| AppError::Internal(format!("Failed to emit completion: {}", e)))?;
+    
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseStatus {
+    pub encrypted: bool,
+    pub locked: bool,
+    pub encryption_supported: bool,
+}
+
+#[tauri::command]
+fn get_database_status(state: State<'_, AppState>) -> DatabaseStatus {
+    let encrypted = db::is_encrypted(&state.db_path());
+    DatabaseStatus {
+        encrypted,
+        locked: encrypted && state.db.lock().unwrap().is_none(),
+        encryption_supported: db::encryption_supported(),
+    }
+}
+
+#[tauri::command]
+async fn unlock_database(state: State<'_, AppState>, passphrase: String) -> Result<(), AppError> {
+    let path = state.db_path();
+    let db = tokio::task::spawn_blocking(move || Database::open(&path, Some(&passphrase)))
+        .await
+        .map_err(|e| AppError::Internal(format!("Database task failed: {}", e)))??;
+    
+    *state.db.lock().unwrap() = Some(db);
+    Ok(())
+}
+
+/// Re-encrypts the plaintext database in place with `passphrase`. The
+/// passphrase is never stored; it must be entered on every launch.
+#[tauri::command]
+async fn encrypt_database(state: State<'_, AppState>, passphrase: String) -> Result<(), AppError> {
+    if passphrase.is_empty() {
+        return Err(AppError::InvalidInput("Passphrase cannot be empty".to_string()));
+    }
+    if db::is_encrypted(&state.db_path()) {
+        return Err(AppError::InvalidInput("Database is already encrypted".to_string()));
+    }
+    
+    let db = state.close_db().await?;
+    let encrypted_path = state.db_path().with_extension("db.encrypting");
+    let _ = std::fs::remove_file(&encrypted_path);
+    
+    let (dest, key) = (encrypted_path.clone(), passphrase.clone());
+    if let Err(e) = db.run(move |db| db.export_encrypted(&dest, &key)).await {
+        *state.db.lock().unwrap() = Some(db);
+        return Err(e);
+    }
+    
+    // The last handle, so this closes the plaintext connection before the
+    // files are swapped underneath it
+    drop(db);
+    let mut db_lock = state.db.lock().unwrap();
+    
+    for suffix in ["", "-wal", "-shm"] {
+        let mut path = state.db_path().into_os_string();
+        path.push(suffix);
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(AppError::Internal(format!("Failed to remove plaintext database: {}", e))),
         }
-        // Check if sync was cancelled
-        if state.sync_cancelled.load(Ordering::Relaxed) {
-            db.update_sync_status("cancelled", Some(all_memos.len() as i64), None)?;
-            return Err("Sync cancelled by user".to_string());
+    }
+    std::fs::rename(&encrypted_path, state.db_path())
+        .map_err(|e| AppError::Internal(format!("Failed to replace database: {}", e)))?;
+    
+    *db_lock = Some(Database::open(&state.db_path(), Some(&passphrase))?);
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataDirInfo {
+    /// Where the database is now
+    pub data_dir: PathBuf,
+    /// The app data directory, used when no folder is chosen
+    pub default_dir: PathBuf,
+    /// Folder of the running executable, for portable installs
+    pub executable_dir: Option<PathBuf>,
+}
+
+#[tauri::command]
+fn get_data_dir(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<DataDirInfo, AppError> {
+    let default_dir = app.path().app_data_dir()
+        .map_err(|e| AppError::Internal(format!("Failed to get app data dir: {}", e)))?;
+    let executable_dir = std::env::current_exe().ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from));
+    
+    Ok(DataDirInfo {
+        data_dir: state.db_path().parent().map(PathBuf::from).unwrap_or_else(|| default_dir.clone()),
+        default_dir,
+        executable_dir,
+    })
+}
+
+/// Moves the database and attachments to `path`, or back to the app data
+/// directory when `None`, and remembers the folder. Memo links to
+/// attachments are updated to the new folder, and the database is reopened
+/// from there; an encrypted one has to be unlocked again. Logs keep going
+/// to the old folder until the app restarts. Returns the new database path.
+#[tauri::command]
+async fn set_data_dir(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+) -> Result<PathBuf, AppError> {
+    // Only the open database would move, leaving the other mode's behind
+    if state.demo {
+        return Err(AppError::InvalidInput("Turn off demo mode before moving the data folder".to_string()));
+    }
+    let default_dir = app.path().app_data_dir()
+        .map_err(|e| AppError::Internal(format!("Failed to get app data dir: {}", e)))?;
+    let target = path.map(PathBuf::from).unwrap_or_else(|| default_dir.clone());
+    if !target.is_absolute() {
+        return Err(AppError::InvalidInput("Data folder must be an absolute path".to_string()));
+    }
+    std::fs::create_dir_all(&target)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", target.display(), e)))?;
+    
+    let dest = target.join(state.db_path().file_name().unwrap_or_default());
+    let same_dir = |a: &std::path::Path, b: &std::path::Path| {
+        matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+    };
+    if state.db_path().parent().is_some_and(|dir| same_dir(dir, &target)) {
+        return Ok(dest);
+    }
+    if dest.exists() {
+        return Err(AppError::InvalidInput(format!("{} already contains a database", target.display())));
+    }
+    
+    // Close the database first so nothing is written to the old copy
+    let db = state.close_db().await?;
+    let old_path = state.db_path();
+    
+    let old_attachments = state.attachments_dir();
+    let new_attachments = target.join("attachments");
+    let copy_dest = dest.clone();
+    let moved = db.run(move |db| {
+        db.relocate_attachments(&old_attachments, &new_attachments)?;
+        let copied = db.snapshot_to(&copy_dest)
+            .and_then(|()| copy_attachments(&old_attachments, &new_attachments));
+        if copied.is_err() {
+            let _ = std::fs::remove_file(&copy_dest);
+            db.relocate_attachments(&new_attachments, &old_attachments)?;
         }
-        
-        let params = client.get_params(latest_slug.as_deref(), latest_updated_at);
-        
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "authorization",
-            HeaderValue::from_str(&client.token).map_err(|e| e.to_string())?,
-        );
-
-        let response = client.client
-            .get(FlomoClient::URL_UPDATED)
-            .headers(headers)
-            .query(&params)
-            .send()
+        copied
+    })
+    .await;
+    if let Err(e) = moved {
+        *state.db.lock().unwrap() = Some(db);
+        return Err(e);
+    }
+    
+    // An encrypted copy stays locked until `unlock_database` opens it with the passphrase
+    let opened = if db::is_encrypted(&dest) {
+        Ok(None)
+    } else {
+        let dest = dest.clone();
+        tokio::task::spawn_blocking(move || Database::new(&dest).map(Some))
             .await
-            .map_err(|e| {
-                let error_msg = e.to_string();
-                let _ = db.update_sync_status("failed", None, Some(&error_msg));
-                error_msg
-            })?;
-
-        let response_text = response.text().await.map_err(|e| e.to_string())?;
-        let api_response: ApiResponse = serde_json::from_str(&response_text)
-            .map_err(|e| {
-                let error_msg = format!("JSON parse error: {}", e);
-                let _ = db.update_sync_status("failed", None, Some(&error_msg));
-                error_msg
-            })?;
-
-        if api_response.code != 0 {
-            let error_msg = format!("API error: code {}", api_response.code);
-            db.update_sync_status("failed", None, Some(&error_msg))?;
-            return Err(error_msg);
-        }
-
-        let memos = api_response.data.unwrap_or_default();
-        
-        println!("API returned {} memos in this batch (iteration {})", memos.len(), iteration_count);
-        
-        if memos.is_empty() {
-            consecutive_empty_batches += 1;
-            if consecutive_empty_batches >= 2 {
-                println!("No more memos to fetch after {} empty batches, ending sync", consecutive_empty_batches);
-                break;
-            }
-        } else {
-            consecutive_empty_batches = 0;
+            .map_err(|e| AppError::Internal(format!("Database task failed: {}", e)))
+            .and_then(|opened| opened)
+    };
+    let moved_db = match opened {
+        Ok(moved_db) => moved_db,
+        Err(e) => {
+            *state.db.lock().unwrap() = Some(db);
+            return Err(e);
         }
-        
-        // Check for duplicates - if we've seen all memos in this batch before, we're looping
-        let new_memos_count = memos.iter()
-            .filter(|memo| !seen_slugs.contains(&memo.slug))
-            .count();
-        
-        // Only break if we have no timestamp AND we're seeing duplicates
-        // With proper timestamp, duplicates shouldn't happen
-        if new_memos_count == 0 && !memos.is_empty() && latest_updated_at.is_none() {
-            println!("WARNING: All {} memos in this batch are duplicates and pagination timestamp is missing.", memos.len());
-            println!("This usually means we've fetched all available memos. Total unique memos: {}", seen_slugs.len());
-            // Don't break immediately - the API might still have more data
-            // Only break if we've seen this multiple times
-            consecutive_empty_batches += 1;
-            if consecutive_empty_batches >= 2 {
-                println!("Breaking after {} duplicate batches to prevent infinite loop.", consecutive_empty_batches);
-                break;
+    };
+    
+    let mut settings = state.settings();
+    settings.data_dir = (!same_dir(&target, &default_dir)).then_some(target);
+    if let Err(e) = settings.save(&app) {
+        *state.db.lock().unwrap() = Some(db);
+        return Err(e);
+    }
+    *state.settings.lock().unwrap() = settings;
+    
+    // The last handle to the old copy, so dropping it closes its files
+    drop(db);
+    *state.db_path.lock().unwrap() = dest.clone();
+    *state.db.lock().unwrap() = moved_db;
+    
+    // The new copy is complete, so failing to clean up the old one isn't an error
+    for suffix in ["", "-wal", "-shm"] {
+        let mut path = old_path.clone().into_os_string();
+        path.push(suffix);
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove old database file {}: {}", path.to_string_lossy(), e);
             }
-        } else if new_memos_count > 0 {
-            consecutive_empty_batches = 0;
-            println!("Found {} new memos in this batch", new_memos_count);
         }
-        
-        // Add new slugs to our seen set
-        for memo in &memos {
-            seen_slugs.insert(memo.slug.clone());
+    }
+    if let Err(e) = std::fs::remove_dir_all(old_path.with_file_name("attachments")) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove old attachments folder: {}", e);
         }
+    }
+    
+    info!("Moved database to {}", dest.display());
+    Ok(dest)
+}
 
-        let should_continue = memos.len() >= FlomoClient::LIMIT;
-        
-        if should_continue {
-            let last_memo = &memos[memos.len() - 1];
-            latest_slug = Some(last_memo.slug.clone());
-            
-            // Parse date format - API returns "YYYY-MM-DD HH:MM:SS" (space-separated)
-            let date_str = &last_memo.updated_at;
-            
-            // Try multiple date formats as the API might return different formats
-            let parsed = if let Ok(naive_dt) = NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S") {
-                Some(naive_dt)
-            } else if let Ok(naive_dt) = NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S") {
-                Some(naive_dt)
-            } else {
-                None
-            };
-            
-            if let Some(naive_dt) = parsed {
-                // Assume the date is in UTC
-                let dt_utc = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
-                latest_updated_at = Some(dt_utc.timestamp());
-                println!("Successfully parsed date: {} -> timestamp: {}", date_str, dt_utc.timestamp());
-            } else {
-                println!("ERROR: Failed to parse date format: '{}'", date_str);
-                // Don't break - continue with just slug pagination
-            }
-            
-            println!("Next page will use slug: {} and updated_at: {:?}", 
-                     latest_slug.as_ref().unwrap(), latest_updated_at);
+// Copies the files of an attachments folder, which has no subfolders
+fn copy_attachments(from: &std::path::Path, to: &std::path::Path) -> Result<(), AppError> {
+    let entries = match std::fs::read_dir(from) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(AppError::Internal(format!("Failed to read attachments: {}", e))),
+    };
+    std::fs::create_dir_all(to)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", to.display(), e)))?;
+    for entry in entries.flatten().filter(|entry| entry.path().is_file()) {
+        std::fs::copy(entry.path(), to.join(entry.file_name()))
+            .map_err(|e| AppError::Internal(format!("Failed to copy attachment: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Uploads a backup to the configured WebDAV folder now.
+#[tauri::command]
+async fn backup_now(state: State<'_, AppState>) -> Result<backup::BackupEntry, AppError> {
+    let db = state.db()?;
+    let settings = backup_settings(&state)?;
+    
+    backup::backup(&db, &settings).await
+}
+
+#[tauri::command]
+async fn list_backups(state: State<'_, AppState>) -> Result<Vec<backup::BackupEntry>, AppError> {
+    let settings = backup_settings(&state)?;
+    
+    backup::WebDavClient::new(&settings)?.list().await
+}
+
+/// Restores a backup by name. JSON backups are merged into the local
+/// memos; database backups replace the database, after keeping a copy of
+/// the current one next to it as `flomo.db.before-restore`. `passphrase`
+/// unlocks an encrypted database backup.
+#[tauri::command]
+async fn restore_backup(
+    state: State<'_, AppState>,
+    name: String,
+    passphrase: Option<String>,
+) -> Result<Option<db::UpsertStats>, AppError> {
+    let db = state.db()?;
+    let settings = backup_settings(&state)?;
+    let entry = backup::BackupEntry::parse(&name, None)
+        .ok_or_else(|| AppError::InvalidInput(format!("Not a backup file: {}", name)))?;
+    let content = backup::WebDavClient::new(&settings)?.download(&entry.name).await?;
+    
+    if entry.kind == backup::BackupKind::Json {
+        let stats = db.run(move |db| backup::restore_memos(db, &content)).await?;
+        return Ok(Some(stats));
+    }
+    
+    let restoring_path = state.db_path().with_extension("db.restoring");
+    std::fs::write(&restoring_path, &content)
+        .map_err(|e| AppError::Internal(format!("Failed to save downloaded backup: {}", e)))?;
+    if db::is_encrypted(&restoring_path) && passphrase.is_none() {
+        let _ = std::fs::remove_file(&restoring_path);
+        return Err(AppError::InvalidInput("This backup is encrypted; enter its passphrase".to_string()));
+    }
+    
+    // Opening the backup checks it's a database the passphrase unlocks
+    let (path, key) = (restoring_path.clone(), passphrase.clone());
+    let opened = tokio::task::spawn_blocking(move || Database::open(&path, key.as_deref()).map(drop))
+        .await
+        .map_err(|e| AppError::Internal(format!("Database task failed: {}", e)))?;
+    if let Err(e) = opened {
+        let _ = std::fs::remove_file(&restoring_path);
+        return Err(e);
+    }
+    
+    // Wait for background tasks to let go, so nothing writes to the files
+    // being replaced
+    drop(db);
+    let db = match state.close_db().await {
+        Ok(db) => db,
+        Err(e) => {
+            let _ = std::fs::remove_file(&restoring_path);
+            return Err(e);
+        }
+    };
+    
+    let previous_path = state.db_path().with_extension("db.before-restore");
+    let _ = std::fs::remove_file(&previous_path);
+    let dest = previous_path.clone();
+    if let Err(e) = db.run(move |db| db.snapshot_to(&dest)).await {
+        *state.db.lock().unwrap() = Some(db);
+        let _ = std::fs::remove_file(&restoring_path);
+        return Err(e);
+    }
+    
+    // The last handle, so this closes the current connection before the
+    // files are swapped underneath it
+    drop(db);
+    let mut db_lock = state.db.lock().unwrap();
+    
+    for suffix in ["", "-wal", "-shm"] {
+        let mut path = state.db_path().into_os_string();
+        path.push(suffix);
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(AppError::Internal(format!("Failed to remove current database: {}", e))),
         }
+    }
+    std::fs::rename(&restoring_path, state.db_path())
+        .map_err(|e| AppError::Internal(format!("Failed to replace database: {}", e)))?;
+    
+    *db_lock = Some(Database::open(&state.db_path(), passphrase.as_deref())?);
+    Ok(None)
+}
 
-        // Convert API memos to our Memo struct
-        let batch: Vec<Memo> = memos.into_iter().map(|api_memo| Memo {
-            slug: api_memo.slug.clone(),
-            content: parse_html_to_text(&api_memo.content),
-            created_at: api_memo.created_at,
-            updated_at: api_memo.updated_at,
-            tags: api_memo.tags,
-            url: Some(format!("https://v.flomoapp.com/mine/?memo_id={}", api_memo.slug)),
-        }).collect();
-        
-        // Save batch to database
-        let batch_size = batch.len();
-        db.bulk_upsert_memos(&batch)?;
-        
-        all_memos.extend(batch);
-        
-        // Log unique memos added in this batch (for debugging)
-        println!("Total API calls so far: {}", all_memos.len());
-        
-        // Get actual count from database for accurate progress
-        let db_count = db.get_memo_count().unwrap_or(0) as usize;
-        
-        // Emit progress event
-        let progress = SyncProgress {
-            total: db_count + if should_continue { batch_size } else { 0 }, // More accurate estimate
-            current: db_count,
-            status: "syncing".to_string(),
-            message: format!("Synced {} unique memos...", db_count),
-        };
+fn backup_settings(state: &AppState) -> Result<backup::BackupSettings, AppError> {
+    state.settings().backup
+        .ok_or_else(|| AppError::InvalidInput("No WebDAV backup folder is configured".to_string()))
+}
+
+// Uploads a backup whenever the newest one is older than the configured interval
+async fn run_backups(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(backup::CHECK_INTERVAL).await;
         
-        app.emit("sync-progress", &progress)
-            .map_err(|e| format!("Failed to emit progress: {}", e))?;
+        let state = app.state::<AppState>();
+        let Some(settings) = state.settings().backup else { continue };
+        let Some(interval_hours) = settings.interval_hours else { continue };
+        let Ok(db) = state.db() else { continue };
         
-        if !should_continue {
-            break;
+        let result = async {
+            let newest = backup::WebDavClient::new(&settings)?.list().await?;
+            if !backup::is_due(newest.first(), interval_hours) {
+                return Ok(None);
+            }
+            backup::backup(&db, &settings).await.map(Some)
+        }
+        .await;
+        match result {
+            Ok(Some(entry)) => {
+                let _ = app.emit("backup-completed", &entry);
+            }
+            Ok(None) => {}
+            Err(e) => error!("Scheduled backup failed: {}", e),
         }
     }
+}
+
+#[tauri::command]
+async fn get_sync_status(state: State<'_, AppState>) -> Result<db::SyncStatus, AppError> {
+    let db = state.db()?;
     
-    // Get final count from database
-    let final_count = db.get_memo_count().unwrap_or(0);
+    db.run(|db| db.get_sync_status()).await
+}
+
+#[tauri::command]
+async fn get_sync_history(
+    state: State<'_, AppState>,
+    limit: Option<i64>,
+) -> Result<Vec<db::SyncRun>, AppError> {
+    let db = state.db()?;
+    let limit = limit.unwrap_or(50);
     
-    println!("Sync completed: {} iterations, {} total API records fetched, {} unique slugs seen, {} unique memos in database", 
-             iteration_count, all_memos.len(), seen_slugs.len(), final_count);
+    db.run(move |db| db.get_sync_history(limit)).await
+}
+
+/// Runs of the auto-export rules, newest first.
+#[tauri::command]
+async fn get_export_runs(state: State<'_, AppState>, limit: Option<i64>) -> Result<Vec<db::ExportRun>, AppError> {
+    let db = state.db()?;
+    let limit = limit.unwrap_or(50);
     
-    // Update sync status to completed
-    db.update_sync_status("completed", Some(final_count), None)?;
+    db.run(move |db| db.get_export_runs(limit)).await
+}
+
+/// Flomo responses that couldn't be parsed during syncs, newest first.
+#[tauri::command]
+async fn get_api_diagnostics(
+    state: State<'_, AppState>,
+    limit: Option<i64>,
+) -> Result<Vec<db::ApiDiagnostic>, AppError> {
+    let db = state.db()?;
+    let limit = limit.unwrap_or(50);
     
-    // Emit completion event
-    let progress = SyncProgress {
-        total: final_count as usize,
-        current: final_count as usize,
-        status: "completed".to_string(),
-        message: format!("Successfully synced {} unique memos", final_count),
+    db.run(move |db| db.get_api_diagnostics(limit)).await
+}
+
+#[tauri::command]
+async fn clear_api_diagnostics(state: State<'_, AppState>) -> Result<(), AppError> {
+    let db = state.db()?;
+    db.run(|db| db.clear_api_diagnostics()).await
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppStats {
+    #[serde(flatten)]
+    pub db: db::DbStats,
+    /// Bytes in the attachments folder
+    pub attachments_size: u64,
+    pub app_version: String,
+}
+
+// Bytes in all files under `dir`; missing or unreadable entries count as empty
+fn dir_size(dir: &std::path::Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
     };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Database size and contents, attachment storage, last sync and versions
+/// in one call, for the Settings and About pages.
+#[tauri::command]
+async fn get_app_stats(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<AppStats, AppError> {
+    let db = state.db()?;
+    let attachments_dir = state.attachments_dir();
+    let app_version = app.package_info().version.to_string();
     
-    app.emit("sync-progress", &progress)
-        .map_err(|e| format!("Failed to emit completion: {}", e))?;
-    
-    Ok(())
+    db.run(move |db| {
+        Ok(AppStats {
+            db: db.get_db_stats()?,
+            attachments_size: dir_size(&attachments_dir),
+            app_version,
+        })
+    })
+    .await
 }
 
+/// Checks the database file, rows orphaned from their memo and linked
+/// attachments; with `repair`, removes the orphans and vacuums.
 #[tauri::command]
-async fn get_sync_status(state: State<'_, AppState>) -> Result<db::SyncStatus, String> {
-    let db = {
-        let db_lock = state.db.lock().unwrap();
-        db_lock.as_ref().ok_or("Database not initialized")?.clone()
-    };
+async fn check_database(state: State<'_, AppState>, repair: Option<bool>) -> Result<db::IntegrityReport, AppError> {
+    let db = state.db()?;
+    let attachments_dir = state.attachments_dir();
+    let repair = repair.unwrap_or(false);
     
-    db.get_sync_status()
+    db.run(move |db| db.check_database(&attachments_dir, repair)).await
 }
 
 #[tauri::command]
-async fn clear_local_data(state: State<'_, AppState>) -> Result<(), String> {
-    let db = {
-        let db_lock = state.db.lock().unwrap();
-        db_lock.as_ref().ok_or("Database not initialized")?.clone()
-    };
+async fn clear_local_data(state: State<'_, AppState>) -> Result<(), AppError> {
+    let db = state.db()?;
     
-    db.clear_all_memos()
+    db.run(|db| db.clear_all_memos()).await
 }
 
 #[tauri::command]
-async fn cancel_sync(state: State<'_, AppState>) -> Result<(), String> {
-    state.sync_cancelled.store(true, Ordering::Relaxed);
+async fn cancel_sync(state: State<'_, AppState>) -> Result<(), AppError> {
+    state.sync_cancel.lock().unwrap().cancel();
     Ok(())
 }
\ No newline at end of file
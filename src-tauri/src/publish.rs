@@ -0,0 +1,198 @@
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::{Arc, RwLock};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+
+use crate::db::{Database, MemoFilter};
+use crate::error::AppError;
+use crate::export::{self, ExportFormat, ExportOptions, ImageMode};
+use crate::i18n::Locale;
+
+const DEFAULT_PORT: u16 = 8787;
+/// Tag published when no filter is given.
+const DEFAULT_TAG: &str = "public";
+// Longest request head read before giving up on a client
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// What the publish server shows and where it listens.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PublishOptions {
+    /// Which memos are public, the "public" tag by default; must narrow
+    /// the memos down, so the whole journal is never served
+    pub filter: MemoFilter,
+    /// Listen on all interfaces so phones on the same network can connect,
+    /// instead of only on this computer
+    pub lan: bool,
+    pub port: u16,
+    pub date_format: String,
+    /// Inline images instead of linking to Flomo's servers
+    pub embed_images: bool,
+}
+
+impl Default for PublishOptions {
+    fn default() -> Self {
+        Self {
+            filter: MemoFilter { tag: Some(DEFAULT_TAG.to_string()), ..Default::default() },
+            lan: false,
+            port: DEFAULT_PORT,
+            date_format: String::new(),
+            embed_images: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishStatus {
+    /// Address to open in a browser; the LAN address when `lan` is set
+    pub url: String,
+    pub memo_count: usize,
+}
+
+/// A running publish server. The page is rendered up front and after each
+/// sync, so requests never touch the database.
+pub struct Publisher {
+    page: Arc<RwLock<Vec<u8>>>,
+    cancel: CancellationToken,
+    options: PublishOptions,
+    status: PublishStatus,
+}
+
+impl Publisher {
+    pub async fn start(db: &Database, options: PublishOptions, tz: FixedOffset, locale: Locale) -> Result<Self, AppError> {
+        // Also keeps an empty filter from ever being served on all interfaces
+        if options.filter.is_empty() {
+            return Err(AppError::InvalidInput("Choose a tag or filter for the memos to publish".to_string()));
+        }
+
+        let render_options = options.clone();
+        let (page, memo_count) = db.run(move |db| render(db, &render_options, tz, locale)).await?;
+
+        let host = if options.lan { IpAddr::V4(Ipv4Addr::UNSPECIFIED) } else { IpAddr::V4(Ipv4Addr::LOCALHOST) };
+        let listener = TcpListener::bind(SocketAddr::new(host, options.port))
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to listen on port {}: {}", options.port, e)))?;
+        let port = listener.local_addr().map(|addr| addr.port()).unwrap_or(options.port);
+        let url_host = if options.lan { lan_address().unwrap_or(host) } else { host };
+
+        let page = Arc::new(RwLock::new(page));
+        let cancel = CancellationToken::new();
+        tauri::async_runtime::spawn(serve(listener, page.clone(), cancel.clone()));
+
+        Ok(Self {
+            page,
+            cancel,
+            options,
+            status: PublishStatus { url: format!("http://{}:{}/", url_host, port), memo_count },
+        })
+    }
+
+    pub fn options(&self) -> &PublishOptions {
+        &self.options
+    }
+
+    /// Swaps in a page rendered by `render` from the current memos.
+    pub fn replace_page(&mut self, page: Vec<u8>, memo_count: usize) {
+        *self.page.write().unwrap() = page;
+        self.status.memo_count = memo_count;
+    }
+
+    pub fn status(&self) -> PublishStatus {
+        self.status.clone()
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+/// Renders the published memos as one HTML page, returning it with the
+/// number of memos on it.
+pub fn render(db: &Database, options: &PublishOptions, tz: FixedOffset, locale: Locale) -> Result<(Vec<u8>, usize), AppError> {
+    let export_options = ExportOptions {
+        date_format: options.date_format.clone(),
+        image_mode: if options.embed_images { ImageMode::Embed } else { ImageMode::Link },
+        locale,
+        filter: options.filter.clone(),
+        ..Default::default()
+    };
+    export::export_from_db(db, Vec::new(), ExportFormat::Html, export_options, tz, |_| {})
+}
+
+async fn serve(listener: TcpListener, page: Arc<RwLock<Vec<u8>>>, cancel: CancellationToken) {
+    loop {
+        let stream = tokio::select! {
+            _ = cancel.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("Publish server failed to accept a connection: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let page = page.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = respond(stream, &page).await {
+                tracing::warn!("Publish server request failed: {}", e);
+            }
+        });
+    }
+}
+
+// Answers a single request and closes the connection. Only GET and HEAD of
+// the page itself are served; everything is read-only.
+async fn respond(mut stream: TcpStream, page: &RwLock<Vec<u8>>) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET" | "HEAD", "/" | "/index.html") => ("200 OK", "text/html; charset=utf-8", page.read().unwrap().clone()),
+        ("GET" | "HEAD", _) => ("404 Not Found", "text/plain; charset=utf-8", b"Not found".to_vec()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", b"Method not allowed".to_vec()),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nX-Content-Type-Options: nosniff\r\nConnection: close\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    if status.starts_with("405") {
+        response.push_str("Allow: GET, HEAD\r\n");
+    }
+    response.push_str("\r\n");
+
+    stream.write_all(response.as_bytes()).await?;
+    if method != "HEAD" {
+        stream.write_all(&body).await?;
+    }
+    stream.shutdown().await
+}
+
+// The address other devices reach this computer at. Connecting a UDP socket
+// sends nothing; it only picks the interface with the default route.
+fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 80)).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
@@ -0,0 +1,31 @@
+use tauri_plugin_store::StoreExt;
+
+use crate::error::AppError;
+pub use flomo_core::settings::AppSettings;
+use flomo_core::settings::{CONFIG_FILE, SETTINGS_KEY};
+
+/// Reads and writes `AppSettings` in the Tauri store.
+pub trait SettingsStore: Sized {
+    /// Also returns why any saved fields were reset to their defaults.
+    fn load(app: &tauri::AppHandle) -> Result<(Self, Vec<String>), AppError>;
+    fn save(&self, app: &tauri::AppHandle) -> Result<(), AppError>;
+}
+
+impl SettingsStore for AppSettings {
+    fn load(app: &tauri::AppHandle) -> Result<(Self, Vec<String>), AppError> {
+        let store = app.store(CONFIG_FILE).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        match store.get(SETTINGS_KEY) {
+            Some(value) => Ok(Self::from_json(value)),
+            None => Ok((Self::default(), Vec::new())),
+        }
+    }
+
+    fn save(&self, app: &tauri::AppHandle) -> Result<(), AppError> {
+        let store = app.store(CONFIG_FILE).map_err(|e| AppError::Internal(e.to_string()))?;
+        store.set(SETTINGS_KEY, serde_json::to_value(self)?);
+        store.save().map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -17,6 +17,7 @@ import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@
 import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/ui/tabs";
 import { Badge } from "@/components/ui/badge";
 import { ScrollArea } from "@/components/ui/scroll-area";
+import { errorMessage } from "@/lib/errors";
 
 interface Memo {
   slug: string;
@@ -25,12 +26,45 @@ interface Memo {
   updated_at: string;
   tags: string[];
   url?: string;
+  pinned?: boolean;
+  word_count?: number;
+  char_count?: number;
+}
+
+interface MemoPage {
+  memos: Memo[];
+  next_cursor: string | null;
+}
+
+interface DatabaseStatus {
+  encrypted: boolean;
+  locked: boolean;
+  encryption_supported: boolean;
 }
 
 type ViewMode = "list" | "search" | "settings";
-type OrderBy = "created_at" | "updated_at";
+type OrderBy =
+  | "created_at"
+  | "updated_at"
+  | "synced_at"
+  | "content_length"
+  | "word_count"
+  | "char_count"
+  | "tag_count"
+  | "random";
 type OrderDir = "asc" | "desc";
 
+const ORDER_LABELS: Record<OrderBy, string> = {
+  created_at: "creation date",
+  updated_at: "update date",
+  word_count: "word count",
+  char_count: "character count",
+  synced_at: "sync date",
+  content_length: "content length",
+  tag_count: "tag count",
+  random: "random order",
+};
+
 function App() {
   const [error, setError] = useState<string | null>(null);
   const [token, setToken] = useState<string>("");
@@ -51,7 +85,7 @@ function App() {
   // Load saved token and check for local data on mount
   useEffect(() => {
     loadToken();
-    checkLocalData();
+    unlockDatabase().then(checkLocalData);
     
     // Listen for update available event from settings
     const handleUpdateAvailable = () => {
@@ -81,7 +115,28 @@ function App() {
       setError(null);
       alert("Token saved successfully!");
     } catch (err) {
-      setError(`Failed to save token: ${err}`);
+      setError(`Failed to save token: ${errorMessage(err)}`);
+    }
+  };
+
+  // Encrypted databases stay closed until the passphrase is entered
+  const unlockDatabase = async () => {
+    try {
+      const status = await invoke<DatabaseStatus>("get_database_status");
+      if (!status.locked) return;
+
+      while (true) {
+        const passphrase = prompt("Enter the passphrase for your encrypted database:");
+        if (passphrase === null) return;
+        try {
+          await invoke("unlock_database", { passphrase });
+          return;
+        } catch (err) {
+          alert(`Failed to unlock database: ${errorMessage(err)}`);
+        }
+      }
+    } catch (err) {
+      console.error("Failed to check database status:", err);
     }
   };
 
@@ -106,38 +161,23 @@ function App() {
     refetch: refetchMemos,
   } = useInfiniteQuery({
     queryKey: ["memos", token, orderBy, orderDir, hasLocalData],
-    queryFn: async ({ pageParam = 0 }) => {
+    queryFn: async ({ pageParam }) => {
       if (!token) throw new Error("No token configured");
       
       if (!hasLocalData) {
         // If no local data, show empty and prompt for sync
-        return { memos: [], has_more: false };
+        return { memos: [], next_cursor: null };
       }
       
-      const limit = 50;
-      const memos = await invoke<Memo[]>("get_memos_from_db", {
+      return invoke<MemoPage>("get_memos_from_db", {
         orderBy,
         orderDir,
-        offset: pageParam,
-        limit: limit + 1, // Get one extra to check if there's more
+        cursor: pageParam,
+        limit: 50,
       });
-      
-      const hasMore = memos.length > limit;
-      if (hasMore) {
-        memos.pop(); // Remove the extra item
-      }
-      
-      return {
-        memos,
-        has_more: hasMore,
-        offset: pageParam,
-      };
-    },
-    initialPageParam: 0,
-    getNextPageParam: (lastPage: any) => {
-      if (!lastPage.has_more) return undefined;
-      return lastPage.offset + 50;
     },
+    initialPageParam: null as string | null,
+    getNextPageParam: (lastPage: MemoPage) => lastPage.next_cursor ?? undefined,
     enabled: viewMode === "list" && !!token && hasLocalData,
   });
 
@@ -152,35 +192,22 @@ function App() {
     error: searchError,
   } = useInfiniteQuery({
     queryKey: ["search", token, searchQuery, orderBy, orderDir, hasLocalData],
-    queryFn: async ({ pageParam = 0 }) => {
+    queryFn: async ({ pageParam }) => {
       if (!token) throw new Error("No token configured");
-      if (!hasLocalData) return { memos: [], has_more: false };
+      if (!hasLocalData) return { memos: [], next_cursor: null };
       
-      const limit = 50;
-      const memos = await invoke<Memo[]>("search_memos_from_db", {
+      const page = await invoke<{ hits: Memo[]; next_cursor: string | null }>("search_memos_from_db", {
         query: searchQuery,
         orderBy,
         orderDir,
-        offset: pageParam,
-        limit: limit + 1,
+        cursor: pageParam,
+        limit: 50,
       });
       
-      const hasMore = memos.length > limit;
-      if (hasMore) {
-        memos.pop();
-      }
-      
-      return {
-        memos,
-        has_more: hasMore,
-        offset: pageParam,
-      };
-    },
-    initialPageParam: 0,
-    getNextPageParam: (lastPage: any) => {
-      if (!lastPage.has_more) return undefined;
-      return lastPage.offset + 50;
+      return { memos: page.hits, next_cursor: page.next_cursor };
     },
+    initialPageParam: null as string | null,
+    getNextPageParam: (lastPage: MemoPage) => lastPage.next_cursor ?? undefined,
     enabled: viewMode === "search" && !!token && searchQuery.trim().length > 0 && hasLocalData,
   });
 
@@ -232,10 +259,10 @@ function App() {
         return;
       }
       
-      const allMemos = await invoke<Memo[]>("get_memos_from_db", {
+      const { memos: allMemos } = await invoke<MemoPage>("get_memos_from_db", {
         orderBy: "created_at",
         orderDir: "desc",
-        offset: 0,
+        cursor: null,
         limit: 999999, // Get all
       });
       
@@ -247,7 +274,7 @@ function App() {
       setExportMemos(allMemos);
       setShowExportPreview(true);
     } catch (err) {
-      setError(`Failed to load memos: ${err}`);
+      setError(`Failed to load memos: ${errorMessage(err)}`);
     }
   };
 
@@ -265,7 +292,10 @@ function App() {
       <CardHeader className="pb-3 bg-gradient-to-r from-transparent to-muted/20">
         <div className="flex justify-between items-center text-sm">
           <span className="font-bold text-primary text-base">#{index + 1}</span>
-          <span className="text-muted-foreground text-xs">{formatDate(memo.created_at)}</span>
+          <span className="text-muted-foreground text-xs">
+            {memo.word_count !== undefined && `${memo.word_count} words · `}
+            {formatDate(memo.created_at)}
+          </span>
         </div>
       </CardHeader>
       <CardContent className="pb-3 px-6">
@@ -436,6 +466,12 @@ function App() {
                           <SelectContent>
                             <SelectItem value="created_at">Created Date</SelectItem>
                             <SelectItem value="updated_at">Updated Date</SelectItem>
+                            <SelectItem value="word_count">Word Count</SelectItem>
+                            <SelectItem value="char_count">Character Count</SelectItem>
+                            <SelectItem value="synced_at">Synced Date</SelectItem>
+                            <SelectItem value="content_length">Content Length</SelectItem>
+                            <SelectItem value="tag_count">Tag Count</SelectItem>
+                            <SelectItem value="random">Random</SelectItem>
                           </SelectContent>
                         </Select>
                         <Select value={orderDir} onValueChange={(v) => setOrderDir(v as OrderDir)}>
@@ -480,7 +516,7 @@ function App() {
                     
                     {hasLocalData && getAllMemos().length > 0 && (
                       <p className="text-sm text-muted-foreground">
-                        Showing: {getAllMemos().length} memos (sorted by {orderBy === "created_at" ? "creation date" : "update date"})
+                        Showing: {getAllMemos().length} memos (sorted by {ORDER_LABELS[orderBy]})
                         {!hasNextMemos && (
                           <span className="italic"> • All loaded</span>
                         )}
@@ -546,6 +582,12 @@ function App() {
                         <SelectContent>
                           <SelectItem value="created_at">Created Date</SelectItem>
                           <SelectItem value="updated_at">Updated Date</SelectItem>
+                          <SelectItem value="word_count">Word Count</SelectItem>
+                          <SelectItem value="char_count">Character Count</SelectItem>
+                          <SelectItem value="synced_at">Synced Date</SelectItem>
+                          <SelectItem value="content_length">Content Length</SelectItem>
+                          <SelectItem value="tag_count">Tag Count</SelectItem>
+                          <SelectItem value="random">Random</SelectItem>
                         </SelectContent>
                       </Select>
                       <Select value={orderDir} onValueChange={(v) => setOrderDir(v as OrderDir)}>
@@ -4,6 +4,7 @@ import { writeTextFile } from "@tauri-apps/plugin-fs";
 import { invoke } from "@tauri-apps/api/core";
 import { format } from "date-fns";
 import { MemoContent } from "./MemoContent";
+import { errorMessage } from "./lib/errors";
 import "./ExportPreview.css";
 
 interface Memo {
@@ -32,6 +33,8 @@ type DateFormatOption =
   | "dd/MM/yyyy HH:mm"
   | "yyyy年MM月dd日 HH:mm"
   | "MMM dd, yyyy"
+  | "EEEE, MMMM d, yyyy h:mm a"
+  | "relative"
   | "none"
   | "custom";
 
@@ -186,7 +189,7 @@ export function ExportPreview({ memos, onClose }: ExportPreviewProps) {
         onClose();
       }
     } catch (err) {
-      setError(`Export failed: ${err}`);
+      setError(`Export failed: ${errorMessage(err)}`);
     } finally {
       setIsExporting(false);
     }
@@ -341,6 +344,10 @@ export function ExportPreview({ memos, onClose }: ExportPreviewProps) {
                   2024年01月15日 14:30
                 </option>
                 <option value="MMM dd, yyyy">Jan 15, 2024</option>
+                <option value="EEEE, MMMM d, yyyy h:mm a">
+                  Monday, January 15, 2024 2:30 PM
+                </option>
+                <option value="relative">3 days ago</option>
                 <option value="none">No date</option>
                 <option value="custom">Custom format</option>
               </select>
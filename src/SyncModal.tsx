@@ -1,6 +1,7 @@
 import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { errorMessage, isAppError } from "./lib/errors";
 import "./SyncModal.css";
 
 interface SyncProgress {
@@ -8,6 +9,13 @@ interface SyncProgress {
   current: number;
   status: string;
   message: string;
+  new_count?: number;
+  updated_count?: number;
+  unchanged_count?: number;
+  pages_fetched?: number;
+  elapsed_secs?: number;
+  memos_per_sec?: number;
+  eta_secs?: number | null;
 }
 
 interface SyncStatus {
@@ -87,8 +95,7 @@ export function SyncModal({ isOpen, onClose, token, onSyncComplete }: SyncModalP
     try {
       await invoke("sync_all_memos", { token });
     } catch (err) {
-      const errorMessage = String(err);
-      if (errorMessage.includes("cancelled")) {
+      if (isAppError(err) && err.code === "cancelled") {
         setProgress({
           total: progress?.total || 0,
           current: progress?.current || 0,
@@ -100,7 +107,7 @@ export function SyncModal({ isOpen, onClose, token, onSyncComplete }: SyncModalP
           total: 0,
           current: 0,
           status: "failed",
-          message: `Sync failed: ${err}`,
+          message: `Sync failed: ${errorMessage(err)}`,
         });
       }
       setIsSyncing(false);
@@ -132,7 +139,7 @@ export function SyncModal({ isOpen, onClose, token, onSyncComplete }: SyncModalP
         loadSyncStatus();
         onSyncComplete();
       } catch (err) {
-        alert(`Failed to clear data: ${err}`);
+        alert(`Failed to clear data: ${errorMessage(err)}`);
       }
     }
   };
@@ -146,6 +153,12 @@ export function SyncModal({ isOpen, onClose, token, onSyncComplete }: SyncModalP
     }
   };
 
+  const formatDuration = (seconds: number) => {
+    const rounded = Math.max(0, Math.round(seconds));
+    if (rounded < 60) return `${rounded}s`;
+    return `${Math.floor(rounded / 60)}m ${rounded % 60}s`;
+  };
+
   const getProgressPercentage = () => {
     if (!progress || progress.total === 0) return 0;
     return Math.round((progress.current / progress.total) * 100);
@@ -197,6 +210,13 @@ export function SyncModal({ isOpen, onClose, token, onSyncComplete }: SyncModalP
                   <div className="progress-stats">
                     {progress.current} / {progress.total} ({getProgressPercentage()}%)
                   </div>
+                  {progress.pages_fetched !== undefined && (
+                    <div className="progress-stats">
+                      {progress.pages_fetched} pages · {(progress.memos_per_sec ?? 0).toFixed(1)} memos/s
+                      {" · "}{formatDuration(progress.elapsed_secs ?? 0)} elapsed
+                      {progress.eta_secs != null && ` · about ${formatDuration(progress.eta_secs)} left`}
+                    </div>
+                  )}
                 </>
               )}
               {progress.status === "failed" && (
@@ -0,0 +1,31 @@
+export type AppErrorCode =
+  | "network"
+  | "timeout"
+  | "auth"
+  | "captcha_required"
+  | "rate_limited"
+  | "parse"
+  | "db"
+  | "cancelled"
+  | "locked"
+  | "invalid_input"
+  | "not_found"
+  | "internal"
+
+export interface AppError {
+  code: AppErrorCode
+  message: string
+}
+
+export function isAppError(err: unknown): err is AppError {
+  return (
+    typeof err === "object" &&
+    err !== null &&
+    "code" in err &&
+    "message" in err
+  )
+}
+
+export function errorMessage(err: unknown): string {
+  return isAppError(err) ? err.message : String(err)
+}